@@ -0,0 +1,98 @@
+// Stable interface between the AuraAgg router engine and third-party liquidity
+// source adapters. An adapter crate implements LiquidityAdapter and registers a
+// factory with register_adapter!; any binary linking it alongside router-engine
+// discovers it at startup, with no changes to the engine itself.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+pub use async_trait::async_trait;
+pub use inventory;
+pub use num_bigint::BigUint;
+
+// Major version of this interface, compiled into every registration; breaking
+// changes bump it and the engine refuses adapters built against another version
+pub const API_VERSION: u32 = 1;
+
+#[derive(Error, Debug)]
+pub enum AdapterError {
+    #[error("Insufficient liquidity: {0}")]
+    InsufficientLiquidity(String),
+
+    #[error("Unsupported pair: {0}")]
+    UnsupportedPair(String),
+
+    #[error("Configuration error: {0}")]
+    Config(String),
+
+    #[error("Upstream error: {0}")]
+    Upstream(String),
+}
+
+// Token as seen by adapters
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct AdapterToken {
+    pub chain_id: u64,
+    pub address: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+#[derive(Debug, Clone)]
+pub struct AdapterQuote {
+    pub amount_out: BigUint,
+    // Fraction of the price moved by the trade, 0.0 to 1.0
+    pub price_impact: f64,
+}
+
+// A liquidity source provided by a plugin
+#[async_trait]
+pub trait LiquidityAdapter: Send + Sync {
+    async fn quote(
+        &self,
+        token_in: &AdapterToken,
+        token_out: &AdapterToken,
+        amount_in: &BigUint,
+    ) -> Result<AdapterQuote, AdapterError>;
+
+    async fn reserves(
+        &self,
+        token_a: &AdapterToken,
+        token_b: &AdapterToken,
+    ) -> Result<(BigUint, BigUint), AdapterError>;
+}
+
+// Static description of an adapter plugin, collected at link time
+pub struct AdapterRegistration {
+    pub api_version: u32,
+    // Liquidity source id the engine registers the adapter under
+    pub id: &'static str,
+    pub version: &'static str,
+    // Build the adapter from its deployment configuration (null when none is given)
+    pub create: fn(&serde_json::Value) -> Result<Arc<dyn LiquidityAdapter>, AdapterError>,
+}
+
+inventory::collect!(AdapterRegistration);
+
+// Every adapter linked into the current binary
+pub fn registered_adapters() -> impl Iterator<Item = &'static AdapterRegistration> {
+    inventory::iter::<AdapterRegistration>.into_iter()
+}
+
+// Register an adapter factory, e.g.
+// auraagg_adapter_api::register_adapter!("my_dex", MyDexAdapter::from_config);
+#[macro_export]
+macro_rules! register_adapter {
+    ($id:expr, $create:expr) => {
+        $crate::inventory::submit! {
+            $crate::AdapterRegistration {
+                api_version: $crate::API_VERSION,
+                id: $id,
+                version: env!("CARGO_PKG_VERSION"),
+                create: $create,
+            }
+        }
+    };
+}
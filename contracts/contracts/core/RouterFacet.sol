@@ -3,9 +3,12 @@ pragma solidity ^0.8.19;
 
 import "@openzeppelin/contracts/token/ERC20/utils/SafeERC20.sol";
 import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
+import "@openzeppelin/contracts/token/ERC20/extensions/IERC20Permit.sol";
 import "@openzeppelin/contracts/security/ReentrancyGuard.sol";
 import "@openzeppelin/contracts/access/Ownable.sol";
 import "../interfaces/IDiamondCut.sol";
+import "../interfaces/IPermit2.sol";
+import "../interfaces/IWETH.sol";
 import "../libraries/GasSaver.sol";
 
 /**
@@ -30,6 +33,8 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
     // State variables
     mapping(address => bool) public authorizedRelayers;
     uint public constant MAX_STEPS = 10;
+    IPermit2 public permit2;
+    IWETH public weth;
     
     // Structs
     struct SwapStep {
@@ -40,6 +45,23 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
         uint amountOutMin;
         bytes data;
         uint16 feeTier;
+        // Byte offset of the amountIn word in data, overwritten with the amount the
+        // step actually spends; 0 if the exchange call takes a fixed amount
+        uint16 amountInOffset;
+    }
+    
+    struct Recipient {
+        address account;
+        uint16 shareBps;
+    }
+    
+    struct Eip2612Permit {
+        address token;
+        uint value;
+        uint deadline;
+        uint8 v;
+        bytes32 r;
+        bytes32 s;
     }
     
     // Constructor
@@ -80,6 +102,24 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
         emit RelayerRemoved(relayer);
     }
     
+    /**
+     * @dev Set the Permit2 deployment used for batched approvals
+     * @param _permit2 Address of Permit2
+     */
+    function setPermit2(address _permit2) external onlyOwner {
+        require(_permit2 != address(0), "Invalid Permit2 address");
+        permit2 = IPermit2(_permit2);
+    }
+    
+    /**
+     * @dev Set the chain's wrapped native token used to wrap inputs and unwrap outputs
+     * @param _weth Address of the wrapped native token
+     */
+    function setWeth(address _weth) external onlyOwner {
+        require(_weth != address(0), "Invalid WETH address");
+        weth = IWETH(_weth);
+    }
+    
     /**
      * @dev Modifier to protect against MEV attacks
      * Only allows calls from authorized relayers
@@ -90,7 +130,8 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
     }
     
     /**
-     * @dev Execute a multi-step swap across different DEXes
+     * @dev Execute a multi-step swap across different DEXes. Like every multiSwap
+     * entry point it pulls the route's ERC-20 input from the sender first.
      * @param steps Array of swap steps to execute
      * @return outputs Array of output amounts for each step
      */
@@ -102,12 +143,9 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
     {
         require(steps.length > 0 && steps.length <= MAX_STEPS, "Invalid steps length");
         
-        outputs = new uint[](steps.length);
+        _pullInput(steps);
         
-        for (uint i; i < steps.length; ) {
-            outputs[i] = _executeStep(steps[i]);
-            unchecked { ++i; }
-        }
+        outputs = _executeSteps(steps, _routeInput(steps));
         
         // Return any remaining ETH to the sender
         if (address(this).balance > 0) {
@@ -132,13 +170,41 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
     {
         require(steps.length > 0 && steps.length <= MAX_STEPS, "Invalid steps length");
         
-        outputs = new uint[](steps.length);
+        _pullInput(steps);
         
-        for (uint i; i < steps.length; ) {
-            outputs[i] = _executeStep(steps[i]);
-            unchecked { ++i; }
+        outputs = _executeSteps(steps, _routeInput(steps));
+        
+        // Return any remaining ETH to the sender
+        if (address(this).balance > 0) {
+            (bool success, ) = msg.sender.call{value: address(this).balance}("");
+            require(success, "ETH transfer failed");
         }
         
+        return outputs;
+    }
+    
+    /**
+     * @dev Execute a multi-step swap and pay the final output out to several recipients
+     * @param steps Array of swap steps to execute
+     * @param recipients Receivers of the output; shares must add up to 10000 bps and
+     * the last recipient receives the rounding remainder
+     * @return outputs Array of output amounts for each step
+     */
+    function multiSwapAndSplit(SwapStep[] calldata steps, Recipient[] calldata recipients)
+        external
+        payable
+        nonReentrant
+        returns (uint[] memory outputs)
+    {
+        require(steps.length > 0 && steps.length <= MAX_STEPS, "Invalid steps length");
+        _checkRecipients(recipients);
+        
+        _pullInput(steps);
+        
+        outputs = _executeSteps(steps, _routeInput(steps));
+        
+        _payOut(steps[steps.length - 1].tokenOut, outputs[steps.length - 1], recipients);
+        
         // Return any remaining ETH to the sender
         if (address(this).balance > 0) {
             (bool success, ) = msg.sender.call{value: address(this).balance}("");
@@ -148,29 +214,504 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
         return outputs;
     }
     
+    /**
+     * @dev Execute a multi-step swap, pay everything it produced of the final token out
+     * to several recipients, and sweep what is left of other tokens it touched
+     * @param steps Array of swap steps to execute; with split routes several of them
+     * may end in the final token
+     * @param recipients Receivers of the output, as in multiSwapAndSplit
+     * @param sweepTokens Input and intermediate tokens whose residue is swept
+     * @param dustRecipient Receiver of the swept residue
+     * @return outputs Array of output amounts for each step
+     */
+    function multiSwapAndSweep(
+        SwapStep[] calldata steps,
+        Recipient[] calldata recipients,
+        address[] calldata sweepTokens,
+        address dustRecipient
+    )
+        external
+        payable
+        nonReentrant
+        returns (uint[] memory outputs)
+    {
+        require(steps.length > 0 && steps.length <= MAX_STEPS, "Invalid steps length");
+        require(dustRecipient != address(0), "Invalid dust recipient");
+        _checkRecipients(recipients);
+        
+        // Only what this call adds is paid out or swept, never earlier balances
+        address tokenOut = steps[steps.length - 1].tokenOut;
+        uint outBefore = _balanceOf(tokenOut) - (tokenOut == address(0) ? msg.value : 0);
+        uint[] memory sweepBefore = _balancesOf(sweepTokens);
+        uint ethBefore = address(this).balance - msg.value;
+        
+        _pullInput(steps);
+        
+        outputs = _executeSteps(steps, _routeInput(steps));
+        
+        _payOut(tokenOut, _balanceOf(tokenOut) - outBefore, recipients);
+        
+        _sweep(sweepTokens, sweepBefore, tokenOut, dustRecipient);
+        
+        // Return the ETH this call left over, not ETH held before it, to the sender
+        if (address(this).balance > ethBefore) {
+            (bool success, ) = msg.sender.call{value: address(this).balance - ethBefore}("");
+            require(success, "ETH transfer failed");
+        }
+        
+        return outputs;
+    }
+    
+    /**
+     * @dev Execute a multi-step swap whose gas a sponsor paid, reimbursing the sponsor
+     * a fixed amount of the output and paying the rest to the recipient
+     * @param steps Array of swap steps to execute
+     * @param recipient Receiver of the output net of the gas fee
+     * @param sponsor Account that paid the gas, through its relayer or paymaster
+     * @param gasFee Amount of the output token owed to the sponsor
+     * @return outputs Array of output amounts for each step
+     */
+    function multiSwapAndPayGas(
+        SwapStep[] calldata steps,
+        address recipient,
+        address sponsor,
+        uint gasFee
+    )
+        external
+        payable
+        nonReentrant
+        returns (uint[] memory outputs)
+    {
+        require(steps.length > 0 && steps.length <= MAX_STEPS, "Invalid steps length");
+        require(recipient != address(0) && sponsor != address(0), "Invalid recipient");
+        
+        address tokenOut = steps[steps.length - 1].tokenOut;
+        uint outBefore = _balanceOf(tokenOut) - (tokenOut == address(0) ? msg.value : 0);
+        
+        _pullInput(steps);
+        
+        outputs = _executeSteps(steps, _routeInput(steps));
+        
+        uint amountOut = _balanceOf(tokenOut) - outBefore;
+        require(amountOut > gasFee, "Output below gas fee");
+        _transferOut(tokenOut, sponsor, gasFee);
+        _transferOut(tokenOut, recipient, amountOut - gasFee);
+        
+        // Return any remaining ETH to the sender
+        if (address(this).balance > 0) {
+            (bool success, ) = msg.sender.call{value: address(this).balance}("");
+            require(success, "ETH transfer failed");
+        }
+        
+        return outputs;
+    }
+    
+    /**
+     * @dev Execute a multi-step swap between native ETH and tokens, wrapping the sent
+     * ETH into WETH before the first step and/or unwrapping WETH output after the last.
+     * Everything the steps produced of the final token is paid out, so every leg of
+     * a split route reaches the recipient, and what is left of the input and
+     * intermediate tokens is returned to the sender.
+     * @param steps Array of swap steps to execute
+     * @param recipient Receiver of the output
+     * @param wrapInput Wrap msg.value into WETH; the first step must sell WETH
+     * @param unwrapOutput Unwrap the output; the last step must buy WETH
+     * @return outputs Array of output amounts for each step
+     */
+    function multiSwapNative(
+        SwapStep[] calldata steps,
+        address recipient,
+        bool wrapInput,
+        bool unwrapOutput
+    )
+        external
+        payable
+        nonReentrant
+        returns (uint[] memory outputs)
+    {
+        require(steps.length > 0 && steps.length <= MAX_STEPS, "Invalid steps length");
+        require(recipient != address(0), "Invalid recipient");
+        require(address(weth) != address(0), "WETH not configured");
+        
+        // Only what this call adds is paid out or returned, never earlier balances
+        address tokenOut = steps[steps.length - 1].tokenOut;
+        uint outBefore = _balanceOf(tokenOut);
+        address[] memory stepInputs = _stepInputs(steps);
+        uint[] memory stepInputsBefore = _balancesOf(stepInputs);
+        
+        if (wrapInput) {
+            require(steps[0].tokenIn == address(weth), "First step must sell WETH");
+            require(msg.value >= _inputAmount(steps), "Insufficient ETH sent");
+            weth.deposit{value: msg.value}();
+        } else {
+            _pullInput(steps);
+        }
+        
+        outputs = _executeSteps(steps, _routeInput(steps));
+        
+        uint amountOut = _balanceOf(tokenOut) - outBefore;
+        
+        if (unwrapOutput) {
+            require(tokenOut == address(weth), "Last step must buy WETH");
+            weth.withdraw(amountOut);
+            (bool success, ) = recipient.call{value: amountOut}("");
+            require(success, "ETH transfer failed");
+        } else {
+            IERC20(tokenOut).safeTransfer(recipient, amountOut);
+        }
+        _sweep(stepInputs, stepInputsBefore, tokenOut, msg.sender);
+        
+        // Return any remaining ETH to the sender
+        if (address(this).balance > 0) {
+            (bool success, ) = msg.sender.call{value: address(this).balance}("");
+            require(success, "ETH transfer failed");
+        }
+        
+        return outputs;
+    }
+    
+    /**
+     * @dev Pull several input tokens with one Permit2 batch signature plus EIP-2612
+     * permits for tokens not approved to Permit2, execute a multi-step swap, pay
+     * everything it produced of the final token out to the recipients and return
+     * what is left of the pulled tokens to the sender
+     * @param permitBatch Permit2 allowances signed by the sender, spender must be this contract
+     * @param signature Sender's signature of permitBatch, empty if no Permit2 tokens
+     * @param permits EIP-2612 permits signed by the sender
+     * @param steps Array of swap steps to execute
+     * @param recipients Receivers of the output, as in multiSwapAndSplit
+     * @return outputs Array of output amounts for each step
+     */
+    function permitBatchMultiSwap(
+        IPermit2.PermitBatch calldata permitBatch,
+        bytes calldata signature,
+        Eip2612Permit[] calldata permits,
+        SwapStep[] calldata steps,
+        Recipient[] calldata recipients
+    )
+        external
+        payable
+        nonReentrant
+        returns (uint[] memory outputs)
+    {
+        require(steps.length > 0 && steps.length <= MAX_STEPS, "Invalid steps length");
+        _checkRecipients(recipients);
+        
+        // Only what this call adds is paid out or returned, never earlier balances
+        uint outBefore = _balanceOf(steps[steps.length - 1].tokenOut)
+            - (steps[steps.length - 1].tokenOut == address(0) ? msg.value : 0);
+        (address[] memory inputs, uint[] memory inputsBefore) = _pullPermitted(permitBatch, signature, permits);
+        
+        outputs = _executeSteps(steps, inputs);
+        
+        address tokenOut = steps[steps.length - 1].tokenOut;
+        _payOut(tokenOut, _balanceOf(tokenOut) - outBefore, recipients);
+        _sweep(inputs, inputsBefore, tokenOut, msg.sender);
+        
+        // Return any remaining ETH to the sender
+        if (address(this).balance > 0) {
+            (bool success, ) = msg.sender.call{value: address(this).balance}("");
+            require(success, "ETH transfer failed");
+        }
+        
+        return outputs;
+    }
+    
+    /**
+     * @dev Apply the sender's Permit2 batch and EIP-2612 permits and pull the permitted
+     * amounts
+     * @return tokens Every permitted token, in permit order
+     * @return balancesBefore This contract's balance of each token before the pull
+     */
+    function _pullPermitted(
+        IPermit2.PermitBatch calldata permitBatch,
+        bytes calldata signature,
+        Eip2612Permit[] calldata permits
+    ) internal returns (address[] memory tokens, uint[] memory balancesBefore) {
+        uint batchLength = permitBatch.details.length;
+        tokens = new address[](batchLength + permits.length);
+        for (uint i; i < batchLength; ) {
+            tokens[i] = permitBatch.details[i].token;
+            unchecked { ++i; }
+        }
+        for (uint i; i < permits.length; ) {
+            tokens[batchLength + i] = permits[i].token;
+            unchecked { ++i; }
+        }
+        balancesBefore = _balancesOf(tokens);
+        
+        if (batchLength > 0) {
+            require(address(permit2) != address(0), "Permit2 not configured");
+            require(permitBatch.spender == address(this), "Invalid permit spender");
+            permit2.permit(msg.sender, permitBatch, signature);
+            
+            IPermit2.AllowanceTransferDetails[] memory transfers =
+                new IPermit2.AllowanceTransferDetails[](batchLength);
+            for (uint i; i < batchLength; ) {
+                transfers[i] = IPermit2.AllowanceTransferDetails({
+                    from: msg.sender,
+                    to: address(this),
+                    amount: permitBatch.details[i].amount,
+                    token: permitBatch.details[i].token
+                });
+                unchecked { ++i; }
+            }
+            permit2.transferFrom(transfers);
+        }
+        
+        for (uint i; i < permits.length; ) {
+            Eip2612Permit calldata p = permits[i];
+            IERC20Permit(p.token).permit(msg.sender, address(this), p.value, p.deadline, p.v, p.r, p.s);
+            IERC20(p.token).safeTransferFrom(msg.sender, address(this), p.value);
+            unchecked { ++i; }
+        }
+    }
+    
+    /**
+     * @dev Pull the route's ERC-20 input from the sender, who must have approved this
+     * contract: the amountIn of every step selling the first step's tokenIn, so each
+     * leg of a split route is funded. Native ETH input arrives as msg.value instead.
+     */
+    function _pullInput(SwapStep[] calldata steps) internal {
+        address tokenIn = steps[0].tokenIn;
+        if (tokenIn == address(0)) return;
+        
+        IERC20(tokenIn).safeTransferFrom(msg.sender, address(this), _inputAmount(steps));
+    }
+    
+    /**
+     * @dev Total amountIn of the steps selling the first step's tokenIn
+     */
+    function _inputAmount(SwapStep[] calldata steps) internal pure returns (uint amount) {
+        address tokenIn = steps[0].tokenIn;
+        for (uint i; i < steps.length; ) {
+            if (steps[i].tokenIn == tokenIn) {
+                amount += steps[i].amountIn;
+            }
+            unchecked { ++i; }
+        }
+    }
+    
+    /**
+     * @dev Every token a step sells, the input and intermediates, each listed once
+     */
+    function _stepInputs(SwapStep[] calldata steps) internal pure returns (address[] memory tokens) {
+        address[] memory found = new address[](steps.length);
+        uint count;
+        for (uint i; i < steps.length; ) {
+            bool seen;
+            for (uint j; j < count && !seen; ) {
+                seen = found[j] == steps[i].tokenIn;
+                unchecked { ++j; }
+            }
+            if (!seen) {
+                found[count] = steps[i].tokenIn;
+                ++count;
+            }
+            unchecked { ++i; }
+        }
+        
+        tokens = new address[](count);
+        for (uint i; i < count; ) {
+            tokens[i] = found[i];
+            unchecked { ++i; }
+        }
+    }
+    
+    /**
+     * @dev The route's single input token, the one the first step sells
+     */
+    function _routeInput(SwapStep[] calldata steps) internal pure returns (address[] memory inputs) {
+        inputs = new address[](1);
+        inputs[0] = steps[0].tokenIn;
+    }
+    
+    /**
+     * @dev Require valid recipients whose shares add up to 10000 bps
+     */
+    function _checkRecipients(Recipient[] calldata recipients) internal pure {
+        require(recipients.length > 0, "No recipients");
+        
+        uint totalBps;
+        for (uint i; i < recipients.length; ) {
+            require(recipients[i].account != address(0), "Invalid recipient");
+            totalBps += recipients[i].shareBps;
+            unchecked { ++i; }
+        }
+        require(totalBps == 10000, "Shares must total 10000 bps");
+    }
+    
+    /**
+     * @dev Split an amount of a token between recipients; the last one receives the
+     * rounding remainder
+     */
+    function _payOut(address token, uint amount, Recipient[] calldata recipients) internal {
+        uint remaining = amount;
+        
+        for (uint i; i < recipients.length; ) {
+            uint share = i == recipients.length - 1
+                ? remaining
+                : amount * recipients[i].shareBps / 10000;
+            remaining -= share;
+            _transferOut(token, recipients[i].account, share);
+            unchecked { ++i; }
+        }
+    }
+    
+    function _balanceOf(address token) internal view returns (uint) {
+        return token == address(0) ? address(this).balance : IERC20(token).balanceOf(address(this));
+    }
+    
+    /**
+     * @dev This contract's balance of each token, not counting the ETH sent with the call
+     */
+    function _balancesOf(address[] memory tokens) internal view returns (uint[] memory balances) {
+        balances = new uint[](tokens.length);
+        for (uint i; i < tokens.length; ) {
+            balances[i] = _balanceOf(tokens[i]) - (tokens[i] == address(0) ? msg.value : 0);
+            unchecked { ++i; }
+        }
+    }
+    
+    /**
+     * @dev Send `to` whatever each token's balance grew by since `balancesBefore`,
+     * except for `exclude`, the token already paid out
+     */
+    function _sweep(
+        address[] memory tokens,
+        uint[] memory balancesBefore,
+        address exclude,
+        address to
+    ) internal {
+        for (uint i; i < tokens.length; ) {
+            uint balance = _balanceOf(tokens[i]);
+            if (tokens[i] != exclude && balance > balancesBefore[i]) {
+                _transferOut(tokens[i], to, balance - balancesBefore[i]);
+            }
+            unchecked { ++i; }
+        }
+    }
+    
+    function _transferOut(address token, address to, uint amount) internal {
+        if (token == address(0)) {
+            (bool success, ) = to.call{value: amount}("");
+            require(success, "ETH transfer failed");
+        } else {
+            IERC20(token).safeTransfer(to, amount);
+        }
+    }
+    
+    /**
+     * @dev Execute the steps in order. Steps selling one of the tokens the call pulled
+     * in spend their quoted amountIn. Steps selling a token earlier steps bought spend
+     * what those steps actually delivered and was not spent yet, shared pro rata to
+     * the quoted amountIn of the steps still to sell it, the last of them taking the
+     * rest: a hop filling below its quote within its bound shrinks the hops after it
+     * instead of making them revert. Their amountOutMin scales with what they spend,
+     * except on steps buying the final token, whose amountOutMin bounds the route's output.
+     * @param steps Array of swap steps to execute
+     * @param inputTokens Tokens the call pulled from the sender (or wrapped from msg.value)
+     * @return outputs Array of output amounts for each step
+     */
+    function _executeSteps(SwapStep[] calldata steps, address[] memory inputTokens)
+        internal
+        returns (uint[] memory outputs)
+    {
+        outputs = new uint[](steps.length);
+        uint[] memory spent = new uint[](steps.length);
+        address outputToken = steps[steps.length - 1].tokenOut;
+        
+        for (uint i; i < steps.length; ) {
+            SwapStep calldata step = steps[i];
+            spent[i] = _contains(inputTokens, step.tokenIn)
+                ? step.amountIn
+                : _unspentShare(steps, outputs, spent, i);
+            uint minOut = step.tokenOut == outputToken || step.amountIn == 0
+                ? step.amountOutMin
+                : step.amountOutMin * spent[i] / step.amountIn;
+            outputs[i] = _executeStep(step, spent[i], minOut);
+            unchecked { ++i; }
+        }
+    }
+    
+    function _contains(address[] memory tokens, address token) internal pure returns (bool) {
+        for (uint i; i < tokens.length; ) {
+            if (tokens[i] == token) return true;
+            unchecked { ++i; }
+        }
+        return false;
+    }
+    
+    /**
+     * @dev Share of the not yet spent output of earlier steps that step `index` sells
+     */
+    function _unspentShare(
+        SwapStep[] calldata steps,
+        uint[] memory outputs,
+        uint[] memory spent,
+        uint index
+    ) internal pure returns (uint) {
+        address token = steps[index].tokenIn;
+        uint delivered;
+        uint used;
+        uint quotedLeft;
+        
+        for (uint j; j < steps.length; ) {
+            if (j < index) {
+                if (steps[j].tokenOut == token) delivered += outputs[j];
+                if (steps[j].tokenIn == token) used += spent[j];
+            } else if (steps[j].tokenIn == token) {
+                quotedLeft += steps[j].amountIn;
+            }
+            unchecked { ++j; }
+        }
+        
+        uint available = delivered > used ? delivered - used : 0;
+        if (quotedLeft == steps[index].amountIn) return available;
+        return available * steps[index].amountIn / quotedLeft;
+    }
+    
+    /**
+     * @dev Exchange calldata of a step with its amountIn word set to `amountIn`
+     */
+    function _stepData(SwapStep calldata step, uint amountIn) internal pure returns (bytes memory data) {
+        data = step.data;
+        if (amountIn == step.amountIn) return data;
+        
+        uint offset = step.amountInOffset;
+        require(offset >= 4 && offset + 32 <= data.length, "Step amount not patchable");
+        assembly {
+            mstore(add(add(data, 32), offset), amountIn)
+        }
+    }
+    
     /**
      * @dev Internal function to execute a single swap step
      * @param step The swap step to execute
+     * @param amountIn Amount of tokenIn the step spends
+     * @param minOut Least output the step must deliver
      * @return output The amount of tokens received
      */
-    function _executeStep(SwapStep calldata step) internal returns (uint output) {
+    function _executeStep(SwapStep calldata step, uint amountIn, uint minOut) internal returns (uint output) {
+        bytes memory data = _stepData(step, amountIn);
+        
         // Handle ETH as input
         if (step.tokenIn == address(0)) {
             // Execute swap with ETH
-            (bool success, bytes memory result) = step.exchange.call{value: step.amountIn}(step.data);
+            (bool success, bytes memory result) = step.exchange.call{value: amountIn}(data);
             require(success, "Exchange call failed");
             
             // Parse output amount from result if needed
-            output = step.amountOutMin; // Placeholder, actual implementation would parse result
+            output = minOut; // Placeholder, actual implementation would parse result
         } else {
             // Transfer tokens to the exchange if needed
             uint balanceBefore = IERC20(step.tokenOut).balanceOf(address(this));
             
             // Approve exchange to spend tokens
-            GasSaver._approve(IERC20(step.tokenIn), step.exchange, step.amountIn);
+            GasSaver._approve(IERC20(step.tokenIn), step.exchange, amountIn);
             
             // Execute swap
-            (bool success, ) = step.exchange.call(step.data);
+            (bool success, ) = step.exchange.call(data);
             require(success, "Exchange call failed");
             
             // Calculate output amount
@@ -178,10 +719,10 @@ contract RouterFacet is IDiamondCut, ReentrancyGuard, Ownable {
             output = balanceAfter - balanceBefore;
             
             // Verify minimum output
-            require(output >= step.amountOutMin, "Insufficient output amount");
+            require(output >= minOut, "Insufficient output amount");
         }
         
-        emit Swapped(msg.sender, step.tokenIn, step.tokenOut, step.amountIn, output);
+        emit Swapped(msg.sender, step.tokenIn, step.tokenOut, amountIn, output);
         return output;
     }
     
@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title IPermit2
+ * @dev Subset of Uniswap's Permit2 AllowanceTransfer used by the router
+ */
+interface IPermit2 {
+    struct PermitDetails {
+        address token;
+        uint160 amount;
+        uint48 expiration;
+        uint48 nonce;
+    }
+
+    struct PermitBatch {
+        PermitDetails[] details;
+        address spender;
+        uint256 sigDeadline;
+    }
+
+    struct AllowanceTransferDetails {
+        address from;
+        address to;
+        uint160 amount;
+        address token;
+    }
+
+    /**
+     * @dev Set allowances for several tokens from a single owner signature
+     * @param owner Owner of the tokens
+     * @param permitBatch Allowances granted to the spender
+     * @param signature EIP-712 signature of the batch by the owner
+     */
+    function permit(address owner, PermitBatch memory permitBatch, bytes calldata signature) external;
+
+    /**
+     * @dev Transfer tokens using allowances previously granted to the caller
+     * @param transferDetails Transfers to execute
+     */
+    function transferFrom(AllowanceTransferDetails[] calldata transferDetails) external;
+}
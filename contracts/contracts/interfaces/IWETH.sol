@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.19;
+
+/**
+ * @title IWETH
+ * @dev Wrapped native token
+ */
+interface IWETH {
+    function deposit() external payable;
+
+    function withdraw(uint amount) external;
+}
@@ -0,0 +1,22 @@
+// Bundle the executor's creation bytecode when the contracts have been compiled
+// (`npm run compile` in contracts/). Without the artifact the bundle is empty and
+// deployments need the artifact at runtime (deploy::ExecutorArtifact::from_file).
+use std::path::Path;
+
+const ARTIFACT: &str = "../contracts/artifacts/contracts/core/RouterFacet.sol/RouterFacet.json";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", ARTIFACT);
+
+    let bytecode = std::fs::read_to_string(ARTIFACT)
+        .ok()
+        .and_then(|json| serde_json::from_str::<serde_json::Value>(&json).ok())
+        .and_then(|artifact| artifact["bytecode"].as_str().map(str::to_string))
+        .unwrap_or_default();
+
+    // Only rewrite on change so the crate isn't rebuilt every time
+    let out = Path::new(&std::env::var("OUT_DIR").expect("OUT_DIR not set")).join("executor_bytecode.hex");
+    if std::fs::read_to_string(&out).ok().as_deref() != Some(bytecode.as_str()) {
+        std::fs::write(&out, bytecode).expect("Failed to write executor bytecode");
+    }
+}
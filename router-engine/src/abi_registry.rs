@@ -0,0 +1,265 @@
+use std::path::Path;
+
+use ethers::abi::ethabi::AbiError;
+use ethers::abi::{encode, parse_abi, Abi, Token as AbiToken};
+use ethers::utils::id;
+
+use super::*;
+
+// Selectors of Solidity's built-in Error(string) and Panic(uint256)
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+// Custom errors of the routers and pools the engine talks to
+const KNOWN_ABIS: &[(&str, &[&str])] = &[
+    (
+        "universal_router",
+        &[
+            "error ExecutionFailed(uint256 commandIndex, bytes message)",
+            "error TransactionDeadlinePassed()",
+            "error LengthMismatch()",
+            "error InvalidCommandType(uint256 commandType)",
+            "error V2TooLittleReceived()",
+            "error V2TooMuchRequested()",
+            "error V2InvalidPath()",
+            "error V3TooLittleReceived()",
+            "error V3TooMuchRequested()",
+            "error V3InvalidSwap()",
+            "error V3InvalidAmountOut()",
+            "error V3InvalidCaller()",
+            "error InsufficientToken()",
+            "error InsufficientETH()",
+            "error FromAddressIsNotOwner()",
+        ],
+    ),
+    (
+        "permit2",
+        &[
+            "error AllowanceExpired(uint256 deadline)",
+            "error InsufficientAllowance(uint256 amount)",
+            "error ExcessiveInvalidation()",
+            "error InvalidNonce()",
+            "error InvalidSignature()",
+            "error InvalidSigner()",
+            "error SignatureExpired(uint256 signatureDeadline)",
+        ],
+    ),
+];
+
+// Short require() codes used by Uniswap V3 pools and periphery
+const V3_REVERT_CODES: &[(&str, &str)] = &[
+    ("LOK", "pool is locked (reentrancy)"),
+    ("TLU", "lower tick must be below upper tick"),
+    ("TLM", "lower tick below minimum"),
+    ("TUM", "upper tick above maximum"),
+    ("AS", "amount specified is zero"),
+    ("SPL", "sqrt price limit out of bounds"),
+    ("IIA", "insufficient input amount"),
+    ("TF", "token transfer failed"),
+    ("STF", "token transferFrom failed"),
+    ("ST", "token transfer failed"),
+    ("SA", "token approval failed"),
+    ("STE", "native transfer failed"),
+    ("Too little received", "output below amountOutMinimum"),
+    ("Too much requested", "input above amountInMaximum"),
+    ("Transaction too old", "deadline passed"),
+];
+
+// Registry of contract ABIs, indexed by name and by error selector
+pub struct AbiRegistry {
+    abis: DashMap<String, Abi>,
+    errors: DashMap<[u8; 4], (String, AbiError)>,
+}
+
+impl AbiRegistry {
+    pub fn new() -> Self {
+        Self {
+            abis: DashMap::new(),
+            errors: DashMap::new(),
+        }
+    }
+
+    // Registry preloaded with the built-in router and pool errors
+    pub fn with_known_abis() -> Self {
+        let registry = Self::new();
+        for (name, signatures) in KNOWN_ABIS {
+            let abi = parse_abi(signatures).expect("built-in ABI must parse");
+            registry.register(name, abi);
+        }
+        registry
+    }
+
+    pub fn register(&self, name: &str, abi: Abi) {
+        for error in abi.errors() {
+            let mut selector = [0u8; 4];
+            selector.copy_from_slice(&error.signature()[..4]);
+            self.errors.insert(selector, (name.to_string(), error.clone()));
+        }
+        self.abis.insert(name.to_string(), abi);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Abi> {
+        self.abis.get(name).map(|abi| abi.clone())
+    }
+
+    // Load a JSON ABI, either a bare ABI array or a Hardhat/Foundry artifact with an `abi` field
+    pub fn load_file(&self, name: &str, path: impl AsRef<Path>) -> Result<(), RouterError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| RouterError::ConfigError(format!("Failed to read ABI {}: {}", path.display(), e)))?;
+        let json: serde_json::Value = serde_json::from_str(&contents)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid ABI JSON {}: {}", path.display(), e)))?;
+
+        let abi_json = match json {
+            serde_json::Value::Object(mut artifact) => artifact
+                .remove("abi")
+                .ok_or_else(|| RouterError::ConfigError(format!("No abi field in {}", path.display())))?,
+            other => other,
+        };
+        let abi: Abi = serde_json::from_value(abi_json)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid ABI {}: {}", path.display(), e)))?;
+
+        self.register(name, abi);
+        Ok(())
+    }
+
+    // Load every `*.json` file in `dir`, registered under its file stem
+    pub fn load_dir(&self, dir: impl AsRef<Path>) -> Result<usize, RouterError> {
+        let dir = dir.as_ref();
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| RouterError::ConfigError(format!("Failed to read ABI dir {}: {}", dir.display(), e)))?;
+
+        let mut loaded = 0;
+        for entry in entries {
+            let path = entry
+                .map_err(|e| RouterError::ConfigError(format!("Failed to read ABI dir {}: {}", dir.display(), e)))?
+                .path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                continue;
+            }
+            if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+                self.load_file(name, &path)?;
+                loaded += 1;
+            }
+        }
+
+        info!("Loaded {} ABIs from {}", loaded, dir.display());
+        Ok(loaded)
+    }
+
+    // Dynamic binding of a registered ABI to a deployed contract
+    pub fn bind<M: Middleware>(
+        &self,
+        name: &str,
+        address: &str,
+        client: Arc<M>,
+    ) -> Result<Contract<M>, RouterError> {
+        let abi = self
+            .get(name)
+            .ok_or_else(|| RouterError::ConfigError(format!("No ABI registered as {}", name)))?;
+        Ok(Contract::new(parse_address(address)?, abi, client))
+    }
+
+    // Human-readable description of revert data, or None if it isn't recognised
+    pub fn describe_revert(&self, data: &[u8]) -> Option<String> {
+        if data.len() < 4 {
+            return None;
+        }
+
+        let (selector, payload) = data.split_at(4);
+        if selector == ERROR_STRING_SELECTOR {
+            let reason = match ethers::abi::decode(&[ethers::abi::ParamType::String], payload).ok()?.pop()? {
+                AbiToken::String(reason) => reason,
+                _ => return None,
+            };
+            return Some(describe_reason(&reason));
+        }
+
+        if selector == PANIC_SELECTOR {
+            let code = match ethers::abi::decode(&[ethers::abi::ParamType::Uint(256)], payload).ok()?.pop()? {
+                AbiToken::Uint(code) => code.low_u64(),
+                _ => return None,
+            };
+            return Some(format!("panic 0x{:02x}: {}", code, panic_reason(code)));
+        }
+
+        let mut key = [0u8; 4];
+        key.copy_from_slice(selector);
+        let entry = self.errors.get(&key)?;
+        let (source, error) = entry.value();
+        let args = error.decode(payload).ok()?;
+
+        let rendered: Vec<String> = error
+            .inputs
+            .iter()
+            .zip(args)
+            .map(|(param, arg)| format!("{}: {}", param.name, self.describe_arg(arg)))
+            .collect();
+
+        Some(format!("{}.{}({})", source, error.name, rendered.join(", ")))
+    }
+
+    // Map revert data to the matching RouterError variant
+    pub fn decode_revert(&self, data: &[u8]) -> RouterError {
+        let builtin = data.len() >= 4 && (data[..4] == ERROR_STRING_SELECTOR || data[..4] == PANIC_SELECTOR);
+
+        match self.describe_revert(data) {
+            Some(reason) if builtin => RouterError::Reverted(reason),
+            Some(description) => RouterError::ContractError(description),
+            None => RouterError::Reverted(format!("unrecognised revert data 0x{}", hex::encode(data))),
+        }
+    }
+
+    // Same as decode_revert for hex-encoded data as returned by JSON-RPC nodes
+    pub fn decode_revert_hex(&self, data: &str) -> RouterError {
+        match hex::decode(data.trim_start_matches("0x")) {
+            Ok(bytes) => self.decode_revert(&bytes),
+            Err(_) => RouterError::Reverted(data.to_string()),
+        }
+    }
+
+    // Nested revert data (e.g. ExecutionFailed's message) is decoded in place
+    fn describe_arg(&self, arg: AbiToken) -> String {
+        match arg {
+            AbiToken::Bytes(bytes) => self
+                .describe_revert(&bytes)
+                .unwrap_or_else(|| format!("0x{}", hex::encode(bytes))),
+            other => other.to_string(),
+        }
+    }
+}
+
+impl Default for AbiRegistry {
+    fn default() -> Self {
+        Self::with_known_abis()
+    }
+}
+
+// Selector of `signature` followed by the ABI-encoded arguments
+pub fn encode_call(signature: &str, args: &[AbiToken]) -> Vec<u8> {
+    let mut calldata = id(signature).to_vec();
+    calldata.extend(encode(args));
+    calldata
+}
+
+fn describe_reason(reason: &str) -> String {
+    match V3_REVERT_CODES.iter().find(|(code, _)| *code == reason) {
+        Some((code, description)) => format!("{} ({})", description, code),
+        None => reason.to_string(),
+    }
+}
+
+fn panic_reason(code: u64) -> &'static str {
+    match code {
+        0x01 => "assertion failed",
+        0x11 => "arithmetic overflow or underflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "corrupted storage byte array",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to uninitialised function",
+        _ => "unknown panic code",
+    }
+}
@@ -0,0 +1,253 @@
+use std::panic::AssertUnwindSafe;
+
+use futures::FutureExt;
+use num_traits::Zero;
+
+use super::*;
+
+// Pair and trade sizes a source is exercised with
+#[derive(Debug, Clone)]
+pub struct ConformanceCase {
+    pub token_in: Token,
+    pub token_out: Token,
+    // Ascending trade sizes, all well within the pool's depth
+    pub amounts: Vec<BigUint>,
+}
+
+impl ConformanceCase {
+    // Sizes from 10^-6 to 10^-2 of the pool's input reserve
+    pub async fn for_source(source: &dyn LiquiditySource, token_in: Token, token_out: Token) -> Result<Self, RouterError> {
+        let (reserve_in, _) = source.get_reserves(&token_in, &token_out).await?;
+        let amounts = [1_000_000u32, 100_000, 10_000, 1_000, 100]
+            .into_iter()
+            .map(|divisor| &reserve_in / BigUint::from(divisor))
+            .filter(|amount| !amount.is_zero())
+            .collect();
+        Ok(Self {
+            token_in,
+            token_out,
+            amounts,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    #[serde(default)]
+    pub detail: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConformanceReport {
+    pub checks: Vec<CheckResult>,
+}
+
+impl ConformanceReport {
+    pub fn passed(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub fn failures(&self) -> Vec<&CheckResult> {
+        self.checks.iter().filter(|check| !check.passed).collect()
+    }
+
+    fn record(&mut self, name: &str, outcome: Result<(), String>) {
+        if let Err(detail) = &outcome {
+            debug!("Conformance check {} failed: {}", name, detail);
+        }
+        self.checks.push(CheckResult {
+            name: name.to_string(),
+            passed: outcome.is_ok(),
+            detail: outcome.err(),
+        });
+    }
+}
+
+// Await an adapter call, turning a panic into an error
+async fn guarded<T>(call: impl std::future::Future<Output = Result<T, RouterError>>) -> Result<Result<T, RouterError>, String> {
+    AssertUnwindSafe(call).catch_unwind().await.map_err(|_| "adapter panicked".to_string())
+}
+
+async fn quote(source: &dyn LiquiditySource, token_in: &Token, token_out: &Token, amount: &BigUint) -> Result<(BigUint, f64), String> {
+    guarded(source.get_quote(token_in, token_out, amount))
+        .await?
+        .map_err(|e| format!("quote of {} failed: {}", amount, e))
+}
+
+fn unknown_token(like: &Token) -> Token {
+    Token {
+        address: "0x00000000000000000000000000000000000dEaD1".to_string(),
+        symbol: "UNKNOWN".to_string(),
+        ..like.clone()
+    }
+}
+
+// Exercise a LiquiditySource against the invariants the router relies on:
+// consistent reserves, non-zero quotes that grow with the input at a worsening
+// price and never exceed the pool, sane price impact, no round-trip profit, and
+// errors rather than panics or phantom liquidity for bad input.
+pub async fn run(source: &dyn LiquiditySource, case: &ConformanceCase) -> ConformanceReport {
+    let mut report = ConformanceReport::default();
+    let (token_in, token_out) = (&case.token_in, &case.token_out);
+
+    let reserves = match guarded(source.get_reserves(token_in, token_out)).await {
+        Ok(Ok(reserves)) => Some(reserves),
+        Ok(Err(e)) => {
+            report.record("reserves_available", Err(e.to_string()));
+            None
+        }
+        Err(e) => {
+            report.record("reserves_available", Err(e));
+            None
+        }
+    };
+    if let Some((reserve_in, reserve_out)) = &reserves {
+        report.record(
+            "reserves_available",
+            if reserve_in.is_zero() || reserve_out.is_zero() {
+                Err(format!("empty pool: {} / {}", reserve_in, reserve_out))
+            } else {
+                Ok(())
+            },
+        );
+        let reversed = guarded(source.get_reserves(token_out, token_in)).await;
+        report.record(
+            "reserves_symmetric",
+            match reversed {
+                Ok(Ok((a, b))) if a == *reserve_out && b == *reserve_in => Ok(()),
+                Ok(Ok((a, b))) => Err(format!("reversed pair reports {} / {}", a, b)),
+                Ok(Err(e)) => Err(format!("reversed pair failed: {}", e)),
+                Err(e) => Err(e),
+            },
+        );
+    }
+
+    let mut quotes = Vec::with_capacity(case.amounts.len());
+    let mut quote_errors = Vec::new();
+    for amount in &case.amounts {
+        match quote(source, token_in, token_out, amount).await {
+            Ok((amount_out, impact)) => quotes.push((amount.clone(), amount_out, impact)),
+            Err(e) => quote_errors.push(e),
+        }
+    }
+    report.record(
+        "quotes_nonzero",
+        match (quote_errors.first(), quotes.iter().find(|(_, out, _)| out.is_zero())) {
+            (Some(e), _) => Err(e.clone()),
+            (None, Some((amount, _, _))) => Err(format!("{} in returns nothing", amount)),
+            (None, None) if quotes.is_empty() => Err("no trade sizes given".to_string()),
+            (None, None) => Ok(()),
+        },
+    );
+
+    let mut monotonic = Ok(());
+    let mut diminishing = Ok(());
+    let mut impact = Ok(());
+    for pair in quotes.windows(2) {
+        let ((small_in, small_out, small_impact), (large_in, large_out, large_impact)) = (&pair[0], &pair[1]);
+        if large_out < small_out {
+            monotonic = Err(format!("{} in returns {} but {} in returns {}", large_in, large_out, small_in, small_out));
+        }
+        // large_out / large_in <= small_out / small_in, allowing the small quote a
+        // unit of rounding
+        if large_out * small_in > (small_out + 1u8) * large_in {
+            diminishing = Err(format!("price improves from {} to {} in", small_in, large_in));
+        }
+        if large_impact + 1e-12 < *small_impact {
+            impact = Err(format!("impact falls from {} to {} as the input grows", small_impact, large_impact));
+        }
+    }
+    for (amount, _, price_impact) in &quotes {
+        if !(0.0..=1.0).contains(price_impact) || price_impact.is_nan() {
+            impact = Err(format!("impact {} for {} in is outside [0, 1]", price_impact, amount));
+        }
+    }
+    report.record("quote_monotonic", monotonic);
+    report.record("price_diminishing", diminishing);
+    report.record("impact_bounded", impact);
+
+    if let Some((reserve_in, reserve_out)) = &reserves {
+        let within = quotes
+            .iter()
+            .find(|(_, out, _)| out >= reserve_out)
+            .map_or(Ok(()), |(amount, out, _)| Err(format!("{} in returns {}, more than the pool's {}", amount, out, reserve_out)));
+        report.record("output_within_reserves", within);
+
+        // A trade many times the pool's depth must fail or still stay within it
+        let huge = reserve_in * BigUint::from(1_000u32);
+        report.record(
+            "oversized_trade",
+            match guarded(source.get_quote(token_in, token_out, &huge)).await {
+                Ok(Ok((out, _))) if out >= *reserve_out => Err(format!("{} in returns {}, draining the pool", huge, out)),
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            },
+        );
+    }
+
+    if let Some((amount, amount_out, _)) = quotes.first() {
+        report.record(
+            "no_round_trip_profit",
+            match quote(source, token_out, token_in, amount_out).await {
+                Ok((back, _)) if back > *amount => Err(format!("{} in comes back as {}", amount, back)),
+                Ok(_) => Ok(()),
+                Err(e) => Err(e),
+            },
+        );
+    }
+
+    report.record(
+        "zero_amount",
+        match guarded(source.get_quote(token_in, token_out, &BigUint::zero())).await {
+            Ok(Ok((out, _))) if !out.is_zero() => Err(format!("zero in returns {}", out)),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        },
+    );
+
+    let unknown = unknown_token(token_out);
+    let amount = case.amounts.first().cloned().unwrap_or_else(|| BigUint::from(1u8));
+    report.record(
+        "unknown_pair",
+        match guarded(source.get_quote(token_in, &unknown, &amount)).await {
+            Ok(Ok((out, _))) if !out.is_zero() => Err(format!("quotes {} for a pair it doesn't have", out)),
+            Ok(_) => Ok(()),
+            Err(e) => Err(e),
+        },
+    );
+
+    report.record(
+        "state_block_symmetric",
+        match (source.state_block(token_in, token_out), source.state_block(token_out, token_in)) {
+            (a, b) if a == b => Ok(()),
+            (a, b) => Err(format!("state block {:?} one way, {:?} the other", a, b)),
+        },
+    );
+
+    report
+}
+
+impl RouterEngine {
+    // Register a source only if it passes the conformance suite
+    pub async fn register_certified_source(
+        &self,
+        id: String,
+        source: Arc<dyn LiquiditySource>,
+        case: &ConformanceCase,
+    ) -> Result<ConformanceReport, RouterError> {
+        let report = run(&*source, case).await;
+        if !report.passed() {
+            let failed: Vec<&str> = report.failures().iter().map(|check| check.name.as_str()).collect();
+            return Err(RouterError::ConfigError(format!(
+                "Liquidity source {} failed conformance checks: {}",
+                id,
+                failed.join(", ")
+            )));
+        }
+        info!("Liquidity source {} passed {} conformance checks", id, report.checks.len());
+        self.register_liquidity_source(id, source);
+        Ok(report)
+    }
+}
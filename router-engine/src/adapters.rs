@@ -0,0 +1,597 @@
+use ethers::abi::{ParamType, Token as AbiToken};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use num_traits::{ToPrimitive, Zero};
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::execution::Protocol;
+
+// 2^96, the fixed-point scale of Uniswap V3's sqrtPriceX96
+fn q96() -> BigUint {
+    BigUint::from(1u8) << 96
+}
+
+pub(crate) async fn call<M: Middleware>(client: &M, to: Address, data: Vec<u8>, outputs: &[ParamType]) -> Result<Vec<AbiToken>, RouterError> {
+    call_at(client, to, data, outputs, None).await
+}
+
+// `call` against the state of a given block
+pub(crate) async fn call_at<M: Middleware>(
+    client: &M,
+    to: Address,
+    data: Vec<u8>,
+    outputs: &[ParamType],
+    block: Option<BlockId>,
+) -> Result<Vec<AbiToken>, RouterError> {
+    let tx: TypedTransaction = TransactionRequest::new().to(to).data(data).into();
+    let raw = client
+        .call(&tx, block)
+        .await
+        .map_err(|e| RouterError::ChainError(format!("Call to {:?} failed: {}", to, e)))?;
+    ethers::abi::decode(outputs, &raw)
+        .map_err(|e| RouterError::ChainError(format!("Unexpected return data from {:?}: {}", to, e)))
+}
+
+fn uint(token: &AbiToken) -> Result<BigUint, RouterError> {
+    match token {
+        AbiToken::Uint(value) | AbiToken::Int(value) => Ok(math::from_u256(*value)),
+        other => Err(RouterError::ChainError(format!("Expected an integer, got {:?}", other))),
+    }
+}
+
+fn address(token: &AbiToken) -> Result<Address, RouterError> {
+    match token {
+        AbiToken::Address(value) => Ok(*value),
+        other => Err(RouterError::ChainError(format!("Expected an address, got {:?}", other))),
+    }
+}
+
+// Share of the input lost to moving the price, as in StateBackedSource
+fn reserve_impact(amount_in: &BigUint, reserve_in: &BigUint) -> f64 {
+    let amount = amount_in.to_f64().unwrap_or(0.0);
+    let reserve = reserve_in.to_f64().unwrap_or(0.0);
+    if amount + reserve > 0.0 { amount / (amount + reserve) } else { 1.0 }
+}
+
+fn factory(exchange: &Exchange) -> Result<Address, RouterError> {
+    let factory = exchange
+        .factory_address
+        .as_deref()
+        .ok_or_else(|| RouterError::ConfigError(format!("Exchange {} has no factory address", exchange.id)))?;
+    parse_address(factory)
+}
+
+// Uniswap V2 and its forks, quoted from the pair's reserves
+pub struct UniswapV2Source<M: Middleware> {
+    exchange: Exchange,
+    client: Arc<M>,
+    pairs: DashMap<(Address, Address), Address>,
+}
+
+impl<M: Middleware + 'static> UniswapV2Source<M> {
+    pub fn new(exchange: Exchange, client: Arc<M>) -> Self {
+        Self {
+            exchange,
+            client,
+            pairs: DashMap::new(),
+        }
+    }
+
+    // The fork's fee, 0.3% unless the exchange lists another one
+    fn fee(&self) -> u32 {
+        self.exchange.fee_tiers.first().copied().unwrap_or(math::DEFAULT_FEE_TIER)
+    }
+
+    async fn pair(&self, token0: Address, token1: Address) -> Result<Address, RouterError> {
+        if let Some(pair) = self.pairs.get(&(token0, token1)) {
+            return Ok(*pair);
+        }
+
+        let data = encode_call("getPair(address,address)", &[AbiToken::Address(token0), AbiToken::Address(token1)]);
+        let result = call(&*self.client, factory(&self.exchange)?, data, &[ParamType::Address]).await?;
+        let pair = address(&result[0])?;
+        if pair.is_zero() {
+            return Err(RouterError::InsufficientLiquidity(format!(
+                "No {} pair for {:?}/{:?}",
+                self.exchange.id, token0, token1
+            )));
+        }
+
+        self.pairs.insert((token0, token1), pair);
+        Ok(pair)
+    }
+
+    async fn oriented_reserves(&self, token_in: &Token, token_out: &Token) -> Result<(BigUint, BigUint), RouterError> {
+        let token_in = parse_address(&token_in.address)?;
+        let token_out = parse_address(&token_out.address)?;
+        let (token0, token1) = if token_in < token_out { (token_in, token_out) } else { (token_out, token_in) };
+
+        let pair = self.pair(token0, token1).await?;
+        let result = call(
+            &*self.client,
+            pair,
+            encode_call("getReserves()", &[]),
+            &[ParamType::Uint(112), ParamType::Uint(112), ParamType::Uint(32)],
+        )
+        .await?;
+        let (reserve0, reserve1) = (uint(&result[0])?, uint(&result[1])?);
+
+        Ok(if token_in == token0 { (reserve0, reserve1) } else { (reserve1, reserve0) })
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> LiquiditySource for UniswapV2Source<M> {
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let (reserve_in, reserve_out) = self.oriented_reserves(token_in, token_out).await?;
+        let amount_out = math::get_amount_out(amount_in, &reserve_in, &reserve_out, self.fee());
+        Ok((amount_out, reserve_impact(amount_in, &reserve_in)))
+    }
+
+    async fn get_reserves(
+        &self,
+        token_a: &Token,
+        token_b: &Token,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        self.oriented_reserves(token_a, token_b).await
+    }
+}
+
+// In-range state of one Uniswap V3 pool
+#[derive(Debug, Clone)]
+struct V3Pool {
+    address: Address,
+    fee: u32,
+    sqrt_price_x96: BigUint,
+    liquidity: BigUint,
+}
+
+impl V3Pool {
+    fn virtual_reserves(&self) -> (BigUint, BigUint) {
+        v3_virtual_reserves(&self.liquidity, &self.sqrt_price_x96)
+    }
+}
+
+// Virtual reserves (token0, token1) backing a V3 pool's in-range liquidity
+pub fn v3_virtual_reserves(liquidity: &BigUint, sqrt_price_x96: &BigUint) -> (BigUint, BigUint) {
+    if sqrt_price_x96.is_zero() {
+        return (BigUint::zero(), BigUint::zero());
+    }
+    (liquidity * q96() / sqrt_price_x96, liquidity * sqrt_price_x96 / q96())
+}
+
+// Uniswap V3 and its forks, one pool per fee tier of the exchange. Quotes go
+// through QuoterV2, crossing ticks exactly; the current tick's virtual reserves
+// hold only until the next initialized tick, so they are never used to quote.
+pub struct UniswapV3Source<M: Middleware> {
+    exchange: Exchange,
+    client: Arc<M>,
+    quoter: Address,
+    pools: DashMap<(Address, Address, u32), Option<Address>>,
+}
+
+impl<M: Middleware + 'static> UniswapV3Source<M> {
+    pub fn new(exchange: Exchange, client: Arc<M>, quoter: &str) -> Result<Self, RouterError> {
+        Ok(Self {
+            exchange,
+            client,
+            quoter: parse_address(quoter)?,
+            pools: DashMap::new(),
+        })
+    }
+
+    async fn pool_address(&self, token0: Address, token1: Address, fee: u32) -> Result<Option<Address>, RouterError> {
+        if let Some(pool) = self.pools.get(&(token0, token1, fee)) {
+            return Ok(*pool);
+        }
+
+        let data = encode_call(
+            "getPool(address,address,uint24)",
+            &[AbiToken::Address(token0), AbiToken::Address(token1), AbiToken::Uint(U256::from(fee))],
+        );
+        let result = call(&*self.client, factory(&self.exchange)?, data, &[ParamType::Address]).await?;
+        let pool = Some(address(&result[0])?).filter(|p| !p.is_zero());
+
+        self.pools.insert((token0, token1, fee), pool);
+        Ok(pool)
+    }
+
+    async fn pool_state(&self, address: Address, fee: u32) -> Result<V3Pool, RouterError> {
+        let slot0 = call(
+            &*self.client,
+            address,
+            encode_call("slot0()", &[]),
+            &[
+                ParamType::Uint(160),
+                ParamType::Int(24),
+                ParamType::Uint(16),
+                ParamType::Uint(16),
+                ParamType::Uint(16),
+                ParamType::Uint(8),
+                ParamType::Bool,
+            ],
+        )
+        .await?;
+        let liquidity = call(&*self.client, address, encode_call("liquidity()", &[]), &[ParamType::Uint(128)]).await?;
+
+        Ok(V3Pool {
+            address,
+            fee,
+            sqrt_price_x96: uint(&slot0[0])?,
+            liquidity: uint(&liquidity[0])?,
+        })
+    }
+
+    // Pools of every configured fee tier for the pair, with token0 first
+    async fn pools(&self, token_a: &Token, token_b: &Token) -> Result<(Address, Vec<V3Pool>), RouterError> {
+        let a = parse_address(&token_a.address)?;
+        let b = parse_address(&token_b.address)?;
+        let (token0, token1) = if a < b { (a, b) } else { (b, a) };
+
+        let mut pools = Vec::new();
+        for &fee in &self.exchange.fee_tiers {
+            if let Some(address) = self.pool_address(token0, token1, fee).await? {
+                let pool = self.pool_state(address, fee).await?;
+                if !pool.liquidity.is_zero() {
+                    pools.push(pool);
+                }
+            }
+        }
+
+        if pools.is_empty() {
+            return Err(RouterError::InsufficientLiquidity(format!(
+                "No {} pool for {}/{}",
+                self.exchange.id, token_a.symbol, token_b.symbol
+            )));
+        }
+        Ok((token0, pools))
+    }
+
+    // QuoterV2.quoteExactInputSingle: output and the pool's sqrt price after the swap
+    async fn quoter_output(
+        &self,
+        quoter: Address,
+        token_in: Address,
+        token_out: Address,
+        amount_in: &BigUint,
+        fee: u32,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        let data = encode_call(
+            "quoteExactInputSingle((address,address,uint256,uint24,uint160))",
+            &[AbiToken::Tuple(vec![
+                AbiToken::Address(token_in),
+                AbiToken::Address(token_out),
+                AbiToken::Uint(math::to_u256(amount_in)?),
+                AbiToken::Uint(U256::from(fee)),
+                AbiToken::Uint(U256::zero()),
+            ])],
+        );
+        let result = call(
+            &*self.client,
+            quoter,
+            data,
+            &[ParamType::Uint(256), ParamType::Uint(160), ParamType::Uint(32), ParamType::Uint(256)],
+        )
+        .await?;
+        Ok((uint(&result[0])?, uint(&result[1])?))
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> LiquiditySource for UniswapV3Source<M> {
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let (amount_out, price_impact, _) = self.get_quote_with_fee_tier(token_in, token_out, amount_in).await?;
+        Ok((amount_out, price_impact))
+    }
+
+    // Best output across the exchange's fee tiers, and the tier giving it
+    async fn get_quote_with_fee_tier(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64, Option<u32>), RouterError> {
+        let (_, pools) = self.pools(token_in, token_out).await?;
+        let address_in = parse_address(&token_in.address)?;
+        let address_out = parse_address(&token_out.address)?;
+
+        let mut best: Option<(BigUint, f64, Option<u32>)> = None;
+        for pool in pools {
+            let quote = match self.quoter_output(self.quoter, address_in, address_out, amount_in, pool.fee).await {
+                Ok((amount_out, sqrt_after)) => {
+                    // Price moves with the square of sqrtPriceX96
+                    let moved = math::ratio(&sqrt_after, &pool.sqrt_price_x96).powi(2);
+                    (amount_out, (1.0 - moved).abs().min(1.0))
+                }
+                Err(e) => {
+                    debug!("Quoter failed for {:?} at fee {}: {}", pool.address, pool.fee, e);
+                    continue;
+                }
+            };
+
+            if !matches!(&best, Some((best_out, ..)) if *best_out >= quote.0) {
+                best = Some((quote.0, quote.1, Some(pool.fee)));
+            }
+        }
+
+        best.ok_or_else(|| {
+            RouterError::InsufficientLiquidity(format!(
+                "No {} quote for {}/{}",
+                self.exchange.id, token_in.symbol, token_out.symbol
+            ))
+        })
+    }
+
+    // Virtual reserves of the deepest fee tier
+    async fn get_reserves(
+        &self,
+        token_a: &Token,
+        token_b: &Token,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        let (token0, pools) = self.pools(token_a, token_b).await?;
+        let deepest = pools
+            .into_iter()
+            .max_by(|a, b| a.liquidity.cmp(&b.liquidity))
+            .ok_or_else(|| RouterError::InsufficientLiquidity(format!("No {} pool", self.exchange.id)))?;
+
+        let (reserve0, reserve1) = deepest.virtual_reserves();
+        Ok(if parse_address(&token_a.address)? == token0 { (reserve0, reserve1) } else { (reserve1, reserve0) })
+    }
+}
+
+// Curve StableSwap pool; the exchange's router_address is the pool itself
+pub struct CurvePoolSource<M: Middleware> {
+    exchange: Exchange,
+    client: Arc<M>,
+    coins: tokio::sync::OnceCell<Vec<Address>>,
+}
+
+impl<M: Middleware + 'static> CurvePoolSource<M> {
+    pub fn new(exchange: Exchange, client: Arc<M>) -> Self {
+        Self {
+            exchange,
+            client,
+            coins: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    fn pool(&self) -> Result<Address, RouterError> {
+        parse_address(&self.exchange.router_address)
+    }
+
+    // Pool coins, read with coins(i) until the index runs out
+    async fn coins(&self) -> Result<&Vec<Address>, RouterError> {
+        self.coins
+            .get_or_try_init(|| async {
+                let pool = self.pool()?;
+                let mut coins = Vec::new();
+                // Curve pools hold at most 8 coins
+                for i in 0..8u64 {
+                    let data = encode_call("coins(uint256)", &[AbiToken::Uint(U256::from(i))]);
+                    match call(&*self.client, pool, data, &[ParamType::Address]).await {
+                        Ok(result) => coins.push(address(&result[0])?),
+                        Err(_) => break,
+                    }
+                }
+                if coins.len() < 2 {
+                    return Err(RouterError::ConfigError(format!("{} is not a Curve pool", self.exchange.router_address)));
+                }
+                Ok(coins)
+            })
+            .await
+    }
+
+    async fn indices(&self, token_in: &Token, token_out: &Token) -> Result<(usize, usize), RouterError> {
+        let coins = self.coins().await?;
+        let index = |token: &Token| -> Result<usize, RouterError> {
+            let address = parse_address(&token.address)?;
+            coins.iter().position(|coin| *coin == address).ok_or_else(|| {
+                RouterError::InsufficientLiquidity(format!("{} does not hold {}", self.exchange.id, token.symbol))
+            })
+        };
+        Ok((index(token_in)?, index(token_out)?))
+    }
+
+    async fn get_dy(&self, i: usize, j: usize, dx: &BigUint) -> Result<BigUint, RouterError> {
+        let data = encode_call(
+            "get_dy(int128,int128,uint256)",
+            &[
+                AbiToken::Int(U256::from(i)),
+                AbiToken::Int(U256::from(j)),
+                AbiToken::Uint(math::to_u256(dx)?),
+            ],
+        );
+        let result = call(&*self.client, self.pool()?, data, &[ParamType::Uint(256)]).await?;
+        uint(&result[0])
+    }
+
+    async fn balance(&self, i: usize) -> Result<BigUint, RouterError> {
+        let data = encode_call("balances(uint256)", &[AbiToken::Uint(U256::from(i))]);
+        let result = call(&*self.client, self.pool()?, data, &[ParamType::Uint(256)]).await?;
+        uint(&result[0])
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> LiquiditySource for CurvePoolSource<M> {
+    // get_dy works in each coin's own decimals; price impact compares the trade's
+    // rate with the rate of one whole input token, so the decimals cancel out
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let (i, j) = self.indices(token_in, token_out).await?;
+        let amount_out = self.get_dy(i, j, amount_in).await?;
+
+        let unit = BigUint::from(10u8).pow(token_in.decimals as u32);
+        let probe = if *amount_in < unit { amount_in.clone() } else { unit };
+        let price_impact = if probe.is_zero() {
+            0.0
+        } else {
+            let marginal = math::ratio(&self.get_dy(i, j, &probe).await?, &probe);
+            let realized = math::ratio(&amount_out, amount_in);
+            if marginal > 0.0 { (1.0 - realized / marginal).max(0.0) } else { 1.0 }
+        };
+
+        Ok((amount_out, price_impact))
+    }
+
+    async fn get_reserves(
+        &self,
+        token_a: &Token,
+        token_b: &Token,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        let (i, j) = self.indices(token_a, token_b).await?;
+        Ok((self.balance(i).await?, self.balance(j).await?))
+    }
+}
+
+// RPC-backed source for an exchange, chosen by its protocol. `quoter` is the
+// Uniswap V3 QuoterV2, required for V3 exchanges.
+pub fn rpc_source<M: Middleware + 'static>(
+    exchange: &Exchange,
+    client: Arc<M>,
+    quoter: Option<&str>,
+) -> Result<Arc<dyn LiquiditySource>, RouterError> {
+    let protocol = exchange
+        .protocol
+        .ok_or_else(|| RouterError::ConfigError(format!("Exchange {} has no protocol", exchange.id)))?;
+    Ok(match protocol {
+        Protocol::UniswapV2 => Arc::new(UniswapV2Source::new(exchange.clone(), client)),
+        Protocol::UniswapV3 => {
+            let quoter = quoter.ok_or_else(|| {
+                RouterError::ConfigError(format!("Uniswap V3 exchange {} needs a QuoterV2 address", exchange.id))
+            })?;
+            Arc::new(UniswapV3Source::new(exchange.clone(), client, quoter)?)
+        }
+        Protocol::Curve => Arc::new(CurvePoolSource::new(exchange.clone(), client)),
+    })
+}
+
+#[derive(Serialize)]
+struct RemoteQuoteRequest<'a> {
+    token_in: &'a Token,
+    token_out: &'a Token,
+    amount_in: String,
+}
+
+#[derive(Deserialize)]
+struct RemoteQuote {
+    amount_out: String,
+    #[serde(default)]
+    price_impact: f64,
+}
+
+#[derive(Serialize)]
+struct RemoteReservesRequest<'a> {
+    token_a: &'a Token,
+    token_b: &'a Token,
+}
+
+#[derive(Deserialize)]
+struct RemoteReserves {
+    reserve_a: String,
+    reserve_b: String,
+}
+
+// Quotes from an HTTP service, for clients (e.g. browsers) that can't reach an
+// RPC node or would rather not. POST {url}/quote takes {token_in, token_out,
+// amount_in} and answers {amount_out, price_impact}; POST {url}/reserves takes
+// {token_a, token_b} and answers {reserve_a, reserve_b}. Amounts are decimal strings.
+pub struct RemoteSource {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl RemoteSource {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn post<T: Serialize, R: serde::de::DeserializeOwned>(&self, path: &str, body: &T) -> Result<R, RouterError> {
+        let url = format!("{}/{}", self.url, path);
+        self.client
+            .post(&url)
+            .json(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RouterError::ChainError(format!("Remote source {} failed: {}", url, e)))?
+            .json()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Invalid response from {}: {}", url, e)))
+    }
+}
+
+#[async_trait]
+impl LiquiditySource for RemoteSource {
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let request = RemoteQuoteRequest {
+            token_in,
+            token_out,
+            amount_in: amount_in.to_string(),
+        };
+        let quote: RemoteQuote = self.post("quote", &request).await?;
+        Ok((math::parse_amount(&quote.amount_out)?, quote.price_impact))
+    }
+
+    async fn get_reserves(
+        &self,
+        token_a: &Token,
+        token_b: &Token,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        let reserves: RemoteReserves = self.post("reserves", &RemoteReservesRequest { token_a, token_b }).await?;
+        Ok((math::parse_amount(&reserves.reserve_a)?, math::parse_amount(&reserves.reserve_b)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(protocol: Protocol) -> Exchange {
+        Exchange {
+            id: "uniswap_v3".to_string(),
+            name: "Uniswap V3".to_string(),
+            chain_id: 1,
+            router_address: "0xE592427A0AEce92De3Edee1F18E0157C05861564".to_string(),
+            factory_address: Some("0x1F98431c8aD98523631AE4a59f267346ea31F984".to_string()),
+            fee_tiers: vec![500, 3000],
+            router_abi: None,
+            protocol: Some(protocol),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn v3_sources_need_a_quoter() {
+        let client = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+
+        let missing = rpc_source(&exchange(Protocol::UniswapV3), client.clone(), None);
+        assert!(matches!(missing, Err(RouterError::ConfigError(_))));
+
+        let quoter = Some("0x61fFE014bA17989E743c5F6cB21bF9697530B21e");
+        assert!(rpc_source(&exchange(Protocol::UniswapV3), client.clone(), quoter).is_ok());
+        assert!(rpc_source(&exchange(Protocol::UniswapV2), client, None).is_ok());
+    }
+}
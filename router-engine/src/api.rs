@@ -0,0 +1,105 @@
+use serde_json::Value;
+
+use super::*;
+
+// Wire schema of quote requests and responses served by the server and the
+// WASM and Python bindings. Requests without a schema_version are read as v1,
+// the schema integrators used before versioning; responses echo the request's
+// version.
+pub const SCHEMA_VERSION: u32 = 2;
+pub const LEGACY_SCHEMA_VERSION: u32 = 1;
+
+// A request or response body tagged with its schema version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+// v1 types take the current ones' fields wherever the wire shape still matches
+// and only differ in what v2 changed. When a later change breaks v1's shape,
+// give it a frozen copy of the affected type here instead.
+pub mod v1 {
+    use super::*;
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    pub struct QuoteRequest {
+        #[serde(flatten)]
+        pub request: crate::QuoteRequest,
+    }
+
+    // v1 had no per-chain MEV defaults: an absent mev_policy meant the public mempool
+    impl From<QuoteRequest> for crate::QuoteRequest {
+        fn from(v1: QuoteRequest) -> Self {
+            let mut request = v1.request;
+            request.mev_policy.get_or_insert(mev::MevPolicy::PublicMempool);
+            request
+        }
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct QuoteResponse {
+        #[serde(flatten)]
+        pub response: crate::QuoteResponse,
+    }
+
+    impl From<crate::QuoteResponse> for QuoteResponse {
+        fn from(response: crate::QuoteResponse) -> Self {
+            Self { response }
+        }
+    }
+}
+
+// The current schema, which the engine's own types serialize as
+pub mod v2 {
+    pub use crate::{QuoteRequest, QuoteResponse, SwapRoute};
+}
+
+fn schema_version(value: &Value) -> Result<u32, RouterError> {
+    let version = match value.get("schema_version") {
+        None | Some(Value::Null) => return Ok(LEGACY_SCHEMA_VERSION),
+        Some(version) => version.as_u64(),
+    };
+    match version {
+        Some(version) if (LEGACY_SCHEMA_VERSION as u64..=SCHEMA_VERSION as u64).contains(&version) => Ok(version as u32),
+        _ => Err(RouterError::ConfigError(format!(
+            "Unsupported schema_version {}, expected {} to {}",
+            value["schema_version"], LEGACY_SCHEMA_VERSION, SCHEMA_VERSION
+        ))),
+    }
+}
+
+fn invalid_request(e: serde_json::Error) -> RouterError {
+    RouterError::ConfigError(format!("Invalid quote request: {}", e))
+}
+
+// A quote request of any supported schema version, as the engine's QuoteRequest
+// along with the version to answer in
+pub fn decode_quote_request(value: Value) -> Result<(u32, QuoteRequest), RouterError> {
+    let version = schema_version(&value)?;
+    let request = match version {
+        LEGACY_SCHEMA_VERSION => serde_json::from_value::<v1::QuoteRequest>(value).map_err(invalid_request)?.into(),
+        _ => serde_json::from_value::<v2::QuoteRequest>(value).map_err(invalid_request)?,
+    };
+    Ok((version, request))
+}
+
+pub fn parse_quote_request(json: &str) -> Result<(u32, QuoteRequest), RouterError> {
+    decode_quote_request(serde_json::from_str(json).map_err(invalid_request)?)
+}
+
+// The engine's QuoteResponse in the given schema version
+pub fn encode_quote_response(schema_version: u32, response: QuoteResponse) -> Result<Value, RouterError> {
+    let encoded = match schema_version {
+        LEGACY_SCHEMA_VERSION => serde_json::to_value(Envelope {
+            schema_version,
+            body: v1::QuoteResponse::from(response),
+        }),
+        _ => serde_json::to_value(Envelope {
+            schema_version,
+            body: response,
+        }),
+    };
+    encoded.map_err(|e| RouterError::ExecutionError(format!("Failed to encode quote response: {}", e)))
+}
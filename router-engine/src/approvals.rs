@@ -0,0 +1,106 @@
+use super::*;
+use crate::execution::{segments, Protocol};
+
+// Allowance a wallet grants so `spender` can pull `token`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenApproval {
+    pub token: String,
+    pub spender: String,
+}
+
+// The one approval a route's transaction needs from the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalPlan {
+    // Contract the transaction is sent to, which pulls the input
+    pub spender: String,
+    pub via_executor: bool,
+    // Input token to approve; None for native input, which needs no approval
+    pub token: Option<String>,
+    // What the transaction pulls: the first step's input from a router, every
+    // leg's input from the executor
+    pub amount: String,
+    // Approvals calling each leg's router or RFQ settlement contract directly
+    // would have needed on top of this one
+    #[serde(default)]
+    pub avoided_approvals: Vec<TokenApproval>,
+}
+
+impl RouterEngine {
+    // Exchange whose router can take the whole route in one call, making it the
+    // only spender. Routes over several contracts, split routes, native wrapping
+    // and RFQ settlement (no router protocol) go through the executor instead.
+    pub(crate) fn direct_exchange(&self, route: &SwapRoute, chain_id: u64) -> Option<Exchange> {
+        let [segment] = segments(route)[..] else {
+            return None;
+        };
+        if !route.splits.is_empty() || route.native_wrapping.is_some() || route.payouts.is_some() {
+            return None;
+        }
+        // The executor reimburses a gas sponsor out of the output
+        if matches!(&route.gas_payment, Some(payment) if matches!(payment.mode, gas_payment::GasPaymentMode::Sponsored { .. })) {
+            return None;
+        }
+        if segment.iter().any(|step| executor::is_native(&step.token_in) || executor::is_native(&step.token_out)) {
+            return None;
+        }
+        let exchange = self.exchange(chain_id, &segment.first()?.exchange_id).ok()?;
+        matches!(exchange.protocol, Some(Protocol::UniswapV2 | Protocol::UniswapV3)).then_some(exchange)
+    }
+
+    // Approvals sending each run of same-exchange steps to its contract directly
+    // would take: the user holds every intermediate token in between. Steps of
+    // exchanges the engine has no contract for are left out.
+    pub fn direct_approvals(&self, route: &SwapRoute, chain_id: u64) -> Vec<TokenApproval> {
+        let mut approvals: Vec<TokenApproval> = Vec::new();
+        for segment in segments(route) {
+            let Some(first) = segment.first() else {
+                continue;
+            };
+            let Ok(exchange) = self.exchange(chain_id, &first.exchange_id) else {
+                continue;
+            };
+            if executor::is_native(&first.token_in) {
+                continue;
+            }
+            let approval = TokenApproval {
+                token: first.token_in.address.to_lowercase(),
+                spender: exchange.router_address.to_lowercase(),
+            };
+            if !approvals.contains(&approval) {
+                approvals.push(approval);
+            }
+        }
+        approvals
+    }
+
+    // Where the route's transaction goes and the single approval it needs. Routes
+    // that would need approvals to more than one contract (an RFQ settlement
+    // contract and an AMM router, say) run through the executor, which pulls the
+    // input once (executor::pulled_input) and approves the legs itself. None when
+    // the route can't be executed on this chain (no executor registered).
+    pub fn approval_plan(&self, route: &SwapRoute, chain_id: u64) -> Option<ApprovalPlan> {
+        let first = route.steps.first()?;
+        let token = (!executor::is_native(&first.token_in)).then(|| first.token_in.address.to_lowercase());
+
+        let (spender, via_executor, amount) = match self.direct_exchange(route, chain_id) {
+            Some(exchange) => (exchange.router_address.to_lowercase(), false, first.amount_in.clone()),
+            None => {
+                let amount = executor::pulled_input(&route.steps).ok()?.unwrap_or_default();
+                (self.executor(chain_id).ok()?.to_lowercase(), true, amount.to_string())
+            }
+        };
+        let avoided_approvals = self
+            .direct_approvals(route, chain_id)
+            .into_iter()
+            .filter(|approval| !(approval.spender == spender && Some(&approval.token) == token.as_ref()))
+            .collect();
+
+        Some(ApprovalPlan {
+            spender,
+            via_executor,
+            token,
+            amount,
+            avoided_approvals,
+        })
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+
+// EIP-191 signature over a quote, proving which deployment produced it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteAttestation {
+    pub signer: String,
+    pub issued_at: u64,
+    // keccak256 of issued_at (8 bytes, big endian) followed by the response's
+    // canonical JSON (attestation unset, keys sorted)
+    pub digest: String,
+    pub signature: String,
+}
+
+// The response exactly as it was signed
+pub fn quote_digest(response: &QuoteResponse, issued_at: u64) -> Result<H256, RouterError> {
+    let mut unsigned = response.clone();
+    unsigned.attestation = None;
+    // Going through Value sorts object keys, so the bytes don't depend on field order
+    let canonical = serde_json::to_value(&unsigned)
+        .and_then(|value| serde_json::to_vec(&value))
+        .map_err(|e| RouterError::ExecutionError(format!("Failed to serialize quote: {}", e)))?;
+
+    let mut payload = issued_at.to_be_bytes().to_vec();
+    payload.extend(canonical);
+    Ok(H256::from(ethers::utils::keccak256(payload)))
+}
+
+pub fn sign_quote(wallet: &LocalWallet, response: &QuoteResponse) -> Result<QuoteAttestation, RouterError> {
+    let issued_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let digest = quote_digest(response, issued_at)?;
+    let signature = wallet
+        .sign_hash(ethers::utils::hash_message(digest))
+        .map_err(|e| RouterError::ConfigError(format!("Failed to sign quote: {}", e)))?;
+
+    Ok(QuoteAttestation {
+        signer: format!("{:?}", wallet.address()),
+        issued_at,
+        digest: format!("{:?}", digest),
+        signature: format!("0x{}", signature),
+    })
+}
+
+// Check the attestation against the response it came with and, if given, the
+// expected signer; returns the signer
+pub fn verify_quote(response: &QuoteResponse, expected_signer: Option<&str>) -> Result<Address, RouterError> {
+    let attestation = response
+        .attestation
+        .as_ref()
+        .ok_or_else(|| RouterError::ExecutionError("Quote is not signed".to_string()))?;
+
+    let digest = quote_digest(response, attestation.issued_at)?;
+    if format!("{:?}", digest) != attestation.digest.to_lowercase() {
+        return Err(RouterError::ExecutionError("Quote was modified after signing".to_string()));
+    }
+
+    let signature: Signature = attestation
+        .signature
+        .trim_start_matches("0x")
+        .parse()
+        .map_err(|_| RouterError::ExecutionError(format!("Invalid quote signature: {}", attestation.signature)))?;
+    let signer = signature
+        .recover(ethers::utils::hash_message(digest))
+        .map_err(|e| RouterError::ExecutionError(format!("Failed to recover quote signer: {}", e)))?;
+
+    if signer != parse_address(&attestation.signer)? {
+        return Err(RouterError::ExecutionError(format!(
+            "Quote signed by {:?}, not the claimed {}",
+            signer, attestation.signer
+        )));
+    }
+    if let Some(expected) = expected_signer {
+        if signer != parse_address(expected)? {
+            return Err(RouterError::ExecutionError(format!(
+                "Quote signed by {:?}, expected {}",
+                signer, expected
+            )));
+        }
+    }
+    Ok(signer)
+}
+
+impl RouterEngine {
+    // Sign every QuoteResponse with this key; None stops signing
+    pub fn set_quote_signer(&self, wallet: Option<LocalWallet>) {
+        *self.quote_signer.write().unwrap() = wallet;
+    }
+
+    pub fn quote_signer(&self) -> Option<Address> {
+        self.quote_signer.read().unwrap().as_ref().map(|wallet| wallet.address())
+    }
+
+    pub(crate) fn attest(&self, response: &mut QuoteResponse) -> Result<(), RouterError> {
+        if let Some(wallet) = self.quote_signer.read().unwrap().as_ref() {
+            response.attestation = Some(sign_quote(wallet, response)?);
+        }
+        Ok(())
+    }
+}
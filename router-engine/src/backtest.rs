@@ -0,0 +1,187 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::*;
+use crate::bus::MessageBus;
+use crate::events::{EngineEvent, EventEnvelope};
+use crate::state::{PoolState, PoolStateStore, StateBackedSource};
+
+// Historical pool states, e.g. the pool_update events kept by analytics
+#[async_trait]
+pub trait PoolHistory: Send + Sync {
+    // Latest state of every pool as of `block_number`
+    async fn states_at(&self, chain_id: u64, block_number: u64) -> Result<Vec<PoolState>, RouterError>;
+}
+
+// Pool states held in memory, ordered by block per pool
+#[derive(Debug, Default)]
+pub struct RecordedHistory {
+    pools: HashMap<(u64, String), Vec<PoolState>>,
+}
+
+impl RecordedHistory {
+    pub fn from_states(states: impl IntoIterator<Item = PoolState>) -> Self {
+        let mut history = Self::default();
+        for state in states {
+            history.record(state);
+        }
+        history
+    }
+
+    pub fn record(&mut self, state: PoolState) {
+        let states = self.pools.entry((state.chain_id, state.pool.to_lowercase())).or_default();
+        let position = states.partition_point(|s| s.block_number <= state.block_number);
+        states.insert(position, state);
+    }
+
+    // One JSON object per line, either a PoolState or a pool_update event envelope
+    pub fn load_jsonl(path: impl AsRef<Path>) -> Result<Self, RouterError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| RouterError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+
+        let mut history = Self::default();
+        for (number, line) in contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            match parse_state(line.as_bytes()) {
+                Some(state) => history.record(state),
+                None => warn!("Skipping line {} of {}: not a pool state", number + 1, path.display()),
+            }
+        }
+        Ok(history)
+    }
+
+    // Drain the pool_update events of a bus topic, starting at `cursor`
+    // (for Redis streams "0" is the beginning)
+    pub async fn load_bus(bus: &dyn MessageBus, topic: &str, cursor: &str) -> Result<Self, RouterError> {
+        let mut history = Self::default();
+        let mut cursor = Some(cursor.to_string());
+        loop {
+            let (messages, next) = bus.poll(topic, cursor.as_deref(), 1_000).await?;
+            if messages.is_empty() {
+                break;
+            }
+            for message in messages {
+                if let Some(state) = parse_state(&message) {
+                    history.record(state);
+                }
+            }
+            cursor = next;
+        }
+        Ok(history)
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.values().map(|states| states.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+}
+
+fn parse_state(payload: &[u8]) -> Option<PoolState> {
+    if let Ok(EventEnvelope { event: EngineEvent::PoolUpdate { state }, .. }) = serde_json::from_slice(payload) {
+        return Some(state);
+    }
+    serde_json::from_slice(payload).ok()
+}
+
+#[async_trait]
+impl PoolHistory for RecordedHistory {
+    async fn states_at(&self, chain_id: u64, block_number: u64) -> Result<Vec<PoolState>, RouterError> {
+        Ok(self
+            .pools
+            .iter()
+            .filter(|((chain, _), _)| *chain == chain_id)
+            .filter_map(|(_, states)| {
+                let position = states.partition_point(|s| s.block_number <= block_number);
+                position.checked_sub(1).map(|i| states[i].clone())
+            })
+            .collect())
+    }
+}
+
+// Best route for one request at one historical block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BacktestResult {
+    pub block_number: u64,
+    pub request: usize,
+    pub route: Option<SwapRoute>,
+    pub error: Option<String>,
+}
+
+// Replays historical pool states through an engine's normal find_routes. The
+// engine's sources are replaced, so use an engine dedicated to the backtest.
+pub struct Backtest {
+    engine: Arc<RouterEngine>,
+    history: Arc<dyn PoolHistory>,
+    chain_id: u64,
+}
+
+impl Backtest {
+    pub fn new(engine: Arc<RouterEngine>, history: Arc<dyn PoolHistory>, chain_id: u64) -> Self {
+        Self {
+            engine,
+            history,
+            chain_id,
+        }
+    }
+
+    pub fn engine(&self) -> &Arc<RouterEngine> {
+        &self.engine
+    }
+
+    // Point the engine's sources at the pool states of `block_number`
+    pub async fn load_block(&self, block_number: u64) -> Result<usize, RouterError> {
+        let states = self.history.states_at(self.chain_id, block_number).await?;
+        let count = states.len();
+
+        let store = Arc::new(PoolStateStore::new());
+        let mut exchanges: Vec<String> = states.iter().map(|s| s.exchange_id.clone()).collect();
+        exchanges.sort();
+        exchanges.dedup();
+        for state in states {
+            store.apply(state);
+        }
+
+        // Clear every source first so exchanges without pools at this block quote nothing
+        let stale: Vec<String> = self.engine.liquidity_sources.iter().map(|s| s.key().clone()).collect();
+        for exchange_id in stale {
+            self.engine.liquidity_sources.remove(&exchange_id);
+        }
+        for exchange_id in exchanges {
+            let source = StateBackedSource::new(self.chain_id, exchange_id.clone(), store.clone());
+            self.engine.register_liquidity_source(exchange_id, Arc::new(source));
+        }
+        self.engine.record_block(self.chain_id, block_number);
+
+        Ok(count)
+    }
+
+    // Quote every request at every block, in block order
+    pub async fn run(&self, blocks: &[u64], requests: &[QuoteRequest]) -> Result<Vec<BacktestResult>, RouterError> {
+        let mut blocks = blocks.to_vec();
+        blocks.sort_unstable();
+
+        let mut results = Vec::with_capacity(blocks.len() * requests.len());
+        for block_number in blocks {
+            let pools = self.load_block(block_number).await?;
+            debug!("Backtesting block {} with {} pools", block_number, pools);
+
+            for (index, request) in requests.iter().enumerate() {
+                let (route, error) = match self.engine.find_routes(request.clone()).await {
+                    Ok(response) => (response.routes.into_iter().next(), None),
+                    Err(e) => (None, Some(e.to_string())),
+                };
+                results.push(BacktestResult {
+                    block_number,
+                    request: index,
+                    route,
+                    error,
+                });
+            }
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,286 @@
+use futures::future::join_all;
+use num_traits::ToPrimitive;
+
+use super::*;
+use crate::secrets::Credential;
+
+// Margins within this many basis points count as a tie
+const TIE_THRESHOLD_BPS: f64 = 1.0;
+
+// External aggregator with a public quote API
+#[async_trait]
+pub trait ExternalAggregator: Send + Sync {
+    fn name(&self) -> &str;
+
+    // Expected output amount for the request
+    async fn quote(&self, request: &QuoteRequest) -> Result<BigUint, RouterError>;
+}
+
+async fn fetch_json(request: reqwest::RequestBuilder, aggregator: &str) -> Result<serde_json::Value, RouterError> {
+    request
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| RouterError::ExecutionError(format!("{} quote failed: {}", aggregator, e)))?
+        .json()
+        .await
+        .map_err(|e| RouterError::ExecutionError(format!("{} returned invalid JSON: {}", aggregator, e)))
+}
+
+fn amount_field(json: &serde_json::Value, pointer: &str, aggregator: &str) -> Result<BigUint, RouterError> {
+    json.pointer(pointer)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| RouterError::ExecutionError(format!("{} response has no {}", aggregator, pointer)))
+        .and_then(math::parse_amount)
+}
+
+// 0x Swap API price endpoint
+pub struct ZeroExAggregator {
+    base_url: String,
+    api_key: Option<Credential>,
+    client: reqwest::Client,
+}
+
+impl ZeroExAggregator {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        Self {
+            base_url,
+            api_key: api_key.map(Credential::new),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ExternalAggregator for ZeroExAggregator {
+    fn name(&self) -> &str {
+        "0x"
+    }
+
+    async fn quote(&self, request: &QuoteRequest) -> Result<BigUint, RouterError> {
+        let mut http = self
+            .client
+            .get(format!("{}/swap/v1/price", self.base_url))
+            .query(&[
+                ("sellToken", request.token_in.as_str()),
+                ("buyToken", request.token_out.as_str()),
+                ("sellAmount", request.amount_in.as_str()),
+            ]);
+        if let Some(key) = &self.api_key {
+            http = http.header("0x-api-key", key.expose());
+        }
+
+        amount_field(&fetch_json(http, self.name()).await?, "/buyAmount", self.name())
+    }
+}
+
+// 1inch Swap API quote endpoint
+pub struct OneInchAggregator {
+    base_url: String,
+    api_key: Option<Credential>,
+    client: reqwest::Client,
+}
+
+impl OneInchAggregator {
+    pub fn new(base_url: String, api_key: Option<String>) -> Self {
+        Self {
+            base_url,
+            api_key: api_key.map(Credential::new),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl ExternalAggregator for OneInchAggregator {
+    fn name(&self) -> &str {
+        "1inch"
+    }
+
+    async fn quote(&self, request: &QuoteRequest) -> Result<BigUint, RouterError> {
+        let mut http = self
+            .client
+            .get(format!("{}/swap/v6.0/{}/quote", self.base_url, request.chain_id))
+            .query(&[
+                ("src", request.token_in.as_str()),
+                ("dst", request.token_out.as_str()),
+                ("amount", request.amount_in.as_str()),
+            ]);
+        if let Some(key) = &self.api_key {
+            http = http.bearer_auth(key.expose());
+        }
+
+        amount_field(&fetch_json(http, self.name()).await?, "/dstAmount", self.name())
+    }
+}
+
+// Engine output versus one external aggregator for a single request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkRecord {
+    pub aggregator: String,
+    pub engine_amount_out: String,
+    pub external_amount_out: String,
+    // Positive when the engine returned more output
+    pub margin_bps: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct PairStats {
+    samples: u64,
+    wins: u64,
+    losses: u64,
+    ties: u64,
+    total_margin_bps: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PairKey {
+    chain_id: u64,
+    token_in: String,
+    token_out: String,
+    size_bucket: i32,
+    aggregator: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReportEntry {
+    pub chain_id: u64,
+    pub token_in: String,
+    pub token_out: String,
+    // Order of magnitude of amount_in in whole tokens (2 = 100..999)
+    pub size_bucket: i32,
+    pub aggregator: String,
+    pub samples: u64,
+    pub wins: u64,
+    pub losses: u64,
+    pub ties: u64,
+    pub win_rate: f64,
+    pub avg_margin_bps: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkReport {
+    pub entries: Vec<BenchmarkReportEntry>,
+}
+
+// Runs engine quotes side by side with external aggregators and keeps win/loss statistics
+pub struct Benchmark {
+    engine: Arc<RouterEngine>,
+    aggregators: Vec<Arc<dyn ExternalAggregator>>,
+    stats: DashMap<PairKey, PairStats>,
+}
+
+impl Benchmark {
+    pub fn new(engine: Arc<RouterEngine>) -> Self {
+        Self {
+            engine,
+            aggregators: Vec::new(),
+            stats: DashMap::new(),
+        }
+    }
+
+    pub fn add_aggregator(&mut self, aggregator: Arc<dyn ExternalAggregator>) {
+        self.aggregators.push(aggregator);
+    }
+
+    async fn size_bucket(&self, request: &QuoteRequest) -> Result<i32, RouterError> {
+        let decimals = self
+            .engine
+            .get_token(request.chain_id, &request.token_in)
+            .await
+            .map(|t| t.decimals)
+            .unwrap_or(18);
+        let amount = math::parse_amount(&request.amount_in)?.to_f64().unwrap_or(0.0);
+        let whole = amount / 10f64.powi(decimals as i32);
+
+        Ok(if whole > 0.0 { whole.log10().floor() as i32 } else { 0 })
+    }
+
+    // Quote through the engine and every external aggregator, recording the margins.
+    // Aggregators that fail are skipped rather than failing the engine quote.
+    pub async fn run(&self, request: QuoteRequest) -> Result<(QuoteResponse, Vec<BenchmarkRecord>), RouterError> {
+        let size_bucket = self.size_bucket(&request).await?;
+        let external = join_all(self.aggregators.iter().map(|a| a.quote(&request)));
+        let (response, external) = futures::join!(self.engine.find_routes(request.clone()), external);
+        let response = response?;
+
+        let engine_out = response
+            .routes
+            .first()
+            .map(|route| math::parse_amount(&route.expected_amount_out))
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut records = Vec::new();
+        for (aggregator, quote) in self.aggregators.iter().zip(external) {
+            let external_out = match quote {
+                Ok(amount) => amount,
+                Err(e) => {
+                    warn!("Skipping {} in benchmark: {}", aggregator.name(), e);
+                    continue;
+                }
+            };
+
+            let margin_bps = (math::ratio(&engine_out, &external_out) - 1.0) * 10_000.0;
+            let key = PairKey {
+                chain_id: request.chain_id,
+                token_in: request.token_in.clone(),
+                token_out: request.token_out.clone(),
+                size_bucket,
+                aggregator: aggregator.name().to_string(),
+            };
+
+            let mut stats = self.stats.entry(key).or_default();
+            stats.samples += 1;
+            stats.total_margin_bps += margin_bps;
+            if margin_bps.abs() < TIE_THRESHOLD_BPS {
+                stats.ties += 1;
+            } else if margin_bps > 0.0 {
+                stats.wins += 1;
+            } else {
+                stats.losses += 1;
+            }
+
+            records.push(BenchmarkRecord {
+                aggregator: aggregator.name().to_string(),
+                engine_amount_out: engine_out.to_string(),
+                external_amount_out: external_out.to_string(),
+                margin_bps,
+            });
+        }
+
+        Ok((response, records))
+    }
+
+    // Routing-quality report per pair, size bucket and aggregator
+    pub fn report(&self) -> BenchmarkReport {
+        let mut entries: Vec<BenchmarkReportEntry> = self
+            .stats
+            .iter()
+            .map(|entry| {
+                let (key, stats) = entry.pair();
+                let samples = stats.samples.max(1) as f64;
+                BenchmarkReportEntry {
+                    chain_id: key.chain_id,
+                    token_in: key.token_in.clone(),
+                    token_out: key.token_out.clone(),
+                    size_bucket: key.size_bucket,
+                    aggregator: key.aggregator.clone(),
+                    samples: stats.samples,
+                    wins: stats.wins,
+                    losses: stats.losses,
+                    ties: stats.ties,
+                    win_rate: stats.wins as f64 / samples,
+                    avg_margin_bps: stats.total_margin_bps / samples,
+                }
+            })
+            .collect();
+
+        entries.sort_by(|a, b| {
+            (a.chain_id, &a.token_in, &a.token_out, a.size_bucket, &a.aggregator)
+                .cmp(&(b.chain_id, &b.token_in, &b.token_out, b.size_bucket, &b.aggregator))
+        });
+
+        BenchmarkReport { entries }
+    }
+}
@@ -0,0 +1,21 @@
+// Quote server: `auraagg-server [config.toml]`, defaulting to $AURAAGG_CONFIG or server.toml
+use router_engine::server::{serve, ServerConfig};
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let path = std::env::args()
+        .nth(1)
+        .or_else(|| std::env::var("AURAAGG_CONFIG").ok())
+        .unwrap_or_else(|| "server.toml".to_string());
+
+    let result = match ServerConfig::load(&path) {
+        Ok(config) => serve(config).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = result {
+        eprintln!("auraagg-server: {}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,57 @@
+use tokio::runtime::Runtime;
+
+use super::*;
+use crate::executor::{ExecutorTx, StepCall};
+
+// Synchronous wrapper around RouterEngine for CLI tools and non-async code. It owns
+// its Tokio runtime, so it must not be created or dropped inside another runtime.
+pub struct BlockingRouter {
+    engine: Arc<RouterEngine>,
+    runtime: Runtime,
+}
+
+impl BlockingRouter {
+    pub fn new() -> Result<Self, RouterError> {
+        Self::with_engine(Arc::new(RouterEngine::new()))
+    }
+
+    pub fn with_engine(engine: Arc<RouterEngine>) -> Result<Self, RouterError> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| RouterError::ConfigError(format!("Failed to start runtime: {}", e)))?;
+
+        Ok(Self { engine, runtime })
+    }
+
+    // The wrapped engine, for registering sources, tokens and executors
+    pub fn engine(&self) -> &Arc<RouterEngine> {
+        &self.engine
+    }
+
+    pub fn quote(&self, request: QuoteRequest) -> Result<QuoteResponse, RouterError> {
+        self.runtime.block_on(self.engine.find_routes(request))
+    }
+
+    pub fn build_tx(
+        &self,
+        route: &SwapRoute,
+        chain_id: u64,
+        calls: &[StepCall],
+        policy: mev::MevPolicy,
+    ) -> Result<ExecutorTx, RouterError> {
+        self.engine.build_executor_tx(route, chain_id, calls, policy)
+    }
+
+    pub fn spot_price(&self, chain_id: u64, token_in: &str, token_out: &str) -> Result<f64, RouterError> {
+        self.runtime.block_on(async {
+            let token_in = self.token(chain_id, token_in).await?;
+            let token_out = self.token(chain_id, token_out).await?;
+            self.engine.spot_price(&token_in, &token_out).await
+        })
+    }
+
+    async fn token(&self, chain_id: u64, address: &str) -> Result<Token, RouterError> {
+        self.engine.resolve_token(chain_id, address).await
+    }
+}
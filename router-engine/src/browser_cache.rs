@@ -0,0 +1,104 @@
+use wasm_bindgen::prelude::*;
+
+use super::*;
+use crate::state::PoolState;
+
+#[wasm_bindgen]
+extern "C" {
+    // Any JS object with async get(key) -> string | undefined and set(key, value),
+    // e.g. a thin wrapper around an IndexedDB object store
+    pub type AsyncPoolStore;
+
+    #[wasm_bindgen(method, catch)]
+    async fn get(this: &AsyncPoolStore, key: String) -> Result<JsValue, JsValue>;
+
+    #[wasm_bindgen(method, catch)]
+    async fn set(this: &AsyncPoolStore, key: String, value: String) -> Result<JsValue, JsValue>;
+}
+
+enum Backend {
+    LocalStorage(web_sys::Storage),
+    Async(AsyncPoolStore),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedPools {
+    saved_at: f64,
+    pools: Vec<PoolState>,
+}
+
+// Session cache of pool state in the browser, so repeat quotes don't refetch the
+// same pools through the user's RPC. One entry per chain.
+#[wasm_bindgen]
+pub struct BrowserPoolCache {
+    backend: Backend,
+    max_age_ms: f64,
+}
+
+#[wasm_bindgen]
+impl BrowserPoolCache {
+    #[wasm_bindgen(js_name = localStorage)]
+    pub fn local_storage(max_age_ms: f64) -> Result<BrowserPoolCache, JsValue> {
+        let storage = web_sys::window()
+            .ok_or_else(|| JsValue::from_str("localStorage is not available in this context"))?
+            .local_storage()?
+            .ok_or_else(|| JsValue::from_str("localStorage is disabled"))?;
+
+        Ok(Self {
+            backend: Backend::LocalStorage(storage),
+            max_age_ms,
+        })
+    }
+
+    // Backed by an async store; use this in web workers, where localStorage is unavailable
+    #[wasm_bindgen(js_name = fromStore)]
+    pub fn from_store(store: AsyncPoolStore, max_age_ms: f64) -> BrowserPoolCache {
+        Self {
+            backend: Backend::Async(store),
+            max_age_ms,
+        }
+    }
+}
+
+impl BrowserPoolCache {
+    fn key(chain_id: u64) -> String {
+        format!("auraagg:pools:{}", chain_id)
+    }
+
+    async fn read(&self, key: String) -> Result<Option<String>, JsValue> {
+        match &self.backend {
+            Backend::LocalStorage(storage) => storage.get_item(&key),
+            Backend::Async(store) => Ok(store.get(key).await?.as_string()),
+        }
+    }
+
+    async fn write(&self, key: String, value: String) -> Result<(), JsValue> {
+        match &self.backend {
+            Backend::LocalStorage(storage) => storage.set_item(&key, &value),
+            Backend::Async(store) => store.set(key, value).await.map(|_| ()),
+        }
+    }
+
+    // Pools cached for the chain, or nothing if the entry is missing, unreadable or too old
+    pub async fn load(&self, chain_id: u64) -> Vec<PoolState> {
+        let raw = match self.read(Self::key(chain_id)).await {
+            Ok(Some(raw)) => raw,
+            _ => return Vec::new(),
+        };
+
+        match serde_json::from_str::<CachedPools>(&raw) {
+            Ok(cached) if js_sys::Date::now() - cached.saved_at <= self.max_age_ms => cached.pools,
+            _ => Vec::new(),
+        }
+    }
+
+    pub async fn save(&self, chain_id: u64, pools: Vec<PoolState>) -> Result<(), JsValue> {
+        let value = serde_json::to_string(&CachedPools {
+            saved_at: js_sys::Date::now(),
+            pools,
+        })
+        .map_err(|e| JsValue::from_str(&format!("Failed to encode pool cache: {}", e)))?;
+
+        self.write(Self::key(chain_id), value).await
+    }
+}
@@ -0,0 +1,237 @@
+use redis::aio::MultiplexedConnection;
+use redis::streams::StreamReadReply;
+use tokio::sync::OnceCell;
+
+use super::*;
+
+// Message bus shared between engine instances and downstream consumers
+#[async_trait]
+pub trait MessageBus: Send + Sync {
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), RouterError>;
+
+    // Messages on `topic` after `cursor` (None reads only new messages),
+    // together with the cursor to resume from
+    async fn poll(
+        &self,
+        topic: &str,
+        cursor: Option<&str>,
+        max: usize,
+    ) -> Result<(Vec<Vec<u8>>, Option<String>), RouterError>;
+}
+
+// Redis Streams backed bus; each topic is a capped stream
+pub struct RedisStreamBus {
+    client: redis::Client,
+    connection: OnceCell<MultiplexedConnection>,
+    max_len: usize,
+    block_ms: usize,
+}
+
+impl RedisStreamBus {
+    pub fn new(redis_url: &str) -> Result<Self, RouterError> {
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid Redis URL: {}", e)))?;
+
+        Ok(Self {
+            client,
+            connection: OnceCell::new(),
+            max_len: 100_000,
+            block_ms: 1_000,
+        })
+    }
+
+    // Approximate number of messages retained per topic
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len;
+        self
+    }
+
+    // How long poll waits for new messages
+    pub fn with_block_ms(mut self, block_ms: usize) -> Self {
+        self.block_ms = block_ms;
+        self
+    }
+
+    async fn connection(&self) -> Result<MultiplexedConnection, RouterError> {
+        self.connection
+            .get_or_try_init(|| self.client.get_multiplexed_tokio_connection())
+            .await
+            .cloned()
+            .map_err(|e| RouterError::ChainError(format!("Redis connection failed: {}", e)))
+    }
+}
+
+#[async_trait]
+impl MessageBus for RedisStreamBus {
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), RouterError> {
+        let mut connection = self.connection().await?;
+
+        redis::cmd("XADD")
+            .arg(topic)
+            .arg("MAXLEN")
+            .arg("~")
+            .arg(self.max_len)
+            .arg("*")
+            .arg("payload")
+            .arg(payload)
+            .query_async::<_, String>(&mut connection)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to publish to {}: {}", topic, e)))?;
+
+        Ok(())
+    }
+
+    async fn poll(
+        &self,
+        topic: &str,
+        cursor: Option<&str>,
+        max: usize,
+    ) -> Result<(Vec<Vec<u8>>, Option<String>), RouterError> {
+        let mut connection = self.connection().await?;
+
+        let reply: Option<StreamReadReply> = redis::cmd("XREAD")
+            .arg("COUNT")
+            .arg(max)
+            .arg("BLOCK")
+            .arg(self.block_ms)
+            .arg("STREAMS")
+            .arg(topic)
+            .arg(cursor.unwrap_or("$"))
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to read {}: {}", topic, e)))?;
+
+        let mut messages = Vec::new();
+        let mut next_cursor = cursor.map(|c| c.to_string());
+
+        for key in reply.map(|r| r.keys).unwrap_or_default() {
+            for entry in key.ids {
+                if let Some(redis::Value::Data(payload)) = entry.map.get("payload") {
+                    messages.push(payload.clone());
+                }
+                next_cursor = Some(entry.id);
+            }
+        }
+
+        Ok((messages, next_cursor))
+    }
+}
+
+// Kafka topics through a Confluent-compatible REST proxy; publish only
+pub struct KafkaRestBus {
+    proxy_url: String,
+    client: reqwest::Client,
+}
+
+impl KafkaRestBus {
+    pub fn new(proxy_url: String) -> Self {
+        Self {
+            proxy_url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl MessageBus for KafkaRestBus {
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), RouterError> {
+        let value: serde_json::Value = serde_json::from_slice(payload)
+            .unwrap_or_else(|_| serde_json::Value::String(hex::encode(payload)));
+
+        self.client
+            .post(format!("{}/topics/{}", self.proxy_url, topic))
+            .header("Content-Type", "application/vnd.kafka.json.v2+json")
+            .json(&serde_json::json!({ "records": [{ "value": value }] }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RouterError::ChainError(format!("Failed to publish to {}: {}", topic, e)))?;
+
+        Ok(())
+    }
+
+    async fn poll(
+        &self,
+        topic: &str,
+        _cursor: Option<&str>,
+        _max: usize,
+    ) -> Result<(Vec<Vec<u8>>, Option<String>), RouterError> {
+        Err(RouterError::ConfigError(format!(
+            "Kafka REST bus is publish-only, cannot read {}",
+            topic
+        )))
+    }
+}
+
+// Core NATS subjects. NATS keeps no history, so cursors are ignored and poll
+// returns what arrived since the previous poll of the same subject.
+#[cfg(feature = "nats")]
+pub struct NatsBus {
+    client: async_nats::Client,
+    subscriptions: DashMap<String, Arc<tokio::sync::Mutex<async_nats::Subscriber>>>,
+    wait: std::time::Duration,
+}
+
+#[cfg(feature = "nats")]
+impl NatsBus {
+    pub async fn connect(url: &str) -> Result<Self, RouterError> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|e| RouterError::ConfigError(format!("NATS connection failed: {}", e)))?;
+
+        Ok(Self {
+            client,
+            subscriptions: DashMap::new(),
+            wait: std::time::Duration::from_secs(1),
+        })
+    }
+
+    async fn subscription(&self, topic: &str) -> Result<Arc<tokio::sync::Mutex<async_nats::Subscriber>>, RouterError> {
+        if let Some(subscription) = self.subscriptions.get(topic) {
+            return Ok(subscription.clone());
+        }
+
+        let subscriber = self
+            .client
+            .subscribe(topic.to_string())
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to subscribe to {}: {}", topic, e)))?;
+        let subscription = Arc::new(tokio::sync::Mutex::new(subscriber));
+        self.subscriptions.insert(topic.to_string(), subscription.clone());
+
+        Ok(subscription)
+    }
+}
+
+#[cfg(feature = "nats")]
+#[async_trait]
+impl MessageBus for NatsBus {
+    async fn publish(&self, topic: &str, payload: &[u8]) -> Result<(), RouterError> {
+        self.client
+            .publish(topic.to_string(), payload.to_vec().into())
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to publish to {}: {}", topic, e)))
+    }
+
+    async fn poll(
+        &self,
+        topic: &str,
+        _cursor: Option<&str>,
+        max: usize,
+    ) -> Result<(Vec<Vec<u8>>, Option<String>), RouterError> {
+        use futures::StreamExt;
+
+        let subscription = self.subscription(topic).await?;
+        let mut subscriber = subscription.lock().await;
+
+        let mut messages = Vec::new();
+        while messages.len() < max {
+            match tokio::time::timeout(self.wait, subscriber.next()).await {
+                Ok(Some(message)) => messages.push(message.payload.to_vec()),
+                _ => break,
+            }
+        }
+
+        Ok((messages, None))
+    }
+}
@@ -0,0 +1,505 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use super::*;
+
+// Bump when the key derivation changes; old and new replicas then use disjoint keys
+pub const ROUTE_CACHE_KEY_VERSION: u16 = 1;
+
+// Bump when SwapRoute's serialized form changes; mismatching entries are ignored
+pub const ROUTE_SCHEMA_VERSION: u16 = 1;
+
+const KEY_PREFIX: &str = "auraagg:route";
+
+// Blocks an in-process entry outlives its state block, so requests allowing
+// stale quotes can still be served from it
+pub const STALE_RETENTION_BLOCKS: u64 = 5;
+
+// Significant digits kept when bucketing amounts
+const AMOUNT_BUCKET_DIGITS: usize = 3;
+
+// Amount rounded down to a few significant digits, e.g. 1234567 -> "123e4",
+// using integer arithmetic only so every replica derives the same bucket
+pub fn amount_bucket(amount: &BigUint) -> String {
+    let digits = amount.to_string();
+    if digits.len() <= AMOUNT_BUCKET_DIGITS {
+        return format!("{}e0", digits);
+    }
+    let exponent = digits.len() - AMOUNT_BUCKET_DIGITS;
+    format!("{}e{}", &digits[..AMOUNT_BUCKET_DIGITS], exponent)
+}
+
+// Canonical identity of a route cache entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteCacheKey {
+    pub chain_id: u64,
+    pub token_in: String,
+    pub token_out: String,
+    // Sorted, deduplicated pool/source identifiers the routes may use
+    pub pools: Vec<String>,
+    pub state_block: u64,
+    pub amount_bucket: String,
+    // Request options that change the routes, e.g. slippage and flash-loan mode
+    pub options: Vec<String>,
+}
+
+impl RouteCacheKey {
+    pub fn new(
+        chain_id: u64,
+        token_in: &str,
+        token_out: &str,
+        pools: impl IntoIterator<Item = String>,
+        state_block: u64,
+        amount_in: &BigUint,
+        options: Vec<String>,
+    ) -> Self {
+        let mut pools: Vec<String> = pools.into_iter().map(|p| p.to_lowercase()).collect();
+        pools.sort();
+        pools.dedup();
+
+        Self {
+            chain_id,
+            token_in: token_in.to_lowercase(),
+            token_out: token_out.to_lowercase(),
+            pools,
+            state_block,
+            amount_bucket: amount_bucket(amount_in),
+            options,
+        }
+    }
+
+    // Length-prefixed fields in a fixed order, independent of serde or platform
+    fn canonical_bytes(&self) -> Vec<u8> {
+        fn push(out: &mut Vec<u8>, field: &[u8]) {
+            out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            out.extend_from_slice(field);
+        }
+
+        let mut out = Vec::new();
+        push(&mut out, &ROUTE_CACHE_KEY_VERSION.to_be_bytes());
+        push(&mut out, &self.chain_id.to_be_bytes());
+        push(&mut out, self.token_in.as_bytes());
+        push(&mut out, self.token_out.as_bytes());
+        push(&mut out, &(self.pools.len() as u32).to_be_bytes());
+        for pool in &self.pools {
+            push(&mut out, pool.as_bytes());
+        }
+        push(&mut out, &self.state_block.to_be_bytes());
+        push(&mut out, self.amount_bucket.as_bytes());
+        push(&mut out, &(self.options.len() as u32).to_be_bytes());
+        for option in &self.options {
+            push(&mut out, option.as_bytes());
+        }
+        out
+    }
+
+    pub fn digest(&self) -> String {
+        hex::encode(Sha256::digest(self.canonical_bytes()))
+    }
+
+    // Shared-cache key, e.g. auraagg:route:v1:1:<sha256>
+    pub fn storage_key(&self) -> String {
+        format!("{}:v{}:{}:{}", KEY_PREFIX, ROUTE_CACHE_KEY_VERSION, self.chain_id, self.digest())
+    }
+}
+
+// Envelope stored under a RouteCacheKey
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedRoutes {
+    pub key_version: u16,
+    pub schema_version: u16,
+    pub digest: String,
+    pub state_block: u64,
+    pub created_at: u64,
+    pub routes: Vec<SwapRoute>,
+}
+
+impl CachedRoutes {
+    pub fn new(key: &RouteCacheKey, routes: Vec<SwapRoute>) -> Self {
+        let created_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Self {
+            key_version: ROUTE_CACHE_KEY_VERSION,
+            schema_version: ROUTE_SCHEMA_VERSION,
+            digest: key.digest(),
+            state_block: key.state_block,
+            created_at,
+            routes,
+        }
+    }
+
+    pub fn encode(&self) -> Result<Vec<u8>, RouterError> {
+        serde_json::to_vec(self)
+            .map_err(|e| RouterError::ExecutionError(format!("Failed to encode cached routes: {}", e)))
+    }
+
+    // Routes stored for `key`, or None if the entry was written by an incompatible
+    // deployment or under a different key (e.g. a digest collision or stale block)
+    pub fn decode(bytes: &[u8], key: &RouteCacheKey) -> Option<Vec<SwapRoute>> {
+        let entry: CachedRoutes = match serde_json::from_slice(bytes) {
+            Ok(entry) => entry,
+            Err(e) => {
+                debug!("Ignoring undecodable route cache entry: {}", e);
+                return None;
+            }
+        };
+
+        if entry.key_version != ROUTE_CACHE_KEY_VERSION
+            || entry.schema_version != ROUTE_SCHEMA_VERSION
+            || entry.state_block != key.state_block
+            || entry.digest != key.digest()
+        {
+            debug!(
+                "Ignoring route cache entry v{}/s{} for block {}",
+                entry.key_version, entry.schema_version, entry.state_block
+            );
+            return None;
+        }
+
+        Some(entry.routes)
+    }
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Freshness and size bounds shared by the quote and price caches
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheConfig {
+    pub ttl_secs: u64,
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl_secs: 12,
+            max_entries: 10_000,
+        }
+    }
+}
+
+// Routes computed by find_routes, keyed by RouteCacheKey. Keys carry the state
+// block, so entries from older blocks only match lookups for stale quotes;
+// invalidate_before frees them.
+#[async_trait]
+pub trait QuoteCache: Send + Sync {
+    async fn get(&self, key: &RouteCacheKey) -> Option<Vec<SwapRoute>>;
+    async fn put(&self, key: &RouteCacheKey, routes: &[SwapRoute]);
+    // Drop entries of `chain_id` priced before `block_number`
+    fn invalidate_before(&self, chain_id: u64, block_number: u64);
+}
+
+struct MemoryEntry {
+    key: RouteCacheKey,
+    entry: CachedRoutes,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    entries: HashMap<String, MemoryEntry>,
+    // last_used tick -> digest, oldest first
+    recency: std::collections::BTreeMap<u64, String>,
+    tick: u64,
+}
+
+impl MemoryState {
+    fn touch(&mut self, digest: &str) {
+        self.tick += 1;
+        let tick = self.tick;
+        if let Some(entry) = self.entries.get_mut(digest) {
+            self.recency.remove(&entry.last_used);
+            entry.last_used = tick;
+            self.recency.insert(tick, digest.to_string());
+        }
+    }
+
+    fn remove(&mut self, digest: &str) {
+        if let Some(entry) = self.entries.remove(digest) {
+            self.recency.remove(&entry.last_used);
+        }
+    }
+}
+
+// In-process LRU with a TTL
+pub struct MemoryQuoteCache {
+    config: CacheConfig,
+    state: std::sync::Mutex<MemoryState>,
+}
+
+impl MemoryQuoteCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            state: std::sync::Mutex::new(MemoryState::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for MemoryQuoteCache {
+    fn default() -> Self {
+        Self::new(CacheConfig::default())
+    }
+}
+
+#[async_trait]
+impl QuoteCache for MemoryQuoteCache {
+    async fn get(&self, key: &RouteCacheKey) -> Option<Vec<SwapRoute>> {
+        let digest = key.digest();
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entries.get(&digest)?;
+        if entry.key != *key {
+            return None;
+        }
+        if now_secs().saturating_sub(entry.entry.created_at) > self.config.ttl_secs {
+            state.remove(&digest);
+            return None;
+        }
+        let routes = entry.entry.routes.clone();
+        state.touch(&digest);
+        Some(routes)
+    }
+
+    async fn put(&self, key: &RouteCacheKey, routes: &[SwapRoute]) {
+        if self.config.max_entries == 0 {
+            return;
+        }
+        let digest = key.digest();
+        let mut state = self.state.lock().unwrap();
+        state.remove(&digest);
+        while state.entries.len() >= self.config.max_entries {
+            let oldest = match state.recency.values().next() {
+                Some(oldest) => oldest.clone(),
+                None => break,
+            };
+            state.remove(&oldest);
+        }
+        state.entries.insert(
+            digest.clone(),
+            MemoryEntry {
+                key: key.clone(),
+                entry: CachedRoutes::new(key, routes.to_vec()),
+                last_used: 0,
+            },
+        );
+        state.touch(&digest);
+    }
+
+    fn invalidate_before(&self, chain_id: u64, block_number: u64) {
+        let mut state = self.state.lock().unwrap();
+        let stale: Vec<String> = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.key.chain_id == chain_id && entry.key.state_block < block_number)
+            .map(|(digest, _)| digest.clone())
+            .collect();
+        for digest in stale {
+            state.remove(&digest);
+        }
+    }
+}
+
+// Redis-backed cache shared by every engine instance; entries expire with the TTL
+pub struct RedisQuoteCache {
+    client: redis::Client,
+    connection: tokio::sync::OnceCell<redis::aio::MultiplexedConnection>,
+    ttl_secs: u64,
+}
+
+impl RedisQuoteCache {
+    pub fn new(redis_url: &str, ttl_secs: u64) -> Result<Self, RouterError> {
+        let client = redis::Client::open(redis_url)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid Redis URL: {}", e)))?;
+
+        Ok(Self {
+            client,
+            connection: tokio::sync::OnceCell::new(),
+            ttl_secs: ttl_secs.max(1),
+        })
+    }
+
+    async fn connection(&self) -> Result<redis::aio::MultiplexedConnection, RouterError> {
+        self.connection
+            .get_or_try_init(|| self.client.get_multiplexed_tokio_connection())
+            .await
+            .cloned()
+            .map_err(|e| RouterError::ChainError(format!("Redis connection failed: {}", e)))
+    }
+}
+
+#[async_trait]
+impl QuoteCache for RedisQuoteCache {
+    async fn get(&self, key: &RouteCacheKey) -> Option<Vec<SwapRoute>> {
+        let mut connection = match self.connection().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Quote cache unavailable: {}", e);
+                return None;
+            }
+        };
+        let bytes: Option<Vec<u8>> = match redis::cmd("GET")
+            .arg(key.storage_key())
+            .query_async(&mut connection)
+            .await
+        {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                warn!("Quote cache read failed: {}", e);
+                return None;
+            }
+        };
+        bytes.and_then(|bytes| CachedRoutes::decode(&bytes, key))
+    }
+
+    async fn put(&self, key: &RouteCacheKey, routes: &[SwapRoute]) {
+        let encoded = match CachedRoutes::new(key, routes.to_vec()).encode() {
+            Ok(encoded) => encoded,
+            Err(e) => {
+                warn!("Quote cache write skipped: {}", e);
+                return;
+            }
+        };
+        let written = match self.connection().await {
+            Ok(mut connection) => redis::cmd("SET")
+                .arg(key.storage_key())
+                .arg(encoded)
+                .arg("EX")
+                .arg(self.ttl_secs)
+                .query_async::<_, ()>(&mut connection)
+                .await
+                .map_err(|e| RouterError::ChainError(e.to_string())),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            warn!("Quote cache write failed: {}", e);
+        }
+    }
+
+    // Keys include the state block, so old entries just expire
+    fn invalidate_before(&self, _chain_id: u64, _block_number: u64) {}
+}
+
+// How far a quote served from an older block may be off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Staleness {
+    // Blocks between the routes' state and the chain head
+    pub blocks_old: u64,
+    // One standard deviation of the pair's price drift over those blocks, when
+    // the pair's volatility is known
+    pub expected_drift_bps: Option<f64>,
+    // Chance the drift stays within the route's slippage tolerance
+    pub confidence: Option<f64>,
+}
+
+impl Staleness {
+    // Treats the pair's volatility (percent per recorded price) as per block
+    pub fn estimate(blocks_old: u64, volatility: Option<f64>, slippage: Option<f64>) -> Self {
+        let drift = volatility.map(|v| v * (blocks_old as f64).sqrt());
+        let confidence = match (drift, slippage) {
+            (Some(drift), _) if drift <= 0.0 => Some(1.0),
+            (Some(drift), Some(slippage)) => Some(erf(slippage / (drift * std::f64::consts::SQRT_2))),
+            _ => None,
+        };
+        Self {
+            blocks_old,
+            expected_drift_bps: drift.map(|d| d * 100.0),
+            confidence,
+        }
+    }
+}
+
+// Abramowitz and Stegun 7.1.26, accurate to 1.5e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (1.0 - poly * (-x * x).exp()).copysign(x)
+}
+
+impl RouterEngine {
+    // Routes cached for up to request.max_staleness_blocks before `head`, newest
+    // first. Requests expecting a transaction always get fresh routes.
+    pub(crate) async fn stale_cached_routes(&self, request: &QuoteRequest, head: u64) -> Option<(Vec<SwapRoute>, Staleness)> {
+        let max = request.max_staleness_blocks?.min(STALE_RETENTION_BLOCKS);
+        if request.recipient.is_some() {
+            return None;
+        }
+        for blocks_old in 1..=max.min(head) {
+            let key = self.route_cache_key(request, head - blocks_old).ok()?;
+            let Some(routes) = self.quote_cache().get(&key).await else {
+                continue;
+            };
+            let best = routes.first()?;
+            let volatility = match (best.steps.first(), best.steps.last()) {
+                (Some(first), Some(last)) => self.volatility.volatility(&first.token_in, &last.token_out),
+                _ => None,
+            };
+            let staleness = Staleness::estimate(blocks_old, volatility, best.slippage);
+            debug!("Serving quote {} blocks old for {} -> {}", blocks_old, request.token_in, request.token_out);
+            return Some((routes, staleness));
+        }
+        None
+    }
+}
+
+// Latest recorded mid prices, forgotten after the TTL and capped in size
+pub struct PriceCache {
+    config: CacheConfig,
+    prices: DashMap<(Token, Token), (f64, u64)>,
+}
+
+impl PriceCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            prices: DashMap::new(),
+        }
+    }
+
+    // `timestamp` in unix seconds
+    pub fn insert(&self, token_in: &Token, token_out: &Token, price: f64, timestamp: u64) {
+        if self.prices.len() >= self.config.max_entries {
+            let cutoff = now_secs().saturating_sub(self.config.ttl_secs);
+            self.prices.retain(|_, (_, recorded)| *recorded >= cutoff);
+        }
+        if self.prices.len() >= self.config.max_entries {
+            let oldest = self.prices.iter().min_by_key(|entry| entry.value().1).map(|entry| entry.key().clone());
+            if let Some(oldest) = oldest {
+                self.prices.remove(&oldest);
+            }
+        }
+        self.prices.insert((token_in.clone(), token_out.clone()), (price, timestamp));
+    }
+
+    pub fn get(&self, token_in: &Token, token_out: &Token) -> Option<f64> {
+        let key = (token_in.clone(), token_out.clone());
+        let (price, recorded) = *self.prices.get(&key)?;
+        if now_secs().saturating_sub(recorded) > self.config.ttl_secs {
+            self.prices.remove(&key);
+            return None;
+        }
+        Some(price)
+    }
+}
+
+impl Default for PriceCache {
+    fn default() -> Self {
+        Self::new(CacheConfig::default())
+    }
+}
@@ -0,0 +1,130 @@
+use std::collections::HashSet;
+
+use super::*;
+use crate::executor::StepCall;
+
+// Static checks of the calls the executor is about to make for a route. The
+// executor approves each target for the step's input and hands it control, then
+// sweeps leftover tokens; a token posing as one of those contracts, or one that
+// runs unknown code on transfer between steps, could re-enter it mid-plan.
+pub struct CallPlan<'a> {
+    pub route: &'a SwapRoute,
+    pub calls: &'a [StepCall],
+    pub executor: &'a str,
+    pub recipient: &'a str,
+    pub dust_sink: Option<&'a str>,
+}
+
+fn unsafe_route(detail: String) -> RouterError {
+    RouterError::UnsafeRoute(detail)
+}
+
+impl CallPlan<'_> {
+    // Lowercase addresses of the route's tokens, native ETH left out
+    fn tokens(&self) -> HashSet<String> {
+        self.route
+            .steps
+            .iter()
+            .flat_map(|step| [&step.token_in, &step.token_out])
+            .filter(|token| !executor::is_native(token))
+            .map(|token| token.address.to_lowercase())
+            .collect()
+    }
+
+    // Tokens only held by the executor between steps: neither the user's input
+    // nor the output they receive
+    fn intermediate_tokens(&self) -> Vec<&Token> {
+        let (Some(first), Some(last)) = (self.route.steps.first(), self.route.steps.last()) else {
+            return Vec::new();
+        };
+        let endpoints = [first.token_in.address.to_lowercase(), last.token_out.address.to_lowercase()];
+        let mut tokens: Vec<&Token> = Vec::new();
+        for step in &self.route.steps {
+            let address = step.token_out.address.to_lowercase();
+            if !endpoints.contains(&address) && !tokens.iter().any(|t| t.address.to_lowercase() == address) {
+                tokens.push(&step.token_out);
+            }
+        }
+        tokens
+    }
+
+    pub fn validate(&self, registry: &token_registry::TokenRegistry, chain_id: u64) -> Result<(), RouterError> {
+        if self.calls.len() != self.route.steps.len() {
+            return Err(unsafe_route(format!(
+                "{} calls for {} steps",
+                self.calls.len(),
+                self.route.steps.len()
+            )));
+        }
+
+        let executor = self.executor.to_lowercase();
+        let recipient = self.recipient.to_lowercase();
+        let sink = self.dust_sink.map(str::to_lowercase);
+        let tokens = self.tokens();
+
+        for (step, call) in self.route.steps.iter().zip(self.calls) {
+            let target = call.target.to_lowercase();
+            if target == executor {
+                return Err(unsafe_route(format!("step via {} calls the executor itself", step.exchange_id)));
+            }
+            if tokens.contains(&target) {
+                return Err(unsafe_route(format!("step via {} calls token {}", step.exchange_id, call.target)));
+            }
+            if target == recipient || Some(&target) == sink.as_ref() {
+                return Err(unsafe_route(format!(
+                    "step via {} calls the recipient or dust sink {}",
+                    step.exchange_id, call.target
+                )));
+            }
+        }
+
+        for token in &tokens {
+            if *token == executor || *token == recipient || Some(token) == sink.as_ref() {
+                return Err(unsafe_route(format!(
+                    "token {} shares its address with the executor, the recipient or the dust sink",
+                    token
+                )));
+            }
+        }
+
+        // The executor spends the input at each step's quoted amount and sweeps what
+        // is left of it, and pays out everything it gains of the output. Input that a
+        // step buys back would be swept as dust rather than sold, and output that a
+        // step sells would spend what other legs already delivered.
+        if let (Some(first), Some(last)) = (self.route.steps.first(), self.route.steps.last()) {
+            let input = first.token_in.address.to_lowercase();
+            let output = last.token_out.address.to_lowercase();
+            for step in &self.route.steps {
+                if step.token_out.address.to_lowercase() == input {
+                    return Err(unsafe_route(format!(
+                        "step via {} buys back input token {}, which would be swept as dust",
+                        step.exchange_id, step.token_out.symbol
+                    )));
+                }
+                if step.token_in.address.to_lowercase() == output {
+                    return Err(unsafe_route(format!(
+                        "step via {} sells output token {} mid-route",
+                        step.exchange_id, step.token_in.symbol
+                    )));
+                }
+            }
+        }
+
+        // Listed tokens are known plain ERC-20s; anything else could call back
+        // into the executor while it holds the route's funds. Intermediates must
+        // therefore be in the registry (register_token or a loaded token list).
+        // find_routes drops routes failing here for the next ranked one.
+        if let Some(token) = self
+            .intermediate_tokens()
+            .into_iter()
+            .find(|token| !executor::is_native(token) && registry.get(chain_id, &token.address).is_none())
+        {
+            return Err(unsafe_route(format!(
+                "intermediate token {} ({}) isn't in the token registry",
+                token.symbol, token.address
+            )));
+        }
+
+        Ok(())
+    }
+}
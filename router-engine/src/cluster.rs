@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use redis::Script;
+use tokio::task::JoinHandle;
+
+use super::*;
+use crate::bus::MessageBus;
+use crate::events::{EngineEvent, EventPublisher};
+use crate::state::{PoolState, PoolStateStore};
+
+// Extend the lease only if this instance still holds it
+const RENEW_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+// One replica in a cluster sharing pool state. Per chain, the replica holding the
+// Redis lease syncs the chain and publishes deltas; the others apply them locally.
+pub struct ClusterNode {
+    instance_id: String,
+    redis: redis::Client,
+    bus: Arc<dyn MessageBus>,
+    state: Arc<PoolStateStore>,
+    lease_ttl: Duration,
+    leader_of: DashMap<u64, bool>,
+    events: Option<Arc<EventPublisher>>,
+}
+
+impl ClusterNode {
+    pub fn new(
+        instance_id: String,
+        redis_url: &str,
+        bus: Arc<dyn MessageBus>,
+        state: Arc<PoolStateStore>,
+    ) -> Result<Self, RouterError> {
+        let redis = redis::Client::open(redis_url)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid Redis URL: {}", e)))?;
+
+        Ok(Self {
+            instance_id,
+            redis,
+            bus,
+            state,
+            lease_ttl: Duration::from_secs(15),
+            leader_of: DashMap::new(),
+            events: None,
+        })
+    }
+
+    pub fn with_lease_ttl(mut self, lease_ttl: Duration) -> Self {
+        self.lease_ttl = lease_ttl;
+        self
+    }
+
+    // Also publish pool updates as engine events
+    pub fn with_events(mut self, events: Arc<EventPublisher>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn state(&self) -> Arc<PoolStateStore> {
+        self.state.clone()
+    }
+
+    fn lease_key(chain_id: u64) -> String {
+        format!("auraagg:cluster:leader:{}", chain_id)
+    }
+
+    fn topic(chain_id: u64) -> String {
+        format!("auraagg:pool_state:{}", chain_id)
+    }
+
+    pub fn is_leader(&self, chain_id: u64) -> bool {
+        self.leader_of.get(&chain_id).map(|l| *l).unwrap_or(false)
+    }
+
+    // Take the chain's sync lease if it's free, or renew it if we already hold it
+    pub async fn acquire_or_renew(&self, chain_id: u64) -> Result<bool, RouterError> {
+        let mut connection = self
+            .redis
+            .get_multiplexed_tokio_connection()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Redis connection failed: {}", e)))?;
+        let key = Self::lease_key(chain_id);
+        let ttl_ms = self.lease_ttl.as_millis() as u64;
+
+        let acquired: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(&self.instance_id)
+            .arg("NX")
+            .arg("PX")
+            .arg(ttl_ms)
+            .query_async(&mut connection)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to acquire lease: {}", e)))?;
+
+        let leader = if acquired.is_some() {
+            true
+        } else {
+            let renewed: i64 = Script::new(RENEW_SCRIPT)
+                .key(&key)
+                .arg(&self.instance_id)
+                .arg(ttl_ms)
+                .invoke_async(&mut connection)
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to renew lease: {}", e)))?;
+            renewed == 1
+        };
+
+        if leader != self.is_leader(chain_id) {
+            info!(
+                "Instance {} {} chain {} sync",
+                self.instance_id,
+                if leader { "took over" } else { "lost" },
+                chain_id
+            );
+        }
+        self.leader_of.insert(chain_id, leader);
+
+        Ok(leader)
+    }
+
+    pub async fn release(&self, chain_id: u64) -> Result<(), RouterError> {
+        let mut connection = self
+            .redis
+            .get_multiplexed_tokio_connection()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Redis connection failed: {}", e)))?;
+
+        Script::new(RELEASE_SCRIPT)
+            .key(Self::lease_key(chain_id))
+            .arg(&self.instance_id)
+            .invoke_async::<_, i64>(&mut connection)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to release lease: {}", e)))?;
+        self.leader_of.insert(chain_id, false);
+
+        Ok(())
+    }
+
+    // Apply a synced pool state locally and share it with the other replicas
+    pub async fn publish(&self, state: PoolState) -> Result<(), RouterError> {
+        if !self.is_leader(state.chain_id) {
+            return Err(RouterError::ConfigError(format!(
+                "Instance {} does not hold the chain {} sync lease",
+                self.instance_id, state.chain_id
+            )));
+        }
+
+        let payload = serde_json::to_vec(&state)
+            .map_err(|e| RouterError::ExecutionError(format!("Failed to encode pool state: {}", e)))?;
+        self.state.apply(state.clone());
+        self.bus.publish(&Self::topic(state.chain_id), &payload).await?;
+
+        if let Some(events) = &self.events {
+            events.publish_in_background(EngineEvent::PoolUpdate { state });
+        }
+        Ok(())
+    }
+
+    // Apply deltas published by the leader since `cursor`; returns the new cursor
+    pub async fn poll_deltas(&self, chain_id: u64, cursor: Option<String>) -> Result<Option<String>, RouterError> {
+        let (messages, cursor) = self
+            .bus
+            .poll(&Self::topic(chain_id), cursor.as_deref(), 1_000)
+            .await?;
+
+        for message in messages {
+            match serde_json::from_slice::<PoolState>(&message) {
+                Ok(state) => {
+                    self.state.apply(state);
+                }
+                Err(e) => warn!("Ignoring malformed pool state delta on chain {}: {}", chain_id, e),
+            }
+        }
+
+        Ok(cursor)
+    }
+
+    // Keep the lease and replicated state current for a chain. While this node is the
+    // leader the caller's sync loop should check is_leader() and publish() deltas.
+    pub fn spawn(self: Arc<Self>, chain_id: u64) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut cursor = None;
+            loop {
+                match self.acquire_or_renew(chain_id).await {
+                    Ok(true) => {
+                        tokio::time::sleep(self.lease_ttl / 3).await;
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => warn!("Cluster lease check failed for chain {}: {}", chain_id, e),
+                }
+
+                match self.poll_deltas(chain_id, cursor.clone()).await {
+                    Ok(next) => cursor = next,
+                    Err(e) => {
+                        warn!("Failed to replicate chain {} state: {}", chain_id, e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,253 @@
+use rand::RngCore;
+
+use super::*;
+use crate::execution::{ExecutionParams, ExecutionTx};
+
+// How long a quoted route can be committed
+pub const DEFAULT_COMMIT_TTL_SECS: u64 = 30;
+
+const MAX_QUOTED_ROUTES: usize = 100_000;
+
+#[derive(Debug, Clone)]
+struct QuotedRoute {
+    request: QuoteRequest,
+    route: SwapRoute,
+    expires_at: u64,
+}
+
+// Routes handed out in quote responses, by route_id, until committed or expired
+pub struct QuoteBook {
+    routes: DashMap<String, QuotedRoute>,
+    ttl_secs: std::sync::atomic::AtomicU64,
+}
+
+impl Default for QuoteBook {
+    fn default() -> Self {
+        Self {
+            routes: DashMap::new(),
+            ttl_secs: std::sync::atomic::AtomicU64::new(DEFAULT_COMMIT_TTL_SECS),
+        }
+    }
+}
+
+impl QuoteBook {
+    pub fn set_ttl(&self, ttl_secs: u64) {
+        self.ttl_secs.store(ttl_secs, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    // Store the route and return its new id
+    pub fn insert(&self, request: &QuoteRequest, route: &SwapRoute) -> String {
+        let now = rfq::now();
+        if self.routes.len() >= MAX_QUOTED_ROUTES {
+            self.routes.retain(|_, quoted| quoted.expires_at > now);
+        }
+
+        let mut id = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut id);
+        let id = hex::encode(id);
+        self.routes.insert(
+            id.clone(),
+            QuotedRoute {
+                request: request.clone(),
+                route: route.clone(),
+                expires_at: now + self.ttl_secs.load(std::sync::atomic::Ordering::Relaxed),
+            },
+        );
+        id
+    }
+
+    pub fn chain_id(&self, route_id: &str) -> Option<u64> {
+        self.routes.get(route_id).map(|quoted| quoted.request.chain_id)
+    }
+
+    // Remove the route so no one else can commit it
+    fn take(&self, route_id: &str) -> Result<QuotedRoute, RouterError> {
+        let (_, quoted) = self
+            .routes
+            .remove(route_id)
+            .ok_or_else(|| RouterError::ExecutionError(format!("Unknown or already committed route {}", route_id)))?;
+        if quoted.expires_at <= rfq::now() {
+            return Err(RouterError::ExecutionError(format!("Route {} expired, request a new quote", route_id)));
+        }
+        Ok(quoted)
+    }
+
+    pub fn len(&self) -> usize {
+        self.routes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+}
+
+// A committed route: re-validated, with its final transaction and the sender's
+// nonce reserved for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Commitment {
+    pub route_id: String,
+    pub chain_id: u64,
+    pub sender: String,
+    pub route: SwapRoute,
+    pub transaction: ExecutionTx,
+    pub nonce: u64,
+    // Block a private-relay bundle should target; None for public mempool routes
+    #[serde(default)]
+    pub target_block: Option<u64>,
+    // Output of the route re-priced at commit time
+    pub revalidated_amount_out: String,
+}
+
+impl Commitment {
+    // Transaction request ready for TxManager::submit, with the reserved nonce
+    pub fn tx_request(&self) -> Result<Eip1559TransactionRequest, RouterError> {
+        let data = hex::decode(self.transaction.data.trim_start_matches("0x"))
+            .map_err(|e| RouterError::ExecutionError(format!("Invalid calldata: {}", e)))?;
+        let value = U256::from_dec_str(&self.transaction.value)
+            .map_err(|e| RouterError::ExecutionError(format!("Invalid value {}: {}", self.transaction.value, e)))?;
+        Ok(Eip1559TransactionRequest::new()
+            .from(parse_address(&self.sender)?)
+            .to(parse_address(&self.transaction.to)?)
+            .data(data)
+            .value(value)
+            .nonce(self.nonce)
+            .chain_id(self.chain_id))
+    }
+}
+
+// Per-sender nonces and bundle blocks handed out by commit, so concurrent
+// commits never build transactions that collide
+#[derive(Default)]
+pub struct Reservations {
+    nonces: DashMap<(u64, Address), u64>,
+    bundle_blocks: DashMap<(u64, Address), u64>,
+}
+
+impl Reservations {
+    // The next nonce after both the chain's pending count and earlier reservations
+    fn reserve_nonce(&self, chain_id: u64, sender: Address, chain_nonce: u64) -> u64 {
+        let mut next = self.nonces.entry((chain_id, sender)).or_insert(chain_nonce);
+        let nonce = (*next).max(chain_nonce);
+        *next = nonce + 1;
+        nonce
+    }
+
+    // One bundle per sender and block: later commits target later blocks
+    fn reserve_bundle_block(&self, chain_id: u64, sender: Address, head: u64) -> u64 {
+        let mut last = self.bundle_blocks.entry((chain_id, sender)).or_insert(head);
+        let block = (*last + 1).max(head + 1);
+        *last = block;
+        block
+    }
+
+    // Hand back the newest reservations of an abandoned commitment
+    fn release(&self, chain_id: u64, sender: Address, nonce: u64, target_block: Option<u64>) {
+        if let Some(mut next) = self.nonces.get_mut(&(chain_id, sender)) {
+            if *next == nonce + 1 {
+                *next = nonce;
+            }
+        }
+        if let (Some(block), Some(mut last)) = (target_block, self.bundle_blocks.get_mut(&(chain_id, sender))) {
+            if *last == block {
+                *last = block - 1;
+            }
+        }
+    }
+}
+
+impl RouterEngine {
+    pub fn set_commit_ttl(&self, ttl_secs: u64) {
+        self.quote_book.set_ttl(ttl_secs);
+    }
+
+    pub fn quote_book(&self) -> &QuoteBook {
+        &self.quote_book
+    }
+
+    // Output of the route against current pool state, failing when it no longer
+    // covers the route's minimum
+    async fn revalidate(&self, route: &SwapRoute, chain_id: u64) -> Result<BigUint, RouterError> {
+        self.check_state_age(route, chain_id)?;
+
+        let mut fresh_out = BigUint::default();
+        let mut min_out = BigUint::default();
+        for (steps, _) in routing::legs(route) {
+            let (Some(first), Some(last)) = (steps.first(), steps.last()) else {
+                continue;
+            };
+            fresh_out += self.requote_steps(steps, &math::parse_amount(&first.amount_in)?).await?;
+            min_out += math::parse_amount(&last.amount_out_min)?;
+        }
+        if fresh_out < min_out {
+            return Err(RouterError::PriceImpactTooHigh(format!(
+                "route now returns {}, below its minimum of {}; request a new quote",
+                fresh_out, min_out
+            )));
+        }
+        Ok(fresh_out)
+    }
+
+    // Second phase of quote-then-commit: lock a route from an earlier quote,
+    // re-validate it, build its final transaction and reserve the sender's nonce
+    // (and bundle block for private-relay routes). A route can be committed once;
+    // if any step fails it is gone and the client requotes.
+    pub async fn commit<M: Middleware>(
+        &self,
+        client: &M,
+        route_id: &str,
+        sender: &str,
+    ) -> Result<Commitment, RouterError> {
+        let quoted = self.quote_book.take(route_id)?;
+        let request = &quoted.request;
+        let route = quoted.route;
+        let sender_address = parse_address(sender)?;
+
+        let revalidated = self.revalidate(&route, request.chain_id).await?;
+
+        let mut params = ExecutionParams::new(request.recipient.clone().unwrap_or_else(|| sender.to_string()));
+        if let Some(deadline) = request.deadline {
+            params.deadline = deadline;
+        }
+        params.recipients = request.recipients.clone();
+        let transaction = self.build_execution(&route, request.chain_id, &params)?;
+
+        let chain_nonce = client
+            .get_transaction_count(sender_address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch nonce: {}", e)))?
+            .as_u64();
+        let target_block = match self.resolve_mev_policy(request.chain_id, request.mev_policy)? {
+            mev::MevPolicy::PrivateRelay => {
+                let head = client
+                    .get_block_number()
+                    .await
+                    .map_err(|e| RouterError::ChainError(format!("Failed to fetch block number: {}", e)))?
+                    .as_u64();
+                Some(self.reservations.reserve_bundle_block(request.chain_id, sender_address, head))
+            }
+            mev::MevPolicy::PublicMempool => None,
+        };
+        let nonce = self.reservations.reserve_nonce(request.chain_id, sender_address, chain_nonce);
+
+        debug!("Committed route {} for {} with nonce {}", route_id, sender, nonce);
+        Ok(Commitment {
+            route_id: route_id.to_string(),
+            chain_id: request.chain_id,
+            sender: sender.to_string(),
+            route,
+            transaction,
+            nonce,
+            target_block,
+            revalidated_amount_out: revalidated.to_string(),
+        })
+    }
+
+    // Give back a commitment's reservations when it won't be sent; only the
+    // sender's latest reservation can be returned without leaving a nonce gap
+    pub fn release_commitment(&self, commitment: &Commitment) -> Result<(), RouterError> {
+        let sender = parse_address(&commitment.sender)?;
+        self.reservations
+            .release(commitment.chain_id, sender, commitment.nonce, commitment.target_block);
+        Ok(())
+    }
+}
@@ -0,0 +1,572 @@
+use std::time::Duration;
+
+use ethers::abi::{ParamType, Token as AbiToken};
+use rand::Rng;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::adapters::call_at;
+use crate::finality::FinalityPolicy;
+use crate::secrets::Secret;
+use crate::vault::SecretVault;
+
+const INITIATE_ETH_SWAP: &str = "initiateEthSwap(address,bytes32,uint256)";
+const INITIATE_TOKEN_SWAP: &str = "initiateTokenSwap(address,uint256,address,bytes32,uint256)";
+const CLAIM_FUNDS: &str = "claimFunds(bytes32)";
+const REFUND: &str = "refund(bytes32)";
+
+// Status values of CrossChainSwap.sol's Swap struct
+const HTLC_ACTIVE: u64 = 1;
+
+// Lifecycle of an atomic swap we initiated:
+// Initiated -> Locked -> Claimed, or Initiated/Locked -> Expired -> Refunded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwapStatus {
+    // Our funds are locked on the source chain
+    Initiated,
+    // The counterparty locked matching funds for us on the destination chain
+    Locked,
+    // We revealed the secret and took the destination funds
+    Claimed,
+    // Our source funds came back after expiration
+    Refunded,
+    // Too late to claim; the source funds can be refunded
+    Expired,
+}
+
+impl SwapStatus {
+    pub fn is_final(&self) -> bool {
+        matches!(self, SwapStatus::Claimed | SwapStatus::Refunded)
+    }
+}
+
+// Terms of an atomic swap; the zero address is the native token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapTerms {
+    pub source_chain: u64,
+    pub dest_chain: u64,
+    pub token_in: String,
+    pub amount_in: String,
+    // Receives our funds on the source chain once it learns the secret
+    pub counterparty: String,
+    pub token_out: String,
+    pub min_amount_out: String,
+    // Unix seconds after which the source lock can be refunded
+    pub expiration: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HtlcSwap {
+    pub swap_id: String,
+    pub terms: SwapTerms,
+    pub secret_hash: String,
+    pub status: SwapStatus,
+    pub lock_tx: String,
+    pub counter_swap_id: Option<String>,
+    pub counter_expiration: Option<u64>,
+    pub claim_tx: Option<String>,
+    pub refund_tx: Option<String>,
+}
+
+// Published on every status change
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwapUpdate {
+    pub swap_id: String,
+    pub status: SwapStatus,
+    pub tx_hash: Option<String>,
+}
+
+struct HtlcChain<M> {
+    client: Arc<M>,
+    htlc: Address,
+    // Counterparty locks only count once final
+    finality: FinalityPolicy,
+}
+
+// The HTLC contract's view of one swap
+struct LockedSwap {
+    recipient: Address,
+    token: Address,
+    amount: BigUint,
+    expiration: u64,
+    status: u64,
+}
+
+// Drives HTLC atomic swaps through CrossChainSwap.sol deployments. Clients must
+// sign (e.g. SignerMiddleware) since locks, claims and refunds are sent from them.
+pub struct CrossChainSwap<M: Middleware> {
+    chains: HashMap<u64, HtlcChain<M>>,
+    swaps: DashMap<H256, HtlcSwap>,
+    vault: Arc<SecretVault>,
+    updates: broadcast::Sender<SwapUpdate>,
+}
+
+impl<M: Middleware + 'static> Default for CrossChainSwap<M> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M: Middleware + 'static> CrossChainSwap<M> {
+    pub fn new() -> Self {
+        Self {
+            chains: HashMap::new(),
+            swaps: DashMap::new(),
+            vault: Arc::new(SecretVault::ephemeral()),
+            updates: broadcast::channel(256).0,
+        }
+    }
+
+    // Keep secrets somewhere that outlives the process, e.g. a FileVaultStore
+    pub fn with_vault(mut self, vault: Arc<SecretVault>) -> Self {
+        self.vault = vault;
+        self
+    }
+
+    // HTLC contract of a chain and the client that talks to it
+    pub fn register_chain(&mut self, chain_id: u64, htlc_address: &str, client: Arc<M>) -> Result<(), RouterError> {
+        self.chains.insert(
+            chain_id,
+            HtlcChain {
+                client,
+                htlc: parse_address(htlc_address)?,
+                finality: FinalityPolicy::for_chain(chain_id),
+            },
+        );
+        Ok(())
+    }
+
+    // Override the chain's default finality, e.g. with RouterEngine::finality_policy
+    pub fn set_finality(&mut self, chain_id: u64, policy: FinalityPolicy) -> Result<(), RouterError> {
+        let chain = self
+            .chains
+            .get_mut(&chain_id)
+            .ok_or_else(|| RouterError::ConfigError(format!("No HTLC contract registered for chain {}", chain_id)))?;
+        chain.finality = policy;
+        Ok(())
+    }
+
+    // Random 32-byte secret and its keccak256 hash, as CrossChainSwap.sol checks it
+    pub fn generate_secret() -> (Secret<Vec<u8>>, Vec<u8>) {
+        let mut rng = rand::thread_rng();
+        let secret = Secret::new(rng.gen::<[u8; 32]>());
+        let hash = ethers::utils::keccak256(secret.expose()).to_vec();
+
+        (Secret::new(secret.expose().to_vec()), hash)
+    }
+
+    // Status changes of every swap
+    pub fn subscribe(&self) -> broadcast::Receiver<SwapUpdate> {
+        self.updates.subscribe()
+    }
+
+    pub fn swap(&self, swap_id: &str) -> Option<HtlcSwap> {
+        self.swaps.get(&parse_swap_id(swap_id).ok()?).map(|swap| swap.clone())
+    }
+
+    pub fn swaps(&self) -> Vec<HtlcSwap> {
+        self.swaps.iter().map(|swap| swap.clone()).collect()
+    }
+
+    fn chain(&self, chain_id: u64) -> Result<&HtlcChain<M>, RouterError> {
+        self.chains
+            .get(&chain_id)
+            .ok_or_else(|| RouterError::ConfigError(format!("No HTLC contract registered for chain {}", chain_id)))
+    }
+
+    fn sender(chain: &HtlcChain<M>) -> Result<Address, RouterError> {
+        chain
+            .client
+            .default_sender()
+            .ok_or_else(|| RouterError::ConfigError("HTLC client has no signer".to_string()))
+    }
+
+    fn set_status(&self, id: H256, status: SwapStatus, tx_hash: Option<String>) {
+        if let Some(mut swap) = self.swaps.get_mut(&id) {
+            if swap.status == status {
+                return;
+            }
+            info!("Swap {:?}: {:?} -> {:?}", id, swap.status, status);
+            swap.status = status;
+        }
+        // No subscribers is fine
+        let _ = self.updates.send(SwapUpdate {
+            swap_id: format!("{:?}", id),
+            status,
+            tx_hash,
+        });
+    }
+
+    // Lock `amount_in` on the source chain behind a fresh secret. The counterparty
+    // then has to lock `min_amount_out` for us on the destination chain under the
+    // same hash, expiring before our lock does.
+    pub async fn initiate_swap(&self, terms: SwapTerms) -> Result<HtlcSwap, RouterError> {
+        let source = self.chain(terms.source_chain)?;
+        self.chain(terms.dest_chain)?;
+        let from = Self::sender(source)?;
+
+        let secret_hash = self.vault.generate().await?;
+        let hash = secret_hash.as_bytes().to_vec();
+
+        let locked = self.lock(source, from, &terms, secret_hash).await;
+        if locked.is_err() {
+            self.forget_secret(secret_hash).await;
+        }
+        let lock_tx = locked?;
+        let amount_u256 = math::to_u256(&math::parse_amount(&terms.amount_in)?)?;
+        let token = parse_address(&terms.token_in)?;
+        let recipient = parse_address(&terms.counterparty)?;
+        let expiration = U256::from(terms.expiration);
+
+        // generateSwapId in CrossChainSwap.sol
+        let packed = ethers::abi::encode_packed(&[
+            AbiToken::Address(from),
+            AbiToken::Address(recipient),
+            AbiToken::Address(token),
+            AbiToken::Uint(amount_u256),
+            AbiToken::FixedBytes(hash),
+            AbiToken::Uint(expiration),
+        ])
+        .map_err(|e| RouterError::ExecutionError(format!("Failed to encode swap id: {}", e)))?;
+        let id = H256::from(ethers::utils::keccak256(packed));
+
+        let swap = HtlcSwap {
+            swap_id: format!("{:?}", id),
+            terms,
+            secret_hash: format!("{:?}", secret_hash),
+            status: SwapStatus::Initiated,
+            lock_tx: lock_tx.clone(),
+            counter_swap_id: None,
+            counter_expiration: None,
+            claim_tx: None,
+            refund_tx: None,
+        };
+        self.swaps.insert(id, swap.clone());
+        let _ = self.updates.send(SwapUpdate {
+            swap_id: swap.swap_id.clone(),
+            status: SwapStatus::Initiated,
+            tx_hash: Some(lock_tx),
+        });
+
+        Ok(swap)
+    }
+
+    // Approve if needed, then send the lock; returns the lock transaction hash
+    async fn lock(&self, source: &HtlcChain<M>, from: Address, terms: &SwapTerms, secret_hash: H256) -> Result<String, RouterError> {
+        let amount = math::parse_amount(&terms.amount_in)?;
+        let amount_u256 = math::to_u256(&amount)?;
+        let token = parse_address(&terms.token_in)?;
+        let recipient = parse_address(&terms.counterparty)?;
+        let expiration = U256::from(terms.expiration);
+        let hash = secret_hash.as_bytes().to_vec();
+
+        let (data, value) = if token.is_zero() {
+            let data = encode_call(
+                INITIATE_ETH_SWAP,
+                &[AbiToken::Address(recipient), AbiToken::FixedBytes(hash.clone()), AbiToken::Uint(expiration)],
+            );
+            (data, amount_u256)
+        } else {
+            let approval = execution::approval_tx(
+                &*source.client,
+                &terms.token_in,
+                &format!("{:?}", from),
+                &format!("{:?}", source.htlc),
+                &amount,
+            )
+            .await?;
+            if let Some(approval) = approval {
+                let data = hex::decode(approval.data.trim_start_matches("0x"))
+                    .map_err(|e| RouterError::ExecutionError(format!("Invalid approval calldata: {}", e)))?;
+                send(source, parse_address(&approval.to)?, data, U256::zero()).await?;
+            }
+            let data = encode_call(
+                INITIATE_TOKEN_SWAP,
+                &[
+                    AbiToken::Address(token),
+                    AbiToken::Uint(amount_u256),
+                    AbiToken::Address(recipient),
+                    AbiToken::FixedBytes(hash.clone()),
+                    AbiToken::Uint(expiration),
+                ],
+            );
+            (data, U256::zero())
+        };
+        send(source, source.htlc, data, value).await
+    }
+
+    async fn forget_secret(&self, secret_hash: H256) {
+        if let Err(e) = self.vault.remove(secret_hash).await {
+            warn!("Failed to remove secret {:?} from the vault: {}", secret_hash, e);
+        }
+    }
+
+    // Advance a swap from chain state: look for the counterparty's lock and check
+    // expirations. Claiming and refunding stay explicit.
+    pub async fn poll_swap(&self, swap_id: &str) -> Result<SwapStatus, RouterError> {
+        let id = parse_swap_id(swap_id)?;
+        let swap = self
+            .swaps
+            .get(&id)
+            .map(|swap| swap.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown swap {}", swap_id)))?;
+        if swap.status.is_final() || swap.status == SwapStatus::Expired {
+            return Ok(swap.status);
+        }
+
+        let source = self.chain(swap.terms.source_chain)?;
+        let dest = self.chain(swap.terms.dest_chain)?;
+
+        if swap.status == SwapStatus::Initiated {
+            let secret_hash: H256 = swap
+                .secret_hash
+                .parse()
+                .map_err(|_| RouterError::ExecutionError(format!("Invalid secret hash {}", swap.secret_hash)))?;
+            if let Some((counter_id, counter)) = counter_lock(dest, secret_hash).await? {
+                match self.check_counter_lock(&swap, dest, &counter) {
+                    Ok(()) => {
+                        if let Some(mut entry) = self.swaps.get_mut(&id) {
+                            entry.counter_swap_id = Some(format!("{:?}", counter_id));
+                            entry.counter_expiration = Some(counter.expiration);
+                        }
+                        self.set_status(id, SwapStatus::Locked, None);
+                    }
+                    Err(e) => warn!("Ignoring destination lock {:?} for swap {}: {}", counter_id, swap_id, e),
+                }
+            }
+        }
+
+        let status = self.swaps.get(&id).map(|swap| swap.status).unwrap_or(swap.status);
+        let expired = match status {
+            // Claims must land before the destination lock expires
+            SwapStatus::Locked => {
+                let counter_expiration = self.swaps.get(&id).and_then(|swap| swap.counter_expiration);
+                match counter_expiration {
+                    Some(expiration) => chain_time(&*dest.client).await? >= expiration,
+                    None => false,
+                }
+            }
+            _ => chain_time(&*source.client).await? >= swap.terms.expiration,
+        };
+        if expired {
+            self.set_status(id, SwapStatus::Expired, None);
+            return Ok(SwapStatus::Expired);
+        }
+        Ok(status)
+    }
+
+    fn check_counter_lock(&self, swap: &HtlcSwap, dest: &HtlcChain<M>, counter: &LockedSwap) -> Result<(), RouterError> {
+        if counter.status != HTLC_ACTIVE {
+            return Err(RouterError::ExecutionError("not active".to_string()));
+        }
+        if counter.recipient != Self::sender(dest)? {
+            return Err(RouterError::ExecutionError(format!("pays {:?}, not us", counter.recipient)));
+        }
+        if counter.token != parse_address(&swap.terms.token_out)? {
+            return Err(RouterError::ExecutionError(format!("locks token {:?}", counter.token)));
+        }
+        let min_amount_out = math::parse_amount(&swap.terms.min_amount_out)?;
+        if counter.amount < min_amount_out {
+            return Err(RouterError::ExecutionError(format!(
+                "locks {} but at least {} was agreed",
+                counter.amount, min_amount_out
+            )));
+        }
+        // Otherwise the counterparty could refund its side after learning the secret
+        if counter.expiration >= swap.terms.expiration {
+            return Err(RouterError::ExecutionError(format!(
+                "expires at {}, not before our lock at {}",
+                counter.expiration, swap.terms.expiration
+            )));
+        }
+        Ok(())
+    }
+
+    // Poll every swap that is still in flight
+    pub async fn poll(&self) -> Vec<(String, Result<SwapStatus, RouterError>)> {
+        let pending: Vec<String> = self
+            .swaps
+            .iter()
+            .filter(|swap| !swap.status.is_final() && swap.status != SwapStatus::Expired)
+            .map(|swap| swap.swap_id.clone())
+            .collect();
+
+        let mut results = Vec::with_capacity(pending.len());
+        for swap_id in pending {
+            let status = self.poll_swap(&swap_id).await;
+            results.push((swap_id, status));
+        }
+        results
+    }
+
+    // Poll in-flight swaps every `poll_interval`; watch progress through subscribe()
+    pub fn start(self: &Arc<Self>, poll_interval: Duration) -> JoinHandle<()> {
+        let swaps = self.clone();
+        tokio::spawn(async move {
+            loop {
+                for (swap_id, status) in swaps.poll().await {
+                    if let Err(e) = status {
+                        warn!("Polling swap {} failed: {}", swap_id, e);
+                    }
+                }
+                tokio::time::sleep(poll_interval).await;
+            }
+        })
+    }
+
+    // Reveal the secret on the destination chain to take the counterparty's funds
+    pub async fn claim_funds(&self, swap_id: &str) -> Result<String, RouterError> {
+        let id = parse_swap_id(swap_id)?;
+        if self.poll_swap(swap_id).await? != SwapStatus::Locked {
+            let status = self.swaps.get(&id).map(|swap| swap.status);
+            return Err(RouterError::ExecutionError(format!(
+                "Swap {} can't be claimed in status {:?}",
+                swap_id, status
+            )));
+        }
+        let (dest_chain, secret_hash) = self
+            .swaps
+            .get(&id)
+            .map(|swap| (swap.terms.dest_chain, swap.secret_hash.clone()))
+            .unwrap_or_default();
+        let dest = self.chain(dest_chain)?;
+        let secret_hash: H256 = secret_hash
+            .parse()
+            .map_err(|_| RouterError::ExecutionError(format!("Invalid secret hash {}", secret_hash)))?;
+        let secret = self.vault.reveal(secret_hash).await?;
+
+        // Public once the claim lands, but not before
+        let data = encode_call(CLAIM_FUNDS, &[AbiToken::FixedBytes(secret.expose().to_vec())]);
+        let tx_hash = send(dest, dest.htlc, data, U256::zero()).await?;
+
+        if let Some(mut swap) = self.swaps.get_mut(&id) {
+            swap.claim_tx = Some(tx_hash.clone());
+        }
+        self.set_status(id, SwapStatus::Claimed, Some(tx_hash.clone()));
+        self.forget_secret(secret_hash).await;
+        Ok(tx_hash)
+    }
+
+    // Take the source funds back once the lock expired unclaimed
+    pub async fn refund(&self, swap_id: &str) -> Result<String, RouterError> {
+        let id = parse_swap_id(swap_id)?;
+        let swap = self
+            .swaps
+            .get(&id)
+            .map(|swap| swap.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown swap {}", swap_id)))?;
+        if swap.status.is_final() {
+            return Err(RouterError::ExecutionError(format!("Swap {} is already {:?}", swap_id, swap.status)));
+        }
+
+        let source = self.chain(swap.terms.source_chain)?;
+        let now = chain_time(&*source.client).await?;
+        if now < swap.terms.expiration {
+            return Err(RouterError::ExecutionError(format!(
+                "Swap {} can't be refunded before {} (chain time {})",
+                swap_id, swap.terms.expiration, now
+            )));
+        }
+
+        let data = encode_call(REFUND, &[AbiToken::FixedBytes(id.as_bytes().to_vec())]);
+        let tx_hash = send(source, source.htlc, data, U256::zero()).await?;
+
+        if let Some(mut swap) = self.swaps.get_mut(&id) {
+            swap.refund_tx = Some(tx_hash.clone());
+        }
+        self.set_status(id, SwapStatus::Refunded, Some(tx_hash.clone()));
+        if let Ok(secret_hash) = swap.secret_hash.parse() {
+            self.forget_secret(secret_hash).await;
+        }
+        Ok(tx_hash)
+    }
+}
+
+fn parse_swap_id(swap_id: &str) -> Result<H256, RouterError> {
+    swap_id
+        .parse()
+        .map_err(|_| RouterError::ConfigError(format!("Invalid swap id: {}", swap_id)))
+}
+
+async fn chain_time<M: Middleware>(client: &M) -> Result<u64, RouterError> {
+    let block = client
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|e| RouterError::ChainError(format!("Failed to fetch latest block: {}", e)))?
+        .ok_or_else(|| RouterError::ChainError("Latest block not found".to_string()))?;
+    Ok(block.timestamp.as_u64())
+}
+
+// Send and wait for the receipt; reverted transactions are errors
+async fn send<M: Middleware>(chain: &HtlcChain<M>, to: Address, data: Vec<u8>, value: U256) -> Result<String, RouterError> {
+    let tx = Eip1559TransactionRequest::new().to(to).data(data).value(value);
+    let receipt = chain
+        .client
+        .send_transaction(tx, None)
+        .await
+        .map_err(|e| RouterError::ChainError(format!("Failed to send HTLC transaction: {}", e)))?
+        .await
+        .map_err(|e| RouterError::ChainError(format!("Failed to confirm HTLC transaction: {}", e)))?
+        .ok_or_else(|| RouterError::ChainError("HTLC transaction was dropped".to_string()))?;
+
+    let tx_hash = format!("{:?}", receipt.transaction_hash);
+    if receipt.status != Some(U64::from(1)) {
+        return Err(RouterError::Reverted(format!("HTLC transaction {}", tx_hash)));
+    }
+    Ok(tx_hash)
+}
+
+// Lock under `secret_hash` on the chain's HTLC contract, if there is one in a
+// block that is final under the chain's policy
+async fn counter_lock<M: Middleware>(chain: &HtlcChain<M>, secret_hash: H256) -> Result<Option<(H256, LockedSwap)>, RouterError> {
+    let block = Some(BlockId::Number(BlockNumber::Number(chain.finality.final_block(&*chain.client).await?.into())));
+    let data = encode_call("hashedSecrets(bytes32)", &[AbiToken::FixedBytes(secret_hash.as_bytes().to_vec())]);
+    let result = call_at(&*chain.client, chain.htlc, data, &[ParamType::FixedBytes(32)], block).await?;
+    let id = match result.first() {
+        Some(AbiToken::FixedBytes(bytes)) if bytes.len() == 32 => H256::from_slice(bytes),
+        _ => return Err(RouterError::ChainError("Invalid hashedSecrets result".to_string())),
+    };
+    if id.is_zero() {
+        return Ok(None);
+    }
+
+    let data = encode_call("swaps(bytes32)", &[AbiToken::FixedBytes(id.as_bytes().to_vec())]);
+    let fields = call_at(
+        &*chain.client,
+        chain.htlc,
+        data,
+        &[
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Address,
+            ParamType::Uint(256),
+            ParamType::FixedBytes(32),
+            ParamType::Uint(256),
+            ParamType::Uint(8),
+        ],
+        block,
+    )
+    .await?;
+    let uint = |index: usize| match fields.get(index) {
+        Some(AbiToken::Uint(value)) => *value,
+        _ => U256::zero(),
+    };
+    let address = |index: usize| match fields.get(index) {
+        Some(AbiToken::Address(value)) => *value,
+        _ => Address::zero(),
+    };
+
+    Ok(Some((
+        id,
+        LockedSwap {
+            recipient: address(1),
+            token: address(2),
+            amount: math::from_u256(uint(3)),
+            expiration: uint(5).low_u64(),
+            status: uint(6).low_u64(),
+        },
+    )))
+}
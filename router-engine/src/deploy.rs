@@ -0,0 +1,292 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ethers::abi::Token as AbiToken;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::secrets::Credential;
+
+// Canonical Permit2, at the same address on every chain it's deployed to
+pub const PERMIT2_ADDRESS: &str = "0x000000000022D473030F116dDEE9F6B43aC78BA3";
+
+// Fully qualified name of the executor contract, as Etherscan expects it
+pub const EXECUTOR_CONTRACT: &str = "contracts/core/RouterFacet.sol:RouterFacet";
+
+// Executor creation bytecode baked in by build.rs, empty when the contracts weren't compiled
+const BUNDLED_BYTECODE: &str = include_str!(concat!(env!("OUT_DIR"), "/executor_bytecode.hex"));
+
+const VERIFY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+const VERIFY_ATTEMPTS: usize = 24;
+
+// Creation bytecode of the executor contract
+#[derive(Debug, Clone)]
+pub struct ExecutorArtifact {
+    pub bytecode: Bytes,
+}
+
+impl ExecutorArtifact {
+    pub fn bundled() -> Result<Self, RouterError> {
+        if BUNDLED_BYTECODE.trim().is_empty() {
+            return Err(RouterError::ConfigError(
+                "No executor bytecode bundled; run `npm run compile` in contracts/ and rebuild".to_string(),
+            ));
+        }
+        Self::from_hex(BUNDLED_BYTECODE)
+    }
+
+    pub fn from_hex(bytecode: &str) -> Result<Self, RouterError> {
+        let bytecode = hex::decode(bytecode.trim().trim_start_matches("0x"))
+            .map_err(|e| RouterError::ConfigError(format!("Invalid executor bytecode: {}", e)))?;
+        Ok(Self { bytecode: bytecode.into() })
+    }
+
+    // Hardhat artifact JSON (artifacts/contracts/core/RouterFacet.sol/RouterFacet.json)
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, RouterError> {
+        let artifact = read_json(path.as_ref())?;
+        let bytecode = artifact["bytecode"]
+            .as_str()
+            .ok_or_else(|| RouterError::ConfigError(format!("{} has no bytecode", path.as_ref().display())))?;
+        Self::from_hex(bytecode)
+    }
+}
+
+fn read_json(path: &Path) -> Result<serde_json::Value, RouterError> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| RouterError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+    serde_json::from_str(&json).map_err(|e| RouterError::ConfigError(format!("Invalid JSON in {}: {}", path.display(), e)))
+}
+
+// Compiler input needed to verify the source on a block explorer
+#[derive(Debug, Clone)]
+pub struct VerificationSource {
+    // e.g. v0.8.19+commit.7dd6d404
+    pub compiler_version: String,
+    pub standard_json: serde_json::Value,
+}
+
+impl VerificationSource {
+    // From the build info Hardhat links next to an artifact (RouterFacet.dbg.json)
+    pub fn from_hardhat(artifact: impl AsRef<Path>) -> Result<Self, RouterError> {
+        let artifact = artifact.as_ref();
+        let debug_file = artifact.with_extension("dbg.json");
+        let build_info = read_json(&debug_file)?["buildInfo"]
+            .as_str()
+            .map(|relative| artifact.parent().unwrap_or(Path::new(".")).join(relative))
+            .ok_or_else(|| RouterError::ConfigError(format!("{} has no buildInfo", debug_file.display())))?;
+        let build_info = read_json(&build_info)?;
+
+        let version = build_info["solcLongVersion"]
+            .as_str()
+            .ok_or_else(|| RouterError::ConfigError("Build info has no solcLongVersion".to_string()))?;
+        Ok(Self {
+            compiler_version: format!("v{}", version.trim_start_matches('v')),
+            standard_json: build_info["input"].clone(),
+        })
+    }
+}
+
+// Owner setup done right after deployment
+#[derive(Debug, Clone, Default)]
+pub struct DeployOptions {
+    // Defaults to PERMIT2_ADDRESS
+    pub permit2: Option<String>,
+    // Extra relayers allowed to call protectedMultiSwap; the deployer always is one
+    pub relayers: Vec<String>,
+    // Wait for the chain's finality policy before registering the executor
+    pub wait_for_finality: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutorDeployment {
+    pub chain_id: u64,
+    pub address: String,
+    pub tx_hash: String,
+    pub block_number: u64,
+    // Owner calls made after deployment (setWeth, setPermit2, addRelayer)
+    pub setup_txs: Vec<String>,
+}
+
+// Etherscan-compatible verification through the multichain (v2) API
+pub struct EtherscanVerifier {
+    api_url: String,
+    api_key: Credential,
+    client: reqwest::Client,
+}
+
+impl EtherscanVerifier {
+    pub fn new(api_key: &str) -> Self {
+        Self::with_api_url("https://api.etherscan.io/v2/api", api_key)
+    }
+
+    // Self-hosted or per-chain explorers speaking the same API
+    pub fn with_api_url(api_url: &str, api_key: &str) -> Self {
+        Self {
+            api_url: api_url.to_string(),
+            api_key: Credential::new(api_key.to_string()),
+            client: reqwest::Client::new(),
+        }
+    }
+
+    async fn request(&self, chain_id: u64, form: &[(&str, String)]) -> Result<String, RouterError> {
+        let mut params = vec![("apikey", self.api_key.expose().clone())];
+        params.extend_from_slice(form);
+        let response: serde_json::Value = self
+            .client
+            .post(format!("{}?chainid={}", self.api_url, chain_id))
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Explorer request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Invalid explorer response: {}", e)))?;
+
+        let result = response["result"].as_str().unwrap_or_default().to_string();
+        if response["status"].as_str() != Some("1") {
+            return Err(RouterError::ExecutionError(format!("Explorer rejected request: {}", result)));
+        }
+        Ok(result)
+    }
+
+    // Submit the source and wait for the explorer to finish verifying it
+    pub async fn verify(&self, chain_id: u64, address: &str, source: &VerificationSource) -> Result<(), RouterError> {
+        let guid = self
+            .request(
+                chain_id,
+                &[
+                    ("module", "contract".to_string()),
+                    ("action", "verifysourcecode".to_string()),
+                    ("contractaddress", address.to_string()),
+                    ("sourceCode", source.standard_json.to_string()),
+                    ("codeformat", "solidity-standard-json-input".to_string()),
+                    ("contractname", EXECUTOR_CONTRACT.to_string()),
+                    ("compilerversion", source.compiler_version.clone()),
+                    // The executor takes no constructor arguments
+                    ("constructorArguements", String::new()),
+                ],
+            )
+            .await?;
+
+        for _ in 0..VERIFY_ATTEMPTS {
+            tokio::time::sleep(VERIFY_POLL_INTERVAL).await;
+            match self
+                .request(
+                    chain_id,
+                    &[
+                        ("module", "contract".to_string()),
+                        ("action", "checkverifystatus".to_string()),
+                        ("guid", guid.clone()),
+                    ],
+                )
+                .await
+            {
+                Ok(_) => return Ok(()),
+                Err(RouterError::ExecutionError(e)) if e.contains("Pending") => continue,
+                Err(RouterError::ExecutionError(e)) if e.contains("Already Verified") => return Ok(()),
+                Err(e) => return Err(e),
+            }
+        }
+        Err(RouterError::ExecutionError(format!("Verification of {} still pending", address)))
+    }
+}
+
+async fn send_and_confirm<M: Middleware>(client: &M, tx: TransactionRequest, what: &str) -> Result<TransactionReceipt, RouterError> {
+    let receipt = client
+        .send_transaction(tx, None)
+        .await
+        .map_err(|e| RouterError::ChainError(format!("Failed to send {}: {}", what, e)))?
+        .await
+        .map_err(|e| RouterError::ChainError(format!("{} dropped: {}", what, e)))?
+        .ok_or_else(|| RouterError::ChainError(format!("{} dropped from the mempool", what)))?;
+    if receipt.status.map(|s| s.as_u64()) != Some(1) {
+        return Err(RouterError::Reverted(format!("{} reverted in {:?}", what, receipt.transaction_hash)));
+    }
+    Ok(receipt)
+}
+
+impl RouterEngine {
+    // Deploy the executor with the client's signer as owner, point it at the
+    // chain's wrapped native token and Permit2, authorize relayers, and register
+    // it for the chain. Wrapping is left unset when no native token is registered.
+    pub async fn deploy_executor<M: Middleware + 'static>(
+        &self,
+        client: Arc<M>,
+        artifact: &ExecutorArtifact,
+        options: &DeployOptions,
+    ) -> Result<ExecutorDeployment, RouterError> {
+        let chain_id = client
+            .get_chainid()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch chain id: {}", e)))?
+            .as_u64();
+
+        let receipt = send_and_confirm(&*client, TransactionRequest::new().data(artifact.bytecode.clone()), "executor deployment").await?;
+        let address = receipt
+            .contract_address
+            .ok_or_else(|| RouterError::ChainError("Deployment receipt has no contract address".to_string()))?;
+        let code = client
+            .get_code(address, None)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch executor code: {}", e)))?;
+        if code.is_empty() {
+            return Err(RouterError::ExecutionError(format!("No code at deployed executor {:?}", address)));
+        }
+        info!("Deployed executor on chain {} at {:?}", chain_id, address);
+
+        let mut calls = Vec::new();
+        match self.native_tokens.get(&chain_id).map(|t| t.clone()) {
+            Some(native) => calls.push(("setWeth", encode_call("setWeth(address)", &[AbiToken::Address(parse_address(&native.address)?)]))),
+            None => warn!("No native token registered for chain {}, executor can't wrap ETH", chain_id),
+        }
+        let permit2 = parse_address(options.permit2.as_deref().unwrap_or(PERMIT2_ADDRESS))?;
+        calls.push(("setPermit2", encode_call("setPermit2(address)", &[AbiToken::Address(permit2)])));
+        for relayer in &options.relayers {
+            calls.push(("addRelayer", encode_call("addRelayer(address)", &[AbiToken::Address(parse_address(relayer)?)])));
+        }
+
+        let mut setup_txs = Vec::with_capacity(calls.len());
+        for (what, data) in calls {
+            let receipt = send_and_confirm(&*client, TransactionRequest::new().to(address).data(data), what).await?;
+            setup_txs.push(format!("{:?}", receipt.transaction_hash));
+        }
+
+        let block_number = receipt.block_number.map(|n| n.as_u64()).unwrap_or_default();
+        if options.wait_for_finality {
+            let policy = self.finality_policy(chain_id);
+            while !policy.is_final(&*client, block_number).await? {
+                tokio::time::sleep(Duration::from_secs(12)).await;
+            }
+        }
+
+        let address = format!("{:?}", address);
+        self.register_executor(chain_id, address.clone());
+        Ok(ExecutorDeployment {
+            chain_id,
+            address,
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            block_number,
+            setup_txs,
+        })
+    }
+
+    // deploy_executor followed by explorer verification; a failed verification is
+    // logged but keeps the (already registered) deployment
+    pub async fn deploy_and_verify_executor<M: Middleware + 'static>(
+        &self,
+        client: Arc<M>,
+        artifact_path: impl Into<PathBuf>,
+        verifier: &EtherscanVerifier,
+        options: &DeployOptions,
+    ) -> Result<ExecutorDeployment, RouterError> {
+        let artifact_path = artifact_path.into();
+        let artifact = ExecutorArtifact::from_file(&artifact_path)?;
+        let source = VerificationSource::from_hardhat(&artifact_path)?;
+
+        let deployment = self.deploy_executor(client, &artifact, options).await?;
+        if let Err(e) = verifier.verify(deployment.chain_id, &deployment.address, &source).await {
+            warn!("Failed to verify executor {} on chain {}: {}", deployment.address, deployment.chain_id, e);
+        }
+        Ok(deployment)
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::BTreeMap;
+
+use ethers::abi::Token as AbiToken;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::executor::decode_multi_swap_steps;
+
+pub(crate) const MULTI_SWAP_AND_SWEEP: &str =
+    "multiSwapAndSweep((address,address,address,uint256,uint256,bytes,uint16,uint16)[],(address,uint16)[],address[],address)";
+
+// Residue of a token the executor is left holding after a route
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DustAmount {
+    pub token: String,
+    pub amount: String,
+}
+
+fn output_token(route: &SwapRoute) -> Option<String> {
+    route.steps.last().map(|step| step.token_out.address.to_lowercase())
+}
+
+// Input and intermediate tokens of the route, which the executor sweeps after
+// paying out the output
+pub fn sweep_tokens(route: &SwapRoute) -> Vec<String> {
+    let output = output_token(route);
+    let mut tokens: Vec<String> = Vec::new();
+    for step in &route.steps {
+        for token in [&step.token_in, &step.token_out] {
+            let address = token.address.to_lowercase();
+            if Some(&address) != output.as_ref() && !tokens.contains(&address) {
+                tokens.push(address);
+            }
+        }
+    }
+    tokens
+}
+
+// What the route leaves behind at quoted amounts: input no step spends, and
+// outputs of steps that no later step sells, leaving out residue within the dust
+// threshold. Steps selling an intermediate token spend all that earlier steps
+// delivered of it, and the output token is paid out in full, so neither shows up here.
+pub fn expected_dust(route: &SwapRoute, rules: &math::AmountRules) -> Result<Vec<DustAmount>, RouterError> {
+    let output = output_token(route);
+    let input = route.steps.first().map(|step| step.token_in.address.to_lowercase());
+    let mut supplied: BTreeMap<String, BigUint> = BTreeMap::new();
+    let mut spent: BTreeMap<String, BigUint> = BTreeMap::new();
+
+    if let Some(first) = route.steps.first() {
+        *supplied.entry(first.token_in.address.to_lowercase()).or_default() += math::parse_amount(&route.amount_in)?;
+    }
+    for step in &route.steps {
+        *spent.entry(step.token_in.address.to_lowercase()).or_default() += math::parse_amount(&step.amount_in)?;
+        let produced = step.expected_amount_out.as_deref().unwrap_or(&step.amount_out_min);
+        *supplied.entry(step.token_out.address.to_lowercase()).or_default() += math::parse_amount(produced)?;
+    }
+
+    Ok(supplied
+        .into_iter()
+        .filter(|(token, _)| Some(token) != output.as_ref())
+        .filter(|(token, _)| Some(token) == input.as_ref() || !spent.contains_key(token))
+        .filter_map(|(token, amount)| {
+            let spent = spent.get(&token).cloned().unwrap_or_default();
+            (amount > spent && !rules.is_dust(&token, &(&amount - &spent))).then(|| DustAmount {
+                amount: (amount - spent).to_string(),
+                token,
+            })
+        })
+        .collect())
+}
+
+// Executor call running the steps of `multi_swap_calldata` (an encoded multiSwap
+// call), paying everything they produce of the output token to the recipients and
+// sending residue of `sweep_tokens` to `dust_recipient`
+pub fn encode_sweep_call(
+    multi_swap_calldata: &[u8],
+    recipients: &[payout::Recipient],
+    sweep_tokens: &[String],
+    dust_recipient: &str,
+) -> Result<Vec<u8>, RouterError> {
+    let shares = payout::encode_recipients(recipients)?;
+    let sweep_tokens = sweep_tokens
+        .iter()
+        .map(|token| Ok(AbiToken::Address(parse_address(token)?)))
+        .collect::<Result<Vec<_>, RouterError>>()?;
+
+    Ok(encode_call(
+        MULTI_SWAP_AND_SWEEP,
+        &[
+            decode_multi_swap_steps(multi_swap_calldata)?,
+            shares,
+            AbiToken::Array(sweep_tokens),
+            AbiToken::Address(parse_address(dust_recipient)?),
+        ],
+    ))
+}
+
+impl RouterEngine {
+    // Send swept residue on a chain to `sink` (e.g. a treasury) instead of the
+    // route's recipient
+    pub fn set_dust_sink(&self, chain_id: u64, sink: String) {
+        self.dust_sinks.insert(chain_id, sink);
+    }
+
+    pub fn clear_dust_sink(&self, chain_id: u64) {
+        self.dust_sinks.remove(&chain_id);
+    }
+
+    pub fn dust_sink(&self, chain_id: u64) -> Option<String> {
+        self.dust_sinks.get(&chain_id).map(|sink| sink.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::ParamType;
+    use ethers::types::U256;
+
+    use super::*;
+    use crate::executor::StepCall;
+    use crate::routing::RouteSplit;
+
+    const A: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const B: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const C: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+    const ROUTER: &str = "0x3333333333333333333333333333333333333333";
+    const RECIPIENT: &str = "0x1111111111111111111111111111111111111111";
+    const SINK: &str = "0x2222222222222222222222222222222222222222";
+
+    fn token(address: &str) -> Token {
+        Token {
+            chain_id: 1,
+            address: address.to_string(),
+            symbol: address.to_string(),
+            decimals: 18,
+        }
+    }
+
+    fn step(token_in: &str, token_out: &str, amount_in: u64, expected_out: u64) -> SwapStep {
+        SwapStep {
+            exchange_id: "x".to_string(),
+            token_in: token(token_in),
+            token_out: token(token_out),
+            fee_tier: None,
+            amount_in: amount_in.to_string(),
+            amount_out_min: (expected_out - 1).to_string(),
+            expected_amount_out: Some(expected_out.to_string()),
+            firmness: rfq::Firmness::Indicative,
+        }
+    }
+
+    fn leg(share_bps: u32, first_step: usize, step_count: usize, amount_in: u64) -> RouteSplit {
+        RouteSplit {
+            share_bps,
+            first_step,
+            step_count,
+            amount_in: amount_in.to_string(),
+            expected_amount_out: String::new(),
+        }
+    }
+
+    // 1000 A split 600 straight to B and 395 through C, leaving 5 A unspent
+    fn split_route() -> SwapRoute {
+        SwapRoute {
+            steps: vec![step(A, B, 600, 1_190), step(A, C, 395, 790), step(C, B, 790, 780)],
+            amount_in: "1000".to_string(),
+            expected_amount_out: "1970".to_string(),
+            splits: vec![leg(6_000, 0, 1, 600), leg(4_000, 1, 2, 400)],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn split_route_leaves_unspent_input() {
+        let route = split_route();
+        assert_eq!(sweep_tokens(&route), [A, C]);
+
+        // C delivered to the last step is spent in full, so only A shows up
+        let dust = expected_dust(&route, &math::AmountRules::default()).unwrap();
+        assert_eq!(
+            dust,
+            [DustAmount {
+                token: A.to_string(),
+                amount: "5".to_string(),
+            }]
+        );
+
+        let rules = math::AmountRules {
+            token_dust_thresholds: [(A.to_string(), 5)].into_iter().collect(),
+            ..Default::default()
+        };
+        assert!(expected_dust(&route, &rules).unwrap().is_empty());
+    }
+
+    #[test]
+    fn sweep_call_carries_steps_recipients_and_sweep_tokens() {
+        let route = split_route();
+        let calls: Vec<StepCall> = route
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(i, _)| StepCall {
+                target: ROUTER.to_string(),
+                data: format!("0x0{}", i),
+                amount_in_offset: (i == 2).then_some(4),
+            })
+            .collect();
+        let multi_swap = executor::encode_multi_swap(&route, &calls, mev::MevPolicy::PublicMempool).unwrap();
+        let recipients = [payout::Recipient {
+            address: RECIPIENT.to_string(),
+            share_bps: 10_000,
+        }];
+
+        let calldata = encode_sweep_call(&multi_swap, &recipients, &sweep_tokens(&route), SINK).unwrap();
+        assert_eq!(calldata[..4], ethers::utils::id(MULTI_SWAP_AND_SWEEP));
+        let args = ethers::abi::decode(
+            &[
+                ParamType::Array(Box::new(executor::swap_step_type())),
+                ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(16)]))),
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Address,
+            ],
+            &calldata[4..],
+        )
+        .unwrap();
+
+        let AbiToken::Array(steps) = &args[0] else {
+            panic!("steps aren't an array");
+        };
+        let legs: Vec<(Address, Address, U256, Vec<u8>, U256)> = steps
+            .iter()
+            .map(|step| match step {
+                AbiToken::Tuple(fields) => (
+                    fields[1].clone().into_address().unwrap(),
+                    fields[2].clone().into_address().unwrap(),
+                    fields[3].clone().into_uint().unwrap(),
+                    fields[5].clone().into_bytes().unwrap(),
+                    fields[7].clone().into_uint().unwrap(),
+                ),
+                _ => panic!("step isn't a tuple"),
+            })
+            .collect();
+        let (a, b, c) = (parse_address(A).unwrap(), parse_address(B).unwrap(), parse_address(C).unwrap());
+        assert_eq!(
+            legs,
+            [
+                (a, b, U256::from(600), vec![0x00], U256::zero()),
+                (a, c, U256::from(395), vec![0x01], U256::zero()),
+                (c, b, U256::from(790), vec![0x02], U256::from(4)),
+            ]
+        );
+
+        assert_eq!(
+            args[1],
+            AbiToken::Array(vec![AbiToken::Tuple(vec![
+                AbiToken::Address(parse_address(RECIPIENT).unwrap()),
+                AbiToken::Uint(U256::from(10_000)),
+            ])])
+        );
+        assert_eq!(args[2], AbiToken::Array(vec![AbiToken::Address(a), AbiToken::Address(c)]));
+        assert_eq!(args[3], AbiToken::Address(parse_address(SINK).unwrap()));
+    }
+}
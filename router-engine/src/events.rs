@@ -0,0 +1,91 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+use crate::bus::MessageBus;
+use crate::state::PoolState;
+
+// Engine activity published for analytics and risk consumers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EngineEvent {
+    Quote {
+        chain_id: u64,
+        token_in: String,
+        token_out: String,
+        amount_in: String,
+        route_count: usize,
+        best_amount_out: Option<String>,
+    },
+    RouteSelected {
+        chain_id: u64,
+        route: Box<SwapRoute>,
+    },
+    Execution {
+        chain_id: u64,
+        tx_hash: String,
+        status: String,
+        detail: Option<String>,
+    },
+    PoolUpdate {
+        state: PoolState,
+    },
+}
+
+impl EngineEvent {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EngineEvent::Quote { .. } => "quote",
+            EngineEvent::RouteSelected { .. } => "route_selected",
+            EngineEvent::Execution { .. } => "execution",
+            EngineEvent::PoolUpdate { .. } => "pool_update",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub timestamp_ms: u64,
+    #[serde(flatten)]
+    pub event: EngineEvent,
+}
+
+// Publishes each event kind to `<prefix>.<kind>` on the configured bus
+pub struct EventPublisher {
+    bus: Arc<dyn MessageBus>,
+    topic_prefix: String,
+}
+
+impl EventPublisher {
+    pub fn new(bus: Arc<dyn MessageBus>, topic_prefix: String) -> Self {
+        Self { bus, topic_prefix }
+    }
+
+    pub fn topic(&self, event: &EngineEvent) -> String {
+        format!("{}.{}", self.topic_prefix, event.kind())
+    }
+
+    pub async fn publish(&self, event: EngineEvent) -> Result<(), RouterError> {
+        let topic = self.topic(&event);
+        let envelope = EventEnvelope {
+            timestamp_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or_default(),
+            event,
+        };
+        let payload = serde_json::to_vec(&envelope)
+            .map_err(|e| RouterError::ExecutionError(format!("Failed to encode event: {}", e)))?;
+
+        self.bus.publish(&topic, &payload).await
+    }
+
+    // Fire-and-forget publish so event delivery never adds latency to quoting
+    pub fn publish_in_background(self: &Arc<Self>, event: EngineEvent) {
+        let publisher = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = publisher.publish(event).await {
+                warn!("Failed to publish engine event: {}", e);
+            }
+        });
+    }
+}
@@ -0,0 +1,689 @@
+use ethers::abi::{ParamType, Token as AbiToken};
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::adapters::call;
+use crate::executor::{self, StepCall};
+
+// Deadline of built transactions when the caller doesn't set one
+pub const DEFAULT_DEADLINE_SECS: u64 = 1_200;
+
+// Blocks pool state may lag the chain head before calldata is refused, unless
+// configured per chain
+pub const DEFAULT_MAX_STATE_AGE: u64 = 3;
+
+// Gas of a plain ERC-20 approve
+pub const APPROVE_GAS: u64 = 50_000;
+
+pub(crate) const V2_SWAP: &str = "swapExactTokensForTokens(uint256,uint256,address[],address,uint256)";
+pub(crate) const V3_EXACT_INPUT: &str = "exactInput((bytes,address,uint256,uint256,uint256))";
+
+// Router interface of an exchange, deciding how its swaps are encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    UniswapV2,
+    UniswapV3,
+    Curve,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionParams {
+    // Receiver of the route's output
+    pub recipient: String,
+    // Unix time after which the swap reverts
+    pub deadline: u64,
+    // Split the output between these instead of paying it all to `recipient`
+    #[serde(default)]
+    pub recipients: Option<Vec<payout::Recipient>>,
+}
+
+impl ExecutionParams {
+    pub fn new(recipient: String) -> Self {
+        Self {
+            recipient,
+            deadline: rfq::now() + DEFAULT_DEADLINE_SECS,
+            recipients: None,
+        }
+    }
+}
+
+// Native ETH ends of a route whose steps trade the wrapped native token
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NativeWrapping {
+    // The sender pays ETH, wrapped before the first step
+    pub wrap_input: bool,
+    // The recipient receives ETH, unwrapped after the last step
+    pub unwrap_output: bool,
+}
+
+// Transaction executing a route, ready to sign
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionTx {
+    pub to: String,
+    // Hex-encoded calldata
+    pub data: String,
+    // Wei sent along
+    pub value: String,
+    pub gas_estimate: u64,
+}
+
+// V3 path: token (20 bytes), then fee (3 bytes) and token for every hop
+fn v3_path(steps: &[SwapStep], exchange: &Exchange) -> Result<Vec<u8>, RouterError> {
+    let first = steps
+        .first()
+        .ok_or_else(|| RouterError::ExecutionError("Route has no steps".to_string()))?;
+    let mut path = parse_address(&first.token_in.address)?.as_bytes().to_vec();
+
+    for step in steps {
+        let fee = match (step.fee_tier, exchange.fee_tiers.as_slice()) {
+            (Some(fee), _) => fee,
+            (None, [only]) => *only,
+            (None, _) => {
+                return Err(RouterError::ExecutionError(format!(
+                    "{} step {}->{} has no fee tier",
+                    exchange.id, step.token_in.symbol, step.token_out.symbol
+                )))
+            }
+        };
+        path.extend_from_slice(&fee.to_be_bytes()[1..]);
+        path.extend_from_slice(parse_address(&step.token_out.address)?.as_bytes());
+    }
+    Ok(path)
+}
+
+// Byte offset of amountIn in encode_router_call's calldata: the first argument of
+// swapExactTokensForTokens, the fourth field of exactInput's params tuple (after
+// the selector and the tuple's offset word)
+fn amount_in_offset(exchange: &Exchange) -> Option<u16> {
+    match exchange.protocol {
+        Some(Protocol::UniswapV2) => Some(4),
+        Some(Protocol::UniswapV3) => Some(4 + 32 + 3 * 32),
+        Some(Protocol::Curve) | None => None,
+    }
+}
+
+// One router call swapping through consecutive `steps` of `exchange`
+fn encode_router_call(
+    exchange: &Exchange,
+    steps: &[SwapStep],
+    recipient: &str,
+    deadline: u64,
+) -> Result<Vec<u8>, RouterError> {
+    let (first, last) = match (steps.first(), steps.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(RouterError::ExecutionError("Route has no steps".to_string())),
+    };
+    if steps.iter().any(|step| executor::is_native(&step.token_in) || executor::is_native(&step.token_out)) {
+        return Err(RouterError::ExecutionError(format!(
+            "{} swaps of the native token need a wrapped-native path",
+            exchange.id
+        )));
+    }
+
+    let amount_in = AbiToken::Uint(math::to_u256(&math::parse_amount(&first.amount_in)?)?);
+    let min_out = AbiToken::Uint(math::to_u256(&math::parse_amount(&last.amount_out_min)?)?);
+    let recipient = AbiToken::Address(parse_address(recipient)?);
+    let deadline = AbiToken::Uint(U256::from(deadline));
+
+    match exchange.protocol {
+        Some(Protocol::UniswapV2) => {
+            let path = std::iter::once(&first.token_in)
+                .chain(steps.iter().map(|step| &step.token_out))
+                .map(|token| Ok(AbiToken::Address(parse_address(&token.address)?)))
+                .collect::<Result<Vec<_>, RouterError>>()?;
+            Ok(encode_call(V2_SWAP, &[amount_in, min_out, AbiToken::Array(path), recipient, deadline]))
+        }
+        Some(Protocol::UniswapV3) => Ok(encode_call(
+            V3_EXACT_INPUT,
+            &[AbiToken::Tuple(vec![
+                AbiToken::Bytes(v3_path(steps, exchange)?),
+                recipient,
+                deadline,
+                amount_in,
+                min_out,
+            ])],
+        )),
+        Some(Protocol::Curve) | None => Err(RouterError::ConfigError(format!(
+            "No calldata encoder for exchange {}",
+            exchange.id
+        ))),
+    }
+}
+
+// Consecutive steps sharing an exchange, each run a candidate for one router call
+pub(crate) fn segments(route: &SwapRoute) -> Vec<&[SwapStep]> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for i in 1..=route.steps.len() {
+        let boundary = i == route.steps.len()
+            || route.steps[i].exchange_id != route.steps[i - 1].exchange_id
+            || route.steps[i].token_in != route.steps[i - 1].token_out;
+        if boundary {
+            segments.push(&route.steps[start..i]);
+            start = i;
+        }
+    }
+    segments
+}
+
+// Approval the owner needs before `spender` can pull `amount` of `token`, or None
+// if the allowance already covers it. For Permit2 pass the Permit2 contract as
+// spender, then sign the allowance with permit::PermitRequest.
+pub async fn approval_tx<M: Middleware>(
+    client: &M,
+    token: &str,
+    owner: &str,
+    spender: &str,
+    amount: &BigUint,
+) -> Result<Option<ExecutionTx>, RouterError> {
+    let token_address = parse_address(token)?;
+    let spender_address = AbiToken::Address(parse_address(spender)?);
+    let data = encode_call(
+        "allowance(address,address)",
+        &[AbiToken::Address(parse_address(owner)?), spender_address.clone()],
+    );
+    let result = call(client, token_address, data, &[ParamType::Uint(256)]).await?;
+    let allowance = match result.first() {
+        Some(AbiToken::Uint(value)) => math::from_u256(*value),
+        _ => return Err(RouterError::ChainError(format!("Invalid allowance returned by {}", token))),
+    };
+    if allowance >= *amount {
+        return Ok(None);
+    }
+
+    approve_tx(token, spender, amount).map(Some)
+}
+
+// ERC-20 approve of `amount` of `token` to `spender`
+pub fn approve_tx(token: &str, spender: &str, amount: &BigUint) -> Result<ExecutionTx, RouterError> {
+    let data = encode_call(
+        "approve(address,uint256)",
+        &[AbiToken::Address(parse_address(spender)?), AbiToken::Uint(math::to_u256(amount)?)],
+    );
+    Ok(ExecutionTx {
+        to: token.to_string(),
+        data: format!("0x{}", hex::encode(data)),
+        value: "0".to_string(),
+        gas_estimate: APPROVE_GAS,
+    })
+}
+
+impl RouterEngine {
+    pub fn register_exchange(&self, exchange: Exchange) {
+        self.exchanges.insert((exchange.chain_id, exchange.id.clone()), exchange);
+        // Which chains a source builds graph edges on follows its exchanges
+        self.invalidate_token_graph(None);
+    }
+
+    pub fn exchange(&self, chain_id: u64, exchange_id: &str) -> Result<Exchange, RouterError> {
+        self.exchanges
+            .get(&(chain_id, exchange_id.to_string()))
+            .map(|e| e.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown exchange {} on chain {}", exchange_id, chain_id)))
+    }
+
+    pub fn list_exchanges(&self, chain_id: Option<u64>) -> Vec<Exchange> {
+        let mut exchanges: Vec<Exchange> = self
+            .exchanges
+            .iter()
+            .filter(|e| !matches!(chain_id, Some(id) if id != e.chain_id))
+            .map(|e| e.clone())
+            .collect();
+        exchanges.sort_by(|a, b| (a.chain_id, &a.id).cmp(&(b.chain_id, &b.id)));
+        exchanges
+    }
+
+    // Latest block seen on a chain, fed by the chain sync loop
+    pub fn record_block(&self, chain_id: u64, block_number: u64) {
+        let advanced = {
+            let mut head = self.chain_heads.entry(chain_id).or_insert(0);
+            let advanced = *head < block_number;
+            if advanced {
+                *head = block_number;
+            }
+            advanced
+        };
+        if advanced {
+            let keep_from = block_number.saturating_sub(cache::STALE_RETENTION_BLOCKS);
+            self.quote_cache().invalidate_before(chain_id, keep_from);
+        }
+    }
+
+    pub fn set_max_state_age(&self, chain_id: u64, blocks: u64) {
+        self.max_state_age.insert(chain_id, blocks);
+    }
+
+    // Refuse routes priced from pool state too far behind the chain head, whose
+    // min-outs would most likely revert. Passes when either block is unknown.
+    pub fn check_state_age(&self, route: &SwapRoute, chain_id: u64) -> Result<(), RouterError> {
+        let (Some(state_block), Some(current_block)) = (route.state_block, self.chain_heads.get(&chain_id).map(|h| *h)) else {
+            return Ok(());
+        };
+        let max_age = self
+            .max_state_age
+            .get(&chain_id)
+            .map(|a| *a)
+            .unwrap_or(DEFAULT_MAX_STATE_AGE);
+
+        if current_block.saturating_sub(state_block) > max_age {
+            return Err(RouterError::StaleQuote { state_block, current_block });
+        }
+        Ok(())
+    }
+
+    // For unwrap_native requests, route native ETH ends through the chain's wrapped
+    // native token and report which ends the executor has to wrap or unwrap
+    pub fn resolve_native_wrapping(&self, request: &mut QuoteRequest) -> Option<NativeWrapping> {
+        if !request.unwrap_native {
+            return None;
+        }
+        let wrapped = self.native_tokens.get(&request.chain_id)?.address.clone();
+
+        let mut wrapping = NativeWrapping::default();
+        for (address, flag) in [
+            (&mut request.token_in, &mut wrapping.wrap_input),
+            (&mut request.token_out, &mut wrapping.unwrap_output),
+        ] {
+            if executor::is_native_address(address) {
+                *address = wrapped.clone();
+                *flag = true;
+            } else if address.eq_ignore_ascii_case(&wrapped) {
+                *flag = true;
+            }
+        }
+
+        (wrapping.wrap_input || wrapping.unwrap_output).then_some(wrapping)
+    }
+
+    // Executable transaction for `route`. Routes through a single exchange call its
+    // router directly; anything else goes through the executor, one exchange call
+    // per step, paying the output to the recipients via multiSwapAndSplit or, when
+    // native ETH is wrapped or unwrapped, multiSwapNative. Multi-step routes use
+    // multiSwapAndSweep so split legs and intermediate residue aren't left behind.
+    // Output split between several recipients always goes through the executor.
+    pub fn build_execution(
+        &self,
+        route: &SwapRoute,
+        chain_id: u64,
+        params: &ExecutionParams,
+    ) -> Result<ExecutionTx, RouterError> {
+        self.check_state_age(route, chain_id)?;
+        // Only a route one router takes whole is sent to it; anything needing more
+        // than one spender goes through the executor so the user approves once
+        if let Some(exchange) = self.direct_exchange(route, chain_id).filter(|_| params.recipients.is_none()) {
+            let data = encode_router_call(&exchange, &route.steps, &params.recipient, params.deadline)?;
+            return Ok(ExecutionTx {
+                to: exchange.router_address,
+                data: format!("0x{}", hex::encode(data)),
+                value: "0".to_string(),
+                gas_estimate: route.gas_estimate,
+            });
+        }
+
+        let executor_address = self.executor(chain_id)?;
+        let input = route.steps.first().map(|step| step.token_in.address.to_lowercase());
+        let calls = route
+            .steps
+            .iter()
+            .map(|step| {
+                let exchange = self.exchange(chain_id, &step.exchange_id)?;
+                // Steps selling what earlier steps bought spend their actual output,
+                // patched in by the executor, which also checks their min-out scaled
+                // to it; the router's own check would hold them to the quoted input
+                let mut call_step = step.clone();
+                if Some(step.token_in.address.to_lowercase()) != input {
+                    call_step.amount_out_min = "0".to_string();
+                }
+                let data = encode_router_call(&exchange, std::slice::from_ref(&call_step), &executor_address, params.deadline)?;
+                Ok(StepCall {
+                    target: exchange.router_address.clone(),
+                    data: format!("0x{}", hex::encode(data)),
+                    amount_in_offset: amount_in_offset(&exchange),
+                })
+            })
+            .collect::<Result<Vec<_>, RouterError>>()?;
+        let dust_sink = self.dust_sink(chain_id);
+        call_plan::CallPlan {
+            route,
+            calls: &calls,
+            executor: &executor_address,
+            recipient: &params.recipient,
+            dust_sink: dust_sink.as_deref(),
+        }
+        .validate(&self.tokens, chain_id)?;
+
+        let multi_swap = executor::encode_multi_swap(route, &calls, mev::MevPolicy::PublicMempool)?;
+        let sponsored = match &route.gas_payment {
+            Some(gas_payment::GasPaymentQuote {
+                mode: gas_payment::GasPaymentMode::Sponsored { sponsor },
+                token_amount,
+                ..
+            }) => Some((sponsor, math::parse_amount(token_amount)?)),
+            _ => None,
+        };
+        if params.recipients.is_some() && (route.native_wrapping.is_some() || sponsored.is_some()) {
+            return Err(RouterError::ConfigError(
+                "Output split between recipients can't be combined with native wrapping or sponsored gas".to_string(),
+            ));
+        }
+        let data = match (&route.native_wrapping, sponsored) {
+            (Some(_), Some(_)) => {
+                return Err(RouterError::ConfigError(
+                    "Sponsored gas can't be combined with native wrapping".to_string(),
+                ))
+            }
+            (None, Some((sponsor, fee))) => gas_payment::encode_sponsored_call(&multi_swap, &params.recipient, sponsor, &fee)?,
+            (Some(wrapping), None) => executor::encode_native_swap(&multi_swap, &params.recipient, wrapping)?,
+            (None, None) => {
+                let recipients = params.recipients.clone().unwrap_or_else(|| {
+                    vec![payout::Recipient {
+                        address: params.recipient.clone(),
+                        share_bps: 10_000,
+                    }]
+                });
+                if route.steps.len() > 1 {
+                    let sink = dust_sink.unwrap_or_else(|| params.recipient.clone());
+                    dust::encode_sweep_call(&multi_swap, &recipients, &dust::sweep_tokens(route), &sink)?
+                } else {
+                    payout::encode_split_call(&multi_swap, &recipients)?
+                }
+            }
+        };
+        let wraps_input = matches!(route.native_wrapping, Some(w) if w.wrap_input);
+        let value = match route.steps.first() {
+            Some(step) if wraps_input || executor::is_native(&step.token_in) => route.amount_in.clone(),
+            _ => "0".to_string(),
+        };
+
+        Ok(ExecutionTx {
+            to: executor_address,
+            data: format!("0x{}", hex::encode(data)),
+            value,
+            gas_estimate: route.gas_estimate,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routing::RouteSplit;
+
+    const CHAIN: u64 = 1;
+    const A: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const B: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const C: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+    const EXECUTOR: &str = "0x1111111111111111111111111111111111111111";
+    const RECIPIENT: &str = "0x2222222222222222222222222222222222222222";
+
+    fn token(address: &str, symbol: &str) -> Token {
+        Token {
+            chain_id: CHAIN,
+            address: address.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 18,
+        }
+    }
+
+    fn step(exchange_id: &str, token_in: &Token, token_out: &Token, amount_in: u64, amount_out: u64) -> SwapStep {
+        SwapStep {
+            exchange_id: exchange_id.to_string(),
+            token_in: token_in.clone(),
+            token_out: token_out.clone(),
+            fee_tier: None,
+            amount_in: amount_in.to_string(),
+            amount_out_min: (amount_out * 99 / 100).to_string(),
+            expected_amount_out: Some(amount_out.to_string()),
+            firmness: rfq::Firmness::Indicative,
+        }
+    }
+
+    fn engine() -> RouterEngine {
+        let engine = RouterEngine::new();
+        for (address, symbol) in [(A, "A"), (B, "B"), (C, "C")] {
+            engine.register_token(token(address, symbol));
+        }
+        for (id, router) in [
+            ("x", "0x3333333333333333333333333333333333333333"),
+            ("y", "0x4444444444444444444444444444444444444444"),
+        ] {
+            let exchange = serde_json::from_value(serde_json::json!({
+                "id": id,
+                "name": id,
+                "chain_id": CHAIN,
+                "router_address": router,
+                "factory_address": null,
+                "fee_tiers": [3000],
+                "protocol": "uniswap_v2",
+            }))
+            .unwrap();
+            engine.register_exchange(exchange);
+        }
+        engine.register_executor(CHAIN, EXECUTOR.to_string());
+        engine
+    }
+
+    // 600 A straight to B on x, 400 A to B through C on x then y
+    fn split_route() -> SwapRoute {
+        let (a, b, c) = (token(A, "A"), token(B, "B"), token(C, "C"));
+        SwapRoute {
+            steps: vec![
+                step("x", &a, &b, 600, 1_190),
+                step("x", &a, &c, 400, 796),
+                step("y", &c, &b, 796, 790),
+            ],
+            amount_in: "1000".to_string(),
+            expected_amount_out: "1980".to_string(),
+            splits: vec![
+                RouteSplit {
+                    share_bps: 6_000,
+                    first_step: 0,
+                    step_count: 1,
+                    amount_in: "600".to_string(),
+                    expected_amount_out: "1190".to_string(),
+                },
+                RouteSplit {
+                    share_bps: 4_000,
+                    first_step: 1,
+                    step_count: 2,
+                    amount_in: "400".to_string(),
+                    expected_amount_out: "790".to_string(),
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn executor_calldata_pulls_route_input() {
+        let engine = engine();
+        let route = split_route();
+        let tx = engine
+            .build_execution(&route, CHAIN, &ExecutionParams::new(RECIPIENT.to_string()))
+            .unwrap();
+        assert_eq!(tx.to, EXECUTOR);
+        assert_eq!(tx.value, "0");
+
+        let calldata = hex::decode(tx.data.trim_start_matches("0x")).unwrap();
+        assert_eq!(calldata[..4], ethers::utils::id(dust::MULTI_SWAP_AND_SWEEP));
+        let args = ethers::abi::decode(
+            &[
+                ParamType::Array(Box::new(executor::swap_step_type())),
+                ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(16)]))),
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Address,
+            ],
+            &calldata[4..],
+        )
+        .unwrap();
+        let AbiToken::Array(steps) = &args[0] else {
+            panic!("steps argument isn't an array");
+        };
+
+        // The executor pulls the amountIn of every step selling the first step's
+        // tokenIn from the sender: both legs' share of the input, nothing more
+        let fields = |step: &AbiToken| match step {
+            AbiToken::Tuple(fields) => (fields[1].clone().into_address().unwrap(), fields[3].clone().into_uint().unwrap()),
+            _ => panic!("step isn't a tuple"),
+        };
+        let (input_token, _) = fields(&steps[0]);
+        assert_eq!(input_token, parse_address(A).unwrap());
+        let pulled: U256 = steps
+            .iter()
+            .map(fields)
+            .filter(|(token_in, _)| *token_in == input_token)
+            .map(|(_, amount_in)| amount_in)
+            .fold(U256::zero(), |total, amount| total + amount);
+        assert_eq!(pulled, U256::from(1_000));
+    }
+
+    #[test]
+    fn executor_calldata_pays_requested_recipients() {
+        let engine = engine();
+        let recipients = vec![
+            payout::Recipient {
+                address: RECIPIENT.to_string(),
+                share_bps: 2_500,
+            },
+            payout::Recipient {
+                address: EXECUTOR.to_string(),
+                share_bps: 7_500,
+            },
+        ];
+        let mut params = ExecutionParams::new(RECIPIENT.to_string());
+        params.recipients = Some(recipients);
+        let shares = AbiToken::Array(vec![
+            AbiToken::Tuple(vec![AbiToken::Address(parse_address(RECIPIENT).unwrap()), AbiToken::Uint(U256::from(2_500))]),
+            AbiToken::Tuple(vec![AbiToken::Address(parse_address(EXECUTOR).unwrap()), AbiToken::Uint(U256::from(7_500))]),
+        ]);
+        let recipients_type = ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(16)])));
+
+        let tx = engine.build_execution(&split_route(), CHAIN, &params).unwrap();
+        let calldata = hex::decode(tx.data.trim_start_matches("0x")).unwrap();
+        assert_eq!(calldata[..4], ethers::utils::id(dust::MULTI_SWAP_AND_SWEEP));
+        let args = ethers::abi::decode(
+            &[ParamType::Array(Box::new(executor::swap_step_type())), recipients_type.clone()],
+            &calldata[4..],
+        )
+        .unwrap();
+        assert_eq!(args[1], shares);
+
+        // A single step its router could take whole still goes through the executor
+        let (a, b) = (token(A, "A"), token(B, "B"));
+        let route = SwapRoute {
+            steps: vec![step("x", &a, &b, 1_000, 1_990)],
+            amount_in: "1000".to_string(),
+            expected_amount_out: "1990".to_string(),
+            ..Default::default()
+        };
+        let tx = engine.build_execution(&route, CHAIN, &params).unwrap();
+        assert_eq!(tx.to, EXECUTOR);
+        let calldata = hex::decode(tx.data.trim_start_matches("0x")).unwrap();
+        assert_eq!(calldata[..4], ethers::utils::id(payout::MULTI_SWAP_AND_SPLIT));
+        let args = ethers::abi::decode(
+            &[ParamType::Array(Box::new(executor::swap_step_type())), recipients_type],
+            &calldata[4..],
+        )
+        .unwrap();
+        assert_eq!(args[1], shares);
+    }
+
+    #[test]
+    fn approval_plan_covers_pulled_input() {
+        let engine = engine();
+        let route = split_route();
+        let plan = engine.approval_plan(&route, CHAIN).unwrap();
+        assert!(plan.via_executor);
+        assert_eq!(plan.spender, EXECUTOR);
+        assert_eq!(plan.token.as_deref(), Some(A));
+        assert_eq!(plan.amount, "1000");
+    }
+
+    fn word(data: &[u8], offset: usize) -> U256 {
+        U256::from_big_endian(&data[offset..offset + 32])
+    }
+
+    #[test]
+    fn later_hops_leave_amount_in_to_executor() {
+        let engine = engine();
+        let route = split_route();
+        let tx = engine
+            .build_execution(&route, CHAIN, &ExecutionParams::new(RECIPIENT.to_string()))
+            .unwrap();
+        let calldata = hex::decode(tx.data.trim_start_matches("0x")).unwrap();
+        let args = ethers::abi::decode(&[ParamType::Array(Box::new(executor::swap_step_type()))], &calldata[4..]).unwrap();
+        let AbiToken::Array(steps) = &args[0] else {
+            panic!("steps argument isn't an array");
+        };
+
+        for (i, step) in steps.iter().enumerate() {
+            let AbiToken::Tuple(fields) = step else {
+                panic!("step isn't a tuple");
+            };
+            let amount_in = fields[3].clone().into_uint().unwrap();
+            let data = fields[5].clone().into_bytes().unwrap();
+            let offset = fields[7].clone().into_uint().unwrap().as_usize();
+            // The word the executor overwrites is the router call's amountIn
+            assert_eq!(offset, 4);
+            assert_eq!(word(&data, offset), amount_in);
+            // Only steps selling the route's input keep the router's min-out; the
+            // executor checks the others against what they actually spend
+            let router_min_out = word(&data, 36);
+            if i == 2 {
+                assert!(router_min_out.is_zero());
+            } else {
+                assert_eq!(router_min_out, fields[4].clone().into_uint().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn v3_amount_in_offset() {
+        let exchange: Exchange = serde_json::from_value(serde_json::json!({
+            "id": "v3",
+            "name": "v3",
+            "chain_id": CHAIN,
+            "router_address": "0x5555555555555555555555555555555555555555",
+            "factory_address": null,
+            "fee_tiers": [500, 3000],
+            "protocol": "uniswap_v3",
+        }))
+        .unwrap();
+        let mut swap = step("v3", &token(A, "A"), &token(B, "B"), 123_456, 1_000);
+        swap.fee_tier = Some(500);
+        let data = encode_router_call(&exchange, &[swap], RECIPIENT, 1).unwrap();
+        assert_eq!(word(&data, amount_in_offset(&exchange).unwrap() as usize), U256::from(123_456));
+    }
+
+    #[test]
+    fn routes_revisiting_their_input_or_output_are_unsafe() {
+        let engine = engine();
+        let (a, b, c) = (token(A, "A"), token(B, "B"), token(C, "C"));
+        let params = ExecutionParams::new(RECIPIENT.to_string());
+        let single = |steps: Vec<SwapStep>| SwapRoute {
+            amount_in: steps[0].amount_in.clone(),
+            expected_amount_out: steps.last().unwrap().expected_amount_out.clone().unwrap(),
+            steps,
+            ..Default::default()
+        };
+
+        // A back out of C would be left for the sweep instead of sold for B
+        let buys_input = single(vec![
+            step("x", &a, &c, 1_000, 2_000),
+            step("y", &c, &a, 2_000, 990),
+            step("x", &a, &b, 990, 1_960),
+        ]);
+        assert!(matches!(engine.build_execution(&buys_input, CHAIN, &params), Err(RouterError::UnsafeRoute(_))));
+
+        // Selling B mid-route would spend output the recipient is owed
+        let sells_output = single(vec![
+            step("x", &a, &b, 1_000, 2_000),
+            step("y", &b, &c, 2_000, 1_000),
+            step("x", &c, &b, 1_000, 1_990),
+        ]);
+        assert!(matches!(engine.build_execution(&sells_output, CHAIN, &params), Err(RouterError::UnsafeRoute(_))));
+
+        assert!(engine.build_execution(&split_route(), CHAIN, &params).is_ok());
+    }
+}
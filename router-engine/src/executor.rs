@@ -0,0 +1,165 @@
+use ethers::abi::{ParamType, Token as AbiToken};
+
+use super::*;
+use crate::abi_registry::encode_call;
+
+// RouterFacet entry points
+pub const MULTI_SWAP: &str = "multiSwap((address,address,address,uint256,uint256,bytes,uint16,uint16)[])";
+pub const PROTECTED_MULTI_SWAP: &str = "protectedMultiSwap((address,address,address,uint256,uint256,bytes,uint16,uint16)[])";
+pub const MULTI_SWAP_NATIVE: &str = "multiSwapNative((address,address,address,uint256,uint256,bytes,uint16,uint16)[],address,bool,bool)";
+
+// Exchange call the executor makes for one route step
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StepCall {
+    // Contract the executor approves and calls, usually the exchange router
+    pub target: String,
+    // Hex-encoded calldata for the exchange
+    pub data: String,
+    // Byte offset of the amount_in word in data, which the executor overwrites with
+    // what earlier steps actually delivered; None if the call's amount is fixed
+    #[serde(default)]
+    pub amount_in_offset: Option<u16>,
+}
+
+pub(crate) fn swap_step_type() -> ParamType {
+    ParamType::Tuple(vec![
+        ParamType::Address,
+        ParamType::Address,
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Bytes,
+        ParamType::Uint(16),
+        ParamType::Uint(16),
+    ])
+}
+
+// The route's steps as RouterFacet SwapStep tuples; native ETH is address(0)
+pub fn encode_steps(route: &SwapRoute, calls: &[StepCall]) -> Result<AbiToken, RouterError> {
+    if calls.len() != route.steps.len() {
+        return Err(RouterError::ExecutionError(format!(
+            "Route has {} steps but {} exchange calls were given",
+            route.steps.len(),
+            calls.len()
+        )));
+    }
+
+    let steps = route
+        .steps
+        .iter()
+        .zip(calls)
+        .map(|(step, call)| {
+            let data = hex::decode(call.data.trim_start_matches("0x"))
+                .map_err(|_| RouterError::ExecutionError(format!("Invalid step calldata: {}", call.data)))?;
+            Ok(AbiToken::Tuple(vec![
+                AbiToken::Address(parse_address(&call.target)?),
+                AbiToken::Address(parse_address(&step.token_in.address)?),
+                AbiToken::Address(parse_address(&step.token_out.address)?),
+                AbiToken::Uint(math::to_u256(&math::parse_amount(&step.amount_in)?)?),
+                AbiToken::Uint(math::to_u256(&math::parse_amount(&step.amount_out_min)?)?),
+                AbiToken::Bytes(data),
+                // SwapStep.feeTier is uint16; tiers are only informational on-chain
+                AbiToken::Uint(U256::from(step.fee_tier.unwrap_or_default().min(u16::MAX as u32))),
+                AbiToken::Uint(U256::from(call.amount_in_offset.unwrap_or_default())),
+            ]))
+        })
+        .collect::<Result<Vec<_>, RouterError>>()?;
+
+    Ok(AbiToken::Array(steps))
+}
+
+// Executor calldata for the route, through protectedMultiSwap when it goes to a private relay
+pub fn encode_multi_swap(route: &SwapRoute, calls: &[StepCall], policy: mev::MevPolicy) -> Result<Vec<u8>, RouterError> {
+    let signature = match policy {
+        mev::MevPolicy::PublicMempool => MULTI_SWAP,
+        mev::MevPolicy::PrivateRelay => PROTECTED_MULTI_SWAP,
+    };
+    Ok(encode_call(signature, &[encode_steps(route, calls)?]))
+}
+
+// Swap steps argument of an encoded multiSwap call
+pub(crate) fn decode_multi_swap_steps(calldata: &[u8]) -> Result<AbiToken, RouterError> {
+    let selector = ethers::utils::id(MULTI_SWAP);
+    if calldata.len() < 4 || calldata[..4] != selector {
+        return Err(RouterError::ExecutionError("Calldata is not a multiSwap call".to_string()));
+    }
+
+    ethers::abi::decode(&[ParamType::Array(Box::new(swap_step_type()))], &calldata[4..])
+        .map_err(|e| RouterError::ExecutionError(format!("Failed to decode multiSwap steps: {}", e)))?
+        .pop()
+        .ok_or_else(|| RouterError::ExecutionError("multiSwap call has no steps".to_string()))
+}
+
+// Transaction calling the executor, ready to sign
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutorTx {
+    pub to: String,
+    // Hex-encoded calldata
+    pub data: String,
+    // Wei sent along, the input amount when swapping from native ETH
+    pub value: String,
+}
+
+// What RouterFacet pulls from the sender before running `steps`: the amount_in of
+// every step selling the first step's token, so each leg of a split route is
+// funded. None for native input, which is sent as value instead.
+pub fn pulled_input(steps: &[SwapStep]) -> Result<Option<BigUint>, RouterError> {
+    let Some(first) = steps.first().filter(|step| !is_native(&step.token_in)) else {
+        return Ok(None);
+    };
+    let mut amount = BigUint::default();
+    for step in steps.iter().filter(|step| step.token_in.address.eq_ignore_ascii_case(&first.token_in.address)) {
+        amount += math::parse_amount(&step.amount_in)?;
+    }
+    Ok(Some(amount))
+}
+
+pub fn is_native(token: &Token) -> bool {
+    is_native_address(&token.address)
+}
+
+pub fn is_native_address(address: &str) -> bool {
+    parse_address(address).map(|a| a.is_zero()).unwrap_or(false)
+}
+
+// Executor call running the steps of `multi_swap_calldata` (an encoded multiSwap
+// call) between native ETH and tokens, wrapping and unwrapping WETH on the way
+pub fn encode_native_swap(
+    multi_swap_calldata: &[u8],
+    recipient: &str,
+    wrapping: &execution::NativeWrapping,
+) -> Result<Vec<u8>, RouterError> {
+    Ok(encode_call(
+        MULTI_SWAP_NATIVE,
+        &[
+            decode_multi_swap_steps(multi_swap_calldata)?,
+            AbiToken::Address(parse_address(recipient)?),
+            AbiToken::Bool(wrapping.wrap_input),
+            AbiToken::Bool(wrapping.unwrap_output),
+        ],
+    ))
+}
+
+impl RouterEngine {
+    // Executor transaction for a route on its chain's registered executor
+    pub fn build_executor_tx(
+        &self,
+        route: &SwapRoute,
+        chain_id: u64,
+        calls: &[StepCall],
+        policy: mev::MevPolicy,
+    ) -> Result<ExecutorTx, RouterError> {
+        self.check_state_age(route, chain_id)?;
+        let data = encode_multi_swap(route, calls, policy)?;
+        let value = match route.steps.first() {
+            Some(step) if is_native(&step.token_in) => route.amount_in.clone(),
+            _ => "0".to_string(),
+        };
+
+        Ok(ExecutorTx {
+            to: self.executor(chain_id)?,
+            data: format!("0x{}", hex::encode(data)),
+            value,
+        })
+    }
+}
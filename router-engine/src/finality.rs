@@ -0,0 +1,78 @@
+use super::*;
+
+// When a mined transaction counts as final on a chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityPolicy {
+    // Blocks including the transaction's own, e.g. 1 = mined
+    pub confirmations: u64,
+    // Wait for the node's `finalized` block instead of counting confirmations
+    #[serde(default)]
+    pub finalized_tag: bool,
+}
+
+impl FinalityPolicy {
+    pub fn confirmations(confirmations: u64) -> Self {
+        Self {
+            confirmations: confirmations.max(1),
+            finalized_tag: false,
+        }
+    }
+
+    pub fn finalized() -> Self {
+        Self {
+            confirmations: 1,
+            finalized_tag: true,
+        }
+    }
+
+    // Conservative defaults; rollups count their sequencer's confirmation
+    pub fn for_chain(chain_id: u64) -> Self {
+        match chain_id {
+            // Ethereum: one epoch
+            1 => Self::confirmations(32),
+            // Arbitrum, Optimism, Base, zkSync Era, Avalanche C-Chain
+            42161 | 10 | 8453 | 324 | 43114 => Self::confirmations(1),
+            // BNB Chain
+            56 => Self::confirmations(15),
+            // Polygon PoS reorgs deeper than most
+            137 => Self::confirmations(128),
+            _ => Self::confirmations(12),
+        }
+    }
+
+    // Newest block whose contents are final under this policy
+    pub async fn final_block<M: Middleware>(&self, client: &M) -> Result<u64, RouterError> {
+        if self.finalized_tag {
+            let block = client
+                .get_block(BlockNumber::Finalized)
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to fetch finalized block: {}", e)))?
+                .ok_or_else(|| RouterError::ChainError("Finalized block not found".to_string()))?;
+            return Ok(block.number.map(|n| n.as_u64()).unwrap_or_default());
+        }
+
+        let head = client
+            .get_block_number()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch block number: {}", e)))?
+            .as_u64();
+        Ok((head + 1).saturating_sub(self.confirmations.max(1)))
+    }
+
+    pub async fn is_final<M: Middleware>(&self, client: &M, block_number: u64) -> Result<bool, RouterError> {
+        Ok(self.final_block(client).await? >= block_number)
+    }
+}
+
+impl RouterEngine {
+    pub fn set_finality_policy(&self, chain_id: u64, policy: FinalityPolicy) {
+        self.finality.insert(chain_id, policy);
+    }
+
+    pub fn finality_policy(&self, chain_id: u64) -> FinalityPolicy {
+        self.finality
+            .get(&chain_id)
+            .map(|p| *p)
+            .unwrap_or_else(|| FinalityPolicy::for_chain(chain_id))
+    }
+}
@@ -0,0 +1,227 @@
+use ethers::abi::{encode, Token as AbiToken};
+
+use super::*;
+use crate::abi_registry::encode_call;
+
+// Supported flash-loan lenders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlashLoanKind {
+    AaveV3,
+    Balancer,
+}
+
+impl FlashLoanKind {
+    // Protocol fee in basis points
+    pub fn default_fee_bps(&self) -> u32 {
+        match self {
+            FlashLoanKind::AaveV3 => 5,
+            FlashLoanKind::Balancer => 0,
+        }
+    }
+}
+
+// A lender deployment on a specific chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlashLoanProvider {
+    pub kind: FlashLoanKind,
+    pub chain_id: u64,
+    // Aave Pool or Balancer Vault address
+    pub address: String,
+    pub fee_bps: u32,
+}
+
+impl FlashLoanProvider {
+    pub fn new(kind: FlashLoanKind, chain_id: u64, address: String) -> Self {
+        Self {
+            kind,
+            chain_id,
+            address,
+            fee_bps: kind.default_fee_bps(),
+        }
+    }
+
+    pub fn quote(&self, token: &Token, amount: &BigUint) -> FlashLoan {
+        let fee = math::bps_of(amount, self.fee_bps as u64, math::Rounding::Up);
+
+        FlashLoan {
+            kind: self.kind,
+            lender: self.address.clone(),
+            token: token.clone(),
+            amount: amount.to_string(),
+            fee: fee.to_string(),
+            expected_profit: None,
+        }
+    }
+}
+
+// Flash loan funding the start of a route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlashLoan {
+    pub kind: FlashLoanKind,
+    pub lender: String,
+    pub token: Token,
+    pub amount: String,
+    pub fee: String,
+    // Output left after repayment, only for routes ending in the borrowed token
+    pub expected_profit: Option<String>,
+}
+
+impl FlashLoan {
+    pub fn repayment(&self) -> Result<BigUint, RouterError> {
+        Ok(math::parse_amount(&self.amount)? + math::parse_amount(&self.fee)?)
+    }
+}
+
+// Route that starts and ends in the same token, i.e. an arbitrage cycle
+pub fn is_cyclic(route: &SwapRoute) -> bool {
+    match (route.steps.first(), route.steps.last()) {
+        (Some(first), Some(last)) => first.token_in == last.token_out,
+        _ => false,
+    }
+}
+
+// Profit of a cyclic route after repaying the loan, rejecting routes that can't repay
+pub fn check_profit(route: &SwapRoute, loan: &FlashLoan) -> Result<BigUint, RouterError> {
+    let repayment = loan.repayment()?;
+    let amount_out = math::parse_amount(&route.expected_amount_out)?;
+
+    if amount_out <= repayment {
+        return Err(RouterError::Unprofitable(format!(
+            "output {} does not cover flash loan repayment {}",
+            amount_out, repayment
+        )));
+    }
+
+    Ok(amount_out - repayment)
+}
+
+// Data handed to the executor's flash-loan callback: the swap calldata to run,
+// the lender to repay, and the exact repayment amount
+pub fn encode_callback_params(executor_calldata: &[u8], loan: &FlashLoan) -> Result<Vec<u8>, RouterError> {
+    Ok(encode(&[
+        AbiToken::Bytes(executor_calldata.to_vec()),
+        AbiToken::Address(parse_address(&loan.lender)?),
+        AbiToken::Uint(math::to_u256(&loan.repayment()?)?),
+    ]))
+}
+
+// Calldata for the lender call that funds the route and calls back into `receiver`
+pub fn encode_flash_loan(
+    loan: &FlashLoan,
+    receiver: &str,
+    executor_calldata: &[u8],
+) -> Result<Vec<u8>, RouterError> {
+    let receiver = AbiToken::Address(parse_address(receiver)?);
+    let asset = parse_address(&loan.token.address)?;
+    let amount = math::to_u256(&math::parse_amount(&loan.amount)?)?;
+    let params = AbiToken::Bytes(encode_callback_params(executor_calldata, loan)?);
+
+    let (signature, args) = match loan.kind {
+        FlashLoanKind::AaveV3 => (
+            "flashLoanSimple(address,address,uint256,bytes,uint16)",
+            vec![
+                receiver,
+                AbiToken::Address(asset),
+                AbiToken::Uint(amount),
+                params,
+                AbiToken::Uint(U256::zero()),
+            ],
+        ),
+        FlashLoanKind::Balancer => (
+            "flashLoan(address,address[],uint256[],bytes)",
+            vec![
+                receiver,
+                AbiToken::Array(vec![AbiToken::Address(asset)]),
+                AbiToken::Array(vec![AbiToken::Uint(amount)]),
+                params,
+            ],
+        ),
+    };
+
+    Ok(encode_call(signature, &args))
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::{decode, ParamType};
+
+    use super::*;
+
+    const WETH: &str = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+    const LENDER: &str = "0x87870bca3f3fd6335c3f4ce8392d69350b4fa4e2";
+    const RECEIVER: &str = "0x1111111111111111111111111111111111111111";
+
+    fn loan(kind: FlashLoanKind) -> FlashLoan {
+        let weth = Token {
+            chain_id: 1,
+            address: WETH.to_string(),
+            symbol: "WETH".to_string(),
+            decimals: 18,
+        };
+        FlashLoanProvider::new(kind, 1, LENDER.to_string()).quote(&weth, &BigUint::from(1_000_000u64))
+    }
+
+    // The callback data: executor calldata, lender and repayment
+    fn callback_params(params: &AbiToken) -> Vec<AbiToken> {
+        let params = params.clone().into_bytes().unwrap();
+        decode(&[ParamType::Bytes, ParamType::Address, ParamType::Uint(256)], &params).unwrap()
+    }
+
+    #[test]
+    fn aave_loan_calls_flash_loan_simple() {
+        let calldata = encode_flash_loan(&loan(FlashLoanKind::AaveV3), RECEIVER, &[0xde, 0xad]).unwrap();
+        assert_eq!(
+            calldata[..4],
+            ethers::utils::id("flashLoanSimple(address,address,uint256,bytes,uint16)")
+        );
+        let args = decode(
+            &[ParamType::Address, ParamType::Address, ParamType::Uint(256), ParamType::Bytes, ParamType::Uint(16)],
+            &calldata[4..],
+        )
+        .unwrap();
+
+        assert_eq!(args[0], AbiToken::Address(parse_address(RECEIVER).unwrap()));
+        assert_eq!(args[1], AbiToken::Address(parse_address(WETH).unwrap()));
+        assert_eq!(args[2], AbiToken::Uint(U256::from(1_000_000)));
+        assert_eq!(args[4], AbiToken::Uint(U256::zero()));
+        // 5 bps on top of the principal
+        assert_eq!(
+            callback_params(&args[3]),
+            [
+                AbiToken::Bytes(vec![0xde, 0xad]),
+                AbiToken::Address(parse_address(LENDER).unwrap()),
+                AbiToken::Uint(U256::from(1_000_500)),
+            ]
+        );
+    }
+
+    #[test]
+    fn balancer_loan_calls_flash_loan() {
+        let calldata = encode_flash_loan(&loan(FlashLoanKind::Balancer), RECEIVER, &[0xde, 0xad]).unwrap();
+        assert_eq!(calldata[..4], ethers::utils::id("flashLoan(address,address[],uint256[],bytes)"));
+        let args = decode(
+            &[
+                ParamType::Address,
+                ParamType::Array(Box::new(ParamType::Address)),
+                ParamType::Array(Box::new(ParamType::Uint(256))),
+                ParamType::Bytes,
+            ],
+            &calldata[4..],
+        )
+        .unwrap();
+
+        assert_eq!(args[0], AbiToken::Address(parse_address(RECEIVER).unwrap()));
+        assert_eq!(args[1], AbiToken::Array(vec![AbiToken::Address(parse_address(WETH).unwrap())]));
+        assert_eq!(args[2], AbiToken::Array(vec![AbiToken::Uint(U256::from(1_000_000))]));
+        // Balancer charges no fee
+        assert_eq!(
+            callback_params(&args[3]),
+            [
+                AbiToken::Bytes(vec![0xde, 0xad]),
+                AbiToken::Address(parse_address(LENDER).unwrap()),
+                AbiToken::Uint(U256::from(1_000_000)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,298 @@
+use ethers::abi::{AbiParser, Token as AbiToken};
+use ethers::providers::MiddlewareError;
+
+use super::*;
+use crate::execution::{V2_SWAP, V3_EXACT_INPUT};
+
+// Executor entry points whose arguments include the route's SwapStep array
+const EXECUTOR_CALLS: &[&str] = &[
+    executor::MULTI_SWAP,
+    executor::PROTECTED_MULTI_SWAP,
+    executor::MULTI_SWAP_NATIVE,
+    payout::MULTI_SWAP_AND_SPLIT,
+    dust::MULTI_SWAP_AND_SWEEP,
+    gas_payment::MULTI_SWAP_AND_PAY_GAS,
+    permit::PERMIT_BATCH_MULTI_SWAP,
+];
+
+// Revert reasons of V2/V3 routers, the executor and common tokens, by cause
+const MIN_OUT_REASONS: &[&str] = &["INSUFFICIENT_OUTPUT_AMOUNT", "Insufficient output amount", "Too little received"];
+const DEADLINE_REASONS: &[&str] = &["EXPIRED", "Transaction too old", "deadline"];
+const TRANSFER_REASONS: &[&str] = &["UniswapV2: K", "TRANSFER_FAILED", "TRANSFER_FROM_FAILED", "STF", "(TF)", "(ST)"];
+
+// Why a swap transaction reverted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cause", rename_all = "snake_case")]
+pub enum FailureCause {
+    // A step returned less than its amountOutMin, typically after the price moved
+    MinOutBreach {
+        step: Option<usize>,
+        exchange_id: Option<String>,
+        amount_out_min: Option<String>,
+        // What the step returns against current pool state
+        current_amount_out: Option<String>,
+    },
+    DeadlineExpired { deadline: Option<u64>, block_timestamp: u64 },
+    // A token took more in transfer tax than the route was quoted with
+    TaxChanged { token: Option<String>, detail: String },
+    // A pool no longer holds the step's minimum output
+    PoolDrained {
+        step: usize,
+        exchange_id: String,
+        reserve_out: String,
+        amount_out_min: String,
+    },
+    OutOfGas { gas_used: u64, gas_limit: u64 },
+    Unknown,
+}
+
+// Root cause of a failed swap, for answering user complaints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureDiagnosis {
+    pub tx_hash: String,
+    pub block_number: u64,
+    pub block_timestamp: u64,
+    pub from: String,
+    pub to: Option<String>,
+    // Decoded revert of the transaction re-run against its inclusion block's parent state
+    pub revert_reason: Option<String>,
+    pub cause: FailureCause,
+}
+
+// One hop of a decoded executor call
+#[derive(Debug, Clone)]
+struct CalledStep {
+    exchange: Address,
+    token_in: Address,
+    token_out: Address,
+    amount_in: U256,
+    amount_out_min: U256,
+    data: Vec<u8>,
+}
+
+fn decode_call(signature: &str, calldata: &[u8]) -> Option<Vec<AbiToken>> {
+    let function = AbiParser::default().parse_function(&format!("function {}", signature)).ok()?;
+    if calldata.len() < 4 || calldata[..4] != function.short_signature() {
+        return None;
+    }
+    function.decode_input(&calldata[4..]).ok()
+}
+
+fn called_step(token: &AbiToken) -> Option<CalledStep> {
+    let AbiToken::Tuple(fields) = token else {
+        return None;
+    };
+    match fields.as_slice() {
+        [AbiToken::Address(exchange), AbiToken::Address(token_in), AbiToken::Address(token_out), AbiToken::Uint(amount_in), AbiToken::Uint(amount_out_min), AbiToken::Bytes(data), AbiToken::Uint(_), AbiToken::Uint(_)] => {
+            Some(CalledStep {
+                exchange: *exchange,
+                token_in: *token_in,
+                token_out: *token_out,
+                amount_in: *amount_in,
+                amount_out_min: *amount_out_min,
+                data: data.clone(),
+            })
+        }
+        _ => None,
+    }
+}
+
+// SwapStep array of an executor call, empty for any other calldata
+fn called_steps(calldata: &[u8]) -> Vec<CalledStep> {
+    EXECUTOR_CALLS
+        .iter()
+        .find_map(|signature| decode_call(signature, calldata))
+        .and_then(|args| {
+            args.into_iter().find_map(|arg| match arg {
+                AbiToken::Array(steps) => steps.iter().map(called_step).collect(),
+                _ => None,
+            })
+        })
+        .unwrap_or_default()
+}
+
+// Deadline of a V2 or V3 router call
+fn router_deadline(calldata: &[u8]) -> Option<u64> {
+    if let Some(args) = decode_call(V2_SWAP, calldata) {
+        return args.get(4).and_then(|arg| arg.clone().into_uint()).map(|d| d.low_u64());
+    }
+    match decode_call(V3_EXACT_INPUT, calldata)?.first()? {
+        AbiToken::Tuple(params) => params.get(2).and_then(|arg| arg.clone().into_uint()).map(|d| d.low_u64()),
+        _ => None,
+    }
+}
+
+fn matches_any(reason: &str, patterns: &[&str]) -> bool {
+    patterns.iter().any(|pattern| reason.contains(pattern))
+}
+
+impl RouterEngine {
+    fn exchange_at(&self, chain_id: u64, router: Address) -> Option<Exchange> {
+        self.exchanges
+            .iter()
+            .find(|e| e.chain_id == chain_id && parse_address(&e.router_address).ok() == Some(router))
+            .map(|e| e.clone())
+    }
+
+    // Fetch a reverted swap, re-run it against the state its block started from
+    // and work out why it failed. Pool checks use the engine's current view of
+    // the pools, so they are most accurate soon after the failure.
+    pub async fn diagnose_failed_tx<M: Middleware>(&self, client: &M, tx_hash: H256) -> Result<FailureDiagnosis, RouterError> {
+        let tx = client
+            .get_transaction(tx_hash)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch transaction: {}", e)))?
+            .ok_or_else(|| RouterError::ExecutionError(format!("Unknown transaction {:?}", tx_hash)))?;
+        let receipt = client
+            .get_transaction_receipt(tx_hash)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch receipt: {}", e)))?
+            .ok_or_else(|| RouterError::ExecutionError(format!("Transaction {:?} is not mined yet", tx_hash)))?;
+        if receipt.status == Some(U64::from(1)) {
+            return Err(RouterError::ExecutionError(format!("Transaction {:?} did not fail", tx_hash)));
+        }
+        let block_number = receipt
+            .block_number
+            .ok_or_else(|| RouterError::ChainError("Receipt has no block number".to_string()))?
+            .as_u64();
+        let block_timestamp = client
+            .get_block(block_number)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch block {}: {}", block_number, e)))?
+            .map(|block| block.timestamp.as_u64())
+            .unwrap_or_default();
+        let chain_id = match tx.chain_id {
+            Some(chain_id) => chain_id.as_u64(),
+            None => client
+                .get_chainid()
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to fetch chain id: {}", e)))?
+                .as_u64(),
+        };
+
+        let mut call = TransactionRequest::new().from(tx.from).data(tx.input.clone()).value(tx.value).gas(tx.gas);
+        if let Some(to) = tx.to {
+            call = call.to(to);
+        }
+        let replay = client
+            .call(&call.into(), Some(BlockNumber::Number(U64::from(block_number.saturating_sub(1))).into()))
+            .await;
+        let revert_reason = match &replay {
+            Ok(_) => None,
+            Err(e) => Some(match e.as_error_response().and_then(|response| response.as_revert_data()) {
+                Some(data) if data.is_empty() => "reverted without data".to_string(),
+                Some(data) => self.abis.describe_revert(&data).unwrap_or_else(|| format!("0x{}", hex::encode(&data))),
+                None => e.to_string(),
+            }),
+        };
+
+        let cause = self
+            .failure_cause(chain_id, &tx, &receipt, replay.is_ok(), revert_reason.as_deref(), block_timestamp)
+            .await;
+        info!("Diagnosed failed swap {:?}: {:?}", tx_hash, cause);
+        Ok(FailureDiagnosis {
+            tx_hash: format!("{:?}", tx_hash),
+            block_number,
+            block_timestamp,
+            from: format!("{:?}", tx.from),
+            to: tx.to.map(|to| format!("{:?}", to)),
+            revert_reason,
+            cause,
+        })
+    }
+
+    async fn failure_cause(
+        &self,
+        chain_id: u64,
+        tx: &Transaction,
+        receipt: &TransactionReceipt,
+        replay_succeeded: bool,
+        reason: Option<&str>,
+        block_timestamp: u64,
+    ) -> FailureCause {
+        let steps = called_steps(&tx.input);
+        let reason = reason.unwrap_or_default();
+
+        // The router calls carry the deadline, directly or inside executor steps
+        let deadline = std::iter::once(tx.input.as_ref())
+            .chain(steps.iter().map(|step| step.data.as_slice()))
+            .filter_map(router_deadline)
+            .min();
+        if matches!(deadline, Some(deadline) if deadline < block_timestamp) || matches_any(reason, DEADLINE_REASONS) {
+            return FailureCause::DeadlineExpired { deadline, block_timestamp };
+        }
+
+        let gas_used = receipt.gas_used.map(|g| g.as_u64()).unwrap_or_default();
+        if replay_succeeded && gas_used >= tx.gas.as_u64() {
+            return FailureCause::OutOfGas {
+                gas_used,
+                gas_limit: tx.gas.as_u64(),
+            };
+        }
+
+        // Price each hop against current pool state
+        let mut shortfall = None;
+        for (i, step) in steps.iter().enumerate() {
+            let Some(exchange) = self.exchange_at(chain_id, step.exchange) else {
+                continue;
+            };
+            let Some(source) = self.liquidity_sources.get(&exchange.id).map(|s| s.clone()) else {
+                continue;
+            };
+            let (Ok(token_in), Ok(token_out)) = (
+                self.resolve_token(chain_id, &format!("{:?}", step.token_in)).await,
+                self.resolve_token(chain_id, &format!("{:?}", step.token_out)).await,
+            ) else {
+                continue;
+            };
+            let min_out = math::from_u256(step.amount_out_min);
+
+            if let Ok((_, reserve_out)) = source.get_reserves(&token_in, &token_out).await {
+                if reserve_out < min_out {
+                    return FailureCause::PoolDrained {
+                        step: i,
+                        exchange_id: exchange.id,
+                        reserve_out: reserve_out.to_string(),
+                        amount_out_min: min_out.to_string(),
+                    };
+                }
+            }
+            if let Ok((amount_out, _)) = source.get_quote(&token_in, &token_out, &math::from_u256(step.amount_in)).await {
+                if shortfall.is_none() && amount_out < min_out {
+                    shortfall = Some(FailureCause::MinOutBreach {
+                        step: Some(i),
+                        exchange_id: Some(exchange.id.clone()),
+                        amount_out_min: Some(min_out.to_string()),
+                        current_amount_out: Some(amount_out.to_string()),
+                    });
+                }
+            }
+
+            if matches_any(reason, TRANSFER_REASONS) {
+                if let Some(token) = [&token_in, &token_out].into_iter().find(|t| self.token_tax(t).is_some()) {
+                    return FailureCause::TaxChanged {
+                        token: Some(token.address.clone()),
+                        detail: format!("{} ({})", reason, token.symbol),
+                    };
+                }
+            }
+        }
+
+        if matches_any(reason, TRANSFER_REASONS) {
+            return FailureCause::TaxChanged {
+                token: None,
+                detail: reason.to_string(),
+            };
+        }
+        match shortfall {
+            Some(cause) => cause,
+            None if matches_any(reason, MIN_OUT_REASONS) => FailureCause::MinOutBreach {
+                step: None,
+                exchange_id: None,
+                amount_out_min: None,
+                current_amount_out: None,
+            },
+            None => FailureCause::Unknown,
+        }
+    }
+}
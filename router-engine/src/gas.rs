@@ -0,0 +1,154 @@
+use super::*;
+
+// Gas charged once per transaction for the executor call and token transfers
+pub const DEFAULT_ROUTE_OVERHEAD: u64 = 60_000;
+
+// Gas per hop for sources without a calibrated constant
+pub const DEFAULT_HOP_GAS: u64 = 110_000;
+
+// Weight of a new report in the moving averages
+const CALIBRATION_ALPHA: f64 = 0.1;
+
+// Reports whose realized gas is this far off the simulation are treated as outliers
+const MAX_REPORT_RATIO: f64 = 3.0;
+
+// Outcome of an executed route, as fed back into calibration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub chain_id: u64,
+    pub tx_hash: String,
+    // Exchange id of every hop, in order
+    pub sources: Vec<String>,
+    // Estimate the route was ranked with
+    pub simulated_gas: u64,
+    // Gas used according to the receipt
+    pub realized_gas: u64,
+}
+
+impl ExecutionReport {
+    pub fn new(chain_id: u64, route: &SwapRoute, receipt: &TransactionReceipt) -> Self {
+        Self {
+            chain_id,
+            tx_hash: format!("{:?}", receipt.transaction_hash),
+            sources: route.steps.iter().map(|step| step.exchange_id.clone()).collect(),
+            simulated_gas: route.gas_estimate,
+            realized_gas: receipt.gas_used.map(|g| g.as_u64()).unwrap_or_default(),
+        }
+    }
+
+    // Route shape, e.g. "uniswap_v3>curve"
+    pub fn shape(&self) -> String {
+        self.sources.join(">")
+    }
+}
+
+// Calibration state of one route shape
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShapeCalibration {
+    pub samples: u64,
+    // Moving average of realized / simulated gas
+    pub ratio: f64,
+}
+
+// Per-source gas constants, continuously corrected from execution reports
+pub struct GasModel {
+    route_overhead: u64,
+    hop_gas: DashMap<(u64, String), u64>,
+    shapes: DashMap<(u64, String), ShapeCalibration>,
+}
+
+impl GasModel {
+    pub fn new() -> Self {
+        Self {
+            route_overhead: DEFAULT_ROUTE_OVERHEAD,
+            hop_gas: DashMap::new(),
+            shapes: DashMap::new(),
+        }
+    }
+
+    // Seed a source's per-hop gas, e.g. from its adapter's static constant
+    pub fn set_hop_gas(&self, chain_id: u64, source: &str, gas: u64) {
+        self.hop_gas.insert((chain_id, source.to_string()), gas);
+    }
+
+    pub fn route_overhead(&self) -> u64 {
+        self.route_overhead
+    }
+
+    pub fn hop_gas(&self, chain_id: u64, source: &str) -> u64 {
+        self.hop_gas
+            .get(&(chain_id, source.to_string()))
+            .map(|g| *g)
+            .unwrap_or(DEFAULT_HOP_GAS)
+    }
+
+    pub fn shape_calibration(&self, chain_id: u64, shape: &str) -> Option<ShapeCalibration> {
+        self.shapes.get(&(chain_id, shape.to_string())).map(|s| s.clone())
+    }
+
+    pub fn estimate(&self, chain_id: u64, route: &SwapRoute) -> u64 {
+        self.estimate_with(chain_id, route, |_| None)
+    }
+
+    // Estimate where `hop_override` may fix the gas of individual steps
+    pub fn estimate_with<F>(&self, chain_id: u64, route: &SwapRoute, hop_override: F) -> u64
+    where
+        F: Fn(&SwapStep) -> Option<u64>,
+    {
+        self.route_overhead
+            + route
+                .steps
+                .iter()
+                .map(|step| hop_override(step).unwrap_or_else(|| self.hop_gas(chain_id, &step.exchange_id)))
+                .sum::<u64>()
+    }
+
+    // Move the constants of every source in the report towards the realized gas
+    pub fn record(&self, report: &ExecutionReport) {
+        if report.simulated_gas == 0 || report.realized_gas == 0 || report.sources.is_empty() {
+            return;
+        }
+
+        let ratio = report.realized_gas as f64 / report.simulated_gas as f64;
+        if !(1.0 / MAX_REPORT_RATIO..=MAX_REPORT_RATIO).contains(&ratio) {
+            warn!(
+                "Ignoring gas report for {}: realized {} vs simulated {}",
+                report.tx_hash, report.realized_gas, report.simulated_gas
+            );
+            return;
+        }
+
+        let mut shape = self
+            .shapes
+            .entry((report.chain_id, report.shape()))
+            .or_insert(ShapeCalibration { samples: 0, ratio: 1.0 });
+        shape.samples += 1;
+        shape.ratio += CALIBRATION_ALPHA * (ratio - shape.ratio);
+
+        // Attribute the whole deviation to the hops; the overhead is comparatively fixed
+        let realized_hops = report.realized_gas.saturating_sub(self.route_overhead) as f64;
+        let simulated_hops = report.simulated_gas.saturating_sub(self.route_overhead).max(1) as f64;
+        let hop_ratio = realized_hops / simulated_hops;
+
+        for source in &report.sources {
+            let current = self.hop_gas(report.chain_id, source) as f64;
+            let target = current * hop_ratio;
+            let updated = current + CALIBRATION_ALPHA * (target - current);
+            self.hop_gas.insert((report.chain_id, source.clone()), updated.round().max(1.0) as u64);
+        }
+
+        debug!(
+            "Calibrated gas for {} on chain {}: ratio {:.3} over {} samples",
+            report.shape(),
+            report.chain_id,
+            shape.ratio,
+            shape.samples
+        );
+    }
+}
+
+impl Default for GasModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,117 @@
+use ethers::abi::Token as AbiToken;
+use num_traits::Zero;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::executor::decode_multi_swap_steps;
+
+pub(crate) const MULTI_SWAP_AND_PAY_GAS: &str =
+    "multiSwapAndPayGas((address,address,address,uint256,uint256,bytes,uint16,uint16)[],address,address,uint256)";
+
+// Extra native bought over the estimate so gas price drift doesn't leave the user short
+pub const GAS_BUFFER_BPS: u32 = 1_000;
+
+// How a user without native balance pays for gas
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum GasPaymentMode {
+    // Swap a slice of amount_in into the native token as part of the execution
+    InputSlice,
+    // ERC-4337 paymaster charging the input token, plus its markup
+    Paymaster { address: String, markup_bps: u32 },
+    // The integrator's `sponsor` account pays gas through its relayer or
+    // paymaster and is reimbursed the exact cost out of the output
+    Sponsored { sponsor: String },
+}
+
+// Gas cost charged in the input token (the output token when sponsored), already
+// deducted from the quoted output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GasPaymentQuote {
+    pub mode: GasPaymentMode,
+    pub token: Token,
+    // Input token units taken out of amount_in, or output token units paid to the sponsor
+    pub token_amount: String,
+    // Native (wei) the slice buys or the paymaster covers
+    pub native_amount: String,
+}
+
+// Native cost of a route's gas at `gas_price`, including the buffer
+pub fn native_cost(gas_estimate: u64, gas_price: &BigUint) -> BigUint {
+    math::bps_of(&(BigUint::from(gas_estimate) * gas_price), 10_000 + GAS_BUFFER_BPS as u64, math::Rounding::Up)
+}
+
+pub fn apply_markup(amount: &BigUint, mode: &GasPaymentMode) -> BigUint {
+    match mode {
+        GasPaymentMode::Paymaster { markup_bps, .. } => {
+            math::bps_of(amount, 10_000 + *markup_bps as u64, math::Rounding::Up)
+        }
+        GasPaymentMode::InputSlice | GasPaymentMode::Sponsored { .. } => amount.clone(),
+    }
+}
+
+// Re-scale a route to swap `amount_in` instead of its quoted input. Output shrinks
+// proportionally, which understates it slightly as price impact falls with size.
+pub fn scale_route(route: &mut SwapRoute, amount_in: &BigUint) -> Result<(), RouterError> {
+    let quoted_in = math::parse_amount(&route.amount_in)?;
+    if quoted_in.is_zero() {
+        return Err(RouterError::ExecutionError("Route has no input".to_string()));
+    }
+    let scale = |value: &str| -> Result<String, RouterError> {
+        Ok(math::mul_div(&math::parse_amount(value)?, amount_in, &quoted_in, math::Rounding::Down).to_string())
+    };
+
+    for step in route.steps.iter_mut() {
+        step.amount_in = scale(&step.amount_in)?;
+        step.amount_out_min = scale(&step.amount_out_min)?;
+        if let Some(expected) = &step.expected_amount_out {
+            step.expected_amount_out = Some(scale(expected)?);
+        }
+    }
+    for split in route.splits.iter_mut() {
+        split.amount_in = scale(&split.amount_in)?;
+        split.expected_amount_out = scale(&split.expected_amount_out)?;
+    }
+    route.expected_amount_out = scale(&route.expected_amount_out)?;
+    route.amount_in = amount_in.to_string();
+
+    Ok(())
+}
+
+// Take a sponsored route's gas fee out of its expected output. Step minimums
+// stay gross: the executor checks them before paying the sponsor.
+pub fn deduct_sponsored_fee(route: &mut SwapRoute) -> Result<(), RouterError> {
+    let fee = match &route.gas_payment {
+        Some(GasPaymentQuote {
+            mode: GasPaymentMode::Sponsored { .. },
+            token_amount,
+            ..
+        }) => math::parse_amount(token_amount)?,
+        _ => return Ok(()),
+    };
+    let expected = math::parse_amount(&route.expected_amount_out)?;
+    if fee >= expected {
+        return Err(RouterError::Unprofitable(format!("gas fee of {} exceeds the output of {}", fee, expected)));
+    }
+    route.expected_amount_out = (expected - fee).to_string();
+    Ok(())
+}
+
+// Executor call running the steps of `multi_swap_calldata` (an encoded multiSwap
+// call), paying `gas_fee` of the output to the sponsor and the rest to `recipient`
+pub fn encode_sponsored_call(
+    multi_swap_calldata: &[u8],
+    recipient: &str,
+    sponsor: &str,
+    gas_fee: &BigUint,
+) -> Result<Vec<u8>, RouterError> {
+    Ok(encode_call(
+        MULTI_SWAP_AND_PAY_GAS,
+        &[
+            decode_multi_swap_steps(multi_swap_calldata)?,
+            AbiToken::Address(parse_address(recipient)?),
+            AbiToken::Address(parse_address(sponsor)?),
+            AbiToken::Uint(math::to_u256(gas_fee)?),
+        ],
+    ))
+}
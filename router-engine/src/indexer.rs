@@ -0,0 +1,421 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::Duration;
+
+use ethers::abi::{ParamType, Token as AbiToken};
+use tokio::task::JoinHandle;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::adapters::{call, v3_virtual_reserves};
+use crate::pool_log::{PoolEvent, PoolEventLog};
+use crate::state::{PoolState, PoolStateStore, StateBackedSource};
+
+// Blocks of history kept per pool for snapshot_at_block
+pub const DEFAULT_HISTORY_BLOCKS: u64 = 128;
+
+// Largest block range requested per eth_getLogs call
+const MAX_LOG_RANGE: u64 = 1_000;
+
+// Pool kinds the indexer knows how to follow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IndexedPoolKind {
+    // Reserves from Sync(uint112,uint112)
+    UniswapV2,
+    // Price and in-range liquidity from Swap(...)
+    UniswapV3,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedPool {
+    pub exchange_id: String,
+    pub address: String,
+    // token0 and token1 of the pool contract
+    pub token0: Token,
+    pub token1: Token,
+    pub fee_tier: u32,
+    pub kind: IndexedPoolKind,
+}
+
+fn sync_topic() -> H256 {
+    H256::from(ethers::utils::keccak256("Sync(uint112,uint112)"))
+}
+
+fn v3_swap_topic() -> H256 {
+    H256::from(ethers::utils::keccak256("Swap(address,address,int256,int256,uint160,uint128,int24)"))
+}
+
+fn uint(token: &AbiToken) -> BigUint {
+    match token {
+        AbiToken::Uint(value) | AbiToken::Int(value) => math::from_u256(*value),
+        _ => BigUint::default(),
+    }
+}
+
+fn int24(token: &AbiToken) -> Option<i32> {
+    match token {
+        AbiToken::Int(value) => Some(I256::from_raw(*value).as_i32()),
+        _ => None,
+    }
+}
+
+// Keeps the reserves of registered pools in a PoolStateStore by following their
+// events block by block, so quotes read local state instead of calling RPC
+pub struct PoolIndexer {
+    chain_id: u64,
+    pools: DashMap<Address, IndexedPool>,
+    store: Arc<PoolStateStore>,
+    history: DashMap<Address, VecDeque<PoolState>>,
+    history_blocks: u64,
+    poll_interval: Duration,
+    head: std::sync::atomic::AtomicU64,
+    engine: Option<Arc<RouterEngine>>,
+    event_log: Option<Arc<dyn PoolEventLog>>,
+    task: std::sync::Mutex<Option<JoinHandle<()>>>,
+}
+
+impl PoolIndexer {
+    pub fn new(chain_id: u64, store: Arc<PoolStateStore>) -> Self {
+        Self {
+            chain_id,
+            pools: DashMap::new(),
+            store,
+            history: DashMap::new(),
+            history_blocks: DEFAULT_HISTORY_BLOCKS,
+            poll_interval: Duration::from_secs(1),
+            head: std::sync::atomic::AtomicU64::new(0),
+            engine: None,
+            event_log: None,
+            task: std::sync::Mutex::new(None),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn with_history_blocks(mut self, history_blocks: u64) -> Self {
+        self.history_blocks = history_blocks;
+        self
+    }
+
+    // Report indexed blocks to the engine, whose state-age check bounds staleness
+    pub fn with_engine(mut self, engine: Arc<RouterEngine>) -> Self {
+        self.engine = Some(engine);
+        self
+    }
+
+    // Record every bootstrap read and applied log, for replay()
+    pub fn with_event_log(mut self, event_log: Arc<dyn PoolEventLog>) -> Self {
+        self.event_log = Some(event_log);
+        self
+    }
+
+    pub fn store(&self) -> Arc<PoolStateStore> {
+        self.store.clone()
+    }
+
+    pub fn register_pool(&self, pool: IndexedPool) -> Result<(), RouterError> {
+        self.pools.insert(parse_address(&pool.address)?, pool);
+        Ok(())
+    }
+
+    // Quote every indexed exchange from local state
+    pub fn register_sources(&self, engine: &RouterEngine) {
+        let mut exchanges: Vec<String> = self.pools.iter().map(|p| p.exchange_id.clone()).collect();
+        exchanges.sort();
+        exchanges.dedup();
+        for exchange_id in exchanges {
+            let source = StateBackedSource::new(self.chain_id, exchange_id.clone(), self.store.clone());
+            engine.register_liquidity_source(exchange_id, Arc::new(source));
+        }
+    }
+
+    // Last block fully applied
+    pub fn head(&self) -> u64 {
+        self.head.load(std::sync::atomic::Ordering::Acquire)
+    }
+
+    // State of every indexed pool as of `block_number`, from the retained history
+    pub fn snapshot_at_block(&self, block_number: u64) -> Vec<PoolState> {
+        self.history
+            .iter()
+            .filter_map(|entry| entry.iter().rev().find(|state| state.block_number <= block_number).cloned())
+            .collect()
+    }
+
+    fn apply(&self, state: PoolState) {
+        let Ok(address) = parse_address(&state.pool) else {
+            return;
+        };
+        let mut history = self.history.entry(address).or_default();
+        if matches!(history.back(), Some(last) if last.block_number > state.block_number) {
+            return;
+        }
+        if matches!(history.back(), Some(last) if last.block_number == state.block_number) {
+            history.pop_back();
+        }
+        history.push_back(state.clone());
+        let oldest = state.block_number.saturating_sub(self.history_blocks);
+        while matches!(history.front(), Some(first) if first.block_number < oldest) && history.len() > 1 {
+            history.pop_front();
+        }
+        drop(history);
+
+        // A pool appearing or running empty adds or removes a token graph edge
+        let has_liquidity = |state: &PoolState| state.reserve_a != "0" && state.reserve_b != "0";
+        let edge_changed = match self.store.get(state.chain_id, &state.pool) {
+            Some(previous) => has_liquidity(&previous) != has_liquidity(&state),
+            None => true,
+        };
+        if self.store.apply(state) && edge_changed {
+            if let Some(engine) = &self.engine {
+                engine.invalidate_token_graph(Some(self.chain_id));
+            }
+        }
+    }
+
+    fn pool_state(&self, pool: &IndexedPool, reserve0: BigUint, reserve1: BigUint, tick: Option<i32>, block_number: u64) -> PoolState {
+        PoolState {
+            chain_id: self.chain_id,
+            exchange_id: pool.exchange_id.clone(),
+            pool: pool.address.clone(),
+            token_a: pool.token0.clone(),
+            token_b: pool.token1.clone(),
+            reserve_a: reserve0.to_string(),
+            reserve_b: reserve1.to_string(),
+            fee_tier: pool.fee_tier,
+            block_number,
+            tick,
+        }
+    }
+
+    // Read every pool's current state directly, as the starting point for events
+    pub async fn bootstrap<M: Middleware>(&self, client: &M, block_number: u64) -> Result<(), RouterError> {
+        let pools: Vec<(Address, IndexedPool)> = self.pools.iter().map(|p| (*p.key(), p.value().clone())).collect();
+
+        let mut states = Vec::with_capacity(pools.len());
+        for (address, pool) in pools {
+            let state = match pool.kind {
+                IndexedPoolKind::UniswapV2 => {
+                    let result = call(
+                        client,
+                        address,
+                        encode_call("getReserves()", &[]),
+                        &[ParamType::Uint(112), ParamType::Uint(112), ParamType::Uint(32)],
+                    )
+                    .await?;
+                    self.pool_state(&pool, uint(&result[0]), uint(&result[1]), None, block_number)
+                }
+                IndexedPoolKind::UniswapV3 => {
+                    let slot0 = call(
+                        client,
+                        address,
+                        encode_call("slot0()", &[]),
+                        &[
+                            ParamType::Uint(160),
+                            ParamType::Int(24),
+                            ParamType::Uint(16),
+                            ParamType::Uint(16),
+                            ParamType::Uint(16),
+                            ParamType::Uint(8),
+                            ParamType::Bool,
+                        ],
+                    )
+                    .await?;
+                    let liquidity = call(client, address, encode_call("liquidity()", &[]), &[ParamType::Uint(128)]).await?;
+                    let (reserve0, reserve1) = v3_virtual_reserves(&uint(&liquidity[0]), &uint(&slot0[0]));
+                    self.pool_state(&pool, reserve0, reserve1, int24(&slot0[1]), block_number)
+                }
+            };
+            states.push(state);
+        }
+
+        if let Some(event_log) = &self.event_log {
+            let events: Vec<PoolEvent> = states.iter().map(|state| PoolEvent::Bootstrap { state: state.clone() }).collect();
+            event_log.append(&events)?;
+        }
+        for state in states {
+            self.apply(state);
+        }
+        self.set_head(block_number);
+        Ok(())
+    }
+
+    fn set_head(&self, block_number: u64) {
+        self.head.store(block_number, std::sync::atomic::Ordering::Release);
+        if let Some(engine) = &self.engine {
+            engine.record_block(self.chain_id, block_number);
+        }
+    }
+
+    // State a Sync/Swap log of a registered pool sets
+    fn decode_log(&self, address: &Address, log: &Log, block_number: u64) -> Option<PoolState> {
+        let pool = self.pools.get(address).map(|p| p.clone())?;
+        match (pool.kind, log.topics.first()) {
+            (IndexedPoolKind::UniswapV2, Some(topic)) if *topic == sync_topic() => {
+                match ethers::abi::decode(&[ParamType::Uint(112), ParamType::Uint(112)], &log.data) {
+                    Ok(values) => Some(self.pool_state(&pool, uint(&values[0]), uint(&values[1]), None, block_number)),
+                    Err(e) => {
+                        warn!("Malformed Sync log from {:?}: {}", address, e);
+                        None
+                    }
+                }
+            }
+            (IndexedPoolKind::UniswapV3, Some(topic)) if *topic == v3_swap_topic() => {
+                let params = [
+                    ParamType::Int(256),
+                    ParamType::Int(256),
+                    ParamType::Uint(160),
+                    ParamType::Uint(128),
+                    ParamType::Int(24),
+                ];
+                match ethers::abi::decode(&params, &log.data) {
+                    Ok(values) => {
+                        let (reserve0, reserve1) = v3_virtual_reserves(&uint(&values[3]), &uint(&values[2]));
+                        Some(self.pool_state(&pool, reserve0, reserve1, int24(&values[4]), block_number))
+                    }
+                    Err(e) => {
+                        warn!("Malformed Swap log from {:?}: {}", address, e);
+                        None
+                    }
+                }
+            }
+            _ => None,
+        }
+    }
+
+    // Apply the last Sync/Swap event of each pool in every block of the range
+    async fn apply_logs<M: Middleware>(&self, client: &M, from_block: u64, to_block: u64) -> Result<usize, RouterError> {
+        let addresses: Vec<Address> = self.pools.iter().map(|p| *p.key()).collect();
+        if addresses.is_empty() {
+            return Ok(0);
+        }
+
+        let filter = Filter::new()
+            .address(addresses)
+            .topic0(vec![sync_topic(), v3_swap_topic()])
+            .from_block(from_block)
+            .to_block(to_block);
+        let logs = client
+            .get_logs(&filter)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch pool logs: {}", e)))?;
+
+        // Later logs of a pool in the same block supersede earlier ones
+        let mut latest: HashMap<(Address, u64), Log> = HashMap::new();
+        for log in logs {
+            let Some(block_number) = log.block_number.map(|b| b.as_u64()) else {
+                continue;
+            };
+            latest.insert((log.address, block_number), log);
+        }
+        let mut ordered: Vec<((Address, u64), Log)> = latest.into_iter().collect();
+        ordered.sort_by_key(|((_, block), log)| (*block, log.log_index));
+
+        if let Some(event_log) = &self.event_log {
+            let events: Vec<PoolEvent> = ordered
+                .iter()
+                .filter(|((address, _), _)| self.pools.contains_key(address))
+                .map(|(_, log)| PoolEvent::Log { log: log.clone() })
+                .collect();
+            event_log.append(&events)?;
+        }
+
+        let mut applied = 0;
+        for ((address, block_number), log) in ordered {
+            let Some(state) = self.decode_log(&address, &log, block_number) else {
+                continue;
+            };
+            self.apply(state);
+            applied += 1;
+        }
+
+        Ok(applied)
+    }
+
+    // Catch up from the last applied block to the chain head; bootstrap() first
+    pub async fn sync<M: Middleware>(&self, client: &M) -> Result<u64, RouterError> {
+        let head = client
+            .get_block_number()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch block number: {}", e)))?
+            .as_u64();
+
+        let mut from = self.head() + 1;
+        while from <= head {
+            let to = (from + MAX_LOG_RANGE - 1).min(head);
+            let applied = self.apply_logs(client, from, to).await?;
+            debug!("Indexed blocks {}..={} on chain {}: {} pool updates", from, to, self.chain_id, applied);
+            self.set_head(to);
+            from = to + 1;
+        }
+        Ok(head)
+    }
+
+    // Rebuild state from an event log instead of the chain: apply its bootstrap
+    // reads and logs of blocks from_block..=to_block in order, then take to_block
+    // as the head. Run it on an indexer with the same pools and an empty store;
+    // the same log always yields the same state. Pools without an entry in the
+    // range are left out.
+    pub fn replay(&self, event_log: &dyn PoolEventLog, from_block: u64, to_block: u64) -> Result<usize, RouterError> {
+        let mut applied = 0;
+        for event in event_log.read(from_block, to_block)? {
+            let state = match event {
+                PoolEvent::Bootstrap { state } => state,
+                PoolEvent::Log { log } => {
+                    let block_number = log.block_number.map(|b| b.as_u64()).unwrap_or_default();
+                    match self.decode_log(&log.address, &log, block_number) {
+                        Some(state) => state,
+                        None => continue,
+                    }
+                }
+            };
+            self.apply(state);
+            applied += 1;
+        }
+
+        info!("Replayed {} pool updates of blocks {}..={} on chain {}", applied, from_block, to_block, self.chain_id);
+        self.set_head(to_block);
+        Ok(applied)
+    }
+
+    // Bootstrap at the current head, then follow new blocks until stop()
+    pub fn start<M: Middleware + 'static>(self: &Arc<Self>, client: Arc<M>) {
+        let indexer = self.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                let bootstrapped = match client.get_block_number().await {
+                    Ok(head) => indexer.bootstrap(&*client, head.as_u64()).await,
+                    Err(e) => Err(RouterError::ChainError(format!("Failed to fetch block number: {}", e))),
+                };
+                match bootstrapped {
+                    Ok(()) => break,
+                    Err(e) => {
+                        warn!("Pool indexer bootstrap failed on chain {}: {}", indexer.chain_id, e);
+                        tokio::time::sleep(indexer.poll_interval).await;
+                    }
+                }
+            }
+            info!("Pool indexer started on chain {} at block {}", indexer.chain_id, indexer.head());
+
+            loop {
+                if let Err(e) = indexer.sync(&*client).await {
+                    warn!("Pool indexer sync failed on chain {}: {}", indexer.chain_id, e);
+                }
+                tokio::time::sleep(indexer.poll_interval).await;
+            }
+        });
+
+        if let Some(previous) = self.task.lock().unwrap().replace(handle) {
+            previous.abort();
+        }
+    }
+
+    pub fn stop(&self) {
+        if let Some(handle) = self.task.lock().unwrap().take() {
+            handle.abort();
+        }
+    }
+}
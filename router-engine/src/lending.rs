@@ -0,0 +1,478 @@
+use ethers::abi::Token as AbiToken;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::flashloan::{FlashLoan, FlashLoanKind};
+
+// Supported lending protocols
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LendingProtocol {
+    AaveV3,
+    CompoundV3,
+}
+
+// A lending market deployment: Aave Pool or Compound Comet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LendingMarket {
+    pub id: String,
+    pub protocol: LendingProtocol,
+    pub chain_id: u64,
+    pub address: String,
+}
+
+// Call made by the executor inside the flash-loan callback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LendingCall {
+    pub target: String,
+    pub data: String,
+    pub description: String,
+}
+
+impl LendingCall {
+    fn new(target: &str, data: Vec<u8>, description: String) -> Self {
+        Self {
+            target: target.to_string(),
+            data: format!("0x{}", hex::encode(data)),
+            description,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollateralSwapRequest {
+    pub chain_id: u64,
+    pub market_id: String,
+    pub owner: String,
+    pub collateral_from: String,
+    pub collateral_to: String,
+    pub amount: String,
+    pub slippage: f64,
+    pub flash_loan: FlashLoanKind,
+    // Aave aToken of `collateral_from`, pulled from the owner before withdrawing
+    #[serde(default)]
+    pub position_token: Option<String>,
+}
+
+// Atomic collateral swap: borrow `collateral_from`, swap it to `collateral_to`,
+// deposit for the owner, then withdraw the owner's old collateral to repay the loan.
+// Depositing before withdrawing keeps the position's health factor intact throughout.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollateralSwapPlan {
+    pub flash_loan: FlashLoan,
+    pub route: SwapRoute,
+    // Executed after the swap, inside the flash-loan callback
+    pub calls: Vec<LendingCall>,
+    pub collateral_withdrawn: String,
+    pub collateral_deposited_min: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefinanceRequest {
+    pub chain_id: u64,
+    pub from_market: String,
+    pub to_market: String,
+    pub owner: String,
+    pub collateral: String,
+    pub collateral_amount: String,
+    pub debt_asset: String,
+    pub debt_amount: String,
+    // Asset to borrow on the target market, defaults to `debt_asset`
+    #[serde(default)]
+    pub target_debt_asset: Option<String>,
+    pub slippage: f64,
+    pub flash_loan: FlashLoanKind,
+    // Aave aToken of `collateral` on the source market
+    #[serde(default)]
+    pub position_token: Option<String>,
+}
+
+// Debt refinancing: flash-borrow the old debt, repay it, move the collateral to the
+// target market, re-borrow there and, if the debt assets differ, swap the new debt
+// back into the old one to repay the flash loan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefinancePlan {
+    pub flash_loan: FlashLoan,
+    // Executed before the swap, inside the flash-loan callback
+    pub calls: Vec<LendingCall>,
+    pub swap_route: Option<SwapRoute>,
+    // Borrowed on the target market, in target debt units
+    pub new_debt_amount: String,
+    // Swap output left over after repaying the flash loan, in old debt units
+    pub expected_surplus: String,
+    // New debt minus the old debt converted at market rate, in target debt units
+    pub all_in_cost: String,
+}
+
+pub struct LendingPlanner {
+    engine: Arc<RouterEngine>,
+    markets: DashMap<String, LendingMarket>,
+}
+
+impl LendingPlanner {
+    pub fn new(engine: Arc<RouterEngine>) -> Self {
+        Self {
+            engine,
+            markets: DashMap::new(),
+        }
+    }
+
+    pub fn register_market(&self, market: LendingMarket) {
+        self.markets.insert(market.id.clone(), market);
+    }
+
+    fn market(&self, id: &str) -> Result<LendingMarket, RouterError> {
+        self.markets
+            .get(id)
+            .map(|m| m.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown lending market: {}", id)))
+    }
+
+    // Best route for `amount` of `token_in` into `token_out`
+    async fn best_route(
+        &self,
+        chain_id: u64,
+        token_in: &str,
+        token_out: &str,
+        amount: &str,
+        slippage: f64,
+    ) -> Result<SwapRoute, RouterError> {
+        let response = self
+            .engine
+            .find_routes(QuoteRequest {
+                chain_id,
+                token_in: token_in.to_string(),
+                token_out: token_out.to_string(),
+                amount_in: amount.to_string(),
+                slippage: Some(slippage),
+                ..Default::default()
+            })
+            .await?;
+
+        response.routes.into_iter().next().ok_or_else(|| {
+            RouterError::InsufficientLiquidity(format!("No route from {} to {}", token_in, token_out))
+        })
+    }
+
+    pub async fn plan_collateral_swap(
+        &self,
+        request: CollateralSwapRequest,
+    ) -> Result<CollateralSwapPlan, RouterError> {
+        let market = self.market(&request.market_id)?;
+        let executor = self.engine.executor(request.chain_id)?;
+
+        let mut route = self
+            .best_route(
+                request.chain_id,
+                &request.collateral_from,
+                &request.collateral_to,
+                &request.amount,
+                request.slippage,
+            )
+            .await?;
+        self.engine.attach_flash_loan(&mut route, request.chain_id, request.flash_loan)?;
+
+        let flash_loan = route
+            .flash_loan
+            .clone()
+            .ok_or_else(|| RouterError::ExecutionError("Flash loan was not attached".to_string()))?;
+        let withdrawn = flash_loan.repayment()?;
+        let deposited = route
+            .steps
+            .last()
+            .map(|step| math::parse_amount(&step.amount_out_min))
+            .transpose()?
+            .unwrap_or_default();
+
+        let mut calls = deposit_calls(&market, &request.collateral_to, &deposited, &request.owner)?;
+        calls.extend(withdraw_calls(
+            &market,
+            &request.collateral_from,
+            &withdrawn,
+            &request.owner,
+            &executor,
+            request.position_token.as_deref(),
+        )?);
+
+        info!(
+            "Planned collateral swap of {} {} -> {} on {}",
+            request.amount, request.collateral_from, request.collateral_to, market.id
+        );
+
+        Ok(CollateralSwapPlan {
+            flash_loan,
+            route,
+            calls,
+            collateral_withdrawn: withdrawn.to_string(),
+            collateral_deposited_min: deposited.to_string(),
+        })
+    }
+
+    pub async fn plan_refinance(&self, request: RefinanceRequest) -> Result<RefinancePlan, RouterError> {
+        let from_market = self.market(&request.from_market)?;
+        let to_market = self.market(&request.to_market)?;
+        let executor = self.engine.executor(request.chain_id)?;
+
+        let debt_token = self
+            .engine
+            .get_token(request.chain_id, &request.debt_asset)
+            .await
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown token: {}", request.debt_asset)))?;
+        let debt = math::parse_amount(&request.debt_amount)?;
+        let collateral_amount = math::parse_amount(&request.collateral_amount)?;
+
+        let flash_loan = self
+            .engine
+            .quote_flash_loan(request.chain_id, request.flash_loan, &debt_token, &debt)?;
+        let repayment = flash_loan.repayment()?;
+
+        let target_debt_asset = request
+            .target_debt_asset
+            .clone()
+            .unwrap_or_else(|| request.debt_asset.clone());
+
+        let (new_debt, swap_route, surplus, cost) = if target_debt_asset == request.debt_asset {
+            let fee = &repayment - &debt;
+            (repayment.clone(), None, BigUint::default(), fee)
+        } else {
+            // Price the old debt in the new asset, then size the borrow so the
+            // swap's minimum output still covers the flash-loan repayment
+            let reverse = self
+                .best_route(
+                    request.chain_id,
+                    &request.debt_asset,
+                    &target_debt_asset,
+                    &repayment.to_string(),
+                    request.slippage,
+                )
+                .await?;
+            let fair_repayment = math::parse_amount(&reverse.expected_amount_out)?;
+            let mut borrow = slippage::add_slippage(&fair_repayment, request.slippage);
+
+            let mut route = None;
+            for _ in 0..3 {
+                let candidate = self
+                    .best_route(
+                        request.chain_id,
+                        &target_debt_asset,
+                        &request.debt_asset,
+                        &borrow.to_string(),
+                        request.slippage,
+                    )
+                    .await?;
+                let min_out = candidate
+                    .steps
+                    .last()
+                    .map(|step| math::parse_amount(&step.amount_out_min))
+                    .transpose()?
+                    .unwrap_or_default();
+
+                if min_out >= repayment {
+                    route = Some(candidate);
+                    break;
+                }
+                // Scale the borrow by the shortfall plus a 0.1% margin
+                borrow = &borrow * &repayment * BigUint::from(1001u32) / (min_out.max(BigUint::from(1u8)) * BigUint::from(1000u32));
+            }
+
+            let route = route.ok_or_else(|| {
+                RouterError::InsufficientLiquidity(format!(
+                    "Could not size a {} borrow covering {} {}",
+                    target_debt_asset, repayment, request.debt_asset
+                ))
+            })?;
+            let surplus = math::parse_amount(&route.expected_amount_out)? - &repayment;
+            let fair_debt = &fair_repayment * &debt / &repayment;
+            let cost = if borrow > fair_debt { &borrow - fair_debt } else { BigUint::default() };
+
+            (borrow, Some(route), surplus, cost)
+        };
+
+        let mut calls = repay_calls(&from_market, &request.debt_asset, &debt, &request.owner)?;
+        calls.extend(withdraw_calls(
+            &from_market,
+            &request.collateral,
+            &collateral_amount,
+            &request.owner,
+            &executor,
+            request.position_token.as_deref(),
+        )?);
+        calls.extend(deposit_calls(&to_market, &request.collateral, &collateral_amount, &request.owner)?);
+        calls.extend(borrow_calls(&to_market, &target_debt_asset, &new_debt, &request.owner, &executor)?);
+
+        info!(
+            "Planned refinance of {} {} from {} to {}",
+            request.debt_amount, request.debt_asset, from_market.id, to_market.id
+        );
+
+        Ok(RefinancePlan {
+            flash_loan,
+            calls,
+            swap_route,
+            new_debt_amount: new_debt.to_string(),
+            expected_surplus: surplus.to_string(),
+            all_in_cost: cost.to_string(),
+        })
+    }
+}
+
+// Approve the market and deposit `amount` of `asset` as the owner's collateral
+pub fn deposit_calls(
+    market: &LendingMarket,
+    asset: &str,
+    amount: &BigUint,
+    owner: &str,
+) -> Result<Vec<LendingCall>, RouterError> {
+    let amount = AbiToken::Uint(math::to_u256(amount)?);
+    let market_address = AbiToken::Address(parse_address(&market.address)?);
+    let asset_address = AbiToken::Address(parse_address(asset)?);
+    let owner_address = AbiToken::Address(parse_address(owner)?);
+
+    let approve = LendingCall::new(
+        asset,
+        encode_call("approve(address,uint256)", &[market_address, amount.clone()]),
+        format!("approve {} for {}", asset, market.id),
+    );
+    let deposit = match market.protocol {
+        LendingProtocol::AaveV3 => encode_call(
+            "supply(address,uint256,address,uint16)",
+            &[asset_address, amount, owner_address, AbiToken::Uint(U256::zero())],
+        ),
+        LendingProtocol::CompoundV3 => encode_call(
+            "supplyTo(address,address,uint256)",
+            &[owner_address, asset_address, amount],
+        ),
+    };
+
+    Ok(vec![
+        approve,
+        LendingCall::new(&market.address, deposit, format!("deposit {} for {}", asset, owner)),
+    ])
+}
+
+// Withdraw `amount` of the owner's `asset` collateral to the executor. Aave needs the
+// aToken pulled first; Compound needs the owner to have allowed the executor on Comet.
+pub fn withdraw_calls(
+    market: &LendingMarket,
+    asset: &str,
+    amount: &BigUint,
+    owner: &str,
+    executor: &str,
+    position_token: Option<&str>,
+) -> Result<Vec<LendingCall>, RouterError> {
+    let amount = AbiToken::Uint(math::to_u256(amount)?);
+    let asset_address = AbiToken::Address(parse_address(asset)?);
+    let owner_address = AbiToken::Address(parse_address(owner)?);
+    let executor_address = AbiToken::Address(parse_address(executor)?);
+
+    match market.protocol {
+        LendingProtocol::AaveV3 => {
+            let a_token = position_token.ok_or_else(|| {
+                RouterError::ConfigError(format!("Aave withdrawals of {} need the aToken address", asset))
+            })?;
+
+            Ok(vec![
+                LendingCall::new(
+                    a_token,
+                    encode_call(
+                        "transferFrom(address,address,uint256)",
+                        &[owner_address, executor_address.clone(), amount.clone()],
+                    ),
+                    format!("pull {} from {}", a_token, owner),
+                ),
+                LendingCall::new(
+                    &market.address,
+                    encode_call(
+                        "withdraw(address,uint256,address)",
+                        &[asset_address, amount, executor_address],
+                    ),
+                    format!("withdraw {} to executor", asset),
+                ),
+            ])
+        }
+        LendingProtocol::CompoundV3 => Ok(vec![LendingCall::new(
+            &market.address,
+            encode_call(
+                "withdrawFrom(address,address,address,uint256)",
+                &[owner_address, executor_address, asset_address, amount],
+            ),
+            format!("withdraw {} from {} to executor", asset, owner),
+        )]),
+    }
+}
+
+// Repay the owner's variable-rate debt; Compound repays by supplying the base asset
+pub fn repay_calls(
+    market: &LendingMarket,
+    asset: &str,
+    amount: &BigUint,
+    owner: &str,
+) -> Result<Vec<LendingCall>, RouterError> {
+    let amount = AbiToken::Uint(math::to_u256(amount)?);
+    let market_address = AbiToken::Address(parse_address(&market.address)?);
+    let asset_address = AbiToken::Address(parse_address(asset)?);
+    let owner_address = AbiToken::Address(parse_address(owner)?);
+
+    let approve = LendingCall::new(
+        asset,
+        encode_call("approve(address,uint256)", &[market_address, amount.clone()]),
+        format!("approve {} for {}", asset, market.id),
+    );
+    let repay = match market.protocol {
+        LendingProtocol::AaveV3 => encode_call(
+            "repay(address,uint256,uint256,address)",
+            &[asset_address, amount, AbiToken::Uint(U256::from(2u8)), owner_address],
+        ),
+        LendingProtocol::CompoundV3 => encode_call(
+            "supplyTo(address,address,uint256)",
+            &[owner_address, asset_address, amount],
+        ),
+    };
+
+    Ok(vec![
+        approve,
+        LendingCall::new(&market.address, repay, format!("repay {} for {}", asset, owner)),
+    ])
+}
+
+// Borrow `amount` of `asset` against the owner's position, delivered to the executor.
+// Aave needs credit delegation to the executor, Compound needs Comet allowance.
+pub fn borrow_calls(
+    market: &LendingMarket,
+    asset: &str,
+    amount: &BigUint,
+    owner: &str,
+    executor: &str,
+) -> Result<Vec<LendingCall>, RouterError> {
+    let amount = AbiToken::Uint(math::to_u256(amount)?);
+    let asset_address = AbiToken::Address(parse_address(asset)?);
+    let owner_address = AbiToken::Address(parse_address(owner)?);
+
+    let borrow = match market.protocol {
+        LendingProtocol::AaveV3 => encode_call(
+            "borrow(address,uint256,uint256,uint16,address)",
+            &[
+                asset_address,
+                amount,
+                AbiToken::Uint(U256::from(2u8)),
+                AbiToken::Uint(U256::zero()),
+                owner_address,
+            ],
+        ),
+        LendingProtocol::CompoundV3 => encode_call(
+            "withdrawFrom(address,address,address,uint256)",
+            &[
+                owner_address,
+                AbiToken::Address(parse_address(executor)?),
+                asset_address,
+                amount,
+            ],
+        ),
+    };
+
+    Ok(vec![LendingCall::new(
+        &market.address,
+        borrow,
+        format!("borrow {} for {}", asset, owner),
+    )])
+}
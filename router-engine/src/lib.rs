@@ -4,15 +4,75 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use dashmap::DashMap;
 use ethers::prelude::*;
-use num_bigint::BigUint;
+use num_bigint::{BigInt, BigUint};
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 
+pub mod abi_registry;
+pub mod adapter_conformance;
+pub mod adapters;
+pub mod api;
+pub mod approvals;
+pub mod attestation;
+pub mod backtest;
+pub mod benchmark;
+pub mod blocking;
+#[cfg(feature = "wasm")]
+pub mod browser_cache;
+pub mod bus;
+pub mod cache;
+pub mod call_plan;
+pub mod cluster;
+pub mod commit;
+pub mod crosschain;
+pub mod deploy;
+pub mod dust;
+pub mod events;
+pub mod execution;
+pub mod executor;
+pub mod finality;
+pub mod flashloan;
+pub mod forensics;
+pub mod gas;
+pub mod gas_payment;
+pub mod indexer;
+pub mod lending;
+pub mod math;
+pub mod oracle;
+pub mod overrides;
+pub mod pair_stats;
+pub mod payout;
+pub mod permit;
+pub mod plugins;
+pub mod policy;
+pub mod pool_log;
+pub mod priority;
+pub mod rebate;
+pub mod rfq;
+pub mod rfq_cache;
+pub mod routing;
+#[cfg(feature = "sandbox")]
+pub mod sandbox;
+pub mod scoreboard;
+pub mod scheduler;
+pub mod secrets;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod simulation;
+pub mod slippage;
+pub mod state;
+pub mod tax;
+pub mod token_registry;
+pub mod trace;
+pub mod trust;
+pub mod tx_manager;
+pub mod vault;
+pub mod wallet;
+
 // Error types for the router engine
 #[derive(Error, Debug)]
 pub enum RouterError {
@@ -30,6 +90,30 @@ pub enum RouterError {
     
     #[error("Configuration error: {0}")]
     ConfigError(String),
+    
+    #[error("Execution reverted: {0}")]
+    Reverted(String),
+    
+    #[error("Contract error: {0}")]
+    ContractError(String),
+    
+    #[error("Unprofitable route: {0}")]
+    Unprofitable(String),
+    
+    #[error("Simulation failed: {0}")]
+    SimulationFailed(String),
+    
+    #[error("Stale quote: pool state from block {state_block} but chain is at {current_block}, refresh the quote")]
+    StaleQuote { state_block: u64, current_block: u64 },
+    
+    #[error("Unsafe route: {0}")]
+    UnsafeRoute(String),
+}
+
+pub(crate) fn parse_address(address: &str) -> Result<Address, RouterError> {
+    address
+        .parse()
+        .map_err(|_| RouterError::ConfigError(format!("Invalid address: {}", address)))
 }
 
 // Token representation
@@ -50,6 +134,15 @@ pub struct Exchange {
     pub router_address: String,
     pub factory_address: Option<String>,
     pub fee_tiers: Vec<u32>,
+    // Registered ABI for the router, defaults to the exchange id
+    #[serde(default)]
+    pub router_abi: Option<String>,
+    // Router interface, needed to build calldata for the exchange's steps
+    #[serde(default)]
+    pub protocol: Option<execution::Protocol>,
+    // Launch date, audits and trust tier, used for risk scoring and policy filtering
+    #[serde(default)]
+    pub metadata: trust::ExchangeMetadata,
 }
 
 // Swap route step
@@ -61,10 +154,15 @@ pub struct SwapStep {
     pub fee_tier: Option<u32>,
     pub amount_in: String,
     pub amount_out_min: String,
+    // Output the step was quoted at, before slippage
+    #[serde(default)]
+    pub expected_amount_out: Option<String>,
+    #[serde(default)]
+    pub firmness: rfq::Firmness,
 }
 
 // Complete swap route
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SwapRoute {
     pub steps: Vec<SwapStep>,
     pub amount_in: String,
@@ -72,17 +170,129 @@ pub struct SwapRoute {
     pub price_impact: f64,
     pub gas_estimate: u64,
     pub risk_score: u8,
+    // Slippage (percent) used to derive amount_out_min
+    #[serde(default)]
+    pub slippage: Option<f64>,
+    // Worst-case sandwich outcome, only for public-mempool executions
+    #[serde(default)]
+    pub sandwich_risk: Option<mev::SandwichReport>,
+    #[serde(default)]
+    pub flash_loan: Option<flashloan::FlashLoan>,
+    #[serde(default)]
+    pub rebate: Option<rebate::RouteRebate>,
+    // Set when every leg is firm: the earliest expiry among them
+    #[serde(default)]
+    pub firm_until: Option<u64>,
+    #[serde(default)]
+    pub wallet_hints: Option<wallet::WalletHints>,
+    // Expected and minimum amount per recipient when the output is split
+    #[serde(default)]
+    pub payouts: Option<Vec<payout::Payout>>,
+    #[serde(default)]
+    pub gas_payment: Option<gas_payment::GasPaymentQuote>,
+    // Fee taken by the integrator's routing policy, in output token units
+    #[serde(default)]
+    pub integrator_fee: Option<String>,
+    // Output before transfer taxes; expected_amount_out is net of them
+    #[serde(default)]
+    pub gross_amount_out: Option<String>,
+    // How the input is divided between paths; empty for single-path routes
+    #[serde(default)]
+    pub splits: Vec<routing::RouteSplit>,
+    #[serde(default)]
+    pub ranking: Option<routing::RouteRanking>,
+    // Oldest pool state block the route was priced from, when sources report it
+    #[serde(default)]
+    pub state_block: Option<u64>,
+    // Set when the executor wraps native ETH input or unwraps WETH output
+    #[serde(default)]
+    pub native_wrapping: Option<execution::NativeWrapping>,
+    // Pre-trade simulation of the route's transaction, when a simulator is configured
+    #[serde(default)]
+    pub simulation: Option<simulation::SimulationReport>,
+    // Handle to commit the route with, valid for the engine's commit TTL
+    #[serde(default)]
+    pub route_id: Option<String>,
+    // Input and intermediate tokens left in the executor at quoted amounts, swept
+    // to the recipient or the chain's dust sink
+    #[serde(default)]
+    pub dust: Vec<dust::DustAmount>,
+    // The contract the user approves and sends the transaction to
+    #[serde(default)]
+    pub approval_plan: Option<approvals::ApprovalPlan>,
 }
 
 // Quote request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct QuoteRequest {
     pub chain_id: u64,
     pub token_in: String,
     pub token_out: String,
     pub amount_in: String,
-    pub slippage: f64,
+    // Percent; when omitted the engine uses the integrator's preset for the pair
+    #[serde(default)]
+    pub slippage: Option<f64>,
     pub exchanges: Option<Vec<String>>,
+    // Integrator (frontend or API key) the request comes from
+    #[serde(default)]
+    pub integrator: Option<String>,
+    // Prior approvals and balances, used to prefer routes needing fewer transactions
+    #[serde(default)]
+    pub wallet: Option<wallet::WalletContext>,
+    // Split the output between several recipients instead of the sender
+    #[serde(default)]
+    pub recipients: Option<Vec<payout::Recipient>>,
+    // Pay gas in the input token for users without native balance
+    #[serde(default)]
+    pub gas_payment: Option<gas_payment::GasPaymentMode>,
+    // Routing policy by name; defaults to the one assigned to the integrator
+    #[serde(default)]
+    pub policy: Option<String>,
+    // Let the engine pick slippage per route from volatility and depth
+    #[serde(default)]
+    pub auto_slippage: bool,
+    // Defaults per chain, see RouterEngine::resolve_mev_policy
+    #[serde(default)]
+    pub mev_policy: Option<mev::MevPolicy>,
+    // Fund routes with a flash loan from this lender
+    #[serde(default)]
+    pub flash_loan: Option<flashloan::FlashLoanKind>,
+    // Return the best rejected candidates and why they were pruned
+    #[serde(default)]
+    pub debug: bool,
+    // Page of ranked alternatives to return; all of them when max_routes is omitted
+    #[serde(default)]
+    pub max_routes: Option<usize>,
+    #[serde(default)]
+    pub offset: usize,
+    // Receiver of the output; when set the response carries the best route's transaction
+    #[serde(default)]
+    pub recipient: Option<String>,
+    // Unix deadline of that transaction, by default execution::DEFAULT_DEADLINE_SECS from now
+    #[serde(default)]
+    pub deadline: Option<u64>,
+    // Pay native ETH in and receive native ETH out instead of the wrapped token,
+    // with the executor wrapping and unwrapping; native is address(0)
+    #[serde(default)]
+    pub unwrap_native: bool,
+    // Skip the quote cache for this request
+    #[serde(default)]
+    pub cache_bypass: bool,
+    #[serde(default)]
+    pub mode: routing::QuoteMode,
+    // Accept cached routes up to this many blocks old (at most
+    // cache::STALE_RETENTION_BLOCKS), e.g. for type-ahead UI quotes; ignored when
+    // a recipient is given
+    #[serde(default)]
+    pub max_staleness_blocks: Option<u64>,
+    // A re-quote the client is about to execute: skips the quote cache, quotes
+    // from live sources and goes ahead of indicative quotes
+    #[serde(default)]
+    pub execution_bound: bool,
+    // Most gas the route's transaction may use, for smart wallets and paymasters
+    // that cap it; only route shapes estimated within it are searched
+    #[serde(default)]
+    pub max_gas: Option<u64>,
 }
 
 // Quote response
@@ -90,6 +300,34 @@ pub struct QuoteRequest {
 pub struct QuoteResponse {
     pub routes: Vec<SwapRoute>,
     pub tx_calldata: Option<String>,
+    // Target, value and gas of tx_calldata
+    #[serde(default)]
+    pub transaction: Option<execution::ExecutionTx>,
+    // Routes found before paging
+    #[serde(default)]
+    pub total_routes: usize,
+    // Only set for debug requests
+    #[serde(default)]
+    pub rejected: Option<Vec<trace::RejectedRoute>>,
+    // Set when the engine has a quote signer
+    #[serde(default)]
+    pub attestation: Option<attestation::QuoteAttestation>,
+    // USD values of the best route, set when a price oracle is configured
+    #[serde(default)]
+    pub amount_in_usd: Option<f64>,
+    #[serde(default)]
+    pub amount_out_usd: Option<f64>,
+    #[serde(default)]
+    pub gas_usd: Option<f64>,
+    #[serde(default)]
+    pub fee_usd: Option<f64>,
+    // Set when the routes were priced at an older block
+    #[serde(default)]
+    pub staleness: Option<cache::Staleness>,
+    // Calls to send as one batch instead of `transaction` alone, for smart
+    // accounts that batch and still need to approve the input: approve, then swap
+    #[serde(default)]
+    pub batch: Option<Vec<execution::ExecutionTx>>,
 }
 
 // Liquidity source trait
@@ -107,50 +345,988 @@ pub trait LiquiditySource: Send + Sync {
         token_a: &Token,
         token_b: &Token,
     ) -> Result<(BigUint, BigUint), RouterError>;
+    
+    // Quote along with the fee tier of the pool it came from, for sources choosing
+    // among several pools of the pair; the tier is what calldata is built for
+    async fn get_quote_with_fee_tier(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64, Option<u32>), RouterError> {
+        let (amount_out, price_impact) = self.get_quote(token_in, token_out, amount_in).await?;
+        Ok((amount_out, price_impact, None))
+    }
+    
+    // Block of the pool state quotes for the pair are based on, if the source tracks it
+    fn state_block(&self, _token_a: &Token, _token_b: &Token) -> Option<u64> {
+        None
+    }
+    
+    // Pairs the source already knows it holds liquidity for, such as indexed pools,
+    // which spares the token graph a get_reserves probe per token pair. None if
+    // the source can only answer pair by pair.
+    fn known_pairs(&self) -> Option<Vec<(Token, Token)>> {
+        None
+    }
 }
 
 // Router engine core
 pub struct RouterEngine {
     liquidity_sources: DashMap<String, Arc<dyn LiquiditySource>>,
-    tokens: DashMap<(u64, String), Token>,
-    price_cache: Arc<RwLock<HashMap<(Token, Token), (f64, u64)>>>,
+    tokens: token_registry::TokenRegistry,
+    prices: cache::PriceCache,
+    quote_cache: std::sync::RwLock<Arc<dyn cache::QuoteCache>>,
+    volatility: slippage::VolatilityTracker,
+    abis: abi_registry::AbiRegistry,
+    executors: DashMap<u64, String>,
+    flash_loan_providers: DashMap<(u64, flashloan::FlashLoanKind), flashloan::FlashLoanProvider>,
+    events: std::sync::RwLock<Option<Arc<events::EventPublisher>>>,
+    rebates: DashMap<String, Vec<rebate::RebateProgram>>,
+    dry_run: std::sync::atomic::AtomicBool,
+    token_classes: DashMap<(u64, String), slippage::TokenClass>,
+    slippage_presets: DashMap<String, slippage::SlippagePresets>,
+    default_slippage_presets: std::sync::RwLock<slippage::SlippagePresets>,
+    native_tokens: DashMap<u64, Token>,
+    gas_prices: DashMap<u64, BigUint>,
+    policies: DashMap<String, policy::RoutingPolicy>,
+    integrator_policies: DashMap<String, String>,
+    gas_model: gas::GasModel,
+    token_taxes: DashMap<(u64, String), tax::TokenTax>,
+    blacklisted_pools: DashMap<(u64, String), ()>,
+    routing: std::sync::RwLock<routing::RoutingConfig>,
+    pool_overrides: overrides::PoolOverrides,
+    exchanges: DashMap<(u64, String), Exchange>,
+    chain_heads: DashMap<u64, u64>,
+    max_state_age: DashMap<u64, u64>,
+    quote_signer: std::sync::RwLock<Option<LocalWallet>>,
+    finality: DashMap<u64, finality::FinalityPolicy>,
+    simulator: std::sync::RwLock<Option<Arc<dyn simulation::SimulationBackend>>>,
+    simulation: std::sync::RwLock<simulation::SimulationConfig>,
+    quote_book: commit::QuoteBook,
+    reservations: commit::Reservations,
+    oracle: std::sync::RwLock<Option<Arc<dyn oracle::PriceOracle>>>,
+    dust_sinks: DashMap<u64, String>,
+    mempools: DashMap<u64, mev::MempoolKind>,
+    scoreboard: scoreboard::Scoreboard,
+    priority_lanes: priority::PriorityLanes,
+    live_sources: DashMap<String, Arc<dyn LiquiditySource>>,
+    amount_rules: std::sync::RwLock<math::AmountRules>,
+    rfq_caches: DashMap<String, Arc<rfq_cache::RfqQuoteCache>>,
+    pair_stats: pair_stats::PairStatsTracker,
+    token_graphs: DashMap<u64, routing::CachedTokenGraph>,
 }
 
 impl RouterEngine {
     pub fn new() -> Self {
         Self {
             liquidity_sources: DashMap::new(),
-            tokens: DashMap::new(),
-            price_cache: Arc::new(RwLock::new(HashMap::new())),
+            tokens: token_registry::TokenRegistry::default(),
+            prices: cache::PriceCache::default(),
+            quote_cache: std::sync::RwLock::new(Arc::new(cache::MemoryQuoteCache::default())),
+            volatility: slippage::VolatilityTracker::default(),
+            abis: abi_registry::AbiRegistry::default(),
+            executors: DashMap::new(),
+            flash_loan_providers: DashMap::new(),
+            events: std::sync::RwLock::new(None),
+            rebates: DashMap::new(),
+            dry_run: std::sync::atomic::AtomicBool::new(false),
+            token_classes: DashMap::new(),
+            slippage_presets: DashMap::new(),
+            default_slippage_presets: std::sync::RwLock::new(slippage::SlippagePresets::default()),
+            native_tokens: DashMap::new(),
+            gas_prices: DashMap::new(),
+            policies: DashMap::new(),
+            integrator_policies: DashMap::new(),
+            gas_model: gas::GasModel::default(),
+            token_taxes: DashMap::new(),
+            blacklisted_pools: DashMap::new(),
+            routing: std::sync::RwLock::new(routing::RoutingConfig::default()),
+            pool_overrides: overrides::PoolOverrides::default(),
+            exchanges: DashMap::new(),
+            chain_heads: DashMap::new(),
+            max_state_age: DashMap::new(),
+            quote_signer: std::sync::RwLock::new(None),
+            finality: DashMap::new(),
+            simulator: std::sync::RwLock::new(None),
+            simulation: std::sync::RwLock::new(simulation::SimulationConfig::default()),
+            quote_book: commit::QuoteBook::default(),
+            reservations: commit::Reservations::default(),
+            oracle: std::sync::RwLock::new(None),
+            dust_sinks: DashMap::new(),
+            mempools: DashMap::new(),
+            scoreboard: scoreboard::Scoreboard::default(),
+            priority_lanes: priority::PriorityLanes::default(),
+            live_sources: DashMap::new(),
+            amount_rules: std::sync::RwLock::new(math::AmountRules::default()),
+            rfq_caches: DashMap::new(),
+            pair_stats: pair_stats::PairStatsTracker::default(),
+            token_graphs: DashMap::new(),
         }
     }
     
     pub fn register_liquidity_source(&self, id: String, source: Arc<dyn LiquiditySource>) {
         self.liquidity_sources.insert(id, source);
+        self.invalidate_token_graph(None);
     }
     
     pub fn register_token(&self, token: Token) {
-        self.tokens.insert((token.chain_id, token.address.clone()), token);
+        self.tokens.register(token);
+    }
+    
+    // Token lists, on-chain resolvers and the tokens registered so far
+    pub fn token_registry(&self) -> &token_registry::TokenRegistry {
+        &self.tokens
+    }
+    
+    // Registered tokens, optionally of one chain
+    pub fn list_tokens(&self, chain_id: Option<u64>) -> Vec<Token> {
+        self.tokens.list(chain_id)
+    }
+    
+    // Aggregation executor contract for a chain
+    pub fn register_executor(&self, chain_id: u64, address: String) {
+        self.executors.insert(chain_id, address);
+    }
+    
+    // Exclude a liquidity source from routing on a chain
+    pub fn blacklist_pool(&self, chain_id: u64, exchange_id: &str) {
+        self.blacklisted_pools.insert((chain_id, exchange_id.to_string()), ());
+    }
+    
+    pub fn unblacklist_pool(&self, chain_id: u64, exchange_id: &str) {
+        self.blacklisted_pools.remove(&(chain_id, exchange_id.to_string()));
+    }
+    
+    fn blacklisted_step(&self, chain_id: u64, route: &SwapRoute) -> Option<trace::RejectionReason> {
+        route.steps
+            .iter()
+            .find(|step| self.blacklisted_pools.contains_key(&(chain_id, step.exchange_id.clone())))
+            .map(|step| trace::RejectionReason::BlacklistedPool { exchange_id: step.exchange_id.clone() })
+    }
+    
+    pub fn register_flash_loan_provider(&self, provider: flashloan::FlashLoanProvider) {
+        self.flash_loan_providers.insert((provider.chain_id, provider.kind), provider);
+    }
+    
+    pub fn gas_model(&self) -> &gas::GasModel {
+        &self.gas_model
+    }
+    
+    // Feed realized gas back into the per-source gas constants
+    pub fn record_execution(&self, report: &gas::ExecutionReport) {
+        self.gas_model.record(report);
+    }
+    
+    // Buy/sell taxes detected for a fee-on-transfer token
+    pub fn register_token_tax(&self, chain_id: u64, address: &str, tax: tax::TokenTax) {
+        self.token_taxes.insert((chain_id, address.to_lowercase()), tax);
+    }
+    
+    pub fn token_tax(&self, token: &Token) -> Option<tax::TokenTax> {
+        self.token_taxes
+            .get(&(token.chain_id, token.address.to_lowercase()))
+            .map(|t| *t)
+    }
+    
+    pub fn register_policy(&self, policy: policy::RoutingPolicy) {
+        self.policies.insert(policy.name.clone(), policy);
+    }
+    
+    // Policy used for an integrator's (API key's) requests that don't name one
+    pub fn assign_policy(&self, integrator: String, policy: String) {
+        self.integrator_policies.insert(integrator, policy);
+    }
+    
+    pub fn resolve_policy(&self, request: &QuoteRequest) -> Result<Option<policy::RoutingPolicy>, RouterError> {
+        let name = match &request.policy {
+            Some(name) => name.clone(),
+            None => match request
+                .integrator
+                .as_ref()
+                .and_then(|integrator| self.integrator_policies.get(integrator).map(|p| p.clone()))
+            {
+                Some(name) => name,
+                None => return Ok(None),
+            },
+        };
+        
+        self.policies
+            .get(&name)
+            .map(|p| Some(p.clone()))
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown routing policy: {}", name)))
+    }
+    
+    // Wrapped native token of a chain, e.g. WETH
+    pub fn register_native_token(&self, token: Token) {
+        self.native_tokens.insert(token.chain_id, token);
+    }
+    
+    // Current gas price of a chain in wei
+    pub fn set_gas_price(&self, chain_id: u64, gas_price: BigUint) {
+        self.gas_prices.insert(chain_id, gas_price);
+    }
+    
+    // Input needed to buy `native_amount` of the chain's native token through the
+    // deepest registered pool
+    pub async fn native_cost_in_token(
+        &self,
+        chain_id: u64,
+        token: &Token,
+        native_amount: &BigUint,
+    ) -> Result<BigUint, RouterError> {
+        let native = self.native_tokens
+            .get(&chain_id)
+            .map(|t| t.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("No native token registered for chain {}", chain_id)))?;
+        if native == *token {
+            return Ok(native_amount.clone());
+        }
+        
+        // Buying an exact amount of native, so skip pools overridden as exact-in only
+        let sources: Vec<(String, Arc<dyn LiquiditySource>)> = self.liquidity_sources
+            .iter()
+            .filter(|s| self.pool_overrides.allows_exact_out(s.key(), token, &native))
+            .map(|s| (s.key().clone(), s.value().clone()))
+            .collect();
+        let mut deepest: Option<(String, BigUint, BigUint)> = None;
+        for (exchange_id, source) in sources {
+            if let Ok((reserve_in, reserve_out)) = source.get_reserves(token, &native).await {
+                let deeper = match &deepest {
+                    Some((_, _, best_out)) => reserve_out > *best_out,
+                    None => true,
+                };
+                if deeper {
+                    deepest = Some((exchange_id, reserve_in, reserve_out));
+                }
+            }
+        }
+        
+        let (exchange_id, reserve_in, reserve_out) = deepest.ok_or_else(|| {
+            RouterError::InsufficientLiquidity(format!("No {}/{} pool to pay gas", token.symbol, native.symbol))
+        })?;
+        let fee = self.pool_overrides
+            .get(&exchange_id, token, &native)
+            .and_then(|o| o.fee_tier)
+            .unwrap_or(math::DEFAULT_FEE_TIER);
+        math::get_amount_in(native_amount, &reserve_in, &reserve_out, fee)
+    }
+    
+    // Deduct the route's gas cost from its input and rescale its output
+    // accordingly; sponsored gas is charged to the output instead
+    pub async fn attach_gas_payment(
+        &self,
+        route: &mut SwapRoute,
+        chain_id: u64,
+        mode: &gas_payment::GasPaymentMode,
+    ) -> Result<(), RouterError> {
+        if let gas_payment::GasPaymentMode::Sponsored { sponsor } = mode {
+            return self.attach_sponsored_gas(route, chain_id, sponsor).await;
+        }
+        let token_in = route.steps
+            .first()
+            .map(|step| step.token_in.clone())
+            .ok_or_else(|| RouterError::ExecutionError("Route has no steps".to_string()))?;
+        let gas_price = self.gas_prices
+            .get(&chain_id)
+            .map(|p| p.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("No gas price set for chain {}", chain_id)))?;
+        
+        let native_amount = gas_payment::native_cost(route.gas_estimate, &gas_price);
+        let token_amount = gas_payment::apply_markup(
+            &self.native_cost_in_token(chain_id, &token_in, &native_amount).await?,
+            mode,
+        );
+        
+        let amount_in = math::parse_amount(&route.amount_in)?;
+        if token_amount >= amount_in {
+            return Err(RouterError::Unprofitable(format!(
+                "gas cost of {} {} exceeds the input amount",
+                token_amount, token_in.symbol
+            )));
+        }
+        gas_payment::scale_route(route, &(amount_in - &token_amount))?;
+        
+        route.gas_payment = Some(gas_payment::GasPaymentQuote {
+            mode: mode.clone(),
+            token: token_in,
+            token_amount: token_amount.to_string(),
+            native_amount: native_amount.to_string(),
+        });
+        Ok(())
+    }
+    
+    // Price the sponsor's reimbursement in the output token: exactly the estimated
+    // gas at the current gas price, with no buffer or markup since the sponsor
+    // carries any drift. The output is reduced by it once slippage is applied
+    // (gas_payment::deduct_sponsored_fee) so step minimums still cover the fee.
+    async fn attach_sponsored_gas(&self, route: &mut SwapRoute, chain_id: u64, sponsor: &str) -> Result<(), RouterError> {
+        parse_address(sponsor)?;
+        let token_out = route.steps
+            .last()
+            .map(|step| step.token_out.clone())
+            .ok_or_else(|| RouterError::ExecutionError("Route has no steps".to_string()))?;
+        let gas_price = self.gas_prices
+            .get(&chain_id)
+            .map(|p| p.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("No gas price set for chain {}", chain_id)))?;
+        
+        let native_amount = BigUint::from(route.gas_estimate) * gas_price;
+        let token_amount = self.native_cost_in_token(chain_id, &token_out, &native_amount).await?;
+        if token_amount >= math::parse_amount(&route.expected_amount_out)? {
+            return Err(RouterError::Unprofitable(format!(
+                "gas cost of {} {} exceeds the output amount",
+                token_amount, token_out.symbol
+            )));
+        }
+        
+        route.gas_payment = Some(gas_payment::GasPaymentQuote {
+            mode: gas_payment::GasPaymentMode::Sponsored { sponsor: sponsor.to_string() },
+            token: token_out,
+            token_amount: token_amount.to_string(),
+            native_amount: native_amount.to_string(),
+        });
+        Ok(())
+    }
+    
+    pub fn register_token_class(&self, chain_id: u64, address: &str, class: slippage::TokenClass) {
+        self.token_classes.insert((chain_id, address.to_lowercase()), class);
+    }
+    
+    // Slippage presets for an integrator, or the deployment default when `integrator` is None
+    pub fn set_slippage_presets(&self, integrator: Option<String>, presets: slippage::SlippagePresets) {
+        match integrator {
+            Some(integrator) => {
+                self.slippage_presets.insert(integrator, presets);
+            }
+            None => *self.default_slippage_presets.write().unwrap() = presets,
+        }
+    }
+    
+    pub fn token_class(&self, chain_id: u64, address: &str) -> slippage::TokenClass {
+        self.token_classes
+            .get(&(chain_id, address.to_lowercase()))
+            .map(|c| *c)
+            .unwrap_or_default()
+    }
+    
+    // Slippage used when the request omits one: the integrator's preset for the pair category
+    pub fn preset_slippage(&self, request: &QuoteRequest) -> f64 {
+        let category = slippage::PairCategory::of(
+            self.token_class(request.chain_id, &request.token_in),
+            self.token_class(request.chain_id, &request.token_out),
+        );
+        let presets = request
+            .integrator
+            .as_ref()
+            .and_then(|integrator| self.slippage_presets.get(integrator).map(|p| *p))
+            .unwrap_or_else(|| *self.default_slippage_presets.read().unwrap());
+        
+        presets.for_category(category)
+    }
+    
+    // In dry-run mode execution paths simulate, estimate gas and build bundles but
+    // return what they would have sent instead of broadcasting it
+    pub fn set_dry_run(&self, dry_run: bool) {
+        self.dry_run.store(dry_run, std::sync::atomic::Ordering::Relaxed);
+    }
+    
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run.load(std::sync::atomic::Ordering::Relaxed)
+    }
+    
+    // Bundle submitter honoring the engine's dry-run mode
+    pub fn mev_protection(&self, flashbots_relay: String) -> mev::MevProtection {
+        mev::MevProtection::new(flashbots_relay).with_dry_run(self.is_dry_run())
+    }
+    
+    // Rebate program of a protocol, applied to every hop through `exchange_id`
+    pub fn register_rebate(&self, exchange_id: String, program: rebate::RebateProgram) {
+        self.rebates.entry(exchange_id).or_default().push(program);
+    }
+    
+    pub fn estimate_rebate(&self, route: &SwapRoute) -> Result<Option<rebate::RouteRebate>, RouterError> {
+        rebate::estimate_route_rebate(route, |exchange_id| {
+            self.rebates.get(exchange_id).map(|p| p.clone()).unwrap_or_default()
+        })
+    }
+    
+    pub fn set_event_publisher(&self, publisher: Arc<events::EventPublisher>) {
+        *self.events.write().unwrap() = Some(publisher);
+    }
+    
+    // Publish an engine event if a publisher is configured; never blocks the caller
+    pub fn publish_event(&self, event: events::EngineEvent) {
+        if let Some(publisher) = self.events.read().unwrap().as_ref() {
+            publisher.publish_in_background(event);
+        }
     }
     
     pub async fn get_token(&self, chain_id: u64, address: &str) -> Option<Token> {
-        self.tokens.get(&(chain_id, address.to_string())).map(|t| t.clone())
+        self.resolve_token(chain_id, address).await.ok()
+    }
+    
+    // Like get_token, but says why a token is unusable, e.g. unresolvable decimals
+    pub async fn resolve_token(&self, chain_id: u64, address: &str) -> Result<Token, RouterError> {
+        self.tokens.resolve(chain_id, address).await
+    }
+    
+    pub async fn record_price(&self, token_in: &Token, token_out: &Token, price: f64, timestamp: u64) {
+        self.volatility.record(token_in, token_out, price);
+        self.prices.insert(token_in, token_out, price, timestamp);
+    }
+    
+    // Where find_routes caches routes, e.g. a RedisQuoteCache shared by replicas
+    pub fn set_quote_cache(&self, cache: Arc<dyn cache::QuoteCache>) {
+        *self.quote_cache.write().unwrap() = cache;
+    }
+    
+    pub fn quote_cache(&self) -> Arc<dyn cache::QuoteCache> {
+        self.quote_cache.read().unwrap().clone()
+    }
+    
+    pub fn abi_registry(&self) -> &abi_registry::AbiRegistry {
+        &self.abis
+    }
+    
+    pub fn executor(&self, chain_id: u64) -> Result<String, RouterError> {
+        self.executors
+            .get(&chain_id)
+            .map(|e| e.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("No executor registered for chain {}", chain_id)))
+    }
+    
+    pub fn quote_flash_loan(
+        &self,
+        chain_id: u64,
+        kind: flashloan::FlashLoanKind,
+        token: &Token,
+        amount: &BigUint,
+    ) -> Result<flashloan::FlashLoan, RouterError> {
+        let provider = self.flash_loan_providers
+            .get(&(chain_id, kind))
+            .ok_or_else(|| RouterError::ConfigError(format!("No {:?} flash loan provider on chain {}", kind, chain_id)))?;
+        
+        Ok(provider.quote(token, amount))
+    }
+    
+    // Attach a flash loan covering the route's input; cyclic routes must repay with profit
+    pub fn attach_flash_loan(
+        &self,
+        route: &mut SwapRoute,
+        chain_id: u64,
+        kind: flashloan::FlashLoanKind,
+    ) -> Result<(), RouterError> {
+        let token_in = route.steps
+            .first()
+            .map(|step| step.token_in.clone())
+            .ok_or_else(|| RouterError::ExecutionError("Route has no steps".to_string()))?;
+        
+        let mut loan = self.quote_flash_loan(chain_id, kind, &token_in, &math::parse_amount(&route.amount_in)?)?;
+        if flashloan::is_cyclic(route) {
+            loan.expected_profit = Some(flashloan::check_profit(route, &loan)?.to_string());
+        }
+        route.flash_loan = Some(loan);
+        
+        Ok(())
+    }
+    
+    // Lender calldata that borrows, runs `executor_calldata` in the executor callback and repays
+    pub fn flash_loan_calldata(
+        &self,
+        route: &SwapRoute,
+        chain_id: u64,
+        executor_calldata: &[u8],
+    ) -> Result<(String, Vec<u8>), RouterError> {
+        let loan = route.flash_loan
+            .as_ref()
+            .ok_or_else(|| RouterError::ConfigError("Route has no flash loan".to_string()))?;
+        let executor = self.executor(chain_id)?;
+        
+        Ok((loan.lender.clone(), flashloan::encode_flash_loan(loan, &executor, executor_calldata)?))
+    }
+    
+    // Contract handle for an exchange's router using its registered ABI
+    pub fn bind_router<M: Middleware>(
+        &self,
+        exchange: &Exchange,
+        client: Arc<M>,
+    ) -> Result<Contract<M>, RouterError> {
+        let abi_name = exchange.router_abi.as_deref().unwrap_or(&exchange.id);
+        self.abis.bind(abi_name, &exchange.router_address, client)
+    }
+    
+    // Turn revert data from a failed simulation or execution into a readable error
+    pub fn decode_revert(&self, data: &[u8]) -> RouterError {
+        self.abis.decode_revert(data)
+    }
+    
+    // Shared route cache key for a request against pool state at `state_block`
+    pub fn route_cache_key(&self, request: &QuoteRequest, state_block: u64) -> Result<cache::RouteCacheKey, RouterError> {
+        let policy = self.resolve_policy(request)?;
+        let mut request = request.clone();
+        if let Some(policy) = &policy {
+            policy.apply_to_request(&mut request);
+        }
+        let request = &request;
+        
+        let pools: Vec<String> = match &request.exchanges {
+            Some(exchanges) => exchanges.clone(),
+            None => self.liquidity_sources.iter().map(|s| s.key().clone()).collect(),
+        };
+        
+        let slippage = request.slippage.unwrap_or_else(|| self.preset_slippage(request));
+        let mut options = vec![
+            format!("slippage_bps={}", (slippage * 100.0).round() as i64),
+            format!("mev_policy={:?}", self.resolve_mev_policy(request.chain_id, request.mev_policy)?),
+        ];
+        if request.auto_slippage {
+            options.push("auto_slippage".to_string());
+        }
+        if request.debug {
+            options.push("debug".to_string());
+        }
+        if let Some(max_routes) = request.max_routes {
+            options.push(format!("routes={}+{}", request.offset, max_routes));
+        } else if request.offset > 0 {
+            options.push(format!("routes={}+", request.offset));
+        }
+        if let Some(recipient) = &request.recipient {
+            options.push(format!("recipient={}", recipient.to_lowercase()));
+        }
+        if let Some(recipients) = &request.recipients {
+            let shares: Vec<String> = recipients
+                .iter()
+                .map(|r| format!("{}:{}", r.address.to_lowercase(), r.share_bps))
+                .collect();
+            options.push(format!("recipients={}", shares.join(",")));
+        }
+        if let Some(deadline) = request.deadline {
+            options.push(format!("deadline={}", deadline));
+        }
+        if request.unwrap_native {
+            options.push("unwrap_native".to_string());
+        }
+        if request.mode != routing::QuoteMode::Standard {
+            options.push(format!("mode={:?}", request.mode));
+        }
+        if let Some(kind) = request.flash_loan {
+            options.push(format!("flash_loan={:?}", kind));
+        }
+        if let Some(mode) = &request.gas_payment {
+            options.push(format!("gas_payment={:?}", mode));
+        }
+        if let Some(max_gas) = request.max_gas {
+            options.push(format!("max_gas={}", max_gas));
+        }
+        if let Some(policy) = &policy {
+            options.push(format!("policy={}", policy.name));
+        }
+        if let Some(wallet) = &request.wallet {
+            let mut approved: Vec<String> = wallet.approved_tokens.iter().map(|t| t.to_lowercase()).collect();
+            approved.sort();
+            let mut held: Vec<String> = wallet.balances.keys().map(|t| t.to_lowercase()).collect();
+            held.sort();
+            options.push(format!("wallet={}|{}", approved.join(","), held.join(",")));
+        }
+        
+        Ok(cache::RouteCacheKey::new(
+            request.chain_id,
+            &request.token_in,
+            &request.token_out,
+            pools,
+            state_block,
+            &math::parse_amount(&request.amount_in)?,
+            options,
+        ))
+    }
+    
+    fn source(&self, exchange_id: &str) -> Result<Arc<dyn LiquiditySource>, RouterError> {
+        self.liquidity_sources
+            .get(exchange_id)
+            .map(|s| s.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown exchange: {}", exchange_id)))
+    }
+    
+    // Mid price of token_in in token_out units: the latest recorded price if any,
+    // otherwise the reserve ratio of the deepest registered pool
+    pub async fn spot_price(&self, token_in: &Token, token_out: &Token) -> Result<f64, RouterError> {
+        if let Some(price) = self.prices.get(token_in, token_out) {
+            return Ok(price);
+        }
+        
+        let sources: Vec<Arc<dyn LiquiditySource>> = self.liquidity_sources.iter().map(|s| s.clone()).collect();
+        let mut deepest: Option<(BigUint, BigUint)> = None;
+        for source in sources {
+            if let Ok((reserve_in, reserve_out)) = source.get_reserves(token_in, token_out).await {
+                let deeper = match &deepest {
+                    Some((best_in, _)) => reserve_in > *best_in,
+                    None => true,
+                };
+                if deeper {
+                    deepest = Some((reserve_in, reserve_out));
+                }
+            }
+        }
+        
+        let (reserve_in, reserve_out) = deepest.ok_or_else(|| {
+            RouterError::InsufficientLiquidity(format!("No pool for {}/{}", token_in.symbol, token_out.symbol))
+        })?;
+        let scale = 10f64.powi(token_in.decimals as i32 - token_out.decimals as i32);
+        Ok(math::ratio(&reserve_out, &reserve_in) * scale)
+    }
+    
+    // Recommended slippage for a route, in percent
+    pub async fn recommend_slippage(&self, route: &SwapRoute) -> Result<f64, RouterError> {
+        let mut total = 0.0;
+        
+        for step in &route.steps {
+            let source = self.source(&step.exchange_id)?;
+            let amount_in = math::parse_amount(&step.amount_in)?;
+            let (reserve_in, _) = source.get_reserves(&step.token_in, &step.token_out).await?;
+            
+            let volatility = self.volatility
+                .volatility(&step.token_in, &step.token_out)
+                .unwrap_or(slippage::DEFAULT_VOLATILITY);
+            
+            total += slippage::step_slippage(volatility, slippage::depth_usage(&amount_in, &reserve_in));
+        }
+        
+        Ok(slippage::clamp_auto_slippage(total))
+    }
+    
+    // Worst-case sandwich of every hop at the given slippage tolerance
+    pub async fn simulate_sandwich(
+        &self,
+        route: &SwapRoute,
+        slippage: f64,
+    ) -> Result<mev::SandwichReport, RouterError> {
+        let mut steps = Vec::with_capacity(route.steps.len());
+        let mut retained = 1.0;
+        
+        for step in &route.steps {
+            let source = self.source(&step.exchange_id)?;
+            let amount_in = math::parse_amount(&step.amount_in)?;
+            let (reserve_in, reserve_out) = source.get_reserves(&step.token_in, &step.token_out).await?;
+            let fee = step.fee_tier.unwrap_or(math::DEFAULT_FEE_TIER);
+            
+            let expected = math::get_amount_out(&amount_in, &reserve_in, &reserve_out, fee);
+            let min_out = slippage::apply_slippage(&expected, slippage);
+            let outcome = mev::simulate_step_sandwich(
+                &step.exchange_id,
+                &amount_in,
+                &min_out,
+                &reserve_in,
+                &reserve_out,
+                fee,
+            );
+            
+            if outcome.profitable {
+                let victim_out = math::parse_amount(&outcome.victim_amount_out)?;
+                retained *= math::ratio(&victim_out, &expected);
+            }
+            steps.push(outcome);
+        }
+        
+        let loss_fraction = (1.0 - retained).clamp(0.0, 1.0);
+        let expected_out = math::parse_amount(&route.expected_amount_out)?;
+        let loss_ppm = BigUint::from((loss_fraction * 1_000_000.0).round() as u64);
+        
+        Ok(mev::SandwichReport {
+            steps,
+            max_extractable_loss: (expected_out * loss_ppm / BigUint::from(1_000_000u64)).to_string(),
+            loss_percent: loss_fraction * 100.0,
+        })
+    }
+    
+    // Candidate routes with every per-route adjustment applied, best first
+    async fn price_routes(
+        &self,
+        request: &QuoteRequest,
+        policy: Option<&policy::RoutingPolicy>,
+        native_wrapping: Option<execution::NativeWrapping>,
+        trace: &mut trace::RejectionTrace,
+    ) -> Result<Vec<SwapRoute>, RouterError> {
+        let max_hops = policy.and_then(|p| p.max_hops);
+        let max_pool_share_bps = policy.and_then(|p| p.max_pool_share_bps);
+        let candidates = self.candidate_routes(request, max_hops, max_pool_share_bps, trace).await?;
+        let head = self.chain_heads.get(&request.chain_id).map(|h| *h);
+        let amount_rules = self.amount_rules();
+        let mut routes = Vec::with_capacity(candidates.len());
+        for route in candidates {
+            // Exact quotes only price state from the latest block
+            let stale = match (request.mode, route.state_block, head) {
+                (routing::QuoteMode::Exact, Some(state_block), Some(current_block)) if state_block < current_block => {
+                    Some(trace::RejectionReason::StaleState { state_block, current_block })
+                }
+                _ => None,
+            };
+            let rejection = self
+                .blacklisted_step(request.chain_id, &route)
+                .or(stale)
+                .or_else(|| policy.and_then(|p| p.rejection(&route)))
+                .or_else(|| {
+                    let min = policy.and_then(|p| p.min_trust_tier)?;
+                    self.untrusted_step(request.chain_id, &route, min)
+                });
+            match rejection {
+                Some(reason) => trace.record(&route, reason),
+                None => routes.push(route),
+            }
+        }
+        let default_slippage = request.slippage.unwrap_or_else(|| self.preset_slippage(request));
+        
+        let now = rfq::now();
+        let mut priced = Vec::with_capacity(routes.len());
+        for mut route in routes {
+            route.native_wrapping = native_wrapping;
+            route.risk_score = self.trust_risk(request.chain_id, &route);
+            rfq::refresh_firmness(&mut route, now);
+            route.gas_estimate = self.gas_model.estimate_with(request.chain_id, &route, |step| {
+                self.pool_overrides.step_gas(step)
+            });
+            if let Some(max_gas) = request.max_gas.filter(|max_gas| route.gas_estimate > *max_gas) {
+                let gas_estimate = route.gas_estimate;
+                trace.record(&route, trace::RejectionReason::GasLimit { gas_estimate, max_gas });
+                continue;
+            }
+            tax::apply_taxes(&mut route, |token| self.token_tax(token))?;
+            
+            if let Some(mode) = &request.gas_payment {
+                match self.attach_gas_payment(&mut route, request.chain_id, mode).await {
+                    Ok(()) => {}
+                    Err(RouterError::Unprofitable(detail)) => {
+                        trace.record(&route, trace::RejectionReason::Gas { detail });
+                        continue;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            if let Some(detail) = request.wallet.as_ref().and_then(|wallet| wallet.incompatibility(&route)) {
+                trace.record(&route, trace::RejectionReason::WalletIncompatible { detail });
+                continue;
+            }
+            let route = &mut route;
+            if let Some(policy) = &policy {
+                policy.charge_fee(route)?;
+            }
+            
+            let slippage = if request.auto_slippage {
+                self.recommend_slippage(route).await?
+            } else {
+                default_slippage
+            };
+            slippage::apply_route_slippage(route, slippage)?;
+            amount_rules.pad_min_outs(route)?;
+            gas_payment::deduct_sponsored_fee(route)?;
+            route.dust = dust::expected_dust(route, &amount_rules)?;
+            // Split payouts go through the executor, which the approval plan has to know
+            if let Some(recipients) = &request.recipients {
+                route.payouts = Some(payout::route_payouts(route, recipients)?);
+            }
+            route.approval_plan = self.approval_plan(route, request.chain_id);
+            
+            if self.sandwich_exposed(request.chain_id, self.resolve_mev_policy(request.chain_id, request.mev_policy)?) {
+                route.sandwich_risk = Some(self.simulate_sandwich(route, slippage).await?);
+            }
+            
+            route.rebate = self.estimate_rebate(route)?;
+            if let Some(wallet) = &request.wallet {
+                route.wallet_hints = wallet::route_hints(route, wallet);
+            }
+            priced.push(route.clone());
+        }
+        let mut routes = priced;
+        
+        // Best net output first, counting expected rebates and, when enabled, how
+        // often the route's sources have filled at their quotes
+        if self.scoreboard.reliability_prior() {
+            routes.sort_by_cached_key(|route| {
+                let reliability = self.scoreboard.route_reliability(request.chain_id, route);
+                std::cmp::Reverse(scoreboard::reliability_weighted_out(route, reliability))
+            });
+        } else {
+            routes.sort_by_key(|route| std::cmp::Reverse(rebate::net_amount_out(route)));
+        }
+        if request.wallet.is_some() {
+            wallet::prefer_fewer_transactions(&mut routes);
+        }
+        
+        if let Some(kind) = request.flash_loan {
+            // Arbitrage cycles that can't repay the loan are dropped
+            routes.retain_mut(|route| match self.attach_flash_loan(route, request.chain_id, kind) {
+                Ok(()) => true,
+                Err(e) => {
+                    trace.record(route, trace::RejectionReason::Unprofitable { detail: e.to_string() });
+                    false
+                }
+            });
+        }
+        self.scoreboard.record_quote(request.chain_id, &routes);
+        
+        Ok(routes)
     }
     
     pub async fn find_routes(
         &self,
-        request: QuoteRequest,
+        mut request: QuoteRequest,
     ) -> Result<QuoteResponse, RouterError> {
-        // Implementation of the routing algorithm would go here
-        // This is a placeholder for the actual implementation
-        
         info!("Finding routes for quote request: {:?}", request);
+        let _ticket = self.priority_lanes.admit(request.execution_bound).await?;
         
-        // For now, return a dummy response
-        Ok(QuoteResponse {
-            routes: vec![],
-            tx_calldata: None,
-        })
+        if let Some(recipients) = &request.recipients {
+            payout::validate(recipients)?;
+        }
+        
+        let policy = self.resolve_policy(&request)?;
+        if let Some(policy) = &policy {
+            policy.apply_to_request(&mut request);
+        }
+        
+        request.mev_policy = Some(self.resolve_mev_policy(request.chain_id, request.mev_policy)?);
+        let native_wrapping = self.resolve_native_wrapping(&mut request);
+        let mut trace = trace::RejectionTrace::new(request.debug);
+        
+        // Debug requests need the rejections, which aren't cached
+        let cache_key = if request.cache_bypass
+            || request.debug
+            || request.execution_bound
+            || request.mode == routing::QuoteMode::Exact
+        {
+            None
+        } else {
+            let head = self.chain_heads.get(&request.chain_id).map(|h| *h).unwrap_or_default();
+            Some(self.route_cache_key(&request, head)?)
+        };
+        let mut staleness = None;
+        let cached = match &cache_key {
+            Some(key) => match self.quote_cache().get(key).await {
+                Some(routes) => Some(routes),
+                None => self.stale_cached_routes(&request, key.state_block).await.map(|(routes, stale)| {
+                    staleness = Some(stale);
+                    routes
+                }),
+            },
+            None => None,
+        };
+        let mut routes = match cached {
+            Some(routes) => {
+                debug!("Quote cache hit for {} -> {}", request.token_in, request.token_out);
+                routes
+            }
+            None => {
+                let routes = match self.price_routes(&request, policy.as_ref(), native_wrapping, &mut trace).await {
+                    Ok(routes) => routes,
+                    Err(e) => {
+                        self.pair_stats.record(&request, None);
+                        return Err(e);
+                    }
+                };
+                if let Some(key) = &cache_key {
+                    self.quote_cache().put(key, &routes).await;
+                }
+                routes
+            }
+        };
+        
+        // A route whose executor call plan is unsafe gives way to the next ranked one
+        let mut transaction = None;
+        if let Some(recipient) = &request.recipient {
+            let mut params = execution::ExecutionParams::new(recipient.clone());
+            if let Some(deadline) = request.deadline {
+                params.deadline = deadline;
+            }
+            params.recipients = request.recipients.clone();
+            while let Some(best) = routes.first() {
+                match self.build_execution(best, request.chain_id, &params) {
+                    Ok(tx) => {
+                        transaction = Some(tx);
+                        break;
+                    }
+                    Err(RouterError::UnsafeRoute(reason)) if routes.len() > 1 => {
+                        warn!("Dropping route for {}: {}", request.token_out, reason);
+                        routes.remove(0);
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        
+        self.publish_event(events::EngineEvent::Quote {
+            chain_id: request.chain_id,
+            token_in: request.token_in.clone(),
+            token_out: request.token_out.clone(),
+            amount_in: request.amount_in.clone(),
+            route_count: routes.len(),
+            best_amount_out: routes.first().map(|r| r.expected_amount_out.clone()),
+        });
+        if let Some(best) = routes.first() {
+            self.publish_event(events::EngineEvent::RouteSelected {
+                chain_id: request.chain_id,
+                route: Box::new(best.clone()),
+            });
+        }
+        
+        // The approval the route's plan calls for, sent to the same contract as the swap
+        let batch = match (&transaction, routes.first()) {
+            (Some(tx), Some(best)) if matches!(&best.wallet_hints, Some(hints) if hints.batched) => {
+                match &best.approval_plan {
+                    Some(approvals::ApprovalPlan { token: Some(token), spender, amount, .. }) => {
+                        let approve = execution::approve_tx(token, spender, &math::parse_amount(amount)?)?;
+                        Some(vec![approve, tx.clone()])
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+        // The sender isn't known, so the recipient stands in for it
+        if let (Some(tx), Some(recipient), Some(best)) = (&transaction, &request.recipient, routes.first_mut()) {
+            match request.mode {
+                routing::QuoteMode::Fast => {}
+                routing::QuoteMode::Standard => self.apply_simulation(best, tx, recipient, recipient).await?,
+                routing::QuoteMode::Exact => {
+                    if self.simulator.read().unwrap().is_none() {
+                        return Err(RouterError::ConfigError("Exact quotes need a simulator".to_string()));
+                    }
+                    self.apply_simulation(best, tx, recipient, recipient).await?;
+                }
+            }
+        }
+        
+        routing::rank_routes(&mut routes);
+        let best = routes.first().cloned();
+        self.pair_stats.record(&request, best.as_ref());
+        let total_routes = routes.len();
+        let routes: Vec<SwapRoute> = routes
+            .into_iter()
+            .skip(request.offset)
+            .take(request.max_routes.unwrap_or(usize::MAX))
+            .map(|mut route| {
+                route.route_id = Some(self.quote_book.insert(&request, &route));
+                route
+            })
+            .collect();
+        
+        let mut response = QuoteResponse {
+            routes,
+            tx_calldata: transaction.as_ref().map(|tx| tx.data.clone()),
+            transaction,
+            total_routes,
+            rejected: trace.finish(),
+            attestation: None,
+            amount_in_usd: None,
+            amount_out_usd: None,
+            gas_usd: None,
+            fee_usd: None,
+            staleness,
+            batch,
+        };
+        if let Some(best) = &best {
+            self.value_in_usd(&mut response, best, request.chain_id).await;
+        }
+        self.attest(&mut response)?;
+        Ok(response)
     }
 }
 
@@ -161,99 +1337,681 @@ pub mod mev {
     use rand_chacha::ChaCha20Rng;
     use rand::SeedableRng;
     
+    // How a transaction reaches the block builder
+    #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum MevPolicy {
+        #[default]
+        PublicMempool,
+        PrivateRelay,
+    }
+    
+    // How a chain's transactions reach block production by default
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(rename_all = "snake_case")]
+    pub enum MempoolKind {
+        // Public mempool with private relays (e.g. Flashbots) to bypass it
+        Public,
+        // Rollup sequencer ordering first come first served, no public mempool
+        Sequencer,
+        // Transactions go to builders privately unless sent elsewhere
+        PrivateOrderflow,
+    }
+    
+    impl MempoolKind {
+        // Chains known to the engine; others are left to configuration
+        pub fn for_chain(chain_id: u64) -> Option<Self> {
+            match chain_id {
+                // Ethereum, Sepolia, Holesky, BNB Chain
+                1 | 11155111 | 17000 | 56 => Some(Self::Public),
+                // Arbitrum One and Nova, Optimism, Base, zkSync Era, Linea, Scroll,
+                // Blast, Mantle, Polygon zkEVM, Zora, Mode
+                42161 | 42170 | 10 | 8453 | 324 | 59144 | 534352 | 81457 | 5000 | 1101 | 7777777 | 34443 => {
+                    Some(Self::Sequencer)
+                }
+                _ => None,
+            }
+        }
+        
+        // Whether pending transactions are visible to searchers
+        pub fn is_public(&self) -> bool {
+            *self == Self::Public
+        }
+    }
+    
+    // Worst-case sandwich of a single hop
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct StepSandwich {
+        pub exchange_id: String,
+        pub frontrun_amount_in: String,
+        pub victim_amount_out: String,
+        pub attacker_profit: String,
+        pub profitable: bool,
+    }
+    
+    // Worst-case sandwich of a whole route
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct SandwichReport {
+        pub steps: Vec<StepSandwich>,
+        // Loss against expected_amount_out, in output token units
+        pub max_extractable_loss: String,
+        pub loss_percent: f64,
+    }
+    
+    // Largest front-run that still lets the victim receive `min_out`, followed by
+    // the attacker selling the proceeds back into the same constant-product pool
+    pub fn simulate_step_sandwich(
+        exchange_id: &str,
+        amount_in: &BigUint,
+        min_out: &BigUint,
+        reserve_in: &BigUint,
+        reserve_out: &BigUint,
+        fee: u32,
+    ) -> StepSandwich {
+        let victim_out_after = |frontrun: &BigUint| {
+            let frontrun_out = math::get_amount_out(frontrun, reserve_in, reserve_out, fee);
+            let victim_out = math::get_amount_out(
+                amount_in,
+                &(reserve_in + frontrun),
+                &(reserve_out - &frontrun_out),
+                fee,
+            );
+            (frontrun_out, victim_out)
+        };
+        
+        let one = BigUint::from(1u8);
+        let mut lo = BigUint::from(0u8);
+        let mut hi = reserve_in.clone().max(one.clone());
+        
+        // Grow the upper bound until the victim's min-out would be breached
+        for _ in 0..128 {
+            if victim_out_after(&hi).1 < *min_out {
+                break;
+            }
+            lo = hi.clone();
+            hi *= 2u8;
+        }
+        
+        while &hi - &lo > one {
+            let mid = (&lo + &hi) / 2u8;
+            if victim_out_after(&mid).1 >= *min_out {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        
+        let frontrun = lo;
+        let (frontrun_out, victim_out) = victim_out_after(&frontrun);
+        let backrun_out = math::get_amount_out(
+            &frontrun_out,
+            &(reserve_out - &frontrun_out - &victim_out),
+            &(reserve_in + &frontrun + amount_in),
+            fee,
+        );
+        let profitable = backrun_out > frontrun;
+        let profit = if profitable { &backrun_out - &frontrun } else { BigUint::from(0u8) };
+        
+        StepSandwich {
+            exchange_id: exchange_id.to_string(),
+            frontrun_amount_in: frontrun.to_string(),
+            victim_amount_out: victim_out.to_string(),
+            attacker_profit: profit.to_string(),
+            profitable,
+        }
+    }
+    
+    // Per-transaction result of eth_callBundle
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase", default)]
+    pub struct BundleTxResult {
+        pub tx_hash: String,
+        pub gas_used: u64,
+        pub gas_fees: String,
+        pub eth_sent_to_coinbase: String,
+        pub coinbase_diff: String,
+        // Hex-encoded return data
+        pub value: Option<String>,
+        pub error: Option<String>,
+        pub revert: Option<String>,
+    }
+    
+    // eth_callBundle response; wei amounts are decimal strings
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    #[serde(rename_all = "camelCase", default)]
+    pub struct BundleSimulation {
+        pub results: Vec<BundleTxResult>,
+        pub total_gas_used: u64,
+        pub gas_fees: String,
+        pub eth_sent_to_coinbase: String,
+        pub coinbase_diff: String,
+        pub state_block_number: u64,
+    }
+    
+    // Searcher profit of a simulated bundle, in wei
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct BundleProfit {
+        pub gross_profit: String,
+        pub gas_cost: String,
+        pub tips: String,
+        pub net_profit: String,
+    }
+    
+    // Gross profit of an executor call, from the multiSwap outputs it returned
+    pub fn executor_gross_profit(return_data: &str, amount_in: &BigUint) -> Result<BigUint, RouterError> {
+        let data = hex::decode(return_data.trim_start_matches("0x"))
+            .map_err(|_| RouterError::ExecutionError(format!("Invalid return data: {}", return_data)))?;
+        let outputs = ethers::abi::decode(
+            &[ethers::abi::ParamType::Array(Box::new(ethers::abi::ParamType::Uint(256)))],
+            &data,
+        )
+        .map_err(|e| RouterError::ExecutionError(format!("Failed to decode executor outputs: {}", e)))?;
+        
+        let amount_out = match outputs.into_iter().next() {
+            Some(ethers::abi::Token::Array(values)) => match values.last() {
+                Some(ethers::abi::Token::Uint(value)) => math::from_u256(*value),
+                _ => BigUint::default(),
+            },
+            _ => BigUint::default(),
+        };
+        
+        Ok(if amount_out > *amount_in { amount_out - amount_in } else { BigUint::default() })
+    }
+    
+    // Net profit after the gas and coinbase tips paid by our own transactions (`own_txs`)
+    pub fn bundle_profit(
+        simulation: &BundleSimulation,
+        own_txs: &[usize],
+        gross_profit: &BigUint,
+    ) -> Result<BundleProfit, RouterError> {
+        let mut gas_cost = BigUint::default();
+        let mut tips = BigUint::default();
+        
+        for &index in own_txs {
+            let result = simulation.results.get(index).ok_or_else(|| {
+                RouterError::ExecutionError(format!("Bundle simulation has no result for tx {}", index))
+            })?;
+            if let Some(reason) = result.revert.as_ref().or(result.error.as_ref()) {
+                return Err(RouterError::Reverted(format!("bundle tx {} ({}): {}", index, result.tx_hash, reason)));
+            }
+            gas_cost += math::parse_amount(&result.gas_fees)?;
+            tips += math::parse_amount(&result.eth_sent_to_coinbase)?;
+        }
+        
+        let net = BigInt::from(gross_profit.clone()) - BigInt::from(gas_cost.clone()) - BigInt::from(tips.clone());
+        
+        Ok(BundleProfit {
+            gross_profit: gross_profit.to_string(),
+            gas_cost: gas_cost.to_string(),
+            tips: tips.to_string(),
+            net_profit: net.to_string(),
+        })
+    }
+    
+    // A bundle relay; bloXroute-style relays also take an Authorization header
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct Relay {
+        pub url: String,
+        pub authorization: Option<String>,
+    }
+    
+    impl Relay {
+        pub fn new(url: impl Into<String>) -> Self {
+            Self {
+                url: url.into(),
+                authorization: None,
+            }
+        }
+        
+        pub fn with_authorization(mut self, authorization: impl Into<String>) -> Self {
+            self.authorization = Some(authorization.into());
+            self
+        }
+    }
+    
+    // What one relay answered to eth_sendBundle
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct RelayResult {
+        pub relay: String,
+        pub bundle_hash: Option<String>,
+        pub error: Option<String>,
+    }
+    
     pub struct MevProtection {
+        // Bundles are simulated here and submitted to every relay
         flashbots_relay: String,
+        relays: Vec<Relay>,
+        // Signs the X-Flashbots-Signature header; relays reject unsigned bundles
+        searcher_key: Option<LocalWallet>,
+        // Funds the self-transfers mixed into obfuscated bundles
+        decoy_wallet: Option<LocalWallet>,
+        client: reqwest::Client,
+        // Bundles simulating below this net profit (wei) are not submitted
+        min_profit: BigUint,
+        dry_run: bool,
+    }
+    
+    // Outcome of a bundle submission; in dry-run mode nothing was sent and
+    // bundle_hash is None
+    #[derive(Debug, Clone, Serialize, Deserialize)]
+    pub struct BundleSubmission {
+        pub bundle_hash: Option<String>,
+        pub relays: Vec<RelayResult>,
+        // Signed transactions, hex encoded
+        pub txs: Vec<String>,
+        pub tx_hashes: Vec<String>,
+        pub target_block: u64,
+        pub simulation: BundleSimulation,
+        pub profit: Option<BundleProfit>,
+        pub dry_run: bool,
+    }
+    
+    // Inclusion of a submitted bundle in its target block
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    #[serde(tag = "status", rename_all = "snake_case")]
+    pub enum BundleStatus {
+        // The target block has not been mined yet
+        Pending,
+        Included { block_number: u64 },
+        // The target block was mined without the bundle
+        Missed,
+    }
+    
+    fn encode_txs(txs: &[Vec<u8>]) -> Vec<String> {
+        txs.iter().map(|tx| format!("0x{}", hex::encode(tx))).collect()
+    }
+    
+    fn tx_hashes(txs: &[Vec<u8>]) -> Vec<String> {
+        txs.iter()
+            .map(|tx| format!("{:?}", H256::from(ethers::utils::keccak256(tx))))
+            .collect()
     }
     
     impl MevProtection {
         pub fn new(flashbots_relay: String) -> Self {
-            Self { flashbots_relay }
+            Self {
+                relays: vec![Relay::new(flashbots_relay.clone())],
+                flashbots_relay,
+                searcher_key: None,
+                decoy_wallet: None,
+                client: reqwest::Client::new(),
+                min_profit: BigUint::default(),
+                dry_run: false,
+            }
+        }
+        
+        pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+            self.dry_run = dry_run;
+            self
+        }
+        
+        pub fn with_min_profit(mut self, min_profit: BigUint) -> Self {
+            self.min_profit = min_profit;
+            self
+        }
+        
+        // Reputation key of the searcher; it holds no funds
+        pub fn with_searcher_key(mut self, searcher_key: LocalWallet) -> Self {
+            self.searcher_key = Some(searcher_key);
+            self
+        }
+        
+        pub fn with_decoy_wallet(mut self, decoy_wallet: LocalWallet) -> Self {
+            self.decoy_wallet = Some(decoy_wallet);
+            self
+        }
+        
+        // Submit bundles to this relay too, e.g. bloXroute or Eden
+        pub fn with_relay(mut self, relay: Relay) -> Self {
+            match self.relays.iter_mut().find(|r| r.url == relay.url) {
+                Some(existing) => *existing = relay,
+                None => self.relays.push(relay),
+            }
+            self
+        }
+        
+        pub fn relays(&self) -> &[Relay] {
+            &self.relays
         }
         
-        pub fn obfuscate_tx(&self, tx: Vec<u8>) -> Vec<Vec<u8>> {
-            let mut rng = ChaCha20Rng::from_entropy();
-            let dummy_count = rng.gen_range(2..5);
+        async fn relay_call(&self, relay: &Relay, method: &str, params: serde_json::Value) -> Result<serde_json::Value, RouterError> {
+            let body = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            })
+            .to_string();
             
-            // Create dummy transactions (placeholder)
-            let mut result = vec![tx];
-            for _ in 0..dummy_count {
-                let dummy_tx = vec![0u8; 100]; // Placeholder for dummy tx
-                result.push(dummy_tx);
+            let mut http = self.client
+                .post(&relay.url)
+                .header("Content-Type", "application/json");
+            if let Some(key) = &self.searcher_key {
+                let digest = format!("{:?}", H256::from(ethers::utils::keccak256(body.as_bytes())));
+                let signature = key
+                    .sign_message(digest)
+                    .await
+                    .map_err(|e| RouterError::ConfigError(format!("Failed to sign relay payload: {}", e)))?;
+                http = http.header("X-Flashbots-Signature", format!("{:?}:0x{}", key.address(), signature));
             }
+            if let Some(authorization) = &relay.authorization {
+                http = http.header("Authorization", authorization);
+            }
+            
+            let response: serde_json::Value = http
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Relay request to {} failed: {}", relay.url, e)))?
+                .json()
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Invalid response from {}: {}", relay.url, e)))?;
             
-            // Shuffle transactions
-            let mut indices: Vec<usize> = (0..result.len()).collect();
-            for i in (1..indices.len()).rev() {
-                let j = rng.gen_range(0..=i);
-                indices.swap(i, j);
+            if let Some(error) = response.get("error") {
+                return Err(RouterError::ChainError(format!("Relay {} {} error: {}", relay.url, method, error)));
             }
+            response
+                .get("result")
+                .cloned()
+                .ok_or_else(|| RouterError::ChainError(format!("Relay {} {} returned no result", relay.url, method)))
+        }
+        
+        // Simulate the bundle on top of the latest state, targeting `block_number`
+        pub async fn simulate_bundle(&self, txs: &[Vec<u8>], block_number: u64) -> Result<BundleSimulation, RouterError> {
+            let result = self
+                .relay_call(
+                    &Relay::new(self.flashbots_relay.clone()),
+                    "eth_callBundle",
+                    serde_json::json!([{
+                        "txs": encode_txs(txs),
+                        "blockNumber": format!("0x{:x}", block_number),
+                        "stateBlockNumber": "latest",
+                    }]),
+                )
+                .await?;
             
-            indices.into_iter().map(|i| result[i].clone()).collect()
+            serde_json::from_value(result)
+                .map_err(|e| RouterError::ChainError(format!("Invalid eth_callBundle result: {}", e)))
         }
         
-        pub async fn send_bundle(&self, txs: Vec<Vec<u8>>) -> Result<String, RouterError> {
-            // Implementation for sending bundle to Flashbots would go here
-            // This is a placeholder
+        // eth_sendBundle to every relay at once; fails only if none accepted the bundle
+        async fn submit_bundle(&self, txs: &[Vec<u8>], block_number: u64) -> Result<Vec<RelayResult>, RouterError> {
+            if self.searcher_key.is_none() {
+                return Err(RouterError::ConfigError("No searcher key configured for bundle submission".to_string()));
+            }
+            
+            let params = serde_json::json!([{
+                "txs": encode_txs(txs),
+                "blockNumber": format!("0x{:x}", block_number),
+            }]);
+            let results = futures::future::join_all(self.relays.iter().map(|relay| {
+                let params = params.clone();
+                async move {
+                    match self.relay_call(relay, "eth_sendBundle", params).await {
+                        Ok(result) => RelayResult {
+                            relay: relay.url.clone(),
+                            bundle_hash: result
+                                .get("bundleHash")
+                                .and_then(|hash| hash.as_str())
+                                .map(str::to_string),
+                            error: None,
+                        },
+                        Err(e) => {
+                            warn!("Bundle submission to {} failed: {}", relay.url, e);
+                            RelayResult {
+                                relay: relay.url.clone(),
+                                bundle_hash: None,
+                                error: Some(e.to_string()),
+                            }
+                        }
+                    }
+                }
+            }))
+            .await;
             
-            Ok("0x1234567890abcdef".to_string())
+            if results.iter().all(|result| result.error.is_some()) {
+                let errors: Vec<String> = results.iter().filter_map(|result| result.error.clone()).collect();
+                return Err(RouterError::ChainError(format!("No relay accepted the bundle: {}", errors.join("; "))));
+            }
+            Ok(results)
         }
-    }
-}
-
-// Cross-chain module
-pub mod crosschain {
-    use super::*;
-    use sha2::{Sha256, Digest};
-    
-    pub struct CrossChainSwap {
-        bridges: HashMap<(u64, u64), String>, // (source_chain, dest_chain) -> bridge_address
-    }
-    
-    impl CrossChainSwap {
-        pub fn new() -> Self {
-            Self {
-                bridges: HashMap::new(),
+        
+        // Simulate an arbitrage or backrun bundle and submit it only if its net profit
+        // clears the floor. The last of `own_txs` must be the executor call, and its
+        // route must start and end in the wrapped native token so profit is in wei.
+        pub async fn send_profitable_bundle(
+            &self,
+            txs: Vec<Vec<u8>>,
+            own_txs: &[usize],
+            amount_in: &BigUint,
+            block_number: u64,
+        ) -> Result<BundleSubmission, RouterError> {
+            let simulation = self.simulate_bundle(&txs, block_number).await?;
+            
+            let executor_tx = own_txs
+                .last()
+                .and_then(|&index| simulation.results.get(index))
+                .ok_or_else(|| RouterError::ExecutionError("Bundle has no executor transaction".to_string()))?;
+            let gross = match &executor_tx.value {
+                Some(value) => executor_gross_profit(value, amount_in)?,
+                None => BigUint::default(),
+            };
+            
+            let profit = bundle_profit(&simulation, own_txs, &gross)?;
+            let net: BigInt = profit.net_profit
+                .parse()
+                .map_err(|_| RouterError::ExecutionError(format!("Invalid net profit: {}", profit.net_profit)))?;
+            if net < BigInt::from(self.min_profit.clone()) {
+                return Err(RouterError::Unprofitable(format!(
+                    "simulated net profit {} wei is below the {} wei floor",
+                    net, self.min_profit
+                )));
             }
+            
+            let mut submission = self.submit_simulated(txs, block_number, simulation).await?;
+            info!(
+                "Bundle for block {} has simulated net profit {} wei",
+                block_number, net
+            );
+            submission.profit = Some(profit);
+            Ok(submission)
         }
         
-        pub fn register_bridge(&mut self, source_chain: u64, dest_chain: u64, bridge_address: String) {
-            self.bridges.insert((source_chain, dest_chain), bridge_address);
+        async fn submit_simulated(
+            &self,
+            txs: Vec<Vec<u8>>,
+            block_number: u64,
+            simulation: BundleSimulation,
+        ) -> Result<BundleSubmission, RouterError> {
+            let (bundle_hash, relays) = if self.dry_run {
+                info!("Dry run: not submitting bundle of {} txs for block {}", txs.len(), block_number);
+                (None, Vec::new())
+            } else {
+                let relays = self.submit_bundle(&txs, block_number).await?;
+                let bundle_hash = relays.iter().find_map(|result| result.bundle_hash.clone());
+                info!(
+                    "Submitted bundle {:?} for block {} to {} relays",
+                    bundle_hash,
+                    block_number,
+                    relays.iter().filter(|result| result.error.is_none()).count()
+                );
+                (bundle_hash, relays)
+            };
+            
+            Ok(BundleSubmission {
+                bundle_hash,
+                relays,
+                txs: encode_txs(&txs),
+                tx_hashes: tx_hashes(&txs),
+                target_block: block_number,
+                simulation,
+                profit: None,
+                dry_run: self.dry_run,
+            })
         }
         
-        pub fn generate_secret() -> (Vec<u8>, Vec<u8>) {
-            let mut rng = rand::thread_rng();
-            let secret: [u8; 32] = rng.gen();
+        // Mix the swap with 2-4 zero-value self-transfers signed by the decoy wallet.
+        // The decoys take consecutive nonces in bundle order; without a decoy wallet
+        // the transaction is returned alone.
+        pub async fn obfuscate_tx<M: Middleware>(&self, client: &M, tx: Vec<u8>) -> Result<Vec<Vec<u8>>, RouterError> {
+            let decoy = match &self.decoy_wallet {
+                Some(decoy) => decoy,
+                None => {
+                    warn!("No decoy wallet configured, sending the transaction without decoys");
+                    return Ok(vec![tx]);
+                }
+            };
             
-            let mut hasher = Sha256::new();
-            hasher.update(&secret);
-            let hash = hasher.finalize().to_vec();
+            let (dummy_count, position) = {
+                let mut rng = ChaCha20Rng::from_entropy();
+                let dummy_count = rng.gen_range(2..5);
+                (dummy_count, rng.gen_range(0..=dummy_count))
+            };
             
-            (secret.to_vec(), hash)
+            let nonce = client
+                .get_transaction_count(decoy.address(), Some(BlockNumber::Pending.into()))
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to fetch decoy nonce: {}", e)))?;
+            let (max_fee, tip) = client
+                .estimate_eip1559_fees(None)
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to estimate fees: {}", e)))?;
+            
+            let mut result = Vec::with_capacity(dummy_count + 1);
+            for i in 0..dummy_count {
+                let request: ethers::types::transaction::eip2718::TypedTransaction = Eip1559TransactionRequest::new()
+                    .from(decoy.address())
+                    .to(decoy.address())
+                    .value(U256::zero())
+                    .gas(21_000u64)
+                    .nonce(nonce + i)
+                    .max_fee_per_gas(max_fee)
+                    .max_priority_fee_per_gas(tip)
+                    .chain_id(decoy.chain_id())
+                    .into();
+                let signature = decoy
+                    .sign_transaction_sync(&request)
+                    .map_err(|e| RouterError::ConfigError(format!("Failed to sign decoy transaction: {}", e)))?;
+                result.push(request.rlp_signed(&signature).to_vec());
+            }
+            result.insert(position, tx);
+            
+            Ok(result)
         }
         
-        pub async fn initiate_swap(
-            &self,
-            source_chain: u64,
-            dest_chain: u64,
-            secret_hash: Vec<u8>,
-            expiration: u64,
-            amount: BigUint,
-        ) -> Result<String, RouterError> {
-            // Implementation for initiating cross-chain swap would go here
-            // This is a placeholder
+        // Simulate the bundle, refuse it if any transaction reverts, then submit it
+        // to every relay for `block_number`
+        pub async fn send_bundle(&self, txs: Vec<Vec<u8>>, block_number: u64) -> Result<BundleSubmission, RouterError> {
+            let simulation = self.simulate_bundle(&txs, block_number).await?;
+            if let Some((index, result)) = simulation
+                .results
+                .iter()
+                .enumerate()
+                .find(|(_, result)| result.revert.is_some() || result.error.is_some())
+            {
+                let reason = result.revert.as_ref().or(result.error.as_ref()).cloned().unwrap_or_default();
+                return Err(RouterError::Reverted(format!("bundle tx {} ({}): {}", index, result.tx_hash, reason)));
+            }
             
-            Ok("0x1234567890abcdef".to_string())
+            self.submit_simulated(txs, block_number, simulation).await
         }
         
-        pub async fn claim_funds(
-            &self,
-            dest_chain: u64,
-            secret: Vec<u8>,
-        ) -> Result<String, RouterError> {
-            // Implementation for claiming funds would go here
-            // This is a placeholder
+        // Whether the bundle landed, judged from its first transaction's receipt
+        pub async fn bundle_status<M: Middleware>(&self, client: &M, submission: &BundleSubmission) -> Result<BundleStatus, RouterError> {
+            if submission.dry_run {
+                return Err(RouterError::ExecutionError("Dry-run bundle was never submitted".to_string()));
+            }
+            let hash: H256 = submission
+                .tx_hashes
+                .first()
+                .ok_or_else(|| RouterError::ExecutionError("Empty bundle".to_string()))?
+                .parse()
+                .map_err(|_| RouterError::ExecutionError("Invalid bundle transaction hash".to_string()))?;
+            
+            let receipt = client
+                .get_transaction_receipt(hash)
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to fetch receipt: {}", e)))?;
+            if let Some(block_number) = receipt.and_then(|receipt| receipt.block_number) {
+                return Ok(BundleStatus::Included { block_number: block_number.as_u64() });
+            }
             
-            Ok("0x1234567890abcdef".to_string())
+            let head = client
+                .get_block_number()
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to fetch block number: {}", e)))?
+                .as_u64();
+            Ok(if head >= submission.target_block { BundleStatus::Missed } else { BundleStatus::Pending })
+        }
+        
+        // Poll until the target block is mined
+        pub async fn wait_for_inclusion<M: Middleware>(
+            &self,
+            client: &M,
+            submission: &BundleSubmission,
+            poll_interval: std::time::Duration,
+        ) -> Result<BundleStatus, RouterError> {
+            loop {
+                let status = self.bundle_status(client, submission).await?;
+                if status != BundleStatus::Pending {
+                    return Ok(status);
+                }
+                tokio::time::sleep(poll_interval).await;
+            }
+        }
+    }
+    
+    impl RouterEngine {
+        pub fn set_mempool_kind(&self, chain_id: u64, kind: MempoolKind) {
+            self.mempools.insert(chain_id, kind);
+        }
+        
+        pub fn mempool_kind(&self, chain_id: u64) -> Option<MempoolKind> {
+            self.mempools
+                .get(&chain_id)
+                .map(|k| *k)
+                .or_else(|| MempoolKind::for_chain(chain_id))
+        }
+        
+        // Policy a request on `chain_id` runs with. Without one, chains with a
+        // public mempool go through a private relay and others use plain
+        // submission. Sequencer and private orderflow chains have no relay to send
+        // bundles to, so asking for one there is an error.
+        pub fn resolve_mev_policy(&self, chain_id: u64, requested: Option<MevPolicy>) -> Result<MevPolicy, RouterError> {
+            match (self.mempool_kind(chain_id), requested) {
+                (Some(kind @ (MempoolKind::Sequencer | MempoolKind::PrivateOrderflow)), Some(MevPolicy::PrivateRelay)) => {
+                    Err(RouterError::ConfigError(format!(
+                        "Chain {} has no private relay ({:?} mempool)",
+                        chain_id, kind
+                    )))
+                }
+                (_, Some(policy)) => Ok(policy),
+                (Some(MempoolKind::Public), None) => Ok(MevPolicy::PrivateRelay),
+                (_, None) => Ok(MevPolicy::PublicMempool),
+            }
+        }
+        
+        // Whether a route sent with `policy` can be sandwiched; unknown chains are
+        // assumed to have a public mempool
+        pub fn sandwich_exposed(&self, chain_id: u64, policy: MevPolicy) -> bool {
+            policy == MevPolicy::PublicMempool && !matches!(self.mempool_kind(chain_id), Some(kind) if !kind.is_public())
+        }
+    }
+    
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        
+        #[test]
+        fn chain_defaults_apply_only_without_a_requested_policy() {
+            let engine = RouterEngine::new();
+            // Ethereum has a public mempool, Arbitrum a sequencer
+            assert_eq!(engine.resolve_mev_policy(1, None).unwrap(), MevPolicy::PrivateRelay);
+            assert_eq!(engine.resolve_mev_policy(1, Some(MevPolicy::PublicMempool)).unwrap(), MevPolicy::PublicMempool);
+            assert_eq!(engine.resolve_mev_policy(42161, None).unwrap(), MevPolicy::PublicMempool);
+            assert_eq!(engine.resolve_mev_policy(42161, Some(MevPolicy::PublicMempool)).unwrap(), MevPolicy::PublicMempool);
+            assert!(engine.resolve_mev_policy(42161, Some(MevPolicy::PrivateRelay)).is_err());
+            assert_eq!(engine.resolve_mev_policy(999, None).unwrap(), MevPolicy::PublicMempool);
         }
     }
 }
@@ -263,6 +2021,24 @@ pub mod crosschain {
 #[wasm_bindgen]
 pub struct WasmRouter {
     engine: Arc<RouterEngine>,
+    pools: Arc<state::PoolStateStore>,
+    pool_cache: Option<browser_cache::BrowserPoolCache>,
+    subscriptions: std::cell::RefCell<Vec<QuoteSubscription>>,
+    next_subscription: std::cell::Cell<u32>,
+}
+
+#[cfg(feature = "wasm")]
+#[derive(Clone)]
+struct QuoteSubscription {
+    id: u32,
+    schema_version: u32,
+    request: QuoteRequest,
+    callback: js_sys::Function,
+}
+
+#[cfg(feature = "wasm")]
+fn js_error(context: &str, e: impl std::fmt::Display) -> JsValue {
+    JsValue::from_str(&format!("{}: {}", context, e))
 }
 
 #[cfg(feature = "wasm")]
@@ -273,50 +2049,467 @@ impl WasmRouter {
         console_error_panic_hook::set_once();
         Self {
             engine: Arc::new(RouterEngine::new()),
+            pools: Arc::new(state::PoolStateStore::new()),
+            pool_cache: None,
+            subscriptions: std::cell::RefCell::new(Vec::new()),
+            next_subscription: std::cell::Cell::new(0),
         }
     }
     
+    // A Token as JSON
+    #[wasm_bindgen(js_name = registerToken)]
+    pub fn register_token(&self, token_json: String) -> Result<(), JsValue> {
+        let token: Token = serde_json::from_str(&token_json).map_err(|e| js_error("Failed to parse token", e))?;
+        self.engine.register_token(token);
+        Ok(())
+    }
+    
+    // An Exchange as JSON, needed to build calldata for its steps
+    #[wasm_bindgen(js_name = registerExchange)]
+    pub fn register_exchange(&self, exchange_json: String) -> Result<(), JsValue> {
+        let exchange: Exchange = serde_json::from_str(&exchange_json).map_err(|e| js_error("Failed to parse exchange", e))?;
+        self.engine.register_exchange(exchange);
+        Ok(())
+    }
+    
+    #[wasm_bindgen(js_name = registerExecutor)]
+    pub fn register_executor(&self, chain_id: u64, address: String) {
+        self.engine.register_executor(chain_id, address);
+    }
+    
+    // Register an exchange and quote it through the RPC node at `rpc_url`
+    #[wasm_bindgen(js_name = registerRpcSource)]
+    pub fn register_rpc_source(&self, exchange_json: String, rpc_url: String, quoter: Option<String>) -> Result<(), JsValue> {
+        let exchange: Exchange = serde_json::from_str(&exchange_json).map_err(|e| js_error("Failed to parse exchange", e))?;
+        let provider = Provider::<Http>::try_from(rpc_url.as_str()).map_err(|e| js_error("Invalid RPC URL", e))?;
+        let source = adapters::rpc_source(&exchange, Arc::new(provider), quoter.as_deref())
+            .map_err(|e| js_error("Failed to create source", e))?;
+        self.engine.register_liquidity_source(exchange.id.clone(), source);
+        self.engine.register_exchange(exchange);
+        Ok(())
+    }
+    
+    // Quote an exchange through an HTTP quote service (see adapters::RemoteSource)
+    #[wasm_bindgen(js_name = registerRemoteSource)]
+    pub fn register_remote_source(&self, exchange_id: String, url: String) {
+        self.engine.register_liquidity_source(exchange_id, Arc::new(adapters::RemoteSource::new(&url)));
+    }
+    
+    // Call `callback` with the quote for `request_json` (a QuoteResponse, or
+    // {"error": ...}) now and whenever pools change or refreshQuotes is called.
+    // Returns the id to unsubscribe with.
+    #[wasm_bindgen(js_name = subscribeQuotes)]
+    pub async fn subscribe_quotes(&self, request_json: String, callback: js_sys::Function) -> Result<u32, JsValue> {
+        let (schema_version, request) =
+            api::parse_quote_request(&request_json).map_err(|e| js_error("Failed to parse request", e))?;
+        let id = self.next_subscription.get();
+        self.next_subscription.set(id.wrapping_add(1));
+        let subscription = QuoteSubscription {
+            id,
+            schema_version,
+            request,
+            callback,
+        };
+        self.subscriptions.borrow_mut().push(subscription.clone());
+        self.notify(&subscription).await;
+        Ok(id)
+    }
+    
+    #[wasm_bindgen(js_name = unsubscribeQuotes)]
+    pub fn unsubscribe_quotes(&self, id: u32) {
+        self.subscriptions.borrow_mut().retain(|s| s.id != id);
+    }
+    
+    // Requote every subscription, e.g. on each new block for RPC-backed sources
+    #[wasm_bindgen(js_name = refreshQuotes)]
+    pub async fn refresh_quotes(&self) {
+        let subscriptions = self.subscriptions.borrow().clone();
+        for subscription in &subscriptions {
+            self.notify(subscription).await;
+        }
+    }
+    
+    #[wasm_bindgen(js_name = setPoolCache)]
+    pub fn set_pool_cache(&mut self, cache: browser_cache::BrowserPoolCache) {
+        self.pool_cache = Some(cache);
+    }
+    
+    // Quote an exchange from pool state supplied through upsertPools
+    #[wasm_bindgen(js_name = registerStateSource)]
+    pub fn register_state_source(&self, chain_id: u64, exchange_id: String) {
+        let source = state::StateBackedSource::new(chain_id, exchange_id.clone(), self.pools.clone());
+        self.engine.register_liquidity_source(exchange_id, Arc::new(source));
+    }
+    
+    // Load the chain's cached pools; returns how many were restored
+    #[wasm_bindgen(js_name = restorePools)]
+    pub async fn restore_pools(&self, chain_id: u64) -> usize {
+        let cache = match &self.pool_cache {
+            Some(cache) => cache,
+            None => return 0,
+        };
+        cache
+            .load(chain_id)
+            .await
+            .into_iter()
+            .filter(|pool| pool.chain_id == chain_id)
+            .map(|pool| self.pools.apply(pool))
+            .filter(|applied| *applied)
+            .count()
+    }
+    
+    // Apply pool states fetched by the app (a JSON array of PoolState) and write
+    // the chain's pools back to the cache
+    #[wasm_bindgen(js_name = upsertPools)]
+    pub async fn upsert_pools(&self, chain_id: u64, pools_json: String) -> Result<(), JsValue> {
+        let pools: Vec<state::PoolState> = serde_json::from_str(&pools_json)
+            .map_err(|e| JsValue::from_str(&format!("Failed to parse pools: {}", e)))?;
+        for pool in pools {
+            self.pools.apply(pool);
+        }
+        
+        if let Some(cache) = &self.pool_cache {
+            cache.save(chain_id, self.pools.snapshot(chain_id)).await?;
+        }
+        self.refresh_quotes().await;
+        Ok(())
+    }
+    
+    // Pools already known for a chain, so the app only fetches the missing ones
+    #[wasm_bindgen(js_name = knownPools)]
+    pub fn known_pools(&self, chain_id: u64) -> Result<String, JsValue> {
+        serde_json::to_string(&self.pools.snapshot(chain_id))
+            .map_err(|e| JsValue::from_str(&format!("Failed to serialize pools: {}", e)))
+    }
+    
     #[wasm_bindgen]
     pub async fn get_quote(&self, request_json: String) -> Result<String, JsValue> {
-        let request: QuoteRequest = serde_json::from_str(&request_json)
+        let (schema_version, request) = api::parse_quote_request(&request_json)
             .map_err(|e| JsValue::from_str(&format!("Failed to parse request: {}", e)))?;
         
         let response = self.engine.find_routes(request)
             .await
             .map_err(|e| JsValue::from_str(&format!("Router error: {}", e)))?;
         
-        serde_json::to_string(&response)
+        api::encode_quote_response(schema_version, response)
+            .map(|response| response.to_string())
             .map_err(|e| JsValue::from_str(&format!("Failed to serialize response: {}", e)))
     }
+    
+    // Same as get_quote, but rejects with "Quote cancelled" as soon as `token` is
+    // cancelled, dropping the in-flight routing work
+    #[wasm_bindgen]
+    pub async fn get_quote_cancellable(
+        &self,
+        request_json: String,
+        token: &CancellationToken,
+    ) -> Result<String, JsValue> {
+        let registration = token.register()?;
+        
+        match futures::future::Abortable::new(self.get_quote(request_json), registration).await {
+            Ok(result) => result,
+            Err(futures::future::Aborted) => Err(JsValue::from_str("Quote cancelled")),
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl WasmRouter {
+    async fn notify(&self, subscription: &QuoteSubscription) {
+        let payload = match self.engine.find_routes(subscription.request.clone()).await {
+            Ok(response) => match api::encode_quote_response(subscription.schema_version, response) {
+                Ok(response) => response.to_string(),
+                Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+            },
+            Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+        };
+        // Unsubscribed while quoting
+        if !self.subscriptions.borrow().iter().any(|s| s.id == subscription.id) {
+            return;
+        }
+        if let Err(e) = subscription.callback.call1(&JsValue::NULL, &JsValue::from_str(&payload)) {
+            web_sys::console::error_1(&e);
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+#[derive(Default)]
+struct CancellationState {
+    cancelled: bool,
+    handles: Vec<futures::future::AbortHandle>,
 }
 
-// Python bindings
-#[cfg(feature = "pyo3")]
+// Cancels in-flight quotes, either directly or when a linked AbortSignal fires.
+// Like an AbortSignal it stays cancelled, so create a new token per quote.
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct CancellationToken {
+    state: std::rc::Rc<std::cell::RefCell<CancellationState>>,
+    on_abort: Option<(web_sys::AbortSignal, Closure<dyn FnMut()>)>,
+}
+
+#[cfg(feature = "wasm")]
+#[wasm_bindgen]
+impl CancellationToken {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    // Token cancelled when `signal` (e.g. AbortController.signal) aborts
+    #[wasm_bindgen(js_name = fromAbortSignal)]
+    pub fn from_abort_signal(signal: &web_sys::AbortSignal) -> Result<CancellationToken, JsValue> {
+        let mut token = Self::new();
+        if signal.aborted() {
+            token.cancel();
+            return Ok(token);
+        }
+        
+        let state = token.state.clone();
+        let on_abort = Closure::<dyn FnMut()>::new(move || Self::cancel_state(&state));
+        signal.add_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref())?;
+        token.on_abort = Some((signal.clone(), on_abort));
+        
+        Ok(token)
+    }
+    
+    pub fn cancel(&self) {
+        Self::cancel_state(&self.state);
+    }
+    
+    #[wasm_bindgen(getter)]
+    pub fn cancelled(&self) -> bool {
+        self.state.borrow().cancelled
+    }
+}
+
+// Detach from the signal so it never calls into a freed token
+#[cfg(feature = "wasm")]
+impl Drop for CancellationToken {
+    fn drop(&mut self) {
+        if let Some((signal, on_abort)) = self.on_abort.take() {
+            let _ = signal.remove_event_listener_with_callback("abort", on_abort.as_ref().unchecked_ref());
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl CancellationToken {
+    fn cancel_state(state: &std::cell::RefCell<CancellationState>) {
+        let mut state = state.borrow_mut();
+        state.cancelled = true;
+        for handle in state.handles.drain(..) {
+            handle.abort();
+        }
+    }
+    
+    fn register(&self) -> Result<futures::future::AbortRegistration, JsValue> {
+        let mut state = self.state.borrow_mut();
+        if state.cancelled {
+            return Err(JsValue::from_str("Quote cancelled"));
+        }
+        
+        let (handle, registration) = futures::future::AbortHandle::new_pair();
+        state.handles.push(handle);
+        Ok(registration)
+    }
+}
+
+// Python bindings, built with the python feature. pyo3 0.19's #[pymethods]
+// expansion trips non_local_definitions on newer compilers.
+#[cfg(feature = "python")]
+#[allow(unknown_lints, non_local_definitions)]
 mod python {
     use super::*;
     use pyo3::prelude::*;
     use pyo3::wrap_pyfunction;
     
+    // Shared by the execution functions so connections and timers outlive a call
+    fn runtime() -> PyResult<&'static tokio::runtime::Runtime> {
+        static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+        if let Some(runtime) = RUNTIME.get() {
+            return Ok(runtime);
+        }
+        let runtime = tokio::runtime::Runtime::new()
+            .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(e.to_string()))?;
+        Ok(RUNTIME.get_or_init(|| runtime))
+    }
+    
+    fn py_err(e: impl std::fmt::Display) -> PyErr {
+        pyo3::exceptions::PyValueError::new_err(e.to_string())
+    }
+    
+    fn provider(rpc_url: &str) -> PyResult<Provider<Http>> {
+        Provider::<Http>::try_from(rpc_url).map_err(py_err)
+    }
+    
+    // Engine kept across calls, configured from Python and driven on the shared runtime
+    #[pyclass(name = "RouterEngine")]
+    struct PyRouterEngine {
+        engine: Arc<RouterEngine>,
+    }
+    
+    #[pymethods]
+    impl PyRouterEngine {
+        #[new]
+        fn new() -> Self {
+            Self {
+                engine: Arc::new(RouterEngine::new()),
+            }
+        }
+        
+        // A Token as JSON
+        fn register_token(&self, token_json: String) -> PyResult<()> {
+            let token: Token = serde_json::from_str(&token_json).map_err(py_err)?;
+            self.engine.register_token(token);
+            Ok(())
+        }
+        
+        // An Exchange as JSON, needed to build calldata for its steps
+        fn register_exchange(&self, exchange_json: String) -> PyResult<()> {
+            let exchange: Exchange = serde_json::from_str(&exchange_json).map_err(py_err)?;
+            self.engine.register_exchange(exchange);
+            Ok(())
+        }
+        
+        fn register_executor(&self, chain_id: u64, address: String) {
+            self.engine.register_executor(chain_id, address);
+        }
+        
+        // Register an exchange and quote it through the RPC node at `rpc_url`
+        fn register_rpc_source(&self, exchange_json: String, rpc_url: String, quoter: Option<String>) -> PyResult<()> {
+            let exchange: Exchange = serde_json::from_str(&exchange_json).map_err(py_err)?;
+            let source = adapters::rpc_source(&exchange, Arc::new(provider(&rpc_url)?), quoter.as_deref()).map_err(py_err)?;
+            self.engine.register_liquidity_source(exchange.id.clone(), source);
+            self.engine.register_exchange(exchange);
+            Ok(())
+        }
+        
+        // Quote an exchange through an HTTP quote service (see adapters::RemoteSource)
+        fn register_remote_source(&self, exchange_id: String, url: String) {
+            self.engine.register_liquidity_source(exchange_id, Arc::new(adapters::RemoteSource::new(&url)));
+        }
+        
+        fn record_block(&self, chain_id: u64, block_number: u64) {
+            self.engine.record_block(chain_id, block_number);
+        }
+        
+        // QuoteResponse as JSON for a QuoteRequest as JSON, in the request's schema version
+        fn find_routes(&self, py: Python<'_>, request_json: String) -> PyResult<String> {
+            let runtime = runtime()?;
+            let (schema_version, request) = api::parse_quote_request(&request_json).map_err(py_err)?;
+            let engine = self.engine.clone();
+            
+            py.allow_threads(|| {
+                runtime.block_on(async {
+                    let response = engine.find_routes(request).await.map_err(py_err)?;
+                    let response = api::encode_quote_response(schema_version, response).map_err(py_err)?;
+                    Ok(response.to_string())
+                })
+            })
+        }
+    }
+    
+    // Quote on a fresh, unconfigured engine, as before engines could be kept;
+    // RouterEngine().find_routes does the same on a configured one
     #[pyfunction]
-    fn find_routes(
+    fn find_routes(py: Python<'_>, request_json: String) -> PyResult<String> {
+        PyRouterEngine::new().find_routes(py, request_json)
+    }
+    
+    // Executor calldata (hex) for a route, given the exchange call of every step
+    #[pyfunction]
+    fn build_calldata(route_json: String, step_calls_json: String, private_relay: Option<bool>) -> PyResult<String> {
+        let route: SwapRoute = serde_json::from_str(&route_json).map_err(py_err)?;
+        let calls: Vec<executor::StepCall> = serde_json::from_str(&step_calls_json).map_err(py_err)?;
+        let policy = if private_relay.unwrap_or(false) {
+            mev::MevPolicy::PrivateRelay
+        } else {
+            mev::MevPolicy::PublicMempool
+        };
+        
+        let calldata = executor::encode_multi_swap(&route, &calls, policy).map_err(py_err)?;
+        Ok(format!("0x{}", hex::encode(calldata)))
+    }
+    
+    // Simulate, sign with `private_key` and send a transaction; returns the submission
+    // as JSON (only the prepared transaction when the dry_run flag is set)
+    #[pyfunction]
+    fn submit_transaction(
         py: Python<'_>,
-        request_json: String,
+        rpc_url: String,
+        private_key: String,
+        to: String,
+        data: String,
+        value: Option<String>,
+        dry_run: Option<bool>,
     ) -> PyResult<String> {
-        let runtime = tokio::runtime::Runtime::new().unwrap();
-        let engine = RouterEngine::new();
+        let runtime = runtime()?;
+        let private_key = secrets::Secret::new(private_key);
         
-        let request: QuoteRequest = serde_json::from_str(&request_json)?;
-        
-        let response = runtime.block_on(async {
-            engine.find_routes(request).await
-        })?;
+        py.allow_threads(|| {
+            runtime.block_on(async {
+                let provider = provider(&rpc_url)?;
+                let chain_id = provider.get_chainid().await.map_err(py_err)?.as_u64();
+                let wallet: LocalWallet = private_key
+                    .expose()
+                    .parse::<LocalWallet>()
+                    .map_err(py_err)?
+                    .with_chain_id(chain_id);
+                let from = wallet.address();
+                let client = Arc::new(SignerMiddleware::new(provider, wallet));
+                
+                let engine = Arc::new(RouterEngine::new());
+                engine.set_dry_run(dry_run.unwrap_or(false));
+                let manager = tx_manager::TxManager::new(client, engine);
+                
+                let data = hex::decode(data.trim_start_matches("0x")).map_err(py_err)?;
+                let value = math::to_u256(&math::parse_amount(value.as_deref().unwrap_or("0")).map_err(py_err)?)
+                    .map_err(py_err)?;
+                let tx = Eip1559TransactionRequest::new()
+                    .from(from)
+                    .to(parse_address(&to).map_err(py_err)?)
+                    .data(data)
+                    .value(value)
+                    .chain_id(chain_id);
+                let request = QuoteRequest {
+                    chain_id,
+                    ..Default::default()
+                };
+                
+                let submission = manager
+                    .submit(tx, request, mev::MevPolicy::PublicMempool)
+                    .await
+                    .map_err(py_err)?;
+                serde_json::to_string(&submission).map_err(py_err)
+            })
+        })
+    }
+    
+    // Status of a transaction as JSON: pending, confirmed, reverted or unknown
+    #[pyfunction]
+    fn execution_status(py: Python<'_>, rpc_url: String, tx_hash: String) -> PyResult<String> {
+        let runtime = runtime()?;
+        let hash: H256 = tx_hash.parse().map_err(py_err)?;
         
-        Ok(serde_json::to_string(&response)?)
+        py.allow_threads(|| {
+            runtime.block_on(async {
+                let provider = provider(&rpc_url)?;
+                let status = tx_manager::execution_status(&provider, hash).await.map_err(py_err)?;
+                serde_json::to_string(&status).map_err(py_err)
+            })
+        })
     }
     
     #[pymodule]
     fn router_engine(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+        m.add_class::<PyRouterEngine>()?;
         m.add_function(wrap_pyfunction!(find_routes, m)?)?;
+        m.add_function(wrap_pyfunction!(build_calldata, m)?)?;
+        m.add_function(wrap_pyfunction!(submit_transaction, m)?)?;
+        m.add_function(wrap_pyfunction!(execution_status, m)?)?;
         Ok(())
     }
 } 
\ No newline at end of file
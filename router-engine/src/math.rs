@@ -0,0 +1,244 @@
+use std::collections::HashMap;
+
+use num_traits::{One, ToPrimitive, Zero};
+
+use super::*;
+
+// Fee tiers are expressed in hundredths of a basis point (3000 = 0.3%)
+pub const FEE_DENOMINATOR: u32 = 1_000_000;
+pub const DEFAULT_FEE_TIER: u32 = 3000;
+
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+// Which way a division rounds. Amounts the user or a recipient receives (quoted
+// outputs, minimum outputs, payout shares) round down; amounts that must be
+// supplied or paid (inputs, fees, repayments, gas) round up. Rounding the other
+// way leaves a quote a wei above what the chain delivers, and a min-out equal to
+// it reverts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+}
+
+// `amount * numerator / denominator`, rounded as given
+pub fn mul_div(amount: &BigUint, numerator: &BigUint, denominator: &BigUint, rounding: Rounding) -> BigUint {
+    if denominator.is_zero() {
+        return BigUint::zero();
+    }
+    let product = amount * numerator;
+    match rounding {
+        Rounding::Down => product / denominator,
+        Rounding::Up => (product + denominator - BigUint::one()) / denominator,
+    }
+}
+
+// `bps` basis points of `amount`
+pub fn bps_of(amount: &BigUint, bps: u64, rounding: Rounding) -> BigUint {
+    mul_div(amount, &BigUint::from(bps), &BigUint::from(BPS_DENOMINATOR), rounding)
+}
+
+// Rounding margins and dust thresholds of amount math
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AmountRules {
+    // Taken off every step's minimum output, absorbing off-by-one differences
+    // between an adapter's quote and the pool's own integer math
+    pub min_out_margin: u64,
+    // Residue at or below this many base units isn't worth reporting or
+    // splitting an order for
+    pub dust_threshold: u64,
+    // Per-token thresholds (lowercase address to base units), for tokens whose
+    // decimals make the default too coarse or too fine
+    #[serde(default)]
+    pub token_dust_thresholds: HashMap<String, u64>,
+}
+
+impl Default for AmountRules {
+    fn default() -> Self {
+        Self {
+            min_out_margin: 1,
+            dust_threshold: 0,
+            token_dust_thresholds: HashMap::new(),
+        }
+    }
+}
+
+impl AmountRules {
+    pub fn dust_threshold(&self, token: &str) -> u64 {
+        self.token_dust_thresholds
+            .get(&token.to_lowercase())
+            .copied()
+            .unwrap_or(self.dust_threshold)
+    }
+
+    pub fn is_dust(&self, token: &str, amount: &BigUint) -> bool {
+        *amount <= BigUint::from(self.dust_threshold(token))
+    }
+
+    // Minimum output to demand for an `expected` output, never below one unit
+    // when anything is expected
+    pub fn min_out(&self, expected: &BigUint) -> BigUint {
+        let margin = BigUint::from(self.min_out_margin);
+        if *expected > margin {
+            expected - margin
+        } else {
+            expected.min(&BigUint::one()).clone()
+        }
+    }
+
+    // Apply the margin to every step's minimum output
+    pub fn pad_min_outs(&self, route: &mut SwapRoute) -> Result<(), RouterError> {
+        for step in route.steps.iter_mut() {
+            step.amount_out_min = self.min_out(&parse_amount(&step.amount_out_min)?).to_string();
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_amount(value: &str) -> Result<BigUint, RouterError> {
+    value
+        .parse()
+        .map_err(|_| RouterError::ExecutionError(format!("Invalid amount: {}", value)))
+}
+
+// Constant-product output for `amount_in` against (reserve_in, reserve_out)
+pub fn get_amount_out(
+    amount_in: &BigUint,
+    reserve_in: &BigUint,
+    reserve_out: &BigUint,
+    fee: u32,
+) -> BigUint {
+    if amount_in.is_zero() || reserve_in.is_zero() || reserve_out.is_zero() {
+        return BigUint::zero();
+    }
+
+    let amount_with_fee = amount_in * BigUint::from(FEE_DENOMINATOR - fee.min(FEE_DENOMINATOR));
+    let denominator = reserve_in * BigUint::from(FEE_DENOMINATOR) + &amount_with_fee;
+
+    mul_div(&amount_with_fee, reserve_out, &denominator, Rounding::Down)
+}
+
+// Constant-product input needed to receive `amount_out`, rounded up
+pub fn get_amount_in(
+    amount_out: &BigUint,
+    reserve_in: &BigUint,
+    reserve_out: &BigUint,
+    fee: u32,
+) -> Result<BigUint, RouterError> {
+    if amount_out >= reserve_out || reserve_in.is_zero() {
+        return Err(RouterError::InsufficientLiquidity(format!(
+            "Cannot buy {} from a reserve of {}",
+            amount_out, reserve_out
+        )));
+    }
+
+    let numerator = amount_out * BigUint::from(FEE_DENOMINATOR);
+    let denominator = (reserve_out - amount_out) * BigUint::from(FEE_DENOMINATOR - fee.min(FEE_DENOMINATOR - 1));
+
+    Ok(mul_div(reserve_in, &numerator, &denominator, Rounding::Up))
+}
+
+// `numerator / denominator` as a float, 1.0 when the denominator is zero
+pub fn ratio(numerator: &BigUint, denominator: &BigUint) -> f64 {
+    match (numerator.to_f64(), denominator.to_f64()) {
+        (Some(n), Some(d)) if d > 0.0 => n / d,
+        _ => 1.0,
+    }
+}
+
+pub fn to_u256(amount: &BigUint) -> Result<U256, RouterError> {
+    let bytes = amount.to_bytes_be();
+    if bytes.len() > 32 {
+        return Err(RouterError::ExecutionError(format!("Amount exceeds uint256: {}", amount)));
+    }
+    Ok(U256::from_big_endian(&bytes))
+}
+
+pub fn from_u256(amount: U256) -> BigUint {
+    let mut bytes = [0u8; 32];
+    amount.to_big_endian(&mut bytes);
+    BigUint::from_bytes_be(&bytes)
+}
+
+impl RouterEngine {
+    pub fn set_amount_rules(&self, rules: AmountRules) {
+        *self.amount_rules.write().unwrap() = rules;
+    }
+
+    pub fn amount_rules(&self) -> AmountRules {
+        self.amount_rules.read().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn n(value: u64) -> BigUint {
+        BigUint::from(value)
+    }
+
+    #[test]
+    fn mul_div_rounds_as_asked() {
+        assert_eq!(mul_div(&n(10), &n(1), &n(3), Rounding::Down), n(3));
+        assert_eq!(mul_div(&n(10), &n(1), &n(3), Rounding::Up), n(4));
+        // Exact quotients don't round up
+        assert_eq!(mul_div(&n(9), &n(1), &n(3), Rounding::Up), n(3));
+        assert_eq!(mul_div(&n(9), &n(1), &n(0), Rounding::Up), n(0));
+        // 30 bps of 1001 is 3.003
+        assert_eq!(bps_of(&n(1001), 30, Rounding::Down), n(3));
+        assert_eq!(bps_of(&n(1001), 30, Rounding::Up), n(4));
+    }
+
+    #[test]
+    fn constant_product_amounts() {
+        // 1000 * 0.997 * 2e6 / (1e6 + 1000 * 0.997) = 1992.01...
+        assert_eq!(get_amount_out(&n(1_000), &n(1_000_000), &n(2_000_000), 3000), n(1_992));
+        assert_eq!(get_amount_out(&n(0), &n(1_000_000), &n(2_000_000), 3000), n(0));
+        // 1e6 * 1992 / ((2e6 - 1992) * 0.997) = 999.99..., rounded up
+        assert_eq!(get_amount_in(&n(1_992), &n(1_000_000), &n(2_000_000), 3000).unwrap(), n(1_000));
+        assert!(get_amount_in(&n(2_000_000), &n(1_000_000), &n(2_000_000), 3000).is_err());
+    }
+
+    #[test]
+    fn amount_in_round_trips() {
+        let out = get_amount_out(&n(1_000), &n(1_000_000), &n(2_000_000), 3000);
+        assert!(get_amount_in(&out, &n(1_000_000), &n(2_000_000), 3000).unwrap() >= n(1_000));
+
+        let pools = [(1_000_000, 2_000_000), (7, 1_000_003), (999_983, 13), (10u64.pow(18), 3 * 10u64.pow(15))];
+        for (reserve_in, reserve_out) in pools {
+            let (reserve_in, reserve_out) = (n(reserve_in), n(reserve_out));
+            for amount in [1, 2, 3, 997, 1_000, 123_457] {
+                // Paying the rounded-up input buys at least the output asked for
+                if n(amount) < reserve_out {
+                    let amount_in = get_amount_in(&n(amount), &reserve_in, &reserve_out, 3000).unwrap();
+                    assert!(get_amount_out(&amount_in, &reserve_in, &reserve_out, 3000) >= n(amount));
+                }
+                // The rounded-down output never costs more than was paid for it
+                let out = get_amount_out(&n(amount), &reserve_in, &reserve_out, 3000);
+                if !out.is_zero() {
+                    assert!(get_amount_in(&out, &reserve_in, &reserve_out, 3000).unwrap() <= n(amount));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn min_out_keeps_one_unit() {
+        let rules = AmountRules::default();
+        assert_eq!(rules.min_out(&n(0)), n(0));
+        assert_eq!(rules.min_out(&n(1)), n(1));
+        assert_eq!(rules.min_out(&n(2)), n(1));
+        assert_eq!(rules.min_out(&n(1_000)), n(999));
+
+        let wide = AmountRules {
+            min_out_margin: 5,
+            ..Default::default()
+        };
+        assert_eq!(wide.min_out(&n(0)), n(0));
+        assert_eq!(wide.min_out(&n(1)), n(1));
+        assert_eq!(wide.min_out(&n(5)), n(1));
+        assert_eq!(wide.min_out(&n(6)), n(1));
+        assert_eq!(wide.min_out(&n(7)), n(2));
+    }
+}
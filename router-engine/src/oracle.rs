@@ -0,0 +1,185 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ethers::abi::{ParamType, Token as AbiToken};
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::adapters::call;
+
+// Chainlink answers older than this are ignored
+pub const DEFAULT_MAX_PRICE_AGE_SECS: u64 = 3_600;
+
+// USD price of a token, if the oracle knows it
+#[async_trait]
+pub trait PriceOracle: Send + Sync {
+    async fn usd_price(&self, token: &Token) -> Result<Option<f64>, RouterError>;
+}
+
+// Fixed prices, e.g. for pegged stablecoins or tests
+#[derive(Debug, Default)]
+pub struct StaticOracle {
+    prices: DashMap<(u64, String), f64>,
+}
+
+impl StaticOracle {
+    pub fn set_price(&self, chain_id: u64, address: &str, usd: f64) {
+        self.prices.insert((chain_id, address.to_lowercase()), usd);
+    }
+}
+
+#[async_trait]
+impl PriceOracle for StaticOracle {
+    async fn usd_price(&self, token: &Token) -> Result<Option<f64>, RouterError> {
+        Ok(self.prices.get(&(token.chain_id, token.address.to_lowercase())).map(|p| *p))
+    }
+}
+
+// Chainlink <TOKEN>/USD aggregators of one chain
+pub struct ChainlinkOracle<M: Middleware> {
+    chain_id: u64,
+    client: Arc<M>,
+    feeds: DashMap<String, Address>,
+    decimals: DashMap<Address, u8>,
+    max_age: u64,
+}
+
+impl<M: Middleware + 'static> ChainlinkOracle<M> {
+    pub fn new(chain_id: u64, client: Arc<M>) -> Self {
+        Self {
+            chain_id,
+            client,
+            feeds: DashMap::new(),
+            decimals: DashMap::new(),
+            max_age: DEFAULT_MAX_PRICE_AGE_SECS,
+        }
+    }
+
+    pub fn with_max_age(mut self, max_age: u64) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    pub fn register_feed(&self, token: &str, aggregator: &str) -> Result<(), RouterError> {
+        self.feeds.insert(token.to_lowercase(), parse_address(aggregator)?);
+        Ok(())
+    }
+
+    async fn feed_decimals(&self, feed: Address) -> Result<u8, RouterError> {
+        if let Some(decimals) = self.decimals.get(&feed) {
+            return Ok(*decimals);
+        }
+        let result = call(&*self.client, feed, encode_call("decimals()", &[]), &[ParamType::Uint(8)]).await?;
+        let decimals = match result.first() {
+            Some(AbiToken::Uint(value)) => value.low_u32() as u8,
+            _ => return Err(RouterError::ChainError(format!("Invalid decimals from feed {:?}", feed))),
+        };
+        self.decimals.insert(feed, decimals);
+        Ok(decimals)
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> PriceOracle for ChainlinkOracle<M> {
+    async fn usd_price(&self, token: &Token) -> Result<Option<f64>, RouterError> {
+        if token.chain_id != self.chain_id {
+            return Ok(None);
+        }
+        let Some(feed) = self.feeds.get(&token.address.to_lowercase()).map(|f| *f) else {
+            return Ok(None);
+        };
+
+        let round = call(
+            &*self.client,
+            feed,
+            encode_call("latestRoundData()", &[]),
+            &[
+                ParamType::Uint(80),
+                ParamType::Int(256),
+                ParamType::Uint(256),
+                ParamType::Uint(256),
+                ParamType::Uint(80),
+            ],
+        )
+        .await?;
+        let (answer, updated_at) = match (round.get(1), round.get(3)) {
+            (Some(AbiToken::Int(answer)), Some(AbiToken::Uint(updated_at))) => (I256::from_raw(*answer), updated_at.low_u64()),
+            _ => return Err(RouterError::ChainError(format!("Invalid round data from feed {:?}", feed))),
+        };
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        if answer <= I256::zero() || now.saturating_sub(updated_at) > self.max_age {
+            warn!("Ignoring stale or invalid {} price from feed {:?}", token.symbol, feed);
+            return Ok(None);
+        }
+
+        let decimals = self.feed_decimals(feed).await?;
+        Ok(Some(math::ratio(&math::from_u256(answer.into_raw()), &BigUint::from(10u32).pow(decimals as u32))))
+    }
+}
+
+// Amount in base units valued at `usd_price`
+pub fn usd_value(amount: &BigUint, decimals: u8, usd_price: f64) -> f64 {
+    math::ratio(amount, &BigUint::from(10u32).pow(decimals as u32)) * usd_price
+}
+
+impl RouterEngine {
+    pub fn set_price_oracle(&self, oracle: Arc<dyn PriceOracle>) {
+        *self.oracle.write().unwrap() = Some(oracle);
+    }
+
+    // The oracle's price, or the token's spot price in the chain's native token
+    // times the native token's oracle price
+    pub async fn usd_price(&self, token: &Token) -> Option<f64> {
+        let oracle = self.oracle.read().unwrap().clone()?;
+        match oracle.usd_price(token).await {
+            Ok(Some(price)) => return Some(price),
+            Ok(None) => {}
+            Err(e) => warn!("Oracle price of {} failed: {}", token.symbol, e),
+        }
+
+        let native = self.native_tokens.get(&token.chain_id).map(|t| t.clone())?;
+        if native == *token {
+            return None;
+        }
+        let native_usd = oracle.usd_price(&native).await.ok().flatten()?;
+        let in_native = self.spot_price(token, &native).await.ok()?;
+        Some(in_native * native_usd)
+    }
+
+    // Fill the response's USD fields from the best route; fields without a price stay None
+    pub(crate) async fn value_in_usd(&self, response: &mut QuoteResponse, best: &SwapRoute, chain_id: u64) {
+        if self.oracle.read().unwrap().is_none() {
+            return;
+        }
+        let (Some(first), Some(last)) = (best.steps.first(), best.steps.last()) else {
+            return;
+        };
+        let token_in = first.token_in.clone();
+        let token_out = last.token_out.clone();
+
+        if let (Some(price), Ok(amount)) = (self.usd_price(&token_in).await, math::parse_amount(&best.amount_in)) {
+            response.amount_in_usd = Some(usd_value(&amount, token_in.decimals, price));
+        }
+        let out_price = self.usd_price(&token_out).await;
+        if let (Some(price), Ok(amount)) = (out_price, math::parse_amount(&best.expected_amount_out)) {
+            response.amount_out_usd = Some(usd_value(&amount, token_out.decimals, price));
+        }
+        if let (Some(price), Some(fee)) = (out_price, &best.integrator_fee) {
+            if let Ok(fee) = math::parse_amount(fee) {
+                response.fee_usd = Some(usd_value(&fee, token_out.decimals, price));
+            }
+        }
+
+        let gas_price = self.gas_prices.get(&chain_id).map(|p| p.clone());
+        let native = self.native_tokens.get(&chain_id).map(|t| t.clone());
+        if let (Some(gas_price), Some(native)) = (gas_price, native) {
+            if let Some(price) = self.usd_price(&native).await {
+                let cost = gas_payment::native_cost(best.gas_estimate, &gas_price);
+                response.gas_usd = Some(usd_value(&cost, native.decimals, price));
+            }
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use super::*;
+
+// Quoting quirks of one deployed pool of a standard AMM, set from config
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PoolOverride {
+    pub chain_id: u64,
+    pub exchange_id: String,
+    pub token_a: String,
+    pub token_b: String,
+    // Fee in hundredths of a basis point, replacing the source's own quote
+    #[serde(default)]
+    pub fee_tier: Option<u32>,
+    // Gas per hop through this pool, replacing the source's constant
+    #[serde(default)]
+    pub gas: Option<u64>,
+    // Never price exact-output swaps (e.g. gas purchases) against this pool
+    #[serde(default)]
+    pub disable_exact_out: bool,
+}
+
+type PoolKey = (u64, String, String, String);
+
+fn pool_key(chain_id: u64, exchange_id: &str, token_a: &str, token_b: &str) -> PoolKey {
+    let a = token_a.to_lowercase();
+    let b = token_b.to_lowercase();
+    let (a, b) = if a < b { (a, b) } else { (b, a) };
+    (chain_id, exchange_id.to_string(), a, b)
+}
+
+#[derive(Debug, Default)]
+pub struct PoolOverrides {
+    pools: DashMap<PoolKey, PoolOverride>,
+}
+
+impl PoolOverrides {
+    pub fn insert(&self, pool: PoolOverride) {
+        let key = pool_key(pool.chain_id, &pool.exchange_id, &pool.token_a, &pool.token_b);
+        self.pools.insert(key, pool);
+    }
+
+    pub fn remove(&self, chain_id: u64, exchange_id: &str, token_a: &str, token_b: &str) {
+        self.pools.remove(&pool_key(chain_id, exchange_id, token_a, token_b));
+    }
+
+    pub fn get(&self, exchange_id: &str, token_a: &Token, token_b: &Token) -> Option<PoolOverride> {
+        self.pools
+            .get(&pool_key(token_a.chain_id, exchange_id, &token_a.address, &token_b.address))
+            .map(|o| o.clone())
+    }
+
+    pub fn step_gas(&self, step: &SwapStep) -> Option<u64> {
+        self.get(&step.exchange_id, &step.token_in, &step.token_out).and_then(|o| o.gas)
+    }
+
+    pub fn allows_exact_out(&self, exchange_id: &str, token_a: &Token, token_b: &Token) -> bool {
+        !matches!(self.get(exchange_id, token_a, token_b), Some(o) if o.disable_exact_out)
+    }
+}
+
+impl RouterEngine {
+    // Apply the `pool_overrides` array of a config file
+    pub fn load_pool_overrides(&self, config: &serde_json::Value) -> Result<usize, RouterError> {
+        let pools: Vec<PoolOverride> = serde_json::from_value(config.clone())
+            .map_err(|e| RouterError::ConfigError(format!("Invalid pool overrides: {}", e)))?;
+        let count = pools.len();
+        for pool in pools {
+            self.register_pool_override(pool);
+        }
+        Ok(count)
+    }
+
+    pub fn register_pool_override(&self, pool: PoolOverride) {
+        self.pool_overrides.insert(pool);
+    }
+
+    pub fn pool_overrides(&self) -> &PoolOverrides {
+        &self.pool_overrides
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use super::*;
+
+// Pools listed per pair, by how many best routes went through them
+pub const TOP_POOLS: usize = 5;
+
+#[derive(Debug, Clone, Default)]
+struct PairRecord {
+    quotes: u64,
+    unrouted: u64,
+    volume_in: BigUint,
+    hops: u64,
+    price_impact: f64,
+    pools: HashMap<(String, String, String), u64>,
+    last_quoted_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolUsage {
+    pub exchange_id: String,
+    pub token_a: String,
+    pub token_b: String,
+    // Best routes through the pool
+    pub routes: u64,
+}
+
+// How one pair's quotes were routed since the engine started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairStats {
+    pub chain_id: u64,
+    pub token_in: String,
+    pub token_out: String,
+    pub quotes: u64,
+    // Quotes that found no route
+    pub unrouted: u64,
+    // Input quoted, in token_in base units
+    pub volume_in: String,
+    // Averages over the best routes of routed quotes
+    pub avg_hops: f64,
+    pub avg_price_impact: f64,
+    pub top_pools: Vec<PoolUsage>,
+    pub last_quoted_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PairStatsFilter {
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    #[serde(default)]
+    pub integrator: Option<String>,
+}
+
+// Quote traffic per integrator, chain and pair. Only best routes count, as
+// those are what gets executed.
+#[derive(Default)]
+pub struct PairStatsTracker {
+    records: DashMap<(Option<String>, u64, String, String), PairRecord>,
+}
+
+fn pool_key(step: &SwapStep) -> (String, String, String) {
+    let a = step.token_in.address.to_lowercase();
+    let b = step.token_out.address.to_lowercase();
+    let (token_a, token_b) = if a <= b { (a, b) } else { (b, a) };
+    (step.exchange_id.clone(), token_a, token_b)
+}
+
+impl PairStatsTracker {
+    pub fn record(&self, request: &QuoteRequest, best: Option<&SwapRoute>) {
+        let key = (
+            request.integrator.clone(),
+            request.chain_id,
+            request.token_in.to_lowercase(),
+            request.token_out.to_lowercase(),
+        );
+        let mut record = self.records.entry(key).or_default();
+        record.quotes += 1;
+        record.last_quoted_at = rfq::now();
+        record.volume_in += math::parse_amount(&request.amount_in).unwrap_or_default();
+
+        let Some(best) = best else {
+            record.unrouted += 1;
+            return;
+        };
+        record.hops += best.steps.len() as u64;
+        record.price_impact += best.price_impact;
+        let mut pools: Vec<_> = best.steps.iter().map(pool_key).collect();
+        pools.sort();
+        pools.dedup();
+        for pool in pools {
+            *record.pools.entry(pool).or_default() += 1;
+        }
+    }
+
+    // Pairs by quote count, merged across integrators unless the filter names one
+    pub fn stats(&self, filter: &PairStatsFilter) -> Vec<PairStats> {
+        let mut merged: HashMap<(u64, String, String), PairRecord> = HashMap::new();
+        for entry in self.records.iter() {
+            let (integrator, chain_id, token_in, token_out) = entry.key();
+            if filter.integrator.is_some() && *integrator != filter.integrator {
+                continue;
+            }
+            if matches!(filter.chain_id, Some(id) if id != *chain_id) {
+                continue;
+            }
+            let record = entry.value();
+            let total = merged.entry((*chain_id, token_in.clone(), token_out.clone())).or_default();
+            total.quotes += record.quotes;
+            total.unrouted += record.unrouted;
+            total.volume_in += &record.volume_in;
+            total.hops += record.hops;
+            total.price_impact += record.price_impact;
+            total.last_quoted_at = total.last_quoted_at.max(record.last_quoted_at);
+            for (pool, routes) in &record.pools {
+                *total.pools.entry(pool.clone()).or_default() += routes;
+            }
+        }
+
+        let mut stats: Vec<PairStats> = merged
+            .into_iter()
+            .map(|((chain_id, token_in, token_out), record)| {
+                let routed = record.quotes - record.unrouted;
+                let mut top_pools: Vec<PoolUsage> = record
+                    .pools
+                    .into_iter()
+                    .map(|((exchange_id, token_a, token_b), routes)| PoolUsage {
+                        exchange_id,
+                        token_a,
+                        token_b,
+                        routes,
+                    })
+                    .collect();
+                top_pools.sort_by(|a, b| b.routes.cmp(&a.routes).then_with(|| a.exchange_id.cmp(&b.exchange_id)));
+                top_pools.truncate(TOP_POOLS);
+                PairStats {
+                    chain_id,
+                    token_in,
+                    token_out,
+                    quotes: record.quotes,
+                    unrouted: record.unrouted,
+                    volume_in: record.volume_in.to_string(),
+                    avg_hops: if routed > 0 { record.hops as f64 / routed as f64 } else { 0.0 },
+                    avg_price_impact: if routed > 0 { record.price_impact / routed as f64 } else { 0.0 },
+                    top_pools,
+                    last_quoted_at: record.last_quoted_at,
+                }
+            })
+            .collect();
+        stats.sort_by(|a, b| b.quotes.cmp(&a.quotes).then_with(|| (&a.token_in, &a.token_out).cmp(&(&b.token_in, &b.token_out))));
+        stats
+    }
+}
+
+impl RouterEngine {
+    pub fn pair_stats(&self, filter: &PairStatsFilter) -> Vec<PairStats> {
+        self.pair_stats.stats(filter)
+    }
+}
@@ -0,0 +1,185 @@
+use ethers::abi::Token as AbiToken;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::executor::decode_multi_swap_steps;
+
+pub(crate) const MULTI_SWAP_AND_SPLIT: &str =
+    "multiSwapAndSplit((address,address,address,uint256,uint256,bytes,uint16,uint16)[],(address,uint16)[])";
+
+const TOTAL_BPS: u32 = 10_000;
+
+// One receiver of a share of the route's output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipient {
+    pub address: String,
+    pub share_bps: u16,
+}
+
+// Amounts a recipient receives from a route
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payout {
+    pub address: String,
+    pub share_bps: u16,
+    pub expected_amount: String,
+    pub min_amount: String,
+}
+
+// Shares must be positive, add up to 100% and name valid addresses
+pub fn validate(recipients: &[Recipient]) -> Result<(), RouterError> {
+    if recipients.is_empty() {
+        return Err(RouterError::ConfigError("No recipients given".to_string()));
+    }
+
+    let mut total = 0u32;
+    for recipient in recipients {
+        parse_address(&recipient.address)?;
+        if recipient.share_bps == 0 {
+            return Err(RouterError::ConfigError(format!("Recipient {} has a zero share", recipient.address)));
+        }
+        total += recipient.share_bps as u32;
+    }
+
+    if total != TOTAL_BPS {
+        return Err(RouterError::ConfigError(format!(
+            "Recipient shares add up to {} bps, expected {}",
+            total, TOTAL_BPS
+        )));
+    }
+    Ok(())
+}
+
+// Split `amount` by share, rounding down; the last recipient gets the remainder
+// exactly as the executor does
+pub fn split(amount: &BigUint, recipients: &[Recipient]) -> Vec<BigUint> {
+    let mut remaining = amount.clone();
+    let mut amounts = Vec::with_capacity(recipients.len());
+
+    for (i, recipient) in recipients.iter().enumerate() {
+        let share = if i + 1 == recipients.len() {
+            remaining.clone()
+        } else {
+            math::bps_of(amount, recipient.share_bps as u64, math::Rounding::Down)
+        };
+        remaining -= &share;
+        amounts.push(share);
+    }
+
+    amounts
+}
+
+// What each recipient is expected to receive and at least receives; the minimum
+// is that of every leg's final step together
+pub fn route_payouts(route: &SwapRoute, recipients: &[Recipient]) -> Result<Vec<Payout>, RouterError> {
+    let expected = math::parse_amount(&route.expected_amount_out)?;
+    let mut min_out = BigUint::default();
+    for (steps, _) in routing::legs(route) {
+        if let Some(last) = steps.last() {
+            min_out += math::parse_amount(&last.amount_out_min)?;
+        }
+    }
+
+    Ok(recipients
+        .iter()
+        .zip(split(&expected, recipients))
+        .zip(split(&min_out, recipients))
+        .map(|((recipient, expected_amount), min_amount)| Payout {
+            address: recipient.address.clone(),
+            share_bps: recipient.share_bps,
+            expected_amount: expected_amount.to_string(),
+            min_amount: min_amount.to_string(),
+        })
+        .collect())
+}
+
+// Validated recipients as the executor's Recipient[] argument
+pub(crate) fn encode_recipients(recipients: &[Recipient]) -> Result<AbiToken, RouterError> {
+    validate(recipients)?;
+
+    let shares = recipients
+        .iter()
+        .map(|recipient| {
+            Ok(AbiToken::Tuple(vec![
+                AbiToken::Address(parse_address(&recipient.address)?),
+                AbiToken::Uint(U256::from(recipient.share_bps)),
+            ]))
+        })
+        .collect::<Result<Vec<_>, RouterError>>()?;
+    Ok(AbiToken::Array(shares))
+}
+
+// Executor call running the steps of `multi_swap_calldata` (an encoded multiSwap
+// call) and paying the final output out to the recipients
+pub fn encode_split_call(multi_swap_calldata: &[u8], recipients: &[Recipient]) -> Result<Vec<u8>, RouterError> {
+    Ok(encode_call(
+        MULTI_SWAP_AND_SPLIT,
+        &[decode_multi_swap_steps(multi_swap_calldata)?, encode_recipients(recipients)?],
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routing::RouteSplit;
+
+    fn token(address: &str) -> Token {
+        Token {
+            chain_id: 1,
+            address: address.to_string(),
+            symbol: address.to_string(),
+            decimals: 18,
+        }
+    }
+
+    fn step(token_in: &str, token_out: &str, amount_in: u64, amount_out_min: u64) -> SwapStep {
+        SwapStep {
+            exchange_id: "x".to_string(),
+            token_in: token(token_in),
+            token_out: token(token_out),
+            fee_tier: None,
+            amount_in: amount_in.to_string(),
+            amount_out_min: amount_out_min.to_string(),
+            expected_amount_out: None,
+            firmness: rfq::Firmness::Indicative,
+        }
+    }
+
+    fn leg(share_bps: u32, first_step: usize, step_count: usize) -> RouteSplit {
+        RouteSplit {
+            share_bps,
+            first_step,
+            step_count,
+            amount_in: String::new(),
+            expected_amount_out: String::new(),
+        }
+    }
+
+    #[test]
+    fn split_route_payouts_count_every_leg() {
+        let route = SwapRoute {
+            steps: vec![step("A", "B", 600, 1_150), step("A", "C", 400, 780), step("C", "B", 780, 770)],
+            amount_in: "1000".to_string(),
+            expected_amount_out: "1980".to_string(),
+            splits: vec![leg(6_000, 0, 1), leg(4_000, 1, 2)],
+            ..Default::default()
+        };
+        let recipients = [
+            Recipient {
+                address: "0x1111111111111111111111111111111111111111".to_string(),
+                share_bps: 3_000,
+            },
+            Recipient {
+                address: "0x2222222222222222222222222222222222222222".to_string(),
+                share_bps: 7_000,
+            },
+        ];
+
+        let payouts = route_payouts(&route, &recipients).unwrap();
+        // Minimum of 1150 + 770 = 1920: 576 and the remaining 1344
+        let amounts: Vec<(&str, &str)> = payouts
+            .iter()
+            .map(|payout| (payout.expected_amount.as_str(), payout.min_amount.as_str()))
+            .collect();
+        assert_eq!(amounts, [("594", "576"), ("1386", "1344")]);
+    }
+}
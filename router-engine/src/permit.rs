@@ -0,0 +1,436 @@
+use ethers::abi::Token as AbiToken;
+use serde_json::{json, Value};
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::executor::decode_multi_swap_steps;
+
+pub(crate) const PERMIT_BATCH_MULTI_SWAP: &str = "permitBatchMultiSwap(((address,uint160,uint48,uint48)[],address,uint256),bytes,(address,uint256,uint256,uint8,bytes32,bytes32)[],(address,address,address,uint256,uint256,bytes,uint16,uint16)[],(address,uint16)[])";
+
+// How the owner authorizes the executor to pull an input token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum PermitMethod {
+    // Token approved to Permit2; all such tokens share one signature
+    Permit2 { nonce: u64 },
+    // Token's own permit(); one signature per token
+    Eip2612 { name: String, version: String, nonce: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermitInput {
+    pub token: Token,
+    pub amount: String,
+    pub method: PermitMethod,
+}
+
+// Input tokens of one zap or portfolio execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermitRequest {
+    pub chain_id: u64,
+    pub owner: String,
+    // Executor pulling the tokens
+    pub spender: String,
+    pub permit2: String,
+    pub inputs: Vec<PermitInput>,
+    // Unix time after which the signatures and allowances expire
+    pub deadline: u64,
+}
+
+// EIP-712 payloads for eth_signTypedData_v4
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermitPayload {
+    // Single PermitBatch covering every Permit2 input
+    pub permit2: Option<Value>,
+    // One Permit per EIP-2612 input, keyed by token address
+    pub eip2612: Vec<(String, Value)>,
+    pub signatures_required: usize,
+}
+
+// Owner signatures over a PermitPayload, hex encoded, in payload order
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PermitSignatures {
+    pub permit2: Option<String>,
+    pub eip2612: Vec<String>,
+}
+
+impl PermitRequest {
+    fn permit2_inputs(&self) -> impl Iterator<Item = (&PermitInput, u64)> {
+        self.inputs.iter().filter_map(|input| match input.method {
+            PermitMethod::Permit2 { nonce } => Some((input, nonce)),
+            _ => None,
+        })
+    }
+
+    fn eip2612_inputs(&self) -> impl Iterator<Item = (&PermitInput, &str, &str, &str)> {
+        self.inputs.iter().filter_map(|input| match &input.method {
+            PermitMethod::Eip2612 { name, version, nonce } => Some((input, name.as_str(), version.as_str(), nonce.as_str())),
+            _ => None,
+        })
+    }
+
+    // Typed data the owner has to sign, batching every Permit2 token into one payload
+    pub fn typed_data(&self) -> Result<PermitPayload, RouterError> {
+        let details = self
+            .permit2_inputs()
+            .map(|(input, nonce)| {
+                check_uint160(&input.amount)?;
+                Ok(json!({
+                    "token": input.token.address,
+                    "amount": input.amount,
+                    "expiration": self.deadline.to_string(),
+                    "nonce": nonce.to_string(),
+                }))
+            })
+            .collect::<Result<Vec<_>, RouterError>>()?;
+
+        let permit2 = if details.is_empty() {
+            None
+        } else {
+            Some(json!({
+                "types": {
+                    "EIP712Domain": [
+                        { "name": "name", "type": "string" },
+                        { "name": "chainId", "type": "uint256" },
+                        { "name": "verifyingContract", "type": "address" },
+                    ],
+                    "PermitBatch": [
+                        { "name": "details", "type": "PermitDetails[]" },
+                        { "name": "spender", "type": "address" },
+                        { "name": "sigDeadline", "type": "uint256" },
+                    ],
+                    "PermitDetails": [
+                        { "name": "token", "type": "address" },
+                        { "name": "amount", "type": "uint160" },
+                        { "name": "expiration", "type": "uint48" },
+                        { "name": "nonce", "type": "uint48" },
+                    ],
+                },
+                "primaryType": "PermitBatch",
+                "domain": {
+                    "name": "Permit2",
+                    "chainId": self.chain_id,
+                    "verifyingContract": self.permit2,
+                },
+                "message": {
+                    "details": details,
+                    "spender": self.spender,
+                    "sigDeadline": self.deadline.to_string(),
+                },
+            }))
+        };
+
+        let eip2612: Vec<(String, Value)> = self
+            .eip2612_inputs()
+            .map(|(input, name, version, nonce)| {
+                let typed_data = json!({
+                    "types": {
+                        "EIP712Domain": [
+                            { "name": "name", "type": "string" },
+                            { "name": "version", "type": "string" },
+                            { "name": "chainId", "type": "uint256" },
+                            { "name": "verifyingContract", "type": "address" },
+                        ],
+                        "Permit": [
+                            { "name": "owner", "type": "address" },
+                            { "name": "spender", "type": "address" },
+                            { "name": "value", "type": "uint256" },
+                            { "name": "nonce", "type": "uint256" },
+                            { "name": "deadline", "type": "uint256" },
+                        ],
+                    },
+                    "primaryType": "Permit",
+                    "domain": {
+                        "name": name,
+                        "version": version,
+                        "chainId": self.chain_id,
+                        "verifyingContract": input.token.address,
+                    },
+                    "message": {
+                        "owner": self.owner,
+                        "spender": self.spender,
+                        "value": input.amount,
+                        "nonce": nonce,
+                        "deadline": self.deadline.to_string(),
+                    },
+                });
+                (input.token.address.clone(), typed_data)
+            })
+            .collect();
+
+        Ok(PermitPayload {
+            signatures_required: permit2.iter().count() + eip2612.len(),
+            permit2,
+            eip2612,
+        })
+    }
+
+    // Single executor call that applies every permit, pulls the inputs, runs the
+    // swap steps of `multi_swap_calldata` (an encoded multiSwap call), pays what
+    // they produce of the output token to the recipients and returns leftover
+    // inputs to the owner
+    pub fn encode_executor_call(
+        &self,
+        signatures: &PermitSignatures,
+        multi_swap_calldata: &[u8],
+        recipients: &[payout::Recipient],
+    ) -> Result<Vec<u8>, RouterError> {
+        let details = self
+            .permit2_inputs()
+            .map(|(input, nonce)| {
+                Ok(AbiToken::Tuple(vec![
+                    AbiToken::Address(parse_address(&input.token.address)?),
+                    AbiToken::Uint(check_uint160(&input.amount)?),
+                    AbiToken::Uint(U256::from(self.deadline)),
+                    AbiToken::Uint(U256::from(nonce)),
+                ]))
+            })
+            .collect::<Result<Vec<_>, RouterError>>()?;
+
+        let batch_signature = match (&signatures.permit2, details.is_empty()) {
+            (Some(signature), false) => decode_hex(signature)?,
+            (None, false) => return Err(RouterError::ConfigError("Missing Permit2 batch signature".to_string())),
+            (_, true) => Vec::new(),
+        };
+
+        let eip2612: Vec<&PermitInput> = self.eip2612_inputs().map(|(input, ..)| input).collect();
+        if eip2612.len() != signatures.eip2612.len() {
+            return Err(RouterError::ConfigError(format!(
+                "Expected {} EIP-2612 signatures, got {}",
+                eip2612.len(),
+                signatures.eip2612.len()
+            )));
+        }
+
+        let permits = eip2612
+            .into_iter()
+            .zip(&signatures.eip2612)
+            .map(|(input, signature)| {
+                let signature: Signature = signature
+                    .parse()
+                    .map_err(|e| RouterError::ConfigError(format!("Invalid permit signature: {}", e)))?;
+                Ok(AbiToken::Tuple(vec![
+                    AbiToken::Address(parse_address(&input.token.address)?),
+                    AbiToken::Uint(math::to_u256(&math::parse_amount(&input.amount)?)?),
+                    AbiToken::Uint(U256::from(self.deadline)),
+                    AbiToken::Uint(U256::from(signature.v)),
+                    AbiToken::FixedBytes(u256_bytes(signature.r)),
+                    AbiToken::FixedBytes(u256_bytes(signature.s)),
+                ]))
+            })
+            .collect::<Result<Vec<_>, RouterError>>()?;
+
+        let steps = decode_multi_swap_steps(multi_swap_calldata)?;
+
+        Ok(encode_call(
+            PERMIT_BATCH_MULTI_SWAP,
+            &[
+                AbiToken::Tuple(vec![
+                    AbiToken::Array(details),
+                    AbiToken::Address(parse_address(&self.spender)?),
+                    AbiToken::Uint(U256::from(self.deadline)),
+                ]),
+                AbiToken::Bytes(batch_signature),
+                AbiToken::Array(permits),
+                steps,
+                payout::encode_recipients(recipients)?,
+            ],
+        ))
+    }
+}
+
+fn check_uint160(amount: &str) -> Result<U256, RouterError> {
+    let value = math::to_u256(&math::parse_amount(amount)?)?;
+    if value.bits() > 160 {
+        return Err(RouterError::ConfigError(format!("Permit2 amount exceeds uint160: {}", amount)));
+    }
+    Ok(value)
+}
+
+fn decode_hex(value: &str) -> Result<Vec<u8>, RouterError> {
+    hex::decode(value.trim_start_matches("0x"))
+        .map_err(|_| RouterError::ConfigError(format!("Invalid hex: {}", value)))
+}
+
+fn u256_bytes(value: U256) -> Vec<u8> {
+    let mut bytes = [0u8; 32];
+    value.to_big_endian(&mut bytes);
+    bytes.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::ParamType;
+
+    use super::*;
+    use crate::executor::{self, StepCall};
+
+    const A: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const B: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const C: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+    const OWNER: &str = "0x1111111111111111111111111111111111111111";
+    const EXECUTOR: &str = "0x2222222222222222222222222222222222222222";
+    const PERMIT2: &str = "0x000000000022d473030f116ddee9f6b43ac78ba3";
+
+    fn token(address: &str) -> Token {
+        Token {
+            chain_id: 1,
+            address: address.to_string(),
+            symbol: address[2..3].to_uppercase(),
+            decimals: 18,
+        }
+    }
+
+    fn step(token_in: &str, amount_in: u64, amount_out: u64) -> SwapStep {
+        SwapStep {
+            exchange_id: "x".to_string(),
+            token_in: token(token_in),
+            token_out: token(B),
+            fee_tier: None,
+            amount_in: amount_in.to_string(),
+            amount_out_min: amount_out.to_string(),
+            expected_amount_out: Some(amount_out.to_string()),
+            firmness: rfq::Firmness::Indicative,
+        }
+    }
+
+    fn recipient(address: &str, share_bps: u16) -> payout::Recipient {
+        payout::Recipient {
+            address: address.to_string(),
+            share_bps,
+        }
+    }
+
+    // Zap of 600 A through Permit2 and 400 C through its own permit into B
+    fn zap() -> (PermitRequest, Vec<u8>) {
+        let request = PermitRequest {
+            chain_id: 1,
+            owner: OWNER.to_string(),
+            spender: EXECUTOR.to_string(),
+            permit2: PERMIT2.to_string(),
+            inputs: vec![
+                PermitInput {
+                    token: token(A),
+                    amount: "600".to_string(),
+                    method: PermitMethod::Permit2 { nonce: 7 },
+                },
+                PermitInput {
+                    token: token(C),
+                    amount: "400".to_string(),
+                    method: PermitMethod::Eip2612 {
+                        name: "C".to_string(),
+                        version: "1".to_string(),
+                        nonce: "0".to_string(),
+                    },
+                },
+            ],
+            deadline: 1_700_000_000,
+        };
+        let route = SwapRoute {
+            steps: vec![step(A, 600, 1_190), step(C, 400, 790)],
+            amount_in: "1000".to_string(),
+            expected_amount_out: "1980".to_string(),
+            ..Default::default()
+        };
+        let calls = vec![
+            StepCall {
+                target: "0x3333333333333333333333333333333333333333".to_string(),
+                data: "0x12345678".to_string(),
+                amount_in_offset: None,
+            };
+            2
+        ];
+        let multi_swap = executor::encode_multi_swap(&route, &calls, mev::MevPolicy::PublicMempool).unwrap();
+        (request, multi_swap)
+    }
+
+    fn signatures() -> PermitSignatures {
+        let signature = format!("0x{}{}1b", "11".repeat(32), "22".repeat(32));
+        PermitSignatures {
+            permit2: Some(signature.clone()),
+            eip2612: vec![signature],
+        }
+    }
+
+    #[test]
+    fn executor_call_pays_recipients() {
+        let (request, multi_swap) = zap();
+        let recipients = [recipient(OWNER, 7_000), recipient(EXECUTOR, 3_000)];
+        let calldata = request.encode_executor_call(&signatures(), &multi_swap, &recipients).unwrap();
+        assert_eq!(calldata[..4], ethers::utils::id(PERMIT_BATCH_MULTI_SWAP));
+
+        let args = ethers::abi::decode(
+            &[
+                ParamType::Tuple(vec![
+                    ParamType::Array(Box::new(ParamType::Tuple(vec![
+                        ParamType::Address,
+                        ParamType::Uint(160),
+                        ParamType::Uint(48),
+                        ParamType::Uint(48),
+                    ]))),
+                    ParamType::Address,
+                    ParamType::Uint(256),
+                ]),
+                ParamType::Bytes,
+                ParamType::Array(Box::new(ParamType::Tuple(vec![
+                    ParamType::Address,
+                    ParamType::Uint(256),
+                    ParamType::Uint(256),
+                    ParamType::Uint(8),
+                    ParamType::FixedBytes(32),
+                    ParamType::FixedBytes(32),
+                ]))),
+                ParamType::Array(Box::new(executor::swap_step_type())),
+                ParamType::Array(Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Uint(16)]))),
+            ],
+            &calldata[4..],
+        )
+        .unwrap();
+
+        // Permit2 batch: A for 600, spent by the executor
+        let AbiToken::Tuple(batch) = &args[0] else {
+            panic!("permit batch isn't a tuple");
+        };
+        assert_eq!(
+            batch[0],
+            AbiToken::Array(vec![AbiToken::Tuple(vec![
+                AbiToken::Address(parse_address(A).unwrap()),
+                AbiToken::Uint(U256::from(600)),
+                AbiToken::Uint(U256::from(request.deadline)),
+                AbiToken::Uint(U256::from(7)),
+            ])])
+        );
+        assert_eq!(batch[1], AbiToken::Address(parse_address(EXECUTOR).unwrap()));
+        assert_eq!(args[1].clone().into_bytes().unwrap().len(), 65);
+
+        // EIP-2612 permit: C for 400
+        let AbiToken::Array(permits) = &args[2] else {
+            panic!("permits argument isn't an array");
+        };
+        let AbiToken::Tuple(permit) = &permits[0] else {
+            panic!("permit isn't a tuple");
+        };
+        assert_eq!(permits.len(), 1);
+        assert_eq!(permit[0], AbiToken::Address(parse_address(C).unwrap()));
+        assert_eq!(permit[1], AbiToken::Uint(U256::from(400)));
+        assert_eq!(permit[3], AbiToken::Uint(U256::from(27)));
+
+        assert_eq!(args[3].clone().into_array().unwrap().len(), 2);
+        assert_eq!(
+            args[4],
+            AbiToken::Array(vec![
+                AbiToken::Tuple(vec![AbiToken::Address(parse_address(OWNER).unwrap()), AbiToken::Uint(U256::from(7_000))]),
+                AbiToken::Tuple(vec![AbiToken::Address(parse_address(EXECUTOR).unwrap()), AbiToken::Uint(U256::from(3_000))]),
+            ])
+        );
+    }
+
+    #[test]
+    fn executor_call_needs_full_recipient_shares() {
+        let (request, multi_swap) = zap();
+        assert!(request.encode_executor_call(&signatures(), &multi_swap, &[]).is_err());
+        assert!(request
+            .encode_executor_call(&signatures(), &multi_swap, &[recipient(OWNER, 9_000)])
+            .is_err());
+    }
+}
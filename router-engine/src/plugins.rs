@@ -0,0 +1,101 @@
+use auraagg_adapter_api::{AdapterError, AdapterToken, LiquidityAdapter, API_VERSION};
+
+use super::*;
+
+impl From<AdapterError> for RouterError {
+    fn from(e: AdapterError) -> Self {
+        match e {
+            AdapterError::InsufficientLiquidity(msg) => RouterError::InsufficientLiquidity(msg),
+            AdapterError::Config(msg) => RouterError::ConfigError(msg),
+            other => RouterError::ChainError(other.to_string()),
+        }
+    }
+}
+
+fn adapter_token(token: &Token) -> AdapterToken {
+    AdapterToken {
+        chain_id: token.chain_id,
+        address: token.address.clone(),
+        symbol: token.symbol.clone(),
+        decimals: token.decimals,
+    }
+}
+
+// Liquidity source backed by a plugin adapter
+pub struct PluginSource {
+    adapter: Arc<dyn LiquidityAdapter>,
+}
+
+impl PluginSource {
+    pub fn new(adapter: Arc<dyn LiquidityAdapter>) -> Self {
+        Self { adapter }
+    }
+}
+
+#[async_trait]
+impl LiquiditySource for PluginSource {
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let quote = self
+            .adapter
+            .quote(&adapter_token(token_in), &adapter_token(token_out), amount_in)
+            .await?;
+        Ok((quote.amount_out, quote.price_impact))
+    }
+
+    async fn get_reserves(
+        &self,
+        token_a: &Token,
+        token_b: &Token,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        Ok(self
+            .adapter
+            .reserves(&adapter_token(token_a), &adapter_token(token_b))
+            .await?)
+    }
+}
+
+// Loaded plugin, as reported at startup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadedPlugin {
+    pub id: String,
+    pub version: String,
+}
+
+impl RouterEngine {
+    // Register every adapter plugin linked into the binary. `configs` holds each
+    // plugin's configuration by id; plugins built against another API version or
+    // failing to initialize are skipped.
+    pub fn load_plugins(&self, configs: &HashMap<String, serde_json::Value>) -> Vec<LoadedPlugin> {
+        let mut loaded = Vec::new();
+
+        for registration in auraagg_adapter_api::registered_adapters() {
+            if registration.api_version != API_VERSION {
+                warn!(
+                    "Skipping adapter {} {}: built for adapter API v{}, engine supports v{}",
+                    registration.id, registration.version, registration.api_version, API_VERSION
+                );
+                continue;
+            }
+
+            let config = configs.get(registration.id).cloned().unwrap_or(serde_json::Value::Null);
+            match (registration.create)(&config) {
+                Ok(adapter) => {
+                    self.register_liquidity_source(registration.id.to_string(), Arc::new(PluginSource::new(adapter)));
+                    info!("Loaded adapter plugin {} {}", registration.id, registration.version);
+                    loaded.push(LoadedPlugin {
+                        id: registration.id.to_string(),
+                        version: registration.version.to_string(),
+                    });
+                }
+                Err(e) => warn!("Failed to initialize adapter {}: {}", registration.id, e),
+            }
+        }
+
+        loaded
+    }
+}
@@ -0,0 +1,81 @@
+use super::*;
+
+// Named routing profile, e.g. "conservative_wallet" or "pro_trader"
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoutingPolicy {
+    pub name: String,
+    // Liquidity sources routes may use; None allows all
+    #[serde(default)]
+    pub allowed_sources: Option<Vec<String>>,
+    #[serde(default)]
+    pub max_hops: Option<usize>,
+    // Highest acceptable price impact, as a fraction
+    #[serde(default)]
+    pub max_price_impact: Option<f64>,
+    // Overrides the request's MEV policy
+    #[serde(default)]
+    pub mev_policy: Option<mev::MevPolicy>,
+    // Integrator fee taken from the output
+    #[serde(default)]
+    pub fee_bps: u32,
+    // Lowest exchange trust tier routes may pass through
+    #[serde(default)]
+    pub min_trust_tier: Option<trust::TrustTier>,
+    // Overrides RoutingConfig::max_pool_share_bps
+    #[serde(default)]
+    pub max_pool_share_bps: Option<u32>,
+}
+
+impl RoutingPolicy {
+    // Narrow the request to what the policy allows
+    pub fn apply_to_request(&self, request: &mut QuoteRequest) {
+        if let Some(allowed) = &self.allowed_sources {
+            let exchanges = match request.exchanges.take() {
+                Some(requested) => requested.into_iter().filter(|e| allowed.contains(e)).collect(),
+                None => allowed.clone(),
+            };
+            request.exchanges = Some(exchanges);
+        }
+        if let Some(mev_policy) = self.mev_policy {
+            request.mev_policy = Some(mev_policy);
+        }
+    }
+
+    pub fn allows(&self, route: &SwapRoute) -> bool {
+        self.rejection(route).is_none()
+    }
+
+    // Why the policy rules a route out, if it does
+    pub fn rejection(&self, route: &SwapRoute) -> Option<trace::RejectionReason> {
+        if let Some(max) = self.max_hops {
+            if route.steps.len() > max {
+                return Some(trace::RejectionReason::TooManyHops { hops: route.steps.len(), max });
+            }
+        }
+        if let Some(max) = self.max_price_impact {
+            if route.price_impact > max {
+                return Some(trace::RejectionReason::PriceImpact { price_impact: route.price_impact, max });
+            }
+        }
+        if let Some(allowed) = &self.allowed_sources {
+            if let Some(step) = route.steps.iter().find(|step| !allowed.contains(&step.exchange_id)) {
+                return Some(trace::RejectionReason::SourceNotAllowed { exchange_id: step.exchange_id.clone() });
+            }
+        }
+        None
+    }
+
+    // Deduct the integrator fee from the route's output before slippage is applied
+    pub fn charge_fee(&self, route: &mut SwapRoute) -> Result<(), RouterError> {
+        if self.fee_bps == 0 {
+            return Ok(());
+        }
+
+        let expected = math::parse_amount(&route.expected_amount_out)?;
+        let fee = math::bps_of(&expected, self.fee_bps as u64, math::Rounding::Up);
+        route.expected_amount_out = (&expected - &fee).to_string();
+        route.integrator_fee = Some(fee.to_string());
+
+        Ok(())
+    }
+}
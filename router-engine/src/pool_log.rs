@@ -0,0 +1,135 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::*;
+use crate::state::PoolState;
+
+// An entry of a pool indexer's event log: a pool's state read directly at
+// bootstrap, or a Sync/Swap log it applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PoolEvent {
+    Bootstrap { state: PoolState },
+    Log { log: Log },
+}
+
+impl PoolEvent {
+    pub fn block_number(&self) -> u64 {
+        match self {
+            PoolEvent::Bootstrap { state } => state.block_number,
+            PoolEvent::Log { log } => log.block_number.map(|b| b.as_u64()).unwrap_or_default(),
+        }
+    }
+
+    // Replay order: by block, a block's logs before the bootstrap read at its
+    // end, logs by index
+    pub fn replay_key(&self) -> (u64, bool, U256) {
+        match self {
+            PoolEvent::Bootstrap { .. } => (self.block_number(), true, U256::zero()),
+            PoolEvent::Log { log } => (self.block_number(), false, log.log_index.unwrap_or_default()),
+        }
+    }
+}
+
+// Append-only record of the events a PoolIndexer applied, from which its state
+// can be rebuilt without a live snapshot
+pub trait PoolEventLog: Send + Sync {
+    fn append(&self, events: &[PoolEvent]) -> Result<(), RouterError>;
+
+    // Events of blocks from_block..=to_block, in replay order
+    fn read(&self, from_block: u64, to_block: u64) -> Result<Vec<PoolEvent>, RouterError>;
+}
+
+fn in_replay_order(mut events: Vec<PoolEvent>) -> Vec<PoolEvent> {
+    events.sort_by_key(PoolEvent::replay_key);
+    events
+}
+
+#[derive(Default)]
+pub struct MemoryPoolEventLog {
+    events: Mutex<Vec<PoolEvent>>,
+}
+
+impl PoolEventLog for MemoryPoolEventLog {
+    fn append(&self, events: &[PoolEvent]) -> Result<(), RouterError> {
+        self.events.lock().unwrap().extend_from_slice(events);
+        Ok(())
+    }
+
+    fn read(&self, from_block: u64, to_block: u64) -> Result<Vec<PoolEvent>, RouterError> {
+        let events = self.events.lock().unwrap();
+        Ok(in_replay_order(
+            events
+                .iter()
+                .filter(|event| (from_block..=to_block).contains(&event.block_number()))
+                .cloned()
+                .collect(),
+        ))
+    }
+}
+
+// One JSON event per line, appended as the indexer goes
+pub struct FilePoolEventLog {
+    path: PathBuf,
+    writer: Mutex<()>,
+}
+
+impl FilePoolEventLog {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            writer: Mutex::new(()),
+        }
+    }
+}
+
+impl PoolEventLog for FilePoolEventLog {
+    fn append(&self, events: &[PoolEvent]) -> Result<(), RouterError> {
+        let mut lines = String::new();
+        for event in events {
+            let line = serde_json::to_string(event)
+                .map_err(|e| RouterError::ExecutionError(format!("Failed to encode pool event: {}", e)))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+
+        let _guard = self.writer.lock().unwrap();
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .map_err(|e| RouterError::ExecutionError(format!("Failed to append to {}: {}", self.path.display(), e)))
+    }
+
+    fn read(&self, from_block: u64, to_block: u64) -> Result<Vec<PoolEvent>, RouterError> {
+        let contents = std::fs::read_to_string(&self.path)
+            .map_err(|e| RouterError::ConfigError(format!("Failed to read {}: {}", self.path.display(), e)))?;
+
+        let lines: Vec<(usize, &str)> = contents.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()).collect();
+        let mut events = Vec::new();
+        for (i, (number, line)) in lines.iter().enumerate() {
+            let event: PoolEvent = match serde_json::from_str(line) {
+                Ok(event) => event,
+                // A write cut short by a crash
+                Err(e) if i + 1 == lines.len() => {
+                    warn!("Ignoring truncated last line of {}: {}", self.path.display(), e);
+                    break;
+                }
+                Err(e) => {
+                    return Err(RouterError::ConfigError(format!(
+                        "Line {} of {} is not a pool event: {}",
+                        number + 1,
+                        self.path.display(),
+                        e
+                    )))
+                }
+            };
+            if (from_block..=to_block).contains(&event.block_number()) {
+                events.push(event);
+            }
+        }
+        Ok(in_replay_order(events))
+    }
+}
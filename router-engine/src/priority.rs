@@ -0,0 +1,94 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+
+use super::*;
+
+// Admission of quote requests. Execution-bound re-quotes are admitted at once;
+// indicative quotes wait while any is in flight and share a bounded number of
+// slots, so a burst of UI traffic can't delay a quote that is about to be sent.
+pub struct PriorityLanes {
+    indicative: std::sync::RwLock<Arc<Semaphore>>,
+    executing: AtomicUsize,
+    drained: Notify,
+}
+
+impl Default for PriorityLanes {
+    fn default() -> Self {
+        Self {
+            indicative: std::sync::RwLock::new(Arc::new(Semaphore::new(Semaphore::MAX_PERMITS))),
+            executing: AtomicUsize::new(0),
+            drained: Notify::new(),
+        }
+    }
+}
+
+// Held for the duration of an admitted quote
+pub enum LaneTicket<'a> {
+    Indicative(OwnedSemaphorePermit),
+    Execution(&'a PriorityLanes),
+}
+
+impl Drop for LaneTicket<'_> {
+    fn drop(&mut self) {
+        if let LaneTicket::Execution(lanes) = self {
+            if lanes.executing.fetch_sub(1, Ordering::AcqRel) == 1 {
+                lanes.drained.notify_waiters();
+            }
+        }
+    }
+}
+
+impl PriorityLanes {
+    // Indicative quotes computed at once; later ones queue
+    pub fn set_max_indicative(&self, max: usize) {
+        *self.indicative.write().unwrap() = Arc::new(Semaphore::new(max.clamp(1, Semaphore::MAX_PERMITS)));
+    }
+
+    pub fn executing(&self) -> usize {
+        self.executing.load(Ordering::Acquire)
+    }
+
+    pub async fn admit(&self, execution_bound: bool) -> Result<LaneTicket<'_>, RouterError> {
+        if execution_bound {
+            self.executing.fetch_add(1, Ordering::AcqRel);
+            return Ok(LaneTicket::Execution(self));
+        }
+
+        loop {
+            let drained = self.drained.notified();
+            if self.executing() == 0 {
+                break;
+            }
+            drained.await;
+        }
+        let semaphore = self.indicative.read().unwrap().clone();
+        semaphore
+            .acquire_owned()
+            .await
+            .map(LaneTicket::Indicative)
+            .map_err(|e| RouterError::ExecutionError(format!("Quote lane closed: {}", e)))
+    }
+}
+
+impl RouterEngine {
+    pub fn priority_lanes(&self) -> &PriorityLanes {
+        &self.priority_lanes
+    }
+
+    // On-chain source to quote `exchange_id` with on execution-bound requests,
+    // in place of its regular source when that serves cached state
+    pub fn register_live_source(&self, exchange_id: String, source: Arc<dyn LiquiditySource>) {
+        self.live_sources.insert(exchange_id, source);
+    }
+
+    // Source to quote an exchange with; `fresh` prefers its live source
+    pub(crate) fn quote_source(&self, exchange_id: &str, fresh: bool) -> Option<Arc<dyn LiquiditySource>> {
+        let live = if fresh {
+            self.live_sources.get(exchange_id).map(|s| s.clone())
+        } else {
+            None
+        };
+        live.or_else(|| self.liquidity_sources.get(exchange_id).map(|s| s.clone()))
+    }
+}
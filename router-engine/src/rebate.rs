@@ -0,0 +1,80 @@
+use super::*;
+
+// Rebate offered by a protocol to routes that trade through it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RebateProgram {
+    // Share of the LP fee returned to the trader, e.g. a ve-token holder discount
+    FeeDiscount { discount_bps: u32 },
+    // Share of the swap's gas refunded, e.g. a referral gas program
+    GasRefund { refund_bps: u32 },
+}
+
+// Expected rebates of a route
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RouteRebate {
+    // Fee discounts, in output token units
+    pub fee_rebate: String,
+    // Refunded gas units
+    pub gas_refund: u64,
+    // expected_amount_out plus fee_rebate
+    pub net_amount_out: String,
+}
+
+// Portion of the route's output attributable to fee discounts. Every hop's fee
+// reduces the final output by roughly fee / (1 - fee), so a hop's rebate is valued
+// against expected_amount_out regardless of which token the fee was charged in.
+pub fn fee_rebate(expected_out: &BigUint, fee_tier: u32, discount_bps: u32) -> BigUint {
+    let fee = fee_tier.min(math::FEE_DENOMINATOR - 1);
+    expected_out * BigUint::from(fee) * BigUint::from(discount_bps)
+        / (BigUint::from(math::FEE_DENOMINATOR - fee) * BigUint::from(10_000u32))
+}
+
+// Rebates of `route`, with `programs` giving the programs of each exchange;
+// None when no hop is eligible
+pub fn estimate_route_rebate<F>(route: &SwapRoute, programs: F) -> Result<Option<RouteRebate>, RouterError>
+where
+    F: Fn(&str) -> Vec<RebateProgram>,
+{
+    let expected_out = math::parse_amount(&route.expected_amount_out)?;
+    let hops = route.steps.len().max(1) as u64;
+
+    let mut fee_total = BigUint::default();
+    let mut gas_refund = 0u64;
+    let mut eligible = false;
+
+    for step in &route.steps {
+        for program in programs(&step.exchange_id) {
+            eligible = true;
+            match program {
+                RebateProgram::FeeDiscount { discount_bps } => {
+                    let fee_tier = step.fee_tier.unwrap_or(math::DEFAULT_FEE_TIER);
+                    fee_total += fee_rebate(&expected_out, fee_tier, discount_bps);
+                }
+                RebateProgram::GasRefund { refund_bps } => {
+                    // Gas is estimated per route, so attribute it evenly to hops
+                    gas_refund += route.gas_estimate / hops * refund_bps as u64 / 10_000;
+                }
+            }
+        }
+    }
+
+    if !eligible {
+        return Ok(None);
+    }
+
+    Ok(Some(RouteRebate {
+        net_amount_out: (&expected_out + &fee_total).to_string(),
+        fee_rebate: fee_total.to_string(),
+        gas_refund,
+    }))
+}
+
+// Output a route is ranked by: expected output plus any fee rebate
+pub fn net_amount_out(route: &SwapRoute) -> BigUint {
+    let amount = match &route.rebate {
+        Some(rebate) => &rebate.net_amount_out,
+        None => &route.expected_amount_out,
+    };
+    math::parse_amount(amount).unwrap_or_default()
+}
@@ -0,0 +1,94 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::*;
+
+// Whether a leg's price holds at execution
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "level", rename_all = "snake_case")]
+pub enum Firmness {
+    // Priced from pool state or an indicative RFQ level; re-priced at execution
+    #[default]
+    Indicative,
+    // Maker-signed RFQ quote, fillable as is until `expiry` (unix seconds)
+    Firm {
+        maker: String,
+        signature: String,
+        expiry: u64,
+    },
+}
+
+impl Firmness {
+    pub fn is_firm_at(&self, timestamp: u64) -> bool {
+        match self {
+            Firmness::Firm { expiry, .. } => *expiry > timestamp,
+            Firmness::Indicative => false,
+        }
+    }
+}
+
+// Quote returned by an RFQ maker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RfqQuote {
+    pub maker: String,
+    pub token_in: Token,
+    pub token_out: Token,
+    pub amount_in: String,
+    pub amount_out: String,
+    // Absent for indicative quotes
+    pub signature: Option<String>,
+    pub expiry: u64,
+}
+
+impl RfqQuote {
+    // Route leg filling this quote through `exchange_id`'s settlement contract
+    pub fn into_step(self, exchange_id: String) -> SwapStep {
+        let firmness = match self.signature {
+            Some(signature) => Firmness::Firm {
+                maker: self.maker,
+                signature,
+                expiry: self.expiry,
+            },
+            None => Firmness::Indicative,
+        };
+
+        SwapStep {
+            exchange_id,
+            token_in: self.token_in,
+            token_out: self.token_out,
+            fee_tier: None,
+            amount_in: self.amount_in,
+            amount_out_min: self.amount_out.clone(),
+            expected_amount_out: Some(self.amount_out),
+            firmness,
+        }
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// Downgrade legs whose firm quote has expired and return when the route stops
+// being fully firm, or None if any leg is indicative
+pub fn refresh_firmness(route: &mut SwapRoute, timestamp: u64) -> Option<u64> {
+    for step in route.steps.iter_mut() {
+        if matches!(step.firmness, Firmness::Firm { .. }) && !step.firmness.is_firm_at(timestamp) {
+            step.firmness = Firmness::Indicative;
+        }
+    }
+
+    route.firm_until = route
+        .steps
+        .iter()
+        .map(|step| match step.firmness {
+            Firmness::Firm { expiry, .. } => Some(expiry),
+            Firmness::Indicative => None,
+        })
+        .try_fold(u64::MAX, |earliest, expiry| expiry.map(|e| earliest.min(e)))
+        .filter(|_| !route.steps.is_empty());
+
+    route.firm_until
+}
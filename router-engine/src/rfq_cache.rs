@@ -0,0 +1,270 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use super::*;
+use crate::rfq::RfqQuote;
+
+// Quota and quote lifetime an RFQ provider declares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RfqLimits {
+    // How long an indicative quote may be reused, capped by the quote's own expiry
+    pub quote_ttl_secs: u64,
+    // Requests the provider accepts per window
+    pub max_requests: u32,
+    pub window_secs: u64,
+    // Share of each window's requests only firm quotes may spend
+    pub firm_reserve_bps: u32,
+}
+
+impl Default for RfqLimits {
+    fn default() -> Self {
+        Self {
+            quote_ttl_secs: 5,
+            max_requests: 60,
+            window_secs: 60,
+            firm_reserve_bps: 2_000,
+        }
+    }
+}
+
+impl RfqLimits {
+    fn budget(&self, firm: bool) -> u32 {
+        if firm {
+            return self.max_requests;
+        }
+        let reserved = (self.max_requests as u64 * self.firm_reserve_bps.min(10_000) as u64).div_ceil(10_000);
+        self.max_requests.saturating_sub(reserved as u32)
+    }
+}
+
+// RFQ maker API. Implementations keep their maker credentials as
+// secrets::Credential.
+#[async_trait]
+pub trait RfqProvider: Send + Sync {
+    fn limits(&self) -> RfqLimits;
+
+    // Counts against the quota. Firm quotes come back signed.
+    async fn request_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+        firm: bool,
+    ) -> Result<RfqQuote, RouterError>;
+
+    // Inventory the maker advertises for the pair, as (token_a, token_b)
+    // amounts. Served from its price levels, so it doesn't count against the quota.
+    async fn depth(&self, token_a: &Token, token_b: &Token) -> Result<(BigUint, BigUint), RouterError>;
+}
+
+#[derive(Debug, Default)]
+struct RequestWindow {
+    started_at: u64,
+    used: u32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RfqCacheStats {
+    pub requests: u64,
+    pub cache_hits: u64,
+    // Indicative quotes refused to keep the firm reserve
+    pub throttled: u64,
+    pub remaining_in_window: u32,
+}
+
+fn pair_key(token_in: &Token, token_out: &Token) -> (String, String) {
+    (token_in.address.to_lowercase(), token_out.address.to_lowercase())
+}
+
+// Quotes of one RFQ provider, reused within their TTL and requested within the
+// provider's quota. Indicative traffic only spends the part of each window not
+// reserved for firm quotes; past that, sources quoted through it fail until the
+// window rolls over.
+pub struct RfqQuoteCache {
+    provider: Arc<dyn RfqProvider>,
+    quotes: DashMap<(String, String, String), (RfqQuote, u64)>,
+    depths: DashMap<(String, String), ((BigUint, BigUint), u64)>,
+    window: Mutex<RequestWindow>,
+    requests: AtomicU64,
+    cache_hits: AtomicU64,
+    throttled: AtomicU64,
+}
+
+impl RfqQuoteCache {
+    pub fn new(provider: Arc<dyn RfqProvider>) -> Self {
+        Self {
+            provider,
+            quotes: DashMap::new(),
+            depths: DashMap::new(),
+            window: Mutex::new(RequestWindow::default()),
+            requests: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+        }
+    }
+
+    // Take one request from the current window's budget
+    fn spend(&self, limits: &RfqLimits, firm: bool, now: u64) -> bool {
+        let mut window = self.window.lock().unwrap();
+        if now >= window.started_at + limits.window_secs {
+            *window = RequestWindow { started_at: now, used: 0 };
+        }
+        if window.used >= limits.budget(firm) {
+            return false;
+        }
+        window.used += 1;
+        true
+    }
+
+    fn cached(&self, key: &(String, String, String), limits: &RfqLimits, firm: bool, now: u64) -> Option<RfqQuote> {
+        let entry = self.quotes.get(key)?;
+        let (quote, fetched_at) = entry.value();
+        let usable = if firm {
+            quote.signature.is_some() && quote.expiry > now
+        } else {
+            now < fetched_at + limits.quote_ttl_secs && (quote.signature.is_none() || quote.expiry > now)
+        };
+        usable.then(|| quote.clone())
+    }
+
+    // A quote for exactly `amount_in`. Firm requests reuse only unexpired signed
+    // quotes; indicative ones reuse any quote within the TTL.
+    pub async fn quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+        firm: bool,
+    ) -> Result<RfqQuote, RouterError> {
+        let limits = self.provider.limits();
+        let now = rfq::now();
+        let (a, b) = pair_key(token_in, token_out);
+        let key = (a, b, amount_in.to_string());
+
+        if let Some(quote) = self.cached(&key, &limits, firm, now) {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(quote);
+        }
+        if !self.spend(&limits, firm, now) {
+            if !firm {
+                self.throttled.fetch_add(1, Ordering::Relaxed);
+            }
+            return Err(RouterError::InsufficientLiquidity(format!(
+                "RFQ quota for {} -> {} spent for this window",
+                token_in.symbol, token_out.symbol
+            )));
+        }
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        let quote = self.provider.request_quote(token_in, token_out, amount_in, firm).await?;
+        self.quotes.insert(key, (quote.clone(), now));
+        Ok(quote)
+    }
+
+    pub async fn depth(&self, token_a: &Token, token_b: &Token) -> Result<(BigUint, BigUint), RouterError> {
+        let limits = self.provider.limits();
+        let now = rfq::now();
+        let key = pair_key(token_a, token_b);
+        if let Some(entry) = self.depths.get(&key) {
+            let (depth, fetched_at) = entry.value();
+            if now < fetched_at + limits.quote_ttl_secs {
+                return Ok(depth.clone());
+            }
+        }
+
+        let depth = self.provider.depth(token_a, token_b).await?;
+        self.depths.insert(key, (depth.clone(), now));
+        Ok(depth)
+    }
+
+    // Drop quotes past their TTL and expiry
+    pub fn evict_expired(&self) {
+        let limits = self.provider.limits();
+        let now = rfq::now();
+        self.quotes.retain(|_, (quote, fetched_at)| {
+            now < *fetched_at + limits.quote_ttl_secs || (quote.signature.is_some() && quote.expiry > now)
+        });
+        self.depths.retain(|_, (_, fetched_at)| now < *fetched_at + limits.quote_ttl_secs);
+    }
+
+    pub fn stats(&self) -> RfqCacheStats {
+        let limits = self.provider.limits();
+        let now = rfq::now();
+        let window = self.window.lock().unwrap();
+        let used = if now >= window.started_at + limits.window_secs { 0 } else { window.used };
+        RfqCacheStats {
+            requests: self.requests.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+            remaining_in_window: limits.max_requests.saturating_sub(used),
+        }
+    }
+}
+
+// An RFQ provider as a liquidity source. The indicative one serves routing;
+// the firm one is registered as the live source, so execution-bound re-quotes
+// spend the firm reserve.
+pub struct RfqSource {
+    cache: Arc<RfqQuoteCache>,
+    firm: bool,
+}
+
+#[async_trait]
+impl LiquiditySource for RfqSource {
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let quote = self.cache.quote(token_in, token_out, amount_in, self.firm).await?;
+        // Makers price the whole size; there is no pool to move
+        Ok((math::parse_amount(&quote.amount_out)?, 0.0))
+    }
+
+    async fn get_reserves(&self, token_a: &Token, token_b: &Token) -> Result<(BigUint, BigUint), RouterError> {
+        self.cache.depth(token_a, token_b).await
+    }
+}
+
+impl RouterEngine {
+    // Route through an RFQ provider as `id`, behind a quote cache that keeps
+    // part of its quota for firm quotes
+    pub fn register_rfq_provider(&self, id: String, provider: Arc<dyn RfqProvider>) -> Arc<RfqQuoteCache> {
+        let cache = Arc::new(RfqQuoteCache::new(provider));
+        self.register_liquidity_source(
+            id.clone(),
+            Arc::new(RfqSource {
+                cache: cache.clone(),
+                firm: false,
+            }),
+        );
+        self.register_live_source(
+            id.clone(),
+            Arc::new(RfqSource {
+                cache: cache.clone(),
+                firm: true,
+            }),
+        );
+        self.rfq_caches.insert(id, cache.clone());
+        cache
+    }
+
+    pub fn rfq_cache(&self, id: &str) -> Option<Arc<RfqQuoteCache>> {
+        self.rfq_caches.get(id).map(|cache| cache.clone())
+    }
+
+    // Signed quote from provider `id`, drawn from the firm reserve
+    pub async fn firm_rfq_quote(
+        &self,
+        id: &str,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<RfqQuote, RouterError> {
+        let cache = self
+            .rfq_cache(id)
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown RFQ provider {}", id)))?;
+        cache.quote(token_in, token_out, amount_in, true).await
+    }
+}
@@ -0,0 +1,894 @@
+use std::collections::{HashMap, HashSet};
+
+use futures::future::join_all;
+use futures::stream::{self, StreamExt};
+use num_traits::Zero;
+
+use super::*;
+
+// Search limits of find_routes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutingConfig {
+    pub max_hops: usize,
+    // Paths kept after ranking them at the full input amount
+    pub max_paths: usize,
+    // Most paths one route may split its input across; 1 disables splitting
+    pub max_splits: usize,
+    // Splits are allocated in increments of 1 / split_parts of the input
+    pub split_parts: u32,
+    // Most of a pool's input-side reserve one route may trade into it, in basis
+    // points; larger orders are split across pools or rejected. None is unlimited.
+    #[serde(default)]
+    pub max_pool_share_bps: Option<u32>,
+    // Most gas a route may be estimated at; a request's max_gas can only lower it
+    #[serde(default)]
+    pub max_gas: Option<u64>,
+}
+
+impl Default for RoutingConfig {
+    fn default() -> Self {
+        Self {
+            max_hops: 3,
+            max_paths: 5,
+            max_splits: 3,
+            split_parts: 10,
+            max_pool_share_bps: None,
+            max_gas: None,
+        }
+    }
+}
+
+// Age after which a chain's cached token graph is rebuilt even if nothing the
+// engine saw changed, picking up pools created since on RPC-backed sources
+pub const TOKEN_GRAPH_TTL_SECS: u64 = 300;
+
+// Most get_reserves probes in flight while building a token graph
+pub const TOKEN_GRAPH_PROBE_CONCURRENCY: usize = 16;
+
+// Accuracy/latency trade-off of a quote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuoteMode {
+    #[default]
+    Standard,
+    // Cached pool state only where available, shallow search and no split
+    // optimization or simulation; aims at answering within 50ms
+    Fast,
+    // Fresh state only, full split optimization, never served from the quote
+    // cache, and the best route's transaction is simulated
+    Exact,
+}
+
+impl RoutingConfig {
+    // Search limits adjusted for the mode
+    pub fn for_mode(&self, mode: QuoteMode) -> RoutingConfig {
+        let mut config = self.clone();
+        match mode {
+            QuoteMode::Standard => {}
+            QuoteMode::Fast => {
+                config.max_hops = config.max_hops.min(2);
+                config.max_paths = config.max_paths.min(3);
+                config.max_splits = 1;
+            }
+            QuoteMode::Exact => {
+                config.max_splits = config.max_splits.max(config.max_paths);
+                config.split_parts = config.split_parts.max(20);
+            }
+        }
+        config
+    }
+}
+
+// Part of a split route's input sent down one path, whose steps are
+// route.steps[first_step..first_step + step_count]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteSplit {
+    pub share_bps: u32,
+    pub first_step: usize,
+    pub step_count: usize,
+    pub amount_in: String,
+    pub expected_amount_out: String,
+}
+
+// Where a route stands among the quote's alternatives
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteRanking {
+    // 1 for the best route
+    pub rank: usize,
+    // Net output relative to the best route, 1.0 for the best
+    pub score: f64,
+    // Net output shortfall against the best route, in basis points
+    pub shortfall_bps: u32,
+}
+
+// A source's pool from one token to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub exchange_id: String,
+    pub token_in: Token,
+    pub token_out: Token,
+}
+
+impl Edge {
+    // Same pool regardless of direction
+    fn pool_key(&self) -> (String, String, String) {
+        let a = self.token_in.address.to_lowercase();
+        let b = self.token_out.address.to_lowercase();
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        (self.exchange_id.clone(), a, b)
+    }
+}
+
+// Registered tokens of a chain linked by every source that has reserves for the pair
+#[derive(Debug, Default)]
+pub struct TokenGraph {
+    edges: HashMap<Token, Vec<Edge>>,
+}
+
+impl TokenGraph {
+    pub fn add_pool(&mut self, exchange_id: &str, token_a: &Token, token_b: &Token) {
+        for (from, to) in [(token_a, token_b), (token_b, token_a)] {
+            self.edges.entry(from.clone()).or_default().push(Edge {
+                exchange_id: exchange_id.to_string(),
+                token_in: from.clone(),
+                token_out: to.clone(),
+            });
+        }
+    }
+
+    pub fn edges(&self, token: &Token) -> &[Edge] {
+        self.edges.get(token).map(|e| e.as_slice()).unwrap_or_default()
+    }
+
+    // The graph's pools of `exchanges` only
+    pub fn restricted_to(&self, exchanges: &[String]) -> TokenGraph {
+        let edges = self
+            .edges
+            .iter()
+            .map(|(token, edges)| {
+                let kept: Vec<Edge> = edges.iter().filter(|e| exchanges.contains(&e.exchange_id)).cloned().collect();
+                (token.clone(), kept)
+            })
+            .filter(|(_, edges)| !edges.is_empty())
+            .collect();
+        TokenGraph { edges }
+    }
+
+    pub fn pool_count(&self) -> usize {
+        self.edges.values().map(|e| e.len()).sum::<usize>() / 2
+    }
+
+    // Paths of at most `max_hops` pools that never revisit a token
+    pub fn paths(&self, token_in: &Token, token_out: &Token, max_hops: usize) -> Vec<Vec<Edge>> {
+        let mut paths = Vec::new();
+        let mut visited = HashSet::from([token_in.clone()]);
+        let mut path = Vec::new();
+        self.walk(token_in, token_out, max_hops, &mut visited, &mut path, &mut paths);
+        paths
+    }
+
+    fn walk(
+        &self,
+        token: &Token,
+        target: &Token,
+        hops_left: usize,
+        visited: &mut HashSet<Token>,
+        path: &mut Vec<Edge>,
+        paths: &mut Vec<Vec<Edge>>,
+    ) {
+        if hops_left == 0 {
+            return;
+        }
+        for edge in self.edges(token) {
+            if edge.token_out == *target {
+                path.push(edge.clone());
+                paths.push(path.clone());
+                path.pop();
+            } else if visited.insert(edge.token_out.clone()) {
+                path.push(edge.clone());
+                self.walk(&edge.token_out, target, hops_left - 1, visited, path, paths);
+                path.pop();
+                visited.remove(&edge.token_out);
+            }
+        }
+    }
+}
+
+// A chain's graph over every source, with what it was built from
+pub(crate) struct CachedTokenGraph {
+    graph: Arc<TokenGraph>,
+    token_count: usize,
+    built_at: u64,
+}
+
+// Path priced for one input amount
+#[derive(Debug, Clone)]
+struct PathQuote {
+    steps: Vec<SwapStep>,
+    amount_in: BigUint,
+    amount_out: BigUint,
+    price_impact: f64,
+    state_block: Option<u64>,
+}
+
+fn shares_pool(a: &[Edge], b: &[Edge]) -> bool {
+    let pools: HashSet<_> = a.iter().map(|e| e.pool_key()).collect();
+    b.iter().any(|e| pools.contains(&e.pool_key()))
+}
+
+// Route over one or more priced paths; several paths make a split route
+fn route_from_legs(amount_in: &BigUint, legs: Vec<PathQuote>) -> SwapRoute {
+    let total_out: BigUint = legs.iter().map(|leg| &leg.amount_out).sum();
+    let price_impact = legs
+        .iter()
+        .map(|leg| leg.price_impact * math::ratio(&leg.amount_in, amount_in))
+        .sum();
+    let state_block = legs.iter().filter_map(|leg| leg.state_block).min();
+
+    let mut splits = Vec::new();
+    let mut steps = Vec::new();
+    if legs.len() > 1 {
+        for leg in &legs {
+            splits.push(RouteSplit {
+                share_bps: (math::ratio(&leg.amount_in, amount_in) * 10_000.0).round() as u32,
+                first_step: steps.len(),
+                step_count: leg.steps.len(),
+                amount_in: leg.amount_in.to_string(),
+                expected_amount_out: leg.amount_out.to_string(),
+            });
+            steps.extend(leg.steps.iter().cloned());
+        }
+    } else {
+        steps = legs.into_iter().flat_map(|leg| leg.steps).collect();
+    }
+
+    SwapRoute {
+        steps,
+        amount_in: amount_in.to_string(),
+        expected_amount_out: total_out.to_string(),
+        price_impact,
+        splits,
+        state_block,
+        ..Default::default()
+    }
+}
+
+// Legs of a route with their expected outputs; a single leg for unsplit routes
+pub fn legs(route: &SwapRoute) -> Vec<(&[SwapStep], BigUint)> {
+    if route.splits.is_empty() {
+        let expected = math::parse_amount(&route.expected_amount_out).unwrap_or_default();
+        return vec![(route.steps.as_slice(), expected)];
+    }
+    route
+        .splits
+        .iter()
+        .filter_map(|split| {
+            let steps = route.steps.get(split.first_step..split.first_step + split.step_count)?;
+            Some((steps, math::parse_amount(&split.expected_amount_out).unwrap_or_default()))
+        })
+        .collect()
+}
+
+impl RouterEngine {
+    pub fn set_routing_config(&self, config: RoutingConfig) {
+        *self.routing.write().unwrap() = config;
+    }
+
+    pub fn routing_config(&self) -> RoutingConfig {
+        self.routing.read().unwrap().clone()
+    }
+
+    // Pools between the chain's registered tokens, limited to `exchanges` if given.
+    // Building it can take a probe per token pair and source, so the chain's full
+    // graph is cached until its tokens or the sources change, an indexer sees a
+    // pool appear or run empty, or TOKEN_GRAPH_TTL_SECS pass.
+    pub async fn token_graph(&self, chain_id: u64, exchanges: Option<&[String]>) -> Arc<TokenGraph> {
+        let tokens = self.tokens.list(Some(chain_id));
+        let now = rfq::now();
+        let cached = self.token_graphs.get(&chain_id).and_then(|cached| {
+            (cached.token_count == tokens.len() && now < cached.built_at + TOKEN_GRAPH_TTL_SECS).then(|| cached.graph.clone())
+        });
+        let graph = match cached {
+            Some(graph) => graph,
+            None => {
+                let graph = Arc::new(self.build_token_graph(chain_id, &tokens).await);
+                self.token_graphs.insert(
+                    chain_id,
+                    CachedTokenGraph {
+                        graph: graph.clone(),
+                        token_count: tokens.len(),
+                        built_at: now,
+                    },
+                );
+                graph
+            }
+        };
+
+        match exchanges {
+            Some(allowed) => Arc::new(graph.restricted_to(allowed)),
+            None => graph,
+        }
+    }
+
+    // Drop the cached token graph of a chain, or of every chain
+    pub fn invalidate_token_graph(&self, chain_id: Option<u64>) {
+        match chain_id {
+            Some(chain_id) => {
+                self.token_graphs.remove(&chain_id);
+            }
+            None => self.token_graphs.clear(),
+        }
+    }
+
+    // Sources that may quote on the chain: those registered as one of its exchanges,
+    // plus sources with no exchange registered on any chain
+    fn chain_sources(&self, chain_id: u64) -> Vec<(String, Arc<dyn LiquiditySource>)> {
+        self.liquidity_sources
+            .iter()
+            .filter(|s| {
+                self.exchanges.contains_key(&(chain_id, s.key().clone()))
+                    || !self.exchanges.iter().any(|e| e.key().1 == *s.key())
+            })
+            .map(|s| (s.key().clone(), s.value().clone()))
+            .collect()
+    }
+
+    // Pools between the chain's registered tokens. Sources listing their pools are
+    // taken at their word; the rest are probed for every token pair, at most
+    // TOKEN_GRAPH_PROBE_CONCURRENCY at a time.
+    async fn build_token_graph(&self, chain_id: u64, tokens: &[Token]) -> TokenGraph {
+        let registered: HashMap<String, &Token> = tokens.iter().map(|t| (t.address.to_lowercase(), t)).collect();
+        let mut graph = TokenGraph::default();
+        let mut probed = Vec::new();
+
+        for (exchange_id, source) in self.chain_sources(chain_id) {
+            match source.known_pairs() {
+                Some(pairs) => {
+                    for (token_a, token_b) in pairs {
+                        let token_a = registered.get(&token_a.address.to_lowercase());
+                        let token_b = registered.get(&token_b.address.to_lowercase());
+                        if let (Some(token_a), Some(token_b)) = (token_a, token_b) {
+                            graph.add_pool(&exchange_id, token_a, token_b);
+                        }
+                    }
+                }
+                None => probed.push((exchange_id, source)),
+            }
+        }
+
+        let mut lookups = Vec::new();
+        for (i, token_a) in tokens.iter().enumerate() {
+            for token_b in &tokens[i + 1..] {
+                for (exchange_id, source) in &probed {
+                    lookups.push(async move {
+                        let reserves = source.get_reserves(token_a, token_b).await;
+                        (exchange_id, token_a, token_b, reserves)
+                    });
+                }
+            }
+        }
+        let probe_count = lookups.len();
+        let results: Vec<_> = stream::iter(lookups)
+            .buffer_unordered(TOKEN_GRAPH_PROBE_CONCURRENCY)
+            .collect()
+            .await;
+        for (exchange_id, token_a, token_b, reserves) in results {
+            if let Ok((reserve_a, reserve_b)) = reserves {
+                if !reserve_a.is_zero() && !reserve_b.is_zero() {
+                    graph.add_pool(exchange_id, token_a, token_b);
+                }
+            }
+        }
+        debug!(
+            "Token graph for chain {}: {} tokens, {} pools, {} probes",
+            chain_id,
+            tokens.len(),
+            graph.pool_count(),
+            probe_count
+        );
+        graph
+    }
+
+    // Whether every pool on the path is quoted from locally cached state
+    fn has_cached_state(&self, path: &[Edge]) -> bool {
+        path.iter().all(|edge| {
+            matches!(
+                self.liquidity_sources.get(&edge.exchange_id),
+                Some(source) if source.state_block(&edge.token_in, &edge.token_out).is_some()
+            )
+        })
+    }
+
+    // Estimated gas of the path's hops, without the route overhead
+    fn path_hop_gas(&self, chain_id: u64, path: &[Edge]) -> u64 {
+        path.iter()
+            .map(|edge| {
+                self.pool_overrides
+                    .get(&edge.exchange_id, &edge.token_in, &edge.token_out)
+                    .and_then(|o| o.gas)
+                    .unwrap_or_else(|| self.gas_model.hop_gas(chain_id, &edge.exchange_id))
+            })
+            .sum()
+    }
+
+    // Quote `path` hop by hop; `fresh` quotes from live sources where registered
+    async fn quote_path(&self, path: &[Edge], amount_in: &BigUint, fresh: bool) -> Result<PathQuote, RouterError> {
+        let mut steps = Vec::with_capacity(path.len());
+        let mut amount = amount_in.clone();
+        let mut retained = 1.0;
+        let mut state_block: Option<u64> = None;
+
+        for edge in path {
+            let source = self
+                .quote_source(&edge.exchange_id, fresh)
+                .ok_or_else(|| RouterError::ConfigError(format!("Unknown liquidity source {}", edge.exchange_id)))?;
+            let fee_override = self
+                .pool_overrides
+                .get(&edge.exchange_id, &edge.token_in, &edge.token_out)
+                .and_then(|o| o.fee_tier);
+            let (amount_out, impact, fee_tier) = match fee_override {
+                // Standard constant-product pool deployed with a non-standard fee
+                Some(fee) => {
+                    let (reserve_in, reserve_out) = source.get_reserves(&edge.token_in, &edge.token_out).await?;
+                    let amount_out = math::get_amount_out(&amount, &reserve_in, &reserve_out, fee);
+                    (amount_out, math::ratio(&amount, &(&reserve_in + &amount)), Some(fee))
+                }
+                None => source.get_quote_with_fee_tier(&edge.token_in, &edge.token_out, &amount).await?,
+            };
+            if amount_out.is_zero() {
+                return Err(RouterError::InsufficientLiquidity(format!(
+                    "{} returns nothing for {} {}",
+                    edge.exchange_id, amount, edge.token_in.symbol
+                )));
+            }
+
+            if let Some(block) = source.state_block(&edge.token_in, &edge.token_out) {
+                state_block = Some(state_block.map_or(block, |oldest| oldest.min(block)));
+            }
+            retained *= 1.0 - impact.clamp(0.0, 1.0);
+            steps.push(SwapStep {
+                exchange_id: edge.exchange_id.clone(),
+                token_in: edge.token_in.clone(),
+                token_out: edge.token_out.clone(),
+                fee_tier,
+                amount_in: amount.to_string(),
+                amount_out_min: amount_out.to_string(),
+                expected_amount_out: Some(amount_out.to_string()),
+                firmness: rfq::Firmness::Indicative,
+            });
+            amount = amount_out;
+        }
+
+        Ok(PathQuote {
+            steps,
+            amount_in: amount_in.clone(),
+            amount_out: amount,
+            price_impact: 1.0 - retained,
+            state_block,
+        })
+    }
+
+    // Output of a route leg re-priced against current pool state
+    pub(crate) async fn requote_steps(&self, steps: &[SwapStep], amount_in: &BigUint) -> Result<BigUint, RouterError> {
+        let path: Vec<Edge> = steps
+            .iter()
+            .map(|step| Edge {
+                exchange_id: step.exchange_id.clone(),
+                token_in: step.token_in.clone(),
+                token_out: step.token_out.clone(),
+            })
+            .collect();
+        Ok(self.quote_path(&path, amount_in, true).await?.amount_out)
+    }
+
+    // Step taking the largest share of its pool's input-side reserve, with that
+    // share in basis points. Sources that don't report reserves are left out.
+    async fn largest_pool_share(&self, steps: &[SwapStep]) -> Option<(String, u32)> {
+        let mut largest: Option<(String, u32)> = None;
+        for step in steps {
+            let Some(source) = self.liquidity_sources.get(&step.exchange_id).map(|s| s.clone()) else {
+                continue;
+            };
+            let (Ok((reserve_in, _)), Ok(amount)) = (
+                source.get_reserves(&step.token_in, &step.token_out).await,
+                math::parse_amount(&step.amount_in),
+            ) else {
+                continue;
+            };
+            let share_bps = (math::ratio(&amount, &reserve_in) * 10_000.0).round().min(u32::MAX as f64) as u32;
+            if !matches!(&largest, Some((_, largest_bps)) if *largest_bps >= share_bps) {
+                largest = Some((step.exchange_id.clone(), share_bps));
+            }
+        }
+        largest
+    }
+
+    // The step over the pool share limit, if any
+    async fn concentration(&self, steps: &[SwapStep], max_bps: Option<u32>) -> Option<trace::RejectionReason> {
+        let max_bps = max_bps?;
+        match self.largest_pool_share(steps).await {
+            Some((exchange_id, share_bps)) if share_bps > max_bps => Some(trace::RejectionReason::PoolConcentration {
+                exchange_id,
+                share_bps,
+                max_bps,
+            }),
+            _ => None,
+        }
+    }
+
+    // Greedily hand each 1/parts of the input to the path with the best marginal
+    // output. Paths sharing a pool are never combined since their quotes would
+    // ignore each other's price impact, and a path stops taking parts once its
+    // next one would put it over the pool share limit. A split whose final quote
+    // fails is recorded in `trace` and dropped.
+    async fn best_split(
+        &self,
+        chain_id: u64,
+        paths: &[Vec<Edge>],
+        amount_in: &BigUint,
+        config: &RoutingConfig,
+        fresh: bool,
+        trace: &mut trace::RejectionTrace,
+    ) -> Option<SwapRoute> {
+        let parts = config.split_parts.max(2);
+        let amount_at = |k: u32| amount_in * BigUint::from(k) / BigUint::from(parts);
+        // Legs of dust size cost more gas than they could gain
+        if let Some(edge) = paths.first().and_then(|path| path.first()) {
+            if self.amount_rules().is_dust(&edge.token_in.address, &amount_at(1)) {
+                return None;
+            }
+        }
+        let hop_gas: Vec<u64> = paths.iter().map(|path| self.path_hop_gas(chain_id, path)).collect();
+        let mut allocation = vec![0u32; paths.len()];
+        let mut quotes: HashMap<(usize, u32), Option<PathQuote>> = HashMap::new();
+
+        for _ in 0..parts {
+            let used: Vec<usize> = (0..paths.len()).filter(|&p| allocation[p] > 0).collect();
+            let used_gas = self.gas_model.route_overhead() + used.iter().map(|&u| hop_gas[u]).sum::<u64>();
+            let mut best: Option<(usize, BigUint)> = None;
+
+            for (p, path) in paths.iter().enumerate() {
+                if allocation[p] == 0
+                    && (used.len() >= config.max_splits
+                        || used.iter().any(|&u| shares_pool(&paths[u], path))
+                        || matches!(config.max_gas, Some(max_gas) if used_gas + hop_gas[p] > max_gas))
+                {
+                    continue;
+                }
+
+                let output = |quote: &Option<PathQuote>| quote.as_ref().map(|q| q.amount_out.clone());
+                let current = match allocation[p] {
+                    0 => BigUint::zero(),
+                    k => match quotes.get(&(p, k)).and_then(output) {
+                        Some(out) => out,
+                        None => continue,
+                    },
+                };
+                let next_k = allocation[p] + 1;
+                let next = match quotes.get(&(p, next_k)) {
+                    Some(cached) => output(cached),
+                    None => {
+                        let quote = match self.quote_path(path, &amount_at(next_k), fresh).await {
+                            Ok(quote) if self.concentration(&quote.steps, config.max_pool_share_bps).await.is_none() => {
+                                Some(quote)
+                            }
+                            _ => None,
+                        };
+                        let next = output(&quote);
+                        quotes.insert((p, next_k), quote);
+                        next
+                    }
+                };
+                let Some(next) = next else {
+                    continue;
+                };
+
+                if next > current {
+                    let gain = next - current;
+                    if !matches!(&best, Some((_, best_gain)) if *best_gain >= gain) {
+                        best = Some((p, gain));
+                    }
+                }
+            }
+
+            match best {
+                Some((p, _)) => allocation[p] += 1,
+                None => return None,
+            }
+        }
+
+        let used: Vec<usize> = (0..paths.len()).filter(|&p| allocation[p] > 0).collect();
+        if used.len() < 2 {
+            return None;
+        }
+
+        // Legs keep the quotes they were allocated with, except the last, which
+        // takes the rounding remainder so the legs add up to amount_in
+        let planned: Vec<PathQuote> = used
+            .iter()
+            .map(|&p| quotes.get(&(p, allocation[p])).cloned().flatten())
+            .collect::<Option<_>>()?;
+        let mut legs = planned.clone();
+        let allocated: BigUint = planned[..planned.len() - 1].iter().map(|leg| &leg.amount_in).sum();
+        let last = legs.len() - 1;
+        let remainder = amount_in - allocated;
+        if remainder != legs[last].amount_in {
+            match self.quote_path(&paths[used[last]], &remainder, fresh).await {
+                Ok(quote) => legs[last] = quote,
+                Err(e) => {
+                    let detail = format!("final quote of the split failed: {}", e);
+                    trace.record(&route_from_legs(amount_in, planned), trace::RejectionReason::QuoteFailed { detail });
+                    return None;
+                }
+            }
+        }
+
+        Some(route_from_legs(amount_in, legs))
+    }
+
+    // Best single-path routes plus, when it beats them, a route split across
+    // paths. Paths too large for one of their pools are left to the split and
+    // recorded in `trace`.
+    pub async fn candidate_routes(
+        &self,
+        request: &QuoteRequest,
+        max_hops: Option<usize>,
+        max_pool_share_bps: Option<u32>,
+        trace: &mut trace::RejectionTrace,
+    ) -> Result<Vec<SwapRoute>, RouterError> {
+        let token_in = self.resolve_token(request.chain_id, &request.token_in).await?;
+        let token_out = self.resolve_token(request.chain_id, &request.token_out).await?;
+        let amount_in = math::parse_amount(&request.amount_in)?;
+        if amount_in.is_zero() {
+            return Err(RouterError::ExecutionError("Amount in must be positive".to_string()));
+        }
+
+        let mut config = self.routing_config().for_mode(request.mode);
+        config.max_pool_share_bps = max_pool_share_bps.or(config.max_pool_share_bps);
+        config.max_gas = match (request.max_gas, config.max_gas) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        let max_hops = max_hops.map_or(config.max_hops, |max| max.min(config.max_hops));
+        let graph = self.token_graph(request.chain_id, request.exchanges.as_deref()).await;
+        let mut paths = graph.paths(&token_in, &token_out, max_hops);
+        if let Some(max_gas) = config.max_gas {
+            let overhead = self.gas_model.route_overhead();
+            paths.retain(|path| overhead + self.path_hop_gas(request.chain_id, path) <= max_gas);
+        }
+
+        // Fast quotes stay off the network when cached paths exist
+        if request.mode == QuoteMode::Fast && paths.iter().any(|path| self.has_cached_state(path)) {
+            paths.retain(|path| self.has_cached_state(path));
+        }
+
+        // Execution-bound and exact quotes read live sources rather than indexed state
+        let fresh = request.execution_bound || request.mode == QuoteMode::Exact;
+        let quotes = join_all(paths.iter().map(|path| self.quote_path(path, &amount_in, fresh))).await;
+        let mut ranked: Vec<(Vec<Edge>, PathQuote)> = paths
+            .into_iter()
+            .zip(quotes)
+            .filter_map(|(path, quote)| match quote {
+                Ok(quote) => Some((path, quote)),
+                Err(e) => {
+                    debug!("Skipping path via {:?}: {}", path.iter().map(|e| &e.exchange_id).collect::<Vec<_>>(), e);
+                    None
+                }
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.amount_out.cmp(&a.1.amount_out));
+        ranked.truncate(config.max_paths);
+
+        let (paths, quotes): (Vec<_>, Vec<_>) = ranked.into_iter().unzip();
+        let mut routes: Vec<SwapRoute> = Vec::with_capacity(quotes.len() + 1);
+        for quote in quotes {
+            let rejection = self.concentration(&quote.steps, config.max_pool_share_bps).await;
+            let route = route_from_legs(&amount_in, vec![quote]);
+            match rejection {
+                Some(reason) => trace.record(&route, reason),
+                None => routes.push(route),
+            }
+        }
+        let best_single = routes.first().map(|route| route.expected_amount_out.clone());
+
+        if config.max_splits > 1 && paths.len() > 1 {
+            if let Some(split) = self.best_split(request.chain_id, &paths, &amount_in, &config, fresh, trace).await {
+                let split_out = math::parse_amount(&split.expected_amount_out)?;
+                if !matches!(&best_single, Some(best) if math::parse_amount(best)? >= split_out) {
+                    routes.push(split);
+                }
+            }
+        }
+
+        Ok(routes)
+    }
+}
+
+// Rank routes in their current order, scoring each against the first
+pub fn rank_routes(routes: &mut [SwapRoute]) {
+    let Some(best) = routes.first().map(rebate::net_amount_out) else {
+        return;
+    };
+    for (i, route) in routes.iter_mut().enumerate() {
+        let score = math::ratio(&rebate::net_amount_out(route), &best);
+        route.ranking = Some(RouteRanking {
+            rank: i + 1,
+            score,
+            shortfall_bps: ((1.0 - score).max(0.0) * 10_000.0).round() as u32,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    const A: &str = "0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    const B: &str = "0xbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    const C: &str = "0xcccccccccccccccccccccccccccccccccccccccc";
+
+    fn token(address: &str, symbol: &str) -> Token {
+        Token {
+            chain_id: 1,
+            address: address.to_string(),
+            symbol: symbol.to_string(),
+            decimals: 18,
+        }
+    }
+
+    // Source with liquidity for every pair, counting its reserve probes
+    #[derive(Default)]
+    struct CountingSource {
+        probes: AtomicUsize,
+        pairs: Option<Vec<(Token, Token)>>,
+    }
+
+    #[async_trait]
+    impl LiquiditySource for CountingSource {
+        async fn get_quote(&self, _: &Token, _: &Token, amount_in: &BigUint) -> Result<(BigUint, f64), RouterError> {
+            Ok((amount_in.clone(), 0.0))
+        }
+
+        async fn get_reserves(&self, _: &Token, _: &Token) -> Result<(BigUint, BigUint), RouterError> {
+            self.probes.fetch_add(1, Ordering::Relaxed);
+            Ok((BigUint::from(1_000u32), BigUint::from(1_000u32)))
+        }
+
+        fn known_pairs(&self) -> Option<Vec<(Token, Token)>> {
+            self.pairs.clone()
+        }
+    }
+
+    fn exchange(id: &str, chain_id: u64) -> Exchange {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": id,
+            "chain_id": chain_id,
+            "router_address": "0x3333333333333333333333333333333333333333",
+            "factory_address": null,
+            "fee_tiers": [3000],
+            "protocol": "uniswap_v2",
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn token_graph_probes_only_unlisted_sources_of_the_chain() {
+        let engine = RouterEngine::new();
+        for (address, symbol) in [(A, "A"), (B, "B"), (C, "C")] {
+            engine.register_token(token(address, symbol));
+        }
+        let probed = Arc::new(CountingSource::default());
+        let elsewhere = Arc::new(CountingSource::default());
+        // Listed with another symbol than registered, matched by address
+        let listed = Arc::new(CountingSource {
+            pairs: Some(vec![(token(A, "a"), token(C, "c"))]),
+            ..Default::default()
+        });
+        engine.register_exchange(exchange("probed", 1));
+        engine.register_exchange(exchange("elsewhere", 10));
+        engine.register_exchange(exchange("listed", 1));
+        engine.register_liquidity_source("probed".to_string(), probed.clone());
+        engine.register_liquidity_source("elsewhere".to_string(), elsewhere.clone());
+        engine.register_liquidity_source("listed".to_string(), listed.clone());
+
+        let graph = engine.token_graph(1, None).await;
+        assert_eq!(probed.probes.load(Ordering::Relaxed), 3);
+        assert_eq!(elsewhere.probes.load(Ordering::Relaxed), 0);
+        assert_eq!(listed.probes.load(Ordering::Relaxed), 0);
+        // Three probed pairs plus the listed A/C pool
+        assert_eq!(graph.pool_count(), 4);
+        let listed_edges: Vec<&Edge> = graph
+            .edges(&token(A, "A"))
+            .iter()
+            .filter(|edge| edge.exchange_id == "listed")
+            .collect();
+        assert_eq!(listed_edges.len(), 1);
+        assert_eq!(listed_edges[0].token_out, token(C, "C"));
+
+        // Served from the cache until something changes
+        engine.token_graph(1, None).await;
+        assert_eq!(probed.probes.load(Ordering::Relaxed), 3);
+        engine.register_token(token("0xdddddddddddddddddddddddddddddddddddddddd", "D"));
+        engine.token_graph(1, None).await;
+        assert_eq!(probed.probes.load(Ordering::Relaxed), 9);
+    }
+
+    // Constant-product pool failing to quote odd amounts below `odd_limit`
+    struct FlakyPool {
+        odd_limit: u64,
+    }
+
+    #[async_trait]
+    impl LiquiditySource for FlakyPool {
+        async fn get_quote(&self, _: &Token, _: &Token, amount_in: &BigUint) -> Result<(BigUint, f64), RouterError> {
+            if amount_in.bit(0) && *amount_in < BigUint::from(self.odd_limit) {
+                return Err(RouterError::ChainError("eth_call timed out".to_string()));
+            }
+            let reserve = BigUint::from(1_000_000u32);
+            Ok((math::get_amount_out(amount_in, &reserve, &reserve, 3000), 0.0))
+        }
+
+        async fn get_reserves(&self, _: &Token, _: &Token) -> Result<(BigUint, BigUint), RouterError> {
+            Ok((BigUint::from(1_000_000u32), BigUint::from(1_000_000u32)))
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_split_requote_keeps_single_path_routes() {
+        let engine = RouterEngine::new();
+        for (address, symbol) in [(A, "A"), (B, "B")] {
+            engine.register_token(token(address, symbol));
+        }
+        for id in ["x", "y"] {
+            engine.register_liquidity_source(id.to_string(), Arc::new(FlakyPool { odd_limit: 100_001 }));
+        }
+        // Parts of 10_000 quote fine; the last leg's remainder of 50_001 fails
+        let request = QuoteRequest {
+            chain_id: 1,
+            token_in: A.to_string(),
+            token_out: B.to_string(),
+            amount_in: "100001".to_string(),
+            ..Default::default()
+        };
+        let mut trace = trace::RejectionTrace::new(true);
+
+        let routes = engine.candidate_routes(&request, None, None, &mut trace).await.unwrap();
+        assert_eq!(routes.len(), 2);
+        assert!(routes.iter().all(|route| route.splits.is_empty()));
+        let rejected = trace.finish().unwrap();
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].route.splits.len(), 2);
+        assert!(matches!(rejected[0].reason, trace::RejectionReason::QuoteFailed { .. }));
+    }
+
+    #[tokio::test]
+    async fn exact_quotes_read_live_sources() {
+        let engine = RouterEngine::new();
+        for (address, symbol) in [(A, "A"), (B, "B")] {
+            engine.register_token(token(address, symbol));
+        }
+        // Indexed state quotes 1:1, the live pool quotes constant product
+        engine.register_liquidity_source("x".to_string(), Arc::new(CountingSource::default()));
+        engine.register_live_source("x".to_string(), Arc::new(FlakyPool { odd_limit: 0 }));
+        let mut request = QuoteRequest {
+            chain_id: 1,
+            token_in: A.to_string(),
+            token_out: B.to_string(),
+            amount_in: "10000".to_string(),
+            ..Default::default()
+        };
+        let mut trace = trace::RejectionTrace::new(false);
+
+        let routes = engine.candidate_routes(&request, None, None, &mut trace).await.unwrap();
+        assert_eq!(routes[0].expected_amount_out, "10000");
+
+        request.mode = QuoteMode::Exact;
+        let routes = engine.candidate_routes(&request, None, None, &mut trace).await.unwrap();
+        let reserve = BigUint::from(1_000_000u32);
+        let live_out = math::get_amount_out(&BigUint::from(10_000u32), &reserve, &reserve, 3000);
+        assert_eq!(routes[0].expected_amount_out, live_out.to_string());
+    }
+}
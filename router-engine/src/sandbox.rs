@@ -0,0 +1,191 @@
+use ethers::abi::{ParamType, Token as AbiToken};
+use revm::db::{CacheDB, EmptyDB};
+use revm::primitives::{
+    AccountInfo, Address as EvmAddress, Bytecode, Bytes as EvmBytes, CreateScheme, ExecutionResult, Output, TransactTo,
+    U256 as EvmU256,
+};
+use revm::EVM;
+
+use super::*;
+use crate::abi_registry::encode_call;
+
+// Sender of sandbox calls and deployments
+const SANDBOX_CALLER: [u8; 20] = [0x5a; 20];
+
+fn evm_address(address: Address) -> EvmAddress {
+    EvmAddress::from(address.0)
+}
+
+fn evm_u256(value: U256) -> EvmU256 {
+    EvmU256::from_limbs(value.0)
+}
+
+// In-memory EVM for contracts that don't exist on any chain yet: experimental
+// pools, V4 hooks and whatever they call into. State only changes through
+// deploy, set_code and set_storage; quotes run against a copy.
+pub struct Sandbox {
+    db: std::sync::RwLock<CacheDB<EmptyDB>>,
+}
+
+impl Default for Sandbox {
+    fn default() -> Self {
+        Self {
+            db: std::sync::RwLock::new(CacheDB::new(EmptyDB::default())),
+        }
+    }
+}
+
+impl Sandbox {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Place runtime bytecode at `address`, e.g. a hook at an address whose flag bits it needs
+    pub fn set_code(&self, address: &str, runtime_code: &[u8]) -> Result<(), RouterError> {
+        let code = Bytecode::new_raw(EvmBytes::copy_from_slice(runtime_code));
+        let info = AccountInfo::new(EvmU256::ZERO, 1, code.hash_slow(), code);
+        self.db.write().unwrap().insert_account_info(evm_address(parse_address(address)?), info);
+        Ok(())
+    }
+
+    pub fn set_storage(&self, address: &str, slot: U256, value: U256) -> Result<(), RouterError> {
+        self.db
+            .write()
+            .unwrap()
+            .insert_account_storage(evm_address(parse_address(address)?), evm_u256(slot), evm_u256(value))
+            .map_err(|e| RouterError::ExecutionError(format!("Failed to set sandbox storage: {:?}", e)))
+    }
+
+    // Run creation code (bytecode followed by ABI-encoded constructor arguments)
+    // and return the new contract's address
+    pub fn deploy(&self, init_code: &[u8]) -> Result<Address, RouterError> {
+        let mut db = self.db.write().unwrap();
+        let mut evm = EVM::new();
+        evm.database(std::mem::take(&mut *db));
+        evm.env.tx.caller = EvmAddress::from(SANDBOX_CALLER);
+        evm.env.tx.transact_to = TransactTo::Create(CreateScheme::Create);
+        evm.env.tx.data = EvmBytes::copy_from_slice(init_code);
+        let result = evm.transact_commit();
+        *db = evm.db.take().unwrap_or_default();
+
+        match result.map_err(|e| RouterError::ExecutionError(format!("Sandbox deployment failed: {:?}", e)))? {
+            ExecutionResult::Success {
+                output: Output::Create(_, Some(address)),
+                ..
+            } => Ok(Address::from_slice(address.as_slice())),
+            ExecutionResult::Revert { output, .. } => {
+                Err(RouterError::Reverted(format!("Sandbox deployment reverted: 0x{}", hex::encode(output))))
+            }
+            other => Err(RouterError::ExecutionError(format!("Sandbox deployment failed: {:?}", other))),
+        }
+    }
+
+    // Call `to` without keeping any state changes
+    pub fn call(&self, to: Address, data: Vec<u8>) -> Result<Vec<u8>, RouterError> {
+        let mut evm = EVM::new();
+        evm.database(self.db.read().unwrap().clone());
+        evm.env.tx.caller = EvmAddress::from(SANDBOX_CALLER);
+        evm.env.tx.transact_to = TransactTo::Call(evm_address(to));
+        evm.env.tx.data = data.into();
+
+        let result = evm
+            .transact_ref()
+            .map_err(|e| RouterError::ExecutionError(format!("Sandbox call to {:?} failed: {:?}", to, e)))?
+            .result;
+        match result {
+            ExecutionResult::Success {
+                output: Output::Call(output),
+                ..
+            } => Ok(output.to_vec()),
+            ExecutionResult::Revert { output, .. } => Err(RouterError::Reverted(format!("0x{}", hex::encode(output)))),
+            other => Err(RouterError::ExecutionError(format!("Sandbox call to {:?} failed: {:?}", to, other))),
+        }
+    }
+}
+
+// A pool living in a Sandbox. The contract at `lens` answers
+//   quote(address tokenIn, address tokenOut, uint256 amountIn) returns (uint256 amountOut)
+//   getReserves(address tokenA, address tokenB) returns (uint256, uint256)
+// which is the pool itself or a small wrapper around it (and its hooks).
+pub struct SandboxPool {
+    sandbox: Arc<Sandbox>,
+    lens: Address,
+}
+
+impl SandboxPool {
+    pub fn new(sandbox: Arc<Sandbox>, lens: &str) -> Result<Self, RouterError> {
+        Ok(Self {
+            sandbox,
+            lens: parse_address(lens)?,
+        })
+    }
+
+    fn call(&self, signature: &str, args: &[AbiToken], outputs: &[ParamType]) -> Result<Vec<AbiToken>, RouterError> {
+        let output = self.sandbox.call(self.lens, encode_call(signature, args))?;
+        ethers::abi::decode(outputs, &output)
+            .map_err(|e| RouterError::ExecutionError(format!("Unexpected {} output from sandbox pool: {}", signature, e)))
+    }
+}
+
+fn uint(token: Option<&AbiToken>) -> Result<BigUint, RouterError> {
+    match token {
+        Some(AbiToken::Uint(value)) => Ok(math::from_u256(*value)),
+        other => Err(RouterError::ExecutionError(format!("Expected an integer from sandbox pool, got {:?}", other))),
+    }
+}
+
+#[async_trait]
+impl LiquiditySource for SandboxPool {
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let (token_in, token_out) = (parse_address(&token_in.address)?, parse_address(&token_out.address)?);
+        let result = self.call(
+            "quote(address,address,uint256)",
+            &[
+                AbiToken::Address(token_in),
+                AbiToken::Address(token_out),
+                AbiToken::Uint(math::to_u256(amount_in)?),
+            ],
+            &[ParamType::Uint(256)],
+        )?;
+        let amount_out = uint(result.first())?;
+
+        // Impact against the pool's input-side reserve, as in StateBackedSource
+        let reserves = self.call(
+            "getReserves(address,address)",
+            &[AbiToken::Address(token_in), AbiToken::Address(token_out)],
+            &[ParamType::Uint(256), ParamType::Uint(256)],
+        )?;
+        let reserve_in = uint(reserves.first())?;
+        Ok((amount_out, math::ratio(amount_in, &(&reserve_in + amount_in))))
+    }
+
+    async fn get_reserves(
+        &self,
+        token_a: &Token,
+        token_b: &Token,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        let result = self.call(
+            "getReserves(address,address)",
+            &[
+                AbiToken::Address(parse_address(&token_a.address)?),
+                AbiToken::Address(parse_address(&token_b.address)?),
+            ],
+            &[ParamType::Uint(256), ParamType::Uint(256)],
+        )?;
+        Ok((uint(result.first())?, uint(result.get(1))?))
+    }
+}
+
+impl RouterEngine {
+    // Quote `exchange_id` from a pool in `sandbox`
+    pub fn register_sandbox_pool(&self, exchange_id: String, sandbox: Arc<Sandbox>, lens: &str) -> Result<(), RouterError> {
+        let pool = SandboxPool::new(sandbox, lens)?;
+        self.register_liquidity_source(exchange_id, Arc::new(pool));
+        Ok(())
+    }
+}
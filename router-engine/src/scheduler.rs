@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use super::*;
+use crate::tx_manager::{Submission, TxManager};
+
+// When a scheduled swap becomes due
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "at", content = "value", rename_all = "snake_case")]
+pub enum ExecutionTarget {
+    Block(u64),
+    Timestamp(u64),
+}
+
+impl ExecutionTarget {
+    fn is_due(&self, block_number: u64, timestamp: u64) -> bool {
+        match self {
+            ExecutionTarget::Block(block) => block_number >= *block,
+            ExecutionTarget::Timestamp(time) => timestamp >= *time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ScheduleStatus {
+    Pending,
+    // Being re-priced and submitted; can no longer be cancelled
+    Executing,
+    Submitted { submission: Box<Submission> },
+    // The re-priced route no longer meets the accepted route's minimum output
+    Rejected { reason: String },
+    Cancelled,
+    Failed { error: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledSwap {
+    pub id: String,
+    pub request: QuoteRequest,
+    // Route the user accepted; its minimum output bounds the re-priced route
+    pub accepted: SwapRoute,
+    pub target: ExecutionTarget,
+    pub status: ScheduleStatus,
+}
+
+// Builds the swap transaction for a re-priced route
+pub type TxBuilder = Arc<dyn Fn(&SwapRoute) -> Result<Eip1559TransactionRequest, RouterError> + Send + Sync>;
+
+// Holds accepted routes until their target block or time, then re-prices,
+// re-validates and submits them through the TxManager
+pub struct Scheduler<M: Middleware> {
+    engine: Arc<RouterEngine>,
+    client: Arc<M>,
+    tx_manager: Arc<TxManager<M>>,
+    build_tx: TxBuilder,
+    swaps: DashMap<String, ScheduledSwap>,
+    poll_interval: Duration,
+}
+
+impl<M: Middleware + 'static> Scheduler<M> {
+    pub fn new(engine: Arc<RouterEngine>, client: Arc<M>, tx_manager: Arc<TxManager<M>>, build_tx: TxBuilder) -> Self {
+        Self {
+            engine,
+            client,
+            tx_manager,
+            build_tx,
+            swaps: DashMap::new(),
+            poll_interval: Duration::from_secs(2),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    pub fn schedule(&self, request: QuoteRequest, accepted: SwapRoute, target: ExecutionTarget) -> String {
+        let id = format!("{:x}", rand::random::<u128>());
+        self.swaps.insert(
+            id.clone(),
+            ScheduledSwap {
+                id: id.clone(),
+                request,
+                accepted,
+                target,
+                status: ScheduleStatus::Pending,
+            },
+        );
+        id
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<(), RouterError> {
+        let mut swap = self
+            .swaps
+            .get_mut(id)
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown scheduled swap: {}", id)))?;
+        if !matches!(swap.status, ScheduleStatus::Pending) {
+            return Err(RouterError::ExecutionError(format!("Scheduled swap {} is no longer pending", id)));
+        }
+        swap.status = ScheduleStatus::Cancelled;
+        Ok(())
+    }
+
+    pub fn get(&self, id: &str) -> Option<ScheduledSwap> {
+        self.swaps.get(id).map(|s| s.clone())
+    }
+
+    // Re-price a due swap and submit it if it still meets the accepted minimum output
+    async fn execute(&self, swap: &ScheduledSwap) -> ScheduleStatus {
+        let min_out = match swap.accepted.steps.last().map(|step| math::parse_amount(&step.amount_out_min)) {
+            Some(Ok(min_out)) => min_out,
+            Some(Err(e)) => return ScheduleStatus::Failed { error: e.to_string() },
+            None => return ScheduleStatus::Failed { error: "Accepted route has no steps".to_string() },
+        };
+
+        let route = match self.engine.find_routes(swap.request.clone()).await {
+            Ok(response) => match response.routes.into_iter().next() {
+                Some(route) => route,
+                None => {
+                    return ScheduleStatus::Rejected {
+                        reason: "No route available at execution time".to_string(),
+                    }
+                }
+            },
+            Err(e) => return ScheduleStatus::Failed { error: e.to_string() },
+        };
+
+        let expected = math::parse_amount(&route.expected_amount_out).unwrap_or_default();
+        if expected < min_out {
+            return ScheduleStatus::Rejected {
+                reason: format!("re-priced output {} is below the accepted minimum {}", expected, min_out),
+            };
+        }
+
+        let policy = match self.engine.resolve_mev_policy(swap.request.chain_id, swap.request.mev_policy) {
+            Ok(policy) => policy,
+            Err(e) => return ScheduleStatus::Failed { error: e.to_string() },
+        };
+        let submitted = match (self.build_tx)(&route) {
+            Ok(tx) => {
+                self.tx_manager
+                    .submit(tx, swap.request.clone(), policy)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+
+        match submitted {
+            Ok(submission) => ScheduleStatus::Submitted {
+                submission: Box::new(submission),
+            },
+            Err(e) => ScheduleStatus::Failed { error: e.to_string() },
+        }
+    }
+
+    // Execute every pending swap that is due at the latest block
+    pub async fn tick(&self) -> Result<usize, RouterError> {
+        let block = self
+            .client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch latest block: {}", e)))?
+            .ok_or_else(|| RouterError::ChainError("Latest block not found".to_string()))?;
+        let block_number = block.number.map(|n| n.as_u64()).unwrap_or_default();
+        let timestamp = block.timestamp.as_u64();
+
+        let mut due = Vec::new();
+        for mut swap in self.swaps.iter_mut() {
+            if matches!(swap.status, ScheduleStatus::Pending) && swap.target.is_due(block_number, timestamp) {
+                swap.status = ScheduleStatus::Executing;
+                due.push(swap.clone());
+            }
+        }
+
+        for swap in &due {
+            let status = self.execute(swap).await;
+            info!("Scheduled swap {} at block {}: {:?}", swap.id, block_number, status);
+            if let Some(mut entry) = self.swaps.get_mut(&swap.id) {
+                entry.status = status;
+            }
+        }
+
+        Ok(due.len())
+    }
+
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = self.tick().await {
+                    warn!("Scheduler tick failed: {}", e);
+                }
+                tokio::time::sleep(self.poll_interval).await;
+            }
+        })
+    }
+}
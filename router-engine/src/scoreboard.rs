@@ -0,0 +1,195 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use super::*;
+
+// Age at which a quote or fill counts half as much as a new one
+pub const DEFAULT_HALF_LIFE_SECS: u64 = 24 * 60 * 60;
+
+// A fill holds up when it returns at least the quoted output less this
+pub const HOLD_TOLERANCE_BPS: u64 = 30;
+
+// Pseudo-fills, all held, every source starts with, so a few bad fills don't
+// bury a new source and an unused one isn't penalized
+const PRIOR_FILLS: f64 = 5.0;
+
+// Time-decayed record of one source on one chain
+#[derive(Debug, Clone, Default)]
+struct SourceRecord {
+    quoted: f64,
+    won: f64,
+    filled: f64,
+    held: f64,
+    updated_at: u64,
+}
+
+impl SourceRecord {
+    fn decay_to(&mut self, now: u64, half_life_secs: u64) {
+        if now > self.updated_at && half_life_secs > 0 {
+            let factor = 0.5f64.powf((now - self.updated_at) as f64 / half_life_secs as f64);
+            self.quoted *= factor;
+            self.won *= factor;
+            self.filled *= factor;
+            self.held *= factor;
+        }
+        self.updated_at = self.updated_at.max(now);
+    }
+
+    fn reliability(&self) -> f64 {
+        (self.held + PRIOR_FILLS) / (self.filled + PRIOR_FILLS)
+    }
+}
+
+// A source's standing, with counts weighted by age
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceStanding {
+    pub chain_id: u64,
+    pub exchange_id: String,
+    // Quotes with a route through the source, and how many of them it was in the best route of
+    pub quoted: f64,
+    pub won: f64,
+    pub win_rate: f64,
+    // Executed routes through the source, and how many returned the quoted output
+    pub filled: f64,
+    pub held: f64,
+    // Share of fills that held, smoothed towards 1 while there are few of them
+    pub reliability: f64,
+}
+
+// Rolling scoreboard of which sources win routes and whether their quotes hold
+// up at execution
+pub struct Scoreboard {
+    records: DashMap<(u64, String), SourceRecord>,
+    half_life_secs: AtomicU64,
+    // Weigh route outputs by their sources' reliability when ranking
+    reliability_prior: AtomicBool,
+}
+
+impl Default for Scoreboard {
+    fn default() -> Self {
+        Self {
+            records: DashMap::new(),
+            half_life_secs: AtomicU64::new(DEFAULT_HALF_LIFE_SECS),
+            reliability_prior: AtomicBool::new(false),
+        }
+    }
+}
+
+fn sources(route: &SwapRoute) -> Vec<&str> {
+    let mut sources: Vec<&str> = route.steps.iter().map(|step| step.exchange_id.as_str()).collect();
+    sources.sort();
+    sources.dedup();
+    sources
+}
+
+impl Scoreboard {
+    pub fn set_half_life(&self, half_life_secs: u64) {
+        self.half_life_secs.store(half_life_secs, Ordering::Relaxed);
+    }
+
+    pub fn set_reliability_prior(&self, enabled: bool) {
+        self.reliability_prior.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn reliability_prior(&self) -> bool {
+        self.reliability_prior.load(Ordering::Relaxed)
+    }
+
+    fn update(&self, chain_id: u64, exchange_id: &str, now: u64, apply: impl FnOnce(&mut SourceRecord)) {
+        let mut record = self.records.entry((chain_id, exchange_id.to_string())).or_default();
+        record.decay_to(now, self.half_life_secs.load(Ordering::Relaxed));
+        apply(&mut record);
+    }
+
+    // Count a quote's routes, the first being the winner
+    pub fn record_quote(&self, chain_id: u64, routes: &[SwapRoute]) {
+        let now = rfq::now();
+        let mut quoted: Vec<&str> = routes.iter().flat_map(sources).collect();
+        quoted.sort();
+        quoted.dedup();
+        for exchange_id in quoted {
+            self.update(chain_id, exchange_id, now, |record| record.quoted += 1.0);
+        }
+        if let Some(best) = routes.first() {
+            for exchange_id in sources(best) {
+                self.update(chain_id, exchange_id, now, |record| record.won += 1.0);
+            }
+        }
+    }
+
+    // Count an executed route; `amount_out` is what it returned, None if it reverted
+    pub fn record_fill(&self, chain_id: u64, route: &SwapRoute, amount_out: Option<&BigUint>) {
+        let expected = math::parse_amount(&route.expected_amount_out).unwrap_or_default();
+        let threshold = &expected * BigUint::from(10_000 - HOLD_TOLERANCE_BPS) / BigUint::from(10_000u64);
+        let held = matches!(amount_out, Some(amount_out) if *amount_out >= threshold);
+
+        let now = rfq::now();
+        for exchange_id in sources(route) {
+            self.update(chain_id, exchange_id, now, |record| {
+                record.filled += 1.0;
+                if held {
+                    record.held += 1.0;
+                }
+            });
+        }
+    }
+
+    pub fn reliability(&self, chain_id: u64, exchange_id: &str) -> f64 {
+        self.records
+            .get(&(chain_id, exchange_id.to_string()))
+            .map(|record| record.reliability())
+            .unwrap_or(1.0)
+    }
+
+    // Chance the route's quote holds, taking its sources as independent
+    pub fn route_reliability(&self, chain_id: u64, route: &SwapRoute) -> f64 {
+        sources(route)
+            .into_iter()
+            .map(|exchange_id| self.reliability(chain_id, exchange_id))
+            .product()
+    }
+
+    // Sources by win rate, decayed to now
+    pub fn standings(&self, chain_id: Option<u64>) -> Vec<SourceStanding> {
+        let now = rfq::now();
+        let half_life_secs = self.half_life_secs.load(Ordering::Relaxed);
+        let mut standings: Vec<SourceStanding> = self
+            .records
+            .iter()
+            .filter(|entry| !matches!(chain_id, Some(chain_id) if entry.key().0 != chain_id))
+            .map(|entry| {
+                let (chain_id, exchange_id) = entry.key().clone();
+                let mut record = entry.value().clone();
+                record.decay_to(now, half_life_secs);
+                SourceStanding {
+                    chain_id,
+                    exchange_id,
+                    quoted: record.quoted,
+                    won: record.won,
+                    win_rate: if record.quoted > 0.0 { record.won / record.quoted } else { 0.0 },
+                    filled: record.filled,
+                    held: record.held,
+                    reliability: record.reliability(),
+                }
+            })
+            .collect();
+        standings.sort_by(|a, b| b.win_rate.total_cmp(&a.win_rate).then_with(|| a.exchange_id.cmp(&b.exchange_id)));
+        standings
+    }
+}
+
+// Net output discounted by the chance the route's quote holds
+pub fn reliability_weighted_out(route: &SwapRoute, reliability: f64) -> BigUint {
+    let ppm = (reliability.clamp(0.0, 1.0) * 1_000_000.0).round() as u64;
+    rebate::net_amount_out(route) * BigUint::from(ppm) / BigUint::from(1_000_000u64)
+}
+
+impl RouterEngine {
+    pub fn scoreboard(&self) -> &Scoreboard {
+        &self.scoreboard
+    }
+
+    // Feed an executed route's output back into its sources' reliability
+    pub fn record_fill(&self, chain_id: u64, route: &SwapRoute, amount_out: Option<&BigUint>) {
+        self.scoreboard.record_fill(chain_id, route, amount_out);
+    }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+// Key material held in memory: HTLC secrets, vault keys, signer keys on their
+// way to a wallet, API credentials. With the `zeroize` feature the contents are
+// overwritten when dropped, so a long-running server doesn't leave them behind
+// in freed heap; without it they are only kept out of Debug output.
+pub struct Secret<T: Wipe>(T);
+
+// Overwrite in place, in a way the compiler can't elide
+pub trait Wipe {
+    fn wipe(&mut self);
+}
+
+#[cfg(feature = "zeroize")]
+impl<T: zeroize::Zeroize> Wipe for T {
+    fn wipe(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(not(feature = "zeroize"))]
+impl<T> Wipe for T {
+    fn wipe(&mut self) {}
+}
+
+impl<T: Wipe> Secret<T> {
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    pub fn expose_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+}
+
+impl<T: Wipe> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.wipe();
+    }
+}
+
+impl<T: Wipe + Clone> Clone for Secret<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T: Wipe> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl<T: Wipe> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self(value)
+    }
+}
+
+// API keys and other credentials sent as strings
+pub type Credential = Secret<String>;
+
+// Wipe a value that can't be wrapped, e.g. a copy handed to a constructor
+pub fn wipe<T: Wipe>(value: &mut T) {
+    value.wipe();
+}
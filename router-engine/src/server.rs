@@ -0,0 +1,368 @@
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Path as UrlPath, Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use tokio::sync::broadcast;
+
+use super::*;
+use crate::adapters::rpc_source;
+use crate::execution::Protocol;
+use crate::finality::FinalityPolicy;
+use crate::token_registry::OnChainMetadata;
+use crate::trust::ExchangeMetadata;
+
+fn default_listen() -> String {
+    "0.0.0.0:8080".to_string()
+}
+
+fn default_block_poll_ms() -> u64 {
+    1_000
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// Server configuration, loaded from TOML at startup
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServerConfig {
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    #[serde(default)]
+    pub chains: Vec<ChainConfig>,
+    #[serde(default)]
+    pub tokens: Vec<Token>,
+    // Token list files or URLs, loaded after `tokens`
+    #[serde(default)]
+    pub token_lists: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ChainConfig {
+    pub chain_id: u64,
+    pub rpc_url: String,
+    pub executor: Option<String>,
+    // Receiver of residue the executor sweeps, instead of each route's recipient
+    pub dust_sink: Option<String>,
+    pub native_token: Option<Token>,
+    pub finality: Option<FinalityPolicy>,
+    // Public, sequencer or private orderflow; picks the default MEV policy
+    pub mempool: Option<mev::MempoolKind>,
+    pub max_state_age: Option<u64>,
+    #[serde(default = "default_block_poll_ms")]
+    pub block_poll_ms: u64,
+    #[serde(default)]
+    pub exchanges: Vec<ExchangeConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExchangeConfig {
+    pub id: String,
+    pub name: Option<String>,
+    pub protocol: Protocol,
+    pub router_address: String,
+    pub factory_address: Option<String>,
+    #[serde(default)]
+    pub fee_tiers: Vec<u32>,
+    // Uniswap V3 QuoterV2, required for V3 exchanges
+    pub quoter: Option<String>,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub metadata: ExchangeMetadata,
+}
+
+impl ServerConfig {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RouterError> {
+        let path = path.as_ref();
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| RouterError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+        toml::from_str(&contents)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid config {}: {}", path.display(), e)))
+    }
+}
+
+// RPC client of one chain
+pub type ChainClient = Arc<Provider<Http>>;
+
+// What build_engine sets up: the engine and every configured chain's client
+pub type ServerEngine = (Arc<RouterEngine>, Vec<(u64, ChainClient)>);
+
+// Shared by every handler
+#[derive(Clone)]
+pub struct AppState {
+    pub engine: Arc<RouterEngine>,
+    // (chain id, block number) of every new block seen
+    pub blocks: broadcast::Sender<(u64, u64)>,
+    pub clients: Arc<HashMap<u64, ChainClient>>,
+}
+
+// Engine with the configured tokens and exchanges registered, plus a client per chain
+pub fn build_engine(config: &ServerConfig) -> Result<ServerEngine, RouterError> {
+    let engine = Arc::new(RouterEngine::new());
+    for token in &config.tokens {
+        engine.register_token(token.clone());
+    }
+
+    let mut clients = Vec::with_capacity(config.chains.len());
+    for chain in &config.chains {
+        let provider = Provider::<Http>::try_from(chain.rpc_url.as_str())
+            .map_err(|e| RouterError::ConfigError(format!("Invalid RPC URL for chain {}: {}", chain.chain_id, e)))?;
+        let client = Arc::new(provider);
+
+        engine
+            .token_registry()
+            .set_resolver(chain.chain_id, Arc::new(OnChainMetadata::new(client.clone())));
+        if let Some(executor) = &chain.executor {
+            engine.register_executor(chain.chain_id, executor.clone());
+        }
+        if let Some(sink) = &chain.dust_sink {
+            engine.set_dust_sink(chain.chain_id, sink.clone());
+        }
+        if let Some(native) = &chain.native_token {
+            engine.register_native_token(native.clone());
+        }
+        if let Some(mempool) = chain.mempool {
+            engine.set_mempool_kind(chain.chain_id, mempool);
+        }
+        if let Some(finality) = chain.finality {
+            engine.set_finality_policy(chain.chain_id, finality);
+        }
+        if let Some(max_state_age) = chain.max_state_age {
+            engine.set_max_state_age(chain.chain_id, max_state_age);
+        }
+
+        for config in chain.exchanges.iter().filter(|e| e.enabled) {
+            let exchange = Exchange {
+                id: config.id.clone(),
+                name: config.name.clone().unwrap_or_else(|| config.id.clone()),
+                chain_id: chain.chain_id,
+                router_address: config.router_address.clone(),
+                factory_address: config.factory_address.clone(),
+                fee_tiers: config.fee_tiers.clone(),
+                router_abi: None,
+                protocol: Some(config.protocol),
+                metadata: config.metadata.clone(),
+            };
+            let source = rpc_source(&exchange, client.clone(), config.quoter.as_deref())?;
+            engine.register_exchange(exchange);
+            engine.register_liquidity_source(config.id.clone(), source);
+        }
+
+        clients.push((chain.chain_id, client));
+    }
+
+    Ok((engine, clients))
+}
+
+// Record each chain's head in the engine and announce new blocks
+pub fn watch_blocks(state: &AppState, chain_id: u64, client: ChainClient, poll_interval: Duration) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut last = 0;
+        loop {
+            match client.get_block_number().await {
+                Ok(head) if head.as_u64() > last => {
+                    last = head.as_u64();
+                    state.engine.record_block(chain_id, last);
+                    let _ = state.blocks.send((chain_id, last));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to fetch block number on chain {}: {}", chain_id, e),
+            }
+            tokio::time::sleep(poll_interval).await;
+        }
+    });
+}
+
+struct ApiError(RouterError);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let status = match &self.0 {
+            RouterError::ConfigError(_) => StatusCode::BAD_REQUEST,
+            RouterError::ChainError(_) => StatusCode::BAD_GATEWAY,
+            _ => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        (status, Json(serde_json::json!({ "error": self.0.to_string() }))).into_response()
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChainFilter {
+    pub chain_id: Option<u64>,
+}
+
+// Any supported schema version of QuoteRequest (see api), answered in the same version
+async fn quote(State(state): State<AppState>, Json(request): Json<serde_json::Value>) -> Result<Json<serde_json::Value>, ApiError> {
+    let (schema_version, request) = api::decode_quote_request(request).map_err(ApiError)?;
+    let response = state.engine.find_routes(request).await.map_err(ApiError)?;
+    api::encode_quote_response(schema_version, response).map(Json).map_err(ApiError)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommitRequest {
+    pub route_id: String,
+    pub sender: String,
+}
+
+async fn commit_route(State(state): State<AppState>, Json(request): Json<CommitRequest>) -> Result<Json<commit::Commitment>, ApiError> {
+    let client = state
+        .engine
+        .quote_book()
+        .chain_id(&request.route_id)
+        .and_then(|chain_id| state.clients.get(&chain_id))
+        .ok_or_else(|| ApiError(RouterError::ConfigError(format!("Unknown or expired route {}", request.route_id))))?;
+    state
+        .engine
+        .commit(&**client, &request.route_id, &request.sender)
+        .await
+        .map(Json)
+        .map_err(ApiError)
+}
+
+// Root cause of a failed swap on a configured chain
+async fn diagnose(
+    State(state): State<AppState>,
+    UrlPath((chain_id, tx_hash)): UrlPath<(u64, String)>,
+) -> Result<Json<forensics::FailureDiagnosis>, ApiError> {
+    let client = state
+        .clients
+        .get(&chain_id)
+        .ok_or_else(|| ApiError(RouterError::ConfigError(format!("Chain {} is not configured", chain_id))))?;
+    let tx_hash: H256 = tx_hash
+        .parse()
+        .map_err(|e| ApiError(RouterError::ConfigError(format!("Invalid transaction hash {}: {}", tx_hash, e))))?;
+    state
+        .engine
+        .diagnose_failed_tx(&**client, tx_hash)
+        .await
+        .map(Json)
+        .map_err(ApiError)
+}
+
+async fn tokens(State(state): State<AppState>, Query(filter): Query<ChainFilter>) -> Json<Vec<Token>> {
+    Json(state.engine.list_tokens(filter.chain_id))
+}
+
+// Sources by how often they win routes and fill at their quotes
+async fn scoreboard(State(state): State<AppState>, Query(filter): Query<ChainFilter>) -> Json<Vec<scoreboard::SourceStanding>> {
+    Json(state.engine.scoreboard().standings(filter.chain_id))
+}
+
+async fn pairs(State(state): State<AppState>, Query(filter): Query<pair_stats::PairStatsFilter>) -> Json<Vec<pair_stats::PairStats>> {
+    Json(state.engine.pair_stats(&filter))
+}
+
+async fn exchanges(State(state): State<AppState>, Query(filter): Query<ChainFilter>) -> Json<Vec<Exchange>> {
+    Json(state.engine.list_exchanges(filter.chain_id))
+}
+
+// Send a QuoteRequest to subscribe; a fresh QuoteResponse follows right away and
+// after every new block on the request's chain. A new request replaces the old one.
+async fn stream(State(state): State<AppState>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| quote_stream(state, socket))
+}
+
+async fn quote_stream(state: AppState, mut socket: WebSocket) {
+    let mut blocks = state.blocks.subscribe();
+    let mut subscription: Option<(u32, QuoteRequest)> = None;
+
+    loop {
+        let requote = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => match api::parse_quote_request(&text) {
+                    Ok(request) => {
+                        subscription = Some(request);
+                        true
+                    }
+                    Err(e) => {
+                        let error = serde_json::json!({ "error": e.to_string() });
+                        if socket.send(Message::Text(error.to_string())).await.is_err() {
+                            return;
+                        }
+                        false
+                    }
+                },
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => return,
+                Some(Ok(_)) => false,
+            },
+            block = blocks.recv() => match block {
+                Ok((chain_id, _)) => matches!(&subscription, Some((_, request)) if request.chain_id == chain_id),
+                // Missed blocks only mean the next quote covers several
+                Err(broadcast::error::RecvError::Lagged(_)) => subscription.is_some(),
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+
+        let Some((schema_version, request)) = subscription.clone().filter(|_| requote) else {
+            continue;
+        };
+        let payload = match state.engine.find_routes(request).await {
+            Ok(response) => match api::encode_quote_response(schema_version, response) {
+                Ok(response) => response.to_string(),
+                Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+            },
+            Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+        };
+        if socket.send(Message::Text(payload)).await.is_err() {
+            return;
+        }
+    }
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/quote", post(quote))
+        .route("/commit", post(commit_route))
+        .route("/diagnose/:chain_id/:tx_hash", get(diagnose))
+        .route("/tokens", get(tokens))
+        .route("/exchanges", get(exchanges))
+        .route("/scoreboard", get(scoreboard))
+        .route("/pairs", get(pairs))
+        .route("/stream", get(stream))
+        .with_state(state)
+}
+
+// Build the engine from `config`, follow every chain's blocks and serve until shutdown
+pub async fn serve(config: ServerConfig) -> Result<(), RouterError> {
+    let (engine, clients) = build_engine(&config)?;
+    for list in &config.token_lists {
+        let added = if list.starts_with("http://") || list.starts_with("https://") {
+            engine.token_registry().load_list_url(list).await?
+        } else {
+            engine.token_registry().load_list_file(list)?
+        };
+        info!("Loaded {} tokens from {}", added, list);
+    }
+    let state = AppState {
+        engine,
+        blocks: broadcast::channel(1_024).0,
+        clients: Arc::new(clients.iter().cloned().collect()),
+    };
+    for (chain_id, client) in clients {
+        let poll_ms = config
+            .chains
+            .iter()
+            .find(|c| c.chain_id == chain_id)
+            .map(|c| c.block_poll_ms)
+            .unwrap_or_else(default_block_poll_ms);
+        watch_blocks(&state, chain_id, client, Duration::from_millis(poll_ms));
+    }
+
+    let address: SocketAddr = config
+        .listen
+        .parse()
+        .map_err(|e| RouterError::ConfigError(format!("Invalid listen address {}: {}", config.listen, e)))?;
+    info!("Quote server listening on {}", address);
+    axum::Server::bind(&address)
+        .serve(router(state).into_make_service())
+        .await
+        .map_err(|e| RouterError::ConfigError(format!("Server failed: {}", e)))
+}
@@ -0,0 +1,579 @@
+use ethers::abi::{ParamType, Token as AbiToken};
+use ethers::providers::call_raw::{spoof, RawCall};
+use ethers::providers::{Http, RpcError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::keccak256;
+use num_traits::ToPrimitive;
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::adapters::call;
+use crate::execution::ExecutionTx;
+
+// Native balance given to the simulated sender to cover value and gas
+const SIMULATION_ETH: u128 = 1_000_000_000_000_000_000_000;
+
+// Deviations below this are rounding, not findings
+const MIN_DEVIATION_BPS: u32 = 1;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimulationConfig {
+    // Fail quotes whose simulated output falls short of the quote by more than this;
+    // None only reports the deviation
+    #[serde(default)]
+    pub max_deviation_bps: Option<u32>,
+}
+
+// Swap transaction to run, as its sender would send it
+#[derive(Debug, Clone)]
+pub struct SimulationRequest {
+    pub from: Address,
+    pub recipient: Address,
+    pub tx: ExecutionTx,
+    pub token_in: Token,
+    pub token_out: Token,
+    pub amount_in: BigUint,
+}
+
+// Raw result of running a swap; what couldn't be measured is None
+#[derive(Debug, Clone, Default)]
+pub struct SimulationOutcome {
+    // Revert data of the swap itself
+    pub revert: Option<Bytes>,
+    // Output returned by the router or executor call, before transfer taxes
+    pub reported_out: Option<BigUint>,
+    // Balance change of the recipient, only measurable on a fork
+    pub received: Option<BigUint>,
+    // Share of a transfer of the output lost on the way, only measurable on a fork
+    pub sell_tax_bps: Option<u32>,
+    // Revert data of transferring the output onward
+    pub transfer_revert: Option<Bytes>,
+    // Accounts the token contracts report as blacklisted, as (token, account)
+    pub blacklisted: Vec<(String, String)>,
+}
+
+// Runs a swap against current chain state without broadcasting it
+#[async_trait]
+pub trait SimulationBackend: Send + Sync {
+    async fn simulate(&self, request: &SimulationRequest) -> Result<SimulationOutcome, RouterError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SimulationFinding {
+    Reverted { reason: String },
+    OutputShortfall { expected: String, actual: String, deviation_bps: u32 },
+    TransferTax { token: String, buy_bps: u32, sell_bps: u32 },
+    // The output can be bought but not transferred or sold
+    Honeypot { token: String, reason: String },
+    Blacklisted { token: String, account: String },
+}
+
+impl SimulationFinding {
+    pub fn risk(&self) -> u8 {
+        match self {
+            SimulationFinding::Reverted { .. }
+            | SimulationFinding::Honeypot { .. }
+            | SimulationFinding::Blacklisted { .. } => 100,
+            SimulationFinding::OutputShortfall { deviation_bps, .. } => (deviation_bps / 10).min(60) as u8,
+            SimulationFinding::TransferTax { buy_bps, sell_bps, .. } => (20 + (buy_bps + sell_bps) / 100).min(60) as u8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SimulationReport {
+    // Output the recipient got, or the call returned when balances can't be read
+    #[serde(default)]
+    pub actual_amount_out: Option<String>,
+    // Shortfall against expected_amount_out; 10000 when the output can't be had
+    pub deviation_bps: u32,
+    pub findings: Vec<SimulationFinding>,
+}
+
+impl SimulationReport {
+    pub fn risk(&self) -> u8 {
+        self.findings
+            .iter()
+            .fold(0u8, |risk, finding| risk.saturating_add(finding.risk()))
+            .min(100)
+    }
+}
+
+fn shortfall_bps(expected: &BigUint, actual: &BigUint) -> u32 {
+    if actual >= expected || *expected == BigUint::default() {
+        return 0;
+    }
+    let bps = (expected - actual) * BigUint::from(10_000u32) / expected;
+    bps.to_u32().unwrap_or(10_000)
+}
+
+// Output of a router or executor call: the last entry of a uint[] (multiSwap,
+// swapExactTokensForTokens) or a single uint (exactInput, exchange)
+fn decode_output(data: &[u8]) -> Option<BigUint> {
+    if let Ok(tokens) = ethers::abi::decode(&[ParamType::Array(Box::new(ParamType::Uint(256)))], data) {
+        if let Some(AbiToken::Array(outputs)) = tokens.into_iter().next() {
+            if let Some(AbiToken::Uint(last)) = outputs.last() {
+                return Some(math::from_u256(*last));
+            }
+        }
+    }
+    match ethers::abi::decode(&[ParamType::Uint(256)], data).ok()?.first() {
+        Some(AbiToken::Uint(value)) => Some(math::from_u256(*value)),
+        _ => None,
+    }
+}
+
+fn transaction(from: Address, tx: &ExecutionTx) -> Result<TypedTransaction, RouterError> {
+    let data = hex::decode(tx.data.trim_start_matches("0x"))
+        .map_err(|e| RouterError::ExecutionError(format!("Invalid calldata: {}", e)))?;
+    let value = U256::from_dec_str(&tx.value)
+        .map_err(|e| RouterError::ExecutionError(format!("Invalid value {}: {}", tx.value, e)))?;
+    Ok(TransactionRequest::new()
+        .from(from)
+        .to(parse_address(&tx.to)?)
+        .data(data)
+        .value(value)
+        .into())
+}
+
+fn transfer_call(token: Address, from: Address, to: Address, amount: &BigUint) -> Result<TypedTransaction, RouterError> {
+    let data = encode_call("transfer(address,uint256)", &[AbiToken::Address(to), AbiToken::Uint(math::to_u256(amount)?)]);
+    Ok(TransactionRequest::new().from(from).to(token).data(data).into())
+}
+
+fn is_native(token: &Token) -> bool {
+    matches!(parse_address(&token.address), Ok(address) if address == Address::zero())
+}
+
+// Revert data of a failed call, or None for errors unrelated to the call itself
+fn revert_data<E: RpcError>(e: &E) -> Option<Bytes> {
+    e.as_error_response().and_then(|response| response.as_revert_data())
+}
+
+async fn balance_of<M: Middleware>(client: &M, token: Address, holder: Address) -> Result<BigUint, RouterError> {
+    let data = encode_call("balanceOf(address)", &[AbiToken::Address(holder)]);
+    match call(client, token, data, &[ParamType::Uint(256)]).await?.first() {
+        Some(AbiToken::Uint(balance)) => Ok(math::from_u256(*balance)),
+        _ => Err(RouterError::ChainError(format!("Invalid balanceOf from {:?}", token))),
+    }
+}
+
+// USDT-style isBlackListed and USDC-style isBlacklisted; tokens without either pass
+async fn blacklisted<M: Middleware>(client: &M, token: &Token, accounts: &[Address]) -> Vec<(String, String)> {
+    let Ok(address) = parse_address(&token.address) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    for account in accounts {
+        for signature in ["isBlackListed(address)", "isBlacklisted(address)"] {
+            let data = encode_call(signature, &[AbiToken::Address(*account)]);
+            if let Ok(result) = call(client, address, data, &[ParamType::Bool]).await {
+                if let Some(AbiToken::Bool(true)) = result.first() {
+                    found.push((token.address.clone(), format!("{:?}", account)));
+                    break;
+                }
+            }
+        }
+    }
+    found
+}
+
+// Solidity storage slots of a token's balance and allowance mappings
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TokenSlots {
+    pub balances: u64,
+    pub allowances: Option<u64>,
+}
+
+impl TokenSlots {
+    fn balance_key(&self, holder: Address) -> H256 {
+        mapping_key(holder, H256::from_low_u64_be(self.balances))
+    }
+
+    fn allowance_key(&self, owner: Address, spender: Address) -> Option<H256> {
+        let slot = self.allowances?;
+        Some(mapping_key(spender, mapping_key(owner, H256::from_low_u64_be(slot))))
+    }
+}
+
+// keccak256(abi.encode(key, slot))
+fn mapping_key(key: Address, slot: H256) -> H256 {
+    let mut preimage = [0u8; 64];
+    preimage[12..32].copy_from_slice(key.as_bytes());
+    preimage[32..].copy_from_slice(slot.as_bytes());
+    H256::from(keccak256(preimage))
+}
+
+fn word(amount: &BigUint) -> Result<H256, RouterError> {
+    let mut bytes = [0u8; 32];
+    math::to_u256(amount)?.to_big_endian(&mut bytes);
+    Ok(H256::from(bytes))
+}
+
+// eth_call with state overrides against any node supporting them. The sender is
+// funded with ETH and, for tokens with known slots, the input balance and
+// allowance, so swaps can be simulated before the user approves. Balances after
+// the swap aren't observable, so transfer taxes are only caught on a fork.
+pub struct EthCallSimulator<M: Middleware> {
+    client: Arc<M>,
+    slots: DashMap<String, TokenSlots>,
+}
+
+impl<M: Middleware + 'static> EthCallSimulator<M> {
+    pub fn new(client: Arc<M>) -> Self {
+        Self {
+            client,
+            slots: DashMap::new(),
+        }
+    }
+
+    pub fn register_slots(&self, token: &str, slots: TokenSlots) {
+        self.slots.insert(token.to_lowercase(), slots);
+    }
+
+    fn slots(&self, token: &Token) -> Option<TokenSlots> {
+        self.slots.get(&token.address.to_lowercase()).map(|s| *s)
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> SimulationBackend for EthCallSimulator<M> {
+    async fn simulate(&self, request: &SimulationRequest) -> Result<SimulationOutcome, RouterError> {
+        let provider = self.client.provider();
+        let spender = parse_address(&request.tx.to)?;
+
+        let mut state = spoof::state();
+        state.account(request.from).balance(U256::from(SIMULATION_ETH));
+        if let (Some(slots), Ok(token)) = (self.slots(&request.token_in), parse_address(&request.token_in.address)) {
+            let account = state.account(token);
+            account.store(slots.balance_key(request.from), word(&request.amount_in)?);
+            if let Some(key) = slots.allowance_key(request.from, spender) {
+                account.store(key, H256::repeat_byte(0xff));
+            }
+        }
+
+        let mut outcome = SimulationOutcome {
+            blacklisted: [
+                blacklisted(&*self.client, &request.token_in, &[request.from]).await,
+                blacklisted(&*self.client, &request.token_out, &[request.recipient]).await,
+            ]
+            .concat(),
+            ..Default::default()
+        };
+
+        let tx = transaction(request.from, &request.tx)?;
+        match provider.call_raw(&tx).state(&state).await {
+            Ok(data) => outcome.reported_out = decode_output(&data),
+            Err(e) => match revert_data(&e) {
+                Some(data) => {
+                    outcome.revert = Some(data);
+                    return Ok(outcome);
+                }
+                None => return Err(RouterError::ChainError(format!("Simulation call failed: {}", e))),
+            },
+        }
+
+        // Hand the recipient the output and try to move it on, as a seller would
+        let (Some(amount), Some(slots), false) = (&outcome.reported_out, self.slots(&request.token_out), is_native(&request.token_out)) else {
+            return Ok(outcome);
+        };
+        let token = parse_address(&request.token_out.address)?;
+        state.account(token).store(slots.balance_key(request.recipient), word(amount)?);
+        let transfer = transfer_call(token, request.recipient, spender, amount)?;
+        if let Err(e) = provider.call_raw(&transfer).state(&state).await {
+            outcome.transfer_revert = revert_data(&e).or_else(|| Some(Bytes::default()));
+        }
+        Ok(outcome)
+    }
+}
+
+// Node of a local or hosted mainnet fork
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ForkKind {
+    Anvil,
+    // Tenderly virtual testnets, whose accounts are unlocked
+    Tenderly,
+}
+
+// Executes the swap on a fork and reverts to a snapshot afterwards, measuring
+// what actually arrives and what survives a further transfer
+pub struct ForkSimulator {
+    provider: Provider<Http>,
+    kind: ForkKind,
+}
+
+impl ForkSimulator {
+    pub fn new(url: &str, kind: ForkKind) -> Result<Self, RouterError> {
+        let provider = Provider::<Http>::try_from(url)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid fork URL {}: {}", url, e)))?;
+        Ok(Self { provider, kind })
+    }
+
+    async fn rpc<T, R>(&self, method: &str, params: T) -> Result<R, RouterError>
+    where
+        T: std::fmt::Debug + Serialize + Send + Sync,
+        R: serde::de::DeserializeOwned + Serialize + std::fmt::Debug + Send,
+    {
+        self.provider
+            .request(method, params)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("{} failed: {}", method, e)))
+    }
+
+    async fn unlock(&self, account: Address) -> Result<(), RouterError> {
+        let balance = U256::from(SIMULATION_ETH);
+        match self.kind {
+            ForkKind::Anvil => {
+                self.rpc::<_, ()>("anvil_setBalance", (account, balance)).await?;
+                self.rpc::<_, ()>("anvil_impersonateAccount", [account]).await
+            }
+            ForkKind::Tenderly => self.rpc::<_, serde_json::Value>("tenderly_setBalance", (vec![account], balance)).await.map(|_| ()),
+        }
+    }
+
+    // Balance of `token` held by `holder`, native for address(0)
+    async fn balance(&self, token: &Token, holder: Address) -> Result<BigUint, RouterError> {
+        if is_native(token) {
+            let balance = self
+                .provider
+                .get_balance(holder, None)
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to fetch balance: {}", e)))?;
+            return Ok(math::from_u256(balance));
+        }
+        balance_of(&self.provider, parse_address(&token.address)?, holder).await
+    }
+
+    // Call first for revert data, then send and mine
+    async fn execute(&self, tx: &TypedTransaction) -> Result<Option<Bytes>, RouterError> {
+        let returned = match self.provider.call(tx, None).await {
+            Ok(data) => data,
+            Err(e) => match revert_data(&e) {
+                Some(data) => return Err(RouterError::Reverted(format!("0x{}", hex::encode(data)))),
+                None => return Err(RouterError::ChainError(format!("Simulation call failed: {}", e))),
+            },
+        };
+        let receipt = self
+            .provider
+            .send_transaction(tx.clone(), None)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to send simulated transaction: {}", e)))?
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Simulated transaction dropped: {}", e)))?;
+        match receipt.and_then(|r| r.status) {
+            Some(status) if status.as_u64() == 1 => Ok(Some(returned)),
+            _ => Ok(None),
+        }
+    }
+
+    async fn run(&self, request: &SimulationRequest) -> Result<SimulationOutcome, RouterError> {
+        self.unlock(request.from).await?;
+        self.unlock(request.recipient).await?;
+        let mut outcome = SimulationOutcome {
+            blacklisted: [
+                blacklisted(&self.provider, &request.token_in, &[request.from]).await,
+                blacklisted(&self.provider, &request.token_out, &[request.recipient]).await,
+            ]
+            .concat(),
+            ..Default::default()
+        };
+
+        let before = self.balance(&request.token_out, request.recipient).await?;
+        let tx = transaction(request.from, &request.tx)?;
+        match self.execute(&tx).await {
+            Ok(Some(returned)) => outcome.reported_out = decode_output(&returned),
+            Ok(None) => {
+                outcome.revert = Some(Bytes::default());
+                return Ok(outcome);
+            }
+            Err(RouterError::Reverted(data)) => {
+                outcome.revert = Some(hex::decode(data.trim_start_matches("0x")).unwrap_or_default().into());
+                return Ok(outcome);
+            }
+            Err(e) => return Err(e),
+        }
+        let after = self.balance(&request.token_out, request.recipient).await?;
+        // Gas paid by a recipient that is also the sender makes native deltas unreliable
+        if is_native(&request.token_out) && request.recipient == request.from {
+            return Ok(outcome);
+        }
+        let received = if after > before { after - before } else { BigUint::default() };
+        outcome.received = Some(received.clone());
+        if is_native(&request.token_out) || received == BigUint::default() {
+            return Ok(outcome);
+        }
+
+        // Sell side: move the output to a fresh address and see what lands
+        let sink = Address::random();
+        let transfer = transfer_call(parse_address(&request.token_out.address)?, request.recipient, sink, &received)?;
+        match self.execute(&transfer).await {
+            Ok(Some(_)) => {
+                let landed = self.balance(&request.token_out, sink).await?;
+                outcome.sell_tax_bps = Some(shortfall_bps(&received, &landed));
+            }
+            Ok(None) => outcome.transfer_revert = Some(Bytes::default()),
+            Err(RouterError::Reverted(data)) => {
+                outcome.transfer_revert = Some(hex::decode(data.trim_start_matches("0x")).unwrap_or_default().into());
+            }
+            Err(e) => return Err(e),
+        }
+        Ok(outcome)
+    }
+}
+
+#[async_trait]
+impl SimulationBackend for ForkSimulator {
+    async fn simulate(&self, request: &SimulationRequest) -> Result<SimulationOutcome, RouterError> {
+        let snapshot: U256 = self.rpc("evm_snapshot", ()).await?;
+        let outcome = self.run(request).await;
+        if let Err(e) = self.rpc::<_, bool>("evm_revert", [snapshot]).await {
+            warn!("Failed to revert simulation fork: {}", e);
+        }
+        outcome
+    }
+}
+
+impl RouterEngine {
+    pub fn set_simulator(&self, backend: Arc<dyn SimulationBackend>) {
+        *self.simulator.write().unwrap() = Some(backend);
+    }
+
+    pub fn set_simulation_config(&self, config: SimulationConfig) {
+        *self.simulation.write().unwrap() = config;
+    }
+
+    pub fn simulation_config(&self) -> SimulationConfig {
+        self.simulation.read().unwrap().clone()
+    }
+
+    // Run the route's transaction through the simulator and judge the outcome.
+    // Transfer taxes found on the way are registered for later quotes.
+    pub async fn simulate_route(
+        &self,
+        route: &SwapRoute,
+        tx: &ExecutionTx,
+        from: &str,
+        recipient: &str,
+    ) -> Result<Option<SimulationReport>, RouterError> {
+        let Some(backend) = self.simulator.read().unwrap().clone() else {
+            return Ok(None);
+        };
+        let (Some(first), Some(last)) = (route.steps.first(), route.steps.last()) else {
+            return Ok(None);
+        };
+        let request = SimulationRequest {
+            from: parse_address(from)?,
+            recipient: parse_address(recipient)?,
+            tx: tx.clone(),
+            token_in: first.token_in.clone(),
+            token_out: last.token_out.clone(),
+            amount_in: math::parse_amount(&route.amount_in)?,
+        };
+        let outcome = backend.simulate(&request).await?;
+        Ok(Some(self.judge_simulation(route, &request, outcome)))
+    }
+
+    fn judge_simulation(&self, route: &SwapRoute, request: &SimulationRequest, outcome: SimulationOutcome) -> SimulationReport {
+        let token_out = &request.token_out;
+        let mut report = SimulationReport::default();
+        for (token, account) in outcome.blacklisted {
+            report.findings.push(SimulationFinding::Blacklisted { token, account });
+        }
+
+        if let Some(data) = outcome.revert {
+            let reason = if data.is_empty() {
+                "reverted without data".to_string()
+            } else {
+                self.decode_revert(&data).to_string()
+            };
+            report.findings.push(SimulationFinding::Reverted { reason });
+            report.deviation_bps = 10_000;
+            return report;
+        }
+        if let Some(data) = outcome.transfer_revert {
+            let reason = if data.is_empty() {
+                "transfer reverted without data".to_string()
+            } else {
+                self.decode_revert(&data).to_string()
+            };
+            report.findings.push(SimulationFinding::Honeypot { token: token_out.address.clone(), reason });
+            report.deviation_bps = 10_000;
+        }
+
+        // Taxes beyond the ones already applied to the quote
+        let known = self.token_tax(token_out).unwrap_or_default();
+        let buy_bps = match (&outcome.reported_out, &outcome.received) {
+            (Some(reported), Some(received)) => shortfall_bps(reported, received),
+            _ => 0,
+        };
+        let sell_bps = outcome.sell_tax_bps.unwrap_or_default();
+        if buy_bps > known.buy_bps + MIN_DEVIATION_BPS || sell_bps > known.sell_bps + MIN_DEVIATION_BPS {
+            let tax = tax::TokenTax {
+                buy_bps: buy_bps.max(known.buy_bps),
+                sell_bps: sell_bps.max(known.sell_bps),
+            };
+            self.register_token_tax(token_out.chain_id, &token_out.address, tax);
+            report.findings.push(SimulationFinding::TransferTax {
+                token: token_out.address.clone(),
+                buy_bps: tax.buy_bps,
+                sell_bps: tax.sell_bps,
+            });
+        }
+
+        // The recipient's balance change when measured, else the call's (pre-tax) output
+        let (expected, actual) = match (&outcome.received, &outcome.reported_out) {
+            (Some(received), _) => (math::parse_amount(&route.expected_amount_out).ok(), Some(received.clone())),
+            (None, Some(reported)) => (
+                math::parse_amount(route.gross_amount_out.as_ref().unwrap_or(&route.expected_amount_out)).ok(),
+                Some(reported.clone()),
+            ),
+            (None, None) => (None, None),
+        };
+        if let (Some(expected), Some(actual)) = (expected, actual) {
+            let deviation_bps = shortfall_bps(&expected, &actual);
+            report.actual_amount_out = Some(actual.to_string());
+            report.deviation_bps = report.deviation_bps.max(deviation_bps);
+            if deviation_bps >= MIN_DEVIATION_BPS {
+                report.findings.push(SimulationFinding::OutputShortfall {
+                    expected: expected.to_string(),
+                    actual: actual.to_string(),
+                    deviation_bps,
+                });
+            }
+        }
+        report
+    }
+
+    // Simulate the route, fold the findings into its risk_score, and fail the
+    // quote when the configured deviation limit is exceeded
+    pub(crate) async fn apply_simulation(
+        &self,
+        route: &mut SwapRoute,
+        tx: &ExecutionTx,
+        from: &str,
+        recipient: &str,
+    ) -> Result<(), RouterError> {
+        let Some(report) = self.simulate_route(route, tx, from, recipient).await? else {
+            return Ok(());
+        };
+        route.risk_score = route.risk_score.saturating_add(report.risk()).min(100);
+        if let Some(max) = self.simulation_config().max_deviation_bps {
+            if report.deviation_bps > max {
+                let detail = report
+                    .findings
+                    .iter()
+                    .map(|f| format!("{:?}", f))
+                    .collect::<Vec<_>>()
+                    .join("; ");
+                return Err(RouterError::SimulationFailed(format!(
+                    "output {} bps below quote (limit {} bps): {}",
+                    report.deviation_bps, max, detail
+                )));
+            }
+        }
+        route.simulation = Some(report);
+        Ok(())
+    }
+}
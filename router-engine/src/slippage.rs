@@ -0,0 +1,255 @@
+use std::collections::VecDeque;
+
+use num_traits::ToPrimitive;
+
+use super::*;
+
+// Bounds for automatically chosen slippage, in percent
+pub const MIN_AUTO_SLIPPAGE: f64 = 0.05;
+pub const MAX_AUTO_SLIPPAGE: f64 = 5.0;
+
+// Volatility assumed for pairs without enough price history, in percent
+pub const DEFAULT_VOLATILITY: f64 = 0.25;
+
+// Token classes used to pick a default slippage when the caller gives none;
+// unclassified tokens are treated as long-tail
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenClass {
+    Stable,
+    Major,
+    #[default]
+    Longtail,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairCategory {
+    StableStable,
+    MajorMajor,
+    MajorLongtail,
+    Exotic,
+}
+
+impl PairCategory {
+    // Stables pair with majors as major-major
+    pub fn of(a: TokenClass, b: TokenClass) -> Self {
+        match (a, b) {
+            (TokenClass::Stable, TokenClass::Stable) => PairCategory::StableStable,
+            (TokenClass::Longtail, TokenClass::Longtail) => PairCategory::Exotic,
+            (TokenClass::Longtail, _) | (_, TokenClass::Longtail) => PairCategory::MajorLongtail,
+            _ => PairCategory::MajorMajor,
+        }
+    }
+}
+
+// Default slippage per pair category, in percent
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SlippagePresets {
+    pub stable_stable: f64,
+    pub major_major: f64,
+    pub major_longtail: f64,
+    pub exotic: f64,
+}
+
+impl SlippagePresets {
+    pub fn for_category(&self, category: PairCategory) -> f64 {
+        match category {
+            PairCategory::StableStable => self.stable_stable,
+            PairCategory::MajorMajor => self.major_major,
+            PairCategory::MajorLongtail => self.major_longtail,
+            PairCategory::Exotic => self.exotic,
+        }
+    }
+}
+
+impl Default for SlippagePresets {
+    fn default() -> Self {
+        Self {
+            stable_stable: 0.05,
+            major_major: 0.3,
+            major_longtail: 1.0,
+            exotic: 3.0,
+        }
+    }
+}
+
+// Rolling window of recent prices per token pair
+pub struct VolatilityTracker {
+    window: usize,
+    observations: DashMap<(Token, Token), VecDeque<f64>>,
+}
+
+impl VolatilityTracker {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(2),
+            observations: DashMap::new(),
+        }
+    }
+
+    pub fn record(&self, token_in: &Token, token_out: &Token, price: f64) {
+        if !price.is_finite() || price <= 0.0 {
+            return;
+        }
+
+        let mut prices = self
+            .observations
+            .entry((token_in.clone(), token_out.clone()))
+            .or_default();
+        prices.push_back(price);
+        while prices.len() > self.window {
+            prices.pop_front();
+        }
+    }
+
+    // Standard deviation of log returns over the window, in percent.
+    // The inverse pair has the same volatility so either direction is used.
+    pub fn volatility(&self, token_in: &Token, token_out: &Token) -> Option<f64> {
+        let prices = self
+            .observations
+            .get(&(token_in.clone(), token_out.clone()))
+            .or_else(|| self.observations.get(&(token_out.clone(), token_in.clone())))?;
+        if prices.len() < 2 {
+            return None;
+        }
+
+        let returns: Vec<f64> = prices
+            .iter()
+            .zip(prices.iter().skip(1))
+            .map(|(prev, next)| (next / prev).ln())
+            .collect();
+        let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / returns.len() as f64;
+
+        Some(variance.sqrt() * 100.0)
+    }
+}
+
+impl Default for VolatilityTracker {
+    fn default() -> Self {
+        Self::new(32)
+    }
+}
+
+// Slippage for a single hop: two standard deviations of price drift plus half of the
+// share of the input reserve the trade consumes, so thin pools get more headroom
+pub fn step_slippage(volatility: f64, depth_usage: f64) -> f64 {
+    2.0 * volatility + depth_usage * 50.0
+}
+
+// Fraction of `reserve_in` consumed by `amount_in`
+pub fn depth_usage(amount_in: &BigUint, reserve_in: &BigUint) -> f64 {
+    let amount = amount_in.to_f64().unwrap_or(0.0);
+    let reserve = reserve_in.to_f64().unwrap_or(0.0);
+    if reserve <= 0.0 {
+        return 1.0;
+    }
+    (amount / reserve).min(1.0)
+}
+
+pub fn clamp_auto_slippage(slippage: f64) -> f64 {
+    slippage.clamp(MIN_AUTO_SLIPPAGE, MAX_AUTO_SLIPPAGE)
+}
+
+// Reduce `amount` by `slippage` percent, rounding down
+pub fn apply_slippage(amount: &BigUint, slippage: f64) -> BigUint {
+    let bps = (slippage * 100.0).round().clamp(0.0, 10_000.0) as u64;
+    math::bps_of(amount, 10_000 - bps, math::Rounding::Down)
+}
+
+// Increase `amount` by `slippage` percent, rounding up
+pub fn add_slippage(amount: &BigUint, slippage: f64) -> BigUint {
+    let bps = (slippage * 100.0).round().max(0.0) as u64;
+    math::bps_of(amount, 10_000 + bps, math::Rounding::Up)
+}
+
+// Record the chosen slippage on a route and derive every step's minimum output from
+// it. Steps delivering the output token share the route's expected output (net of
+// fees and taxes) pro rata; intermediate steps use their own quoted output.
+pub fn apply_route_slippage(route: &mut SwapRoute, slippage: f64) -> Result<(), RouterError> {
+    let expected = math::parse_amount(&route.expected_amount_out)?;
+    let Some(token_out) = route.steps.last().map(|step| step.token_out.clone()) else {
+        route.slippage = Some(slippage);
+        return Ok(());
+    };
+
+    let quoted = |step: &SwapStep| step.expected_amount_out.as_deref().map(math::parse_amount).transpose();
+    let mut final_quoted = BigUint::default();
+    for step in route.steps.iter().filter(|step| step.token_out == token_out) {
+        final_quoted += quoted(step)?.unwrap_or_default();
+    }
+
+    let last = route.steps.len() - 1;
+    for (i, step) in route.steps.iter_mut().enumerate() {
+        let step_expected = if step.token_out == token_out {
+            match quoted(step)? {
+                Some(step_out) if final_quoted > BigUint::default() => {
+                    math::mul_div(&step_out, &expected, &final_quoted, math::Rounding::Down)
+                }
+                // Unquoted steps only occur in single-path routes, where the last one delivers everything
+                _ if i == last => expected.clone(),
+                _ => continue,
+            }
+        } else {
+            match quoted(step)? {
+                Some(step_out) => step_out,
+                None => continue,
+            }
+        };
+        step.amount_out_min = apply_slippage(&step_expected, slippage).to_string();
+    }
+    route.slippage = Some(slippage);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(address: &str) -> Token {
+        Token {
+            chain_id: 1,
+            address: address.to_string(),
+            symbol: address.to_string(),
+            decimals: 18,
+        }
+    }
+
+    fn step(token_in: &str, token_out: &str, amount_in: u64, expected_out: u64) -> SwapStep {
+        SwapStep {
+            exchange_id: "x".to_string(),
+            token_in: token(token_in),
+            token_out: token(token_out),
+            fee_tier: None,
+            amount_in: amount_in.to_string(),
+            amount_out_min: expected_out.to_string(),
+            expected_amount_out: Some(expected_out.to_string()),
+            firmness: rfq::Firmness::Indicative,
+        }
+    }
+
+    #[test]
+    fn split_route_min_outs_share_net_output_pro_rata() {
+        // Legs A->B and A->C->B quoted at 1190 + 790 = 1980 B; 30 B of fees leave 1950
+        let mut route = SwapRoute {
+            steps: vec![step("A", "B", 600, 1_190), step("A", "C", 400, 796), step("C", "B", 796, 790)],
+            amount_in: "1000".to_string(),
+            expected_amount_out: "1950".to_string(),
+            ..Default::default()
+        };
+        apply_route_slippage(&mut route, 1.0).unwrap();
+
+        let min_outs: Vec<&str> = route.steps.iter().map(|step| step.amount_out_min.as_str()).collect();
+        // 1190 * 1950 / 1980 = 1171.9 -> 1171, less 1% = 1159.29 -> 1159
+        // 796 of C less 1% = 788.04 -> 788, from its own quote
+        // 790 * 1950 / 1980 = 778.03 -> 778, less 1% = 770.22 -> 770
+        assert_eq!(min_outs, ["1159", "788", "770"]);
+        assert_eq!(route.slippage, Some(1.0));
+
+        // Rounding down per leg keeps the legs within the route's own bound
+        let total: BigUint = [1159u64, 770].iter().map(|&m| BigUint::from(m)).sum();
+        assert!(total <= apply_slippage(&BigUint::from(1_950u64), 1.0));
+    }
+}
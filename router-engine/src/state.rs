@@ -0,0 +1,193 @@
+use num_traits::ToPrimitive;
+
+use super::*;
+
+// Reserves of a single pool as of a block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolState {
+    pub chain_id: u64,
+    pub exchange_id: String,
+    pub pool: String,
+    pub token_a: Token,
+    pub token_b: Token,
+    pub reserve_a: String,
+    pub reserve_b: String,
+    pub fee_tier: u32,
+    pub block_number: u64,
+    // Current tick of concentrated-liquidity pools, whose reserves are virtual
+    #[serde(default)]
+    pub tick: Option<i32>,
+}
+
+impl PoolState {
+    // Reserves oriented as (token_in, token_out), or None if the pool doesn't hold the pair
+    pub fn reserves_for(&self, token_in: &Token, token_out: &Token) -> Option<Result<(BigUint, BigUint), RouterError>> {
+        let (reserve_in, reserve_out) = if self.token_a == *token_in && self.token_b == *token_out {
+            (&self.reserve_a, &self.reserve_b)
+        } else if self.token_b == *token_in && self.token_a == *token_out {
+            (&self.reserve_b, &self.reserve_a)
+        } else {
+            return None;
+        };
+
+        Some(math::parse_amount(reserve_in).and_then(|r_in| Ok((r_in, math::parse_amount(reserve_out)?))))
+    }
+}
+
+// Latest known state per pool, fed by chain sync or by replicated deltas
+#[derive(Default)]
+pub struct PoolStateStore {
+    pools: DashMap<(u64, String), PoolState>,
+}
+
+impl PoolStateStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Keep `state` unless the store already has a newer block for the pool
+    pub fn apply(&self, state: PoolState) -> bool {
+        let key = (state.chain_id, state.pool.to_lowercase());
+        // Release the read guard before inserting into the same shard
+        if matches!(self.pools.get(&key), Some(existing) if existing.block_number > state.block_number) {
+            return false;
+        }
+        self.pools.insert(key, state);
+        true
+    }
+
+    pub fn get(&self, chain_id: u64, pool: &str) -> Option<PoolState> {
+        self.pools.get(&(chain_id, pool.to_lowercase())).map(|p| p.clone())
+    }
+
+    // Pools of `exchange_id` trading the pair, in either orientation
+    pub fn pools_for(&self, chain_id: u64, exchange_id: &str, token_a: &Token, token_b: &Token) -> Vec<PoolState> {
+        self.pools
+            .iter()
+            .filter(|p| p.chain_id == chain_id && p.exchange_id == exchange_id)
+            .filter(|p| p.reserves_for(token_a, token_b).is_some())
+            .map(|p| p.clone())
+            .collect()
+    }
+
+    // Every pool known on a chain
+    pub fn snapshot(&self, chain_id: u64) -> Vec<PoolState> {
+        self.pools
+            .iter()
+            .filter(|p| p.chain_id == chain_id)
+            .map(|p| p.clone())
+            .collect()
+    }
+
+    // Pools of a chain whose block or reserves differ from `other`'s, or that
+    // only one of the stores has, e.g. a live store against one rebuilt by replay
+    pub fn divergence(&self, other: &PoolStateStore, chain_id: u64) -> Vec<String> {
+        let ours = self.snapshot(chain_id);
+        let theirs = other.snapshot(chain_id);
+        let mut pools: Vec<String> = ours
+            .iter()
+            .chain(theirs.iter())
+            .map(|state| state.pool.to_lowercase())
+            .filter(|pool| match (self.get(chain_id, pool), other.get(chain_id, pool)) {
+                (Some(a), Some(b)) => a.block_number != b.block_number || a.reserve_a != b.reserve_a || a.reserve_b != b.reserve_b,
+                _ => true,
+            })
+            .collect();
+        pools.sort();
+        pools.dedup();
+        pools
+    }
+
+    pub fn len(&self) -> usize {
+        self.pools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pools.is_empty()
+    }
+}
+
+// Liquidity source quoting constant-product pools from a PoolStateStore
+pub struct StateBackedSource {
+    chain_id: u64,
+    exchange_id: String,
+    store: Arc<PoolStateStore>,
+}
+
+impl StateBackedSource {
+    pub fn new(chain_id: u64, exchange_id: String, store: Arc<PoolStateStore>) -> Self {
+        Self {
+            chain_id,
+            exchange_id,
+            store,
+        }
+    }
+
+    // Deepest pool for the pair by input-side reserve
+    fn deepest_pool(&self, token_in: &Token, token_out: &Token) -> Result<(PoolState, BigUint, BigUint), RouterError> {
+        let mut best: Option<(PoolState, BigUint, BigUint)> = None;
+
+        for pool in self.store.pools_for(self.chain_id, &self.exchange_id, token_in, token_out) {
+            if let Some(reserves) = pool.reserves_for(token_in, token_out) {
+                let (reserve_in, reserve_out) = reserves?;
+                let deeper = match &best {
+                    Some((_, best_in, _)) => reserve_in > *best_in,
+                    None => true,
+                };
+                if deeper {
+                    best = Some((pool, reserve_in, reserve_out));
+                }
+            }
+        }
+
+        best.ok_or_else(|| {
+            RouterError::InsufficientLiquidity(format!(
+                "No {} pool for {}/{}",
+                self.exchange_id, token_in.symbol, token_out.symbol
+            ))
+        })
+    }
+}
+
+#[async_trait]
+impl LiquiditySource for StateBackedSource {
+    async fn get_quote(
+        &self,
+        token_in: &Token,
+        token_out: &Token,
+        amount_in: &BigUint,
+    ) -> Result<(BigUint, f64), RouterError> {
+        let (pool, reserve_in, reserve_out) = self.deepest_pool(token_in, token_out)?;
+        let amount_out = math::get_amount_out(amount_in, &reserve_in, &reserve_out, pool.fee_tier);
+
+        let amount = amount_in.to_f64().unwrap_or(0.0);
+        let reserve = reserve_in.to_f64().unwrap_or(0.0);
+        let price_impact = if amount + reserve > 0.0 { amount / (amount + reserve) } else { 1.0 };
+
+        Ok((amount_out, price_impact))
+    }
+
+    async fn get_reserves(
+        &self,
+        token_a: &Token,
+        token_b: &Token,
+    ) -> Result<(BigUint, BigUint), RouterError> {
+        let (_, reserve_a, reserve_b) = self.deepest_pool(token_a, token_b)?;
+        Ok((reserve_a, reserve_b))
+    }
+
+    fn state_block(&self, token_a: &Token, token_b: &Token) -> Option<u64> {
+        self.deepest_pool(token_a, token_b).ok().map(|(pool, ..)| pool.block_number)
+    }
+
+    fn known_pairs(&self) -> Option<Vec<(Token, Token)>> {
+        Some(
+            self.store
+                .snapshot(self.chain_id)
+                .into_iter()
+                .filter(|pool| pool.exchange_id == self.exchange_id && pool.reserve_a != "0" && pool.reserve_b != "0")
+                .map(|pool| (pool.token_a, pool.token_b))
+                .collect(),
+        )
+    }
+}
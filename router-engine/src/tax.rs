@@ -0,0 +1,70 @@
+use super::*;
+
+// Transfer taxes of a fee-on-transfer token, in basis points
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenTax {
+    // Taken when the token is bought from a pool
+    pub buy_bps: u32,
+    // Taken when the token is sold into a pool
+    pub sell_bps: u32,
+}
+
+fn deduct(amount: &BigUint, bps: u32) -> BigUint {
+    math::bps_of(amount, 10_000 - bps.min(10_000) as u64, math::Rounding::Down)
+}
+
+fn net_of_steps<F>(steps: &[SwapStep], gross: &BigUint, tax_of: &F) -> BigUint
+where
+    F: Fn(&Token) -> Option<TokenTax>,
+{
+    let mut net = gross.clone();
+    for step in steps {
+        if let Some(tax) = tax_of(&step.token_in) {
+            net = deduct(&net, tax.sell_bps);
+        }
+        if let Some(tax) = tax_of(&step.token_out) {
+            net = deduct(&net, tax.buy_bps);
+        }
+    }
+    net
+}
+
+// Output after every hop's sell and buy taxes. Pool quotes are pre-tax: selling a
+// taxed token delivers less to the pool, and buying one delivers less to the
+// receiver, so each applicable tax scales the final output down. Split routes are
+// taxed per leg, each leg carrying its share of `gross`.
+pub fn net_amount_out<F>(route: &SwapRoute, gross: &BigUint, tax_of: F) -> BigUint
+where
+    F: Fn(&Token) -> Option<TokenTax>,
+{
+    if route.splits.is_empty() {
+        return net_of_steps(&route.steps, gross, &tax_of);
+    }
+
+    let legs = routing::legs(route);
+    let quoted: BigUint = legs.iter().map(|(_, out)| out).sum();
+    if quoted == BigUint::default() {
+        return net_of_steps(&route.steps, gross, &tax_of);
+    }
+    legs.iter()
+        .map(|(steps, out)| net_of_steps(steps, &(gross * out / &quoted), &tax_of))
+        .sum()
+}
+
+// Replace the route's expected output with the post-tax amount, keeping the
+// pre-tax one in gross_amount_out, so amount_out_min is derived from what the
+// receiver actually gets. Returns whether any tax applied.
+pub fn apply_taxes<F>(route: &mut SwapRoute, tax_of: F) -> Result<bool, RouterError>
+where
+    F: Fn(&Token) -> Option<TokenTax>,
+{
+    let gross = math::parse_amount(&route.expected_amount_out)?;
+    let net = net_amount_out(route, &gross, tax_of);
+    if net == gross {
+        return Ok(false);
+    }
+
+    route.gross_amount_out = Some(gross.to_string());
+    route.expected_amount_out = net.to_string();
+    Ok(true)
+}
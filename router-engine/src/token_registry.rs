@@ -0,0 +1,199 @@
+use std::path::Path;
+
+use ethers::abi::{ParamType, Token as AbiToken};
+
+use super::*;
+use crate::abi_registry::encode_call;
+use crate::adapters::call;
+
+// Entry of a Uniswap-style token list (https://tokenlists.org)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenListEntry {
+    pub chain_id: u64,
+    pub address: String,
+    pub symbol: String,
+    pub decimals: u8,
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenList {
+    #[serde(default)]
+    pub name: String,
+    pub tokens: Vec<TokenListEntry>,
+}
+
+// Symbol and decimals of a token contract
+#[async_trait]
+pub trait TokenMetadataSource: Send + Sync {
+    async fn metadata(&self, address: &str) -> Result<(String, u8), RouterError>;
+}
+
+// Reads symbol() and decimals() from the chain. Symbols may be bytes32 (e.g. MKR)
+// or missing; decimals are required.
+pub struct OnChainMetadata<M: Middleware> {
+    client: Arc<M>,
+}
+
+impl<M: Middleware + 'static> OnChainMetadata<M> {
+    pub fn new(client: Arc<M>) -> Self {
+        Self { client }
+    }
+
+    async fn symbol(&self, token: Address) -> Option<String> {
+        let data = encode_call("symbol()", &[]);
+        if let Ok(result) = call(&*self.client, token, data.clone(), &[ParamType::String]).await {
+            if let Some(AbiToken::String(symbol)) = result.into_iter().next() {
+                return Some(symbol);
+            }
+        }
+        match call(&*self.client, token, data, &[ParamType::FixedBytes(32)]).await.ok()?.into_iter().next() {
+            Some(AbiToken::FixedBytes(bytes)) => {
+                let symbol = String::from_utf8_lossy(&bytes).trim_end_matches('\0').to_string();
+                (!symbol.is_empty()).then_some(symbol)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> TokenMetadataSource for OnChainMetadata<M> {
+    async fn metadata(&self, address: &str) -> Result<(String, u8), RouterError> {
+        let token = parse_address(address)?;
+        let decimals = match call(&*self.client, token, encode_call("decimals()", &[]), &[ParamType::Uint(8)])
+            .await?
+            .first()
+        {
+            Some(AbiToken::Uint(value)) if *value <= U256::from(u8::MAX) => value.low_u32() as u8,
+            _ => return Err(RouterError::ChainError(format!("Invalid decimals() from {}", address))),
+        };
+        let symbol = match self.symbol(token).await {
+            Some(symbol) => symbol,
+            None => {
+                warn!("Token {} has no readable symbol", address);
+                format!("{}…", &address[..address.len().min(10)])
+            }
+        };
+        Ok((symbol, decimals))
+    }
+}
+
+// Tokens by chain and lowercased address, from token lists, manual registration
+// and, for unknown addresses, the chain itself
+#[derive(Default)]
+pub struct TokenRegistry {
+    tokens: DashMap<(u64, String), Token>,
+    resolvers: DashMap<u64, Arc<dyn TokenMetadataSource>>,
+}
+
+impl TokenRegistry {
+    pub fn register(&self, token: Token) {
+        self.tokens.insert((token.chain_id, token.address.to_lowercase()), token);
+    }
+
+    // Resolve addresses of `chain_id` that aren't registered through `source`
+    pub fn set_resolver(&self, chain_id: u64, source: Arc<dyn TokenMetadataSource>) {
+        self.resolvers.insert(chain_id, source);
+    }
+
+    // Registers every valid entry not already known; returns how many were added
+    pub fn load_list(&self, list: &TokenList) -> usize {
+        let mut added = 0;
+        for entry in &list.tokens {
+            if parse_address(&entry.address).is_err() {
+                warn!("Skipping {} in token list {}: invalid address {}", entry.symbol, list.name, entry.address);
+                continue;
+            }
+            let key = (entry.chain_id, entry.address.to_lowercase());
+            if self.tokens.contains_key(&key) {
+                continue;
+            }
+            self.tokens.insert(
+                key,
+                Token {
+                    chain_id: entry.chain_id,
+                    address: entry.address.clone(),
+                    symbol: entry.symbol.clone(),
+                    decimals: entry.decimals,
+                },
+            );
+            added += 1;
+        }
+        added
+    }
+
+    pub fn load_list_json(&self, json: &str) -> Result<usize, RouterError> {
+        let list: TokenList = serde_json::from_str(json)
+            .map_err(|e| RouterError::ConfigError(format!("Invalid token list: {}", e)))?;
+        Ok(self.load_list(&list))
+    }
+
+    pub fn load_list_file(&self, path: impl AsRef<Path>) -> Result<usize, RouterError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| RouterError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+        self.load_list_json(&json)
+    }
+
+    pub async fn load_list_url(&self, url: &str) -> Result<usize, RouterError> {
+        let list: TokenList = reqwest::get(url)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch token list {}: {}", url, e)))?
+            .json()
+            .await
+            .map_err(|e| RouterError::ConfigError(format!("Invalid token list {}: {}", url, e)))?;
+        Ok(self.load_list(&list))
+    }
+
+    // Registered token, without touching the chain
+    pub fn get(&self, chain_id: u64, address: &str) -> Option<Token> {
+        self.tokens.get(&(chain_id, address.to_lowercase())).map(|t| t.clone())
+    }
+
+    // Registered token, or its on-chain metadata, cached once resolved
+    pub async fn resolve(&self, chain_id: u64, address: &str) -> Result<Token, RouterError> {
+        if let Some(token) = self.get(chain_id, address) {
+            return Ok(token);
+        }
+        let resolver = self
+            .resolvers
+            .get(&chain_id)
+            .map(|r| r.clone())
+            .ok_or_else(|| RouterError::ConfigError(format!("Unknown token {} on chain {}", address, chain_id)))?;
+
+        let (symbol, decimals) = resolver.metadata(address).await.map_err(|e| {
+            RouterError::ConfigError(format!("Can't resolve token {} on chain {}: {}", address, chain_id, e))
+        })?;
+        let token = Token {
+            chain_id,
+            address: format!("{:?}", parse_address(address)?),
+            symbol,
+            decimals,
+        };
+        debug!("Resolved token {} ({}) on chain {}", token.symbol, token.address, chain_id);
+        self.register(token.clone());
+        Ok(token)
+    }
+
+    pub fn list(&self, chain_id: Option<u64>) -> Vec<Token> {
+        let mut tokens: Vec<Token> = self
+            .tokens
+            .iter()
+            .filter(|t| !matches!(chain_id, Some(id) if id != t.chain_id))
+            .map(|t| t.clone())
+            .collect();
+        tokens.sort_by(|a, b| (a.chain_id, &a.symbol).cmp(&(b.chain_id, &b.symbol)));
+        tokens
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tokens.is_empty()
+    }
+}
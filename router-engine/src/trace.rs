@@ -0,0 +1,65 @@
+use super::*;
+
+// Rejected candidates returned per debug quote
+pub const MAX_REJECTED: usize = 10;
+
+// Why a candidate route was pruned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RejectionReason {
+    PriceImpact { price_impact: f64, max: f64 },
+    TooManyHops { hops: usize, max: usize },
+    SourceNotAllowed { exchange_id: String },
+    BlacklistedPool { exchange_id: String },
+    UntrustedSource { exchange_id: String, tier: trust::TrustTier, min: trust::TrustTier },
+    // Gas cost not covered by the trade, e.g. when paying gas from the input
+    Gas { detail: String },
+    SimulationRevert { reason: String },
+    StaleState { state_block: u64, current_block: u64 },
+    // One step would take more of its pool's reserve than allowed
+    PoolConcentration { exchange_id: String, share_bps: u32, max_bps: u32 },
+    // The user's wallet lacks a capability the route's execution needs
+    WalletIncompatible { detail: String },
+    // Estimated above the request's max_gas
+    GasLimit { gas_estimate: u64, max_gas: u64 },
+    Unprofitable { detail: String },
+    // A source failed to quote one of the route's paths
+    QuoteFailed { detail: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RejectedRoute {
+    pub route: SwapRoute,
+    pub reason: RejectionReason,
+}
+
+// Candidates pruned while answering one quote. Rejections are always logged at
+// debug level but only kept when the request asked for them.
+#[derive(Debug, Default)]
+pub struct RejectionTrace {
+    enabled: bool,
+    rejected: Vec<RejectedRoute>,
+}
+
+impl RejectionTrace {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, rejected: Vec::new() }
+    }
+
+    pub fn record(&mut self, route: &SwapRoute, reason: RejectionReason) {
+        debug!("Rejected route via {:?}: {:?}", route.steps.iter().map(|s| &s.exchange_id).collect::<Vec<_>>(), reason);
+        if self.enabled {
+            self.rejected.push(RejectedRoute { route: route.clone(), reason });
+        }
+    }
+
+    // Best rejected candidates by expected output, None unless enabled
+    pub fn finish(mut self) -> Option<Vec<RejectedRoute>> {
+        if !self.enabled {
+            return None;
+        }
+        self.rejected.sort_by_key(|r| std::cmp::Reverse(math::parse_amount(&r.route.expected_amount_out).unwrap_or_default()));
+        self.rejected.truncate(MAX_REJECTED);
+        Some(self.rejected)
+    }
+}
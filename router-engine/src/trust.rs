@@ -0,0 +1,89 @@
+use super::*;
+
+// Exchanges launched more recently than this count as unproven
+pub const NEW_EXCHANGE_SECS: u64 = 90 * 24 * 3_600;
+
+// How far an exchange's contracts are trusted, lowest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrustTier {
+    #[default]
+    Unknown,
+    Unaudited,
+    Audited,
+    // Audited, long-lived and holding significant liquidity
+    BlueChip,
+}
+
+impl TrustTier {
+    // Contribution to a route's risk_score (0-100)
+    pub fn risk(&self) -> u8 {
+        match self {
+            TrustTier::Unknown => 40,
+            TrustTier::Unaudited => 30,
+            TrustTier::Audited => 10,
+            TrustTier::BlueChip => 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeMetadata {
+    // Unix timestamp of the exchange's deployment
+    #[serde(default)]
+    pub launched_at: Option<u64>,
+    // Links to published audit reports
+    #[serde(default)]
+    pub audits: Vec<String>,
+    #[serde(default)]
+    pub trust_tier: TrustTier,
+}
+
+impl ExchangeMetadata {
+    pub fn is_new(&self, now: u64) -> bool {
+        matches!(self.launched_at, Some(launched) if now.saturating_sub(launched) < NEW_EXCHANGE_SECS)
+    }
+
+    // Risk of routing through the exchange; recent launches add to their tier's risk
+    pub fn risk(&self, now: u64) -> u8 {
+        let age_risk = if self.is_new(now) { 10 } else { 0 };
+        self.trust_tier.risk() + age_risk
+    }
+}
+
+impl RouterEngine {
+    // Exchanges that aren't registered are Unknown
+    pub fn trust_tier(&self, chain_id: u64, exchange_id: &str) -> TrustTier {
+        self.exchanges
+            .get(&(chain_id, exchange_id.to_string()))
+            .map(|e| e.metadata.trust_tier)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn untrusted_step(&self, chain_id: u64, route: &SwapRoute, min: TrustTier) -> Option<trace::RejectionReason> {
+        route.steps.iter().find_map(|step| {
+            let tier = self.trust_tier(chain_id, &step.exchange_id);
+            (tier < min).then(|| trace::RejectionReason::UntrustedSource {
+                exchange_id: step.exchange_id.clone(),
+                tier,
+                min,
+            })
+        })
+    }
+
+    // Risk of the route's least trusted exchange
+    pub fn trust_risk(&self, chain_id: u64, route: &SwapRoute) -> u8 {
+        let now = rfq::now();
+        route
+            .steps
+            .iter()
+            .map(|step| {
+                self.exchanges
+                    .get(&(chain_id, step.exchange_id.clone()))
+                    .map(|e| e.metadata.risk(now))
+                    .unwrap_or_else(|| TrustTier::Unknown.risk())
+            })
+            .max()
+            .unwrap_or_default()
+    }
+}
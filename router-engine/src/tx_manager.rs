@@ -0,0 +1,425 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ethers::providers::MiddlewareError;
+
+use super::*;
+use crate::events::EngineEvent;
+use crate::finality::FinalityPolicy;
+use crate::mev::MevPolicy;
+
+// Geth and most builders require at least a 10% bump to replace a pending transaction
+const MIN_BUMP_PERCENT: u64 = 10;
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// A submitted swap transaction awaiting inclusion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingSwap {
+    pub hash: H256,
+    pub tx: Eip1559TransactionRequest,
+    // Request the swap was quoted from, used to requote a replacement
+    pub request: QuoteRequest,
+    pub policy: MevPolicy,
+    pub submitted_at: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum StuckReason {
+    // Max fee below the current base fee, so it can't be included
+    Underpriced { max_fee_per_gas: U256, base_fee_per_gas: U256 },
+    // No longer known to the node's mempool
+    Dropped,
+    // Still pending after the stuck threshold
+    Stalled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StuckSwap {
+    pub hash: H256,
+    pub reason: StuckReason,
+    pub pending_for: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Remedy {
+    // Same swap with higher fees
+    FeeBump { tx: Eip1559TransactionRequest },
+    // Zero-value self transfer replacing the swap, plus a fresh quote to resubmit
+    CancelAndRequote { cancel: Eip1559TransactionRequest, quote: Box<QuoteResponse> },
+    // Same swap with higher fees, sent through a private relay instead of the mempool
+    PrivateSubmission { tx: Eip1559TransactionRequest, relay: String },
+}
+
+// Ready-to-sign replacements for a stuck swap, all reusing its nonce
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RescuePlan {
+    pub stuck: StuckSwap,
+    pub remedies: Vec<Remedy>,
+}
+
+// Result of submitting a swap. In dry-run mode the fully prepared transaction and
+// its simulated return data are returned instead of being broadcast.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Submission {
+    Sent { hash: H256, tx: Eip1559TransactionRequest },
+    DryRun { tx: Eip1559TransactionRequest, return_data: String },
+}
+
+// On-chain state of a submitted transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum ExecutionStatus {
+    Pending,
+    Confirmed { block_number: u64, gas_used: u64 },
+    // Confirmed and past the chain's finality policy
+    Finalized { block_number: u64, gas_used: u64 },
+    Reverted { block_number: u64, gas_used: u64 },
+    // Neither mined nor known to the node, e.g. dropped or replaced
+    Unknown,
+}
+
+pub async fn execution_status<M: Middleware>(client: &M, hash: H256) -> Result<ExecutionStatus, RouterError> {
+    let receipt = client
+        .get_transaction_receipt(hash)
+        .await
+        .map_err(|e| RouterError::ChainError(format!("Failed to fetch receipt: {}", e)))?;
+
+    if let Some(receipt) = receipt {
+        let block_number = receipt.block_number.map(|n| n.as_u64()).unwrap_or_default();
+        let gas_used = receipt.gas_used.map(|g| g.as_u64()).unwrap_or_default();
+        return Ok(if receipt.status == Some(U64::from(1)) {
+            ExecutionStatus::Confirmed { block_number, gas_used }
+        } else {
+            ExecutionStatus::Reverted { block_number, gas_used }
+        });
+    }
+
+    let pending = client
+        .get_transaction(hash)
+        .await
+        .map_err(|e| RouterError::ChainError(format!("Failed to fetch transaction: {}", e)))?;
+    Ok(if pending.is_some() { ExecutionStatus::Pending } else { ExecutionStatus::Unknown })
+}
+
+// Tracks submitted swaps and rescues the ones that stop making progress
+pub struct TxManager<M: Middleware> {
+    client: Arc<M>,
+    engine: Arc<RouterEngine>,
+    pending: DashMap<H256, PendingSwap>,
+    stuck_after: Duration,
+    bump_percent: u64,
+    private_relay: Option<String>,
+}
+
+impl<M: Middleware + 'static> TxManager<M> {
+    pub fn new(client: Arc<M>, engine: Arc<RouterEngine>) -> Self {
+        Self {
+            client,
+            engine,
+            pending: DashMap::new(),
+            stuck_after: Duration::from_secs(180),
+            bump_percent: 15,
+            private_relay: None,
+        }
+    }
+
+    pub fn with_stuck_after(mut self, stuck_after: Duration) -> Self {
+        self.stuck_after = stuck_after;
+        self
+    }
+
+    // Fee increase applied to replacements, at least the 10% nodes require
+    pub fn with_bump_percent(mut self, bump_percent: u64) -> Self {
+        self.bump_percent = bump_percent.max(MIN_BUMP_PERCENT);
+        self
+    }
+
+    // Offer private submission through this relay as a remedy
+    pub fn with_private_relay(mut self, relay: String) -> Self {
+        self.private_relay = Some(relay);
+        self
+    }
+
+    pub fn track(&self, hash: H256, tx: Eip1559TransactionRequest, request: QuoteRequest, policy: MevPolicy) {
+        self.pending.insert(
+            hash,
+            PendingSwap {
+                hash,
+                tx,
+                request,
+                policy,
+                submitted_at: now(),
+            },
+        );
+    }
+
+    fn revert_error(&self, context: &str, e: M::Error) -> RouterError {
+        match e.as_error_response().and_then(|response| response.as_revert_data()) {
+            Some(data) => self.engine.decode_revert(&data),
+            None => RouterError::ChainError(format!("{}: {}", context, e)),
+        }
+    }
+
+    // Fill in fees and gas, simulate the swap, and broadcast it unless the engine is
+    // in dry-run mode. Broadcast swaps are tracked for rescue.
+    pub async fn submit(
+        &self,
+        mut tx: Eip1559TransactionRequest,
+        request: QuoteRequest,
+        policy: MevPolicy,
+    ) -> Result<Submission, RouterError> {
+        if tx.max_fee_per_gas.is_none() || tx.max_priority_fee_per_gas.is_none() {
+            let (max_fee, tip) = self
+                .client
+                .estimate_eip1559_fees(None)
+                .await
+                .map_err(|e| RouterError::ChainError(format!("Failed to estimate fees: {}", e)))?;
+            tx = tx.max_fee_per_gas(max_fee).max_priority_fee_per_gas(tip);
+        }
+
+        let typed = tx.clone().into();
+        let return_data = self
+            .client
+            .call(&typed, None)
+            .await
+            .map_err(|e| self.revert_error("Simulation failed", e))?;
+
+        if tx.gas.is_none() {
+            let gas = self
+                .client
+                .estimate_gas(&typed, None)
+                .await
+                .map_err(|e| self.revert_error("Gas estimation failed", e))?;
+            tx = tx.gas(gas);
+        }
+
+        if self.engine.is_dry_run() {
+            info!("Dry run: not broadcasting swap for chain {}", request.chain_id);
+            self.engine.publish_event(EngineEvent::Execution {
+                chain_id: request.chain_id,
+                tx_hash: String::new(),
+                status: "dry_run".to_string(),
+                detail: None,
+            });
+            return Ok(Submission::DryRun {
+                tx,
+                return_data: format!("0x{}", hex::encode(&return_data)),
+            });
+        }
+
+        let hash = self
+            .client
+            .send_transaction(tx.clone(), None)
+            .await
+            .map_err(|e| self.revert_error("Failed to send transaction", e))?
+            .tx_hash();
+
+        self.engine.publish_event(EngineEvent::Execution {
+            chain_id: request.chain_id,
+            tx_hash: format!("{:?}", hash),
+            status: "submitted".to_string(),
+            detail: None,
+        });
+        self.track(hash, tx.clone(), request, policy);
+
+        Ok(Submission::Sent { hash, tx })
+    }
+
+    async fn finality(&self) -> Result<FinalityPolicy, RouterError> {
+        let chain_id = self
+            .client
+            .get_chainid()
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch chain id: {}", e)))?
+            .as_u64();
+        Ok(self.engine.finality_policy(chain_id))
+    }
+
+    // execution_status, reporting confirmed transactions as finalized once the
+    // chain's finality policy is met
+    pub async fn status(&self, hash: H256) -> Result<ExecutionStatus, RouterError> {
+        let status = execution_status(&*self.client, hash).await?;
+        if let ExecutionStatus::Confirmed { block_number, gas_used } = status {
+            if self.finality().await?.is_final(&*self.client, block_number).await? {
+                return Ok(ExecutionStatus::Finalized { block_number, gas_used });
+            }
+        }
+        Ok(status)
+    }
+
+    // Poll until the transaction is finalized, reverted or gone
+    pub async fn wait_for_finality(&self, hash: H256, poll_interval: Duration) -> Result<ExecutionStatus, RouterError> {
+        loop {
+            match self.status(hash).await? {
+                ExecutionStatus::Pending | ExecutionStatus::Confirmed { .. } => tokio::time::sleep(poll_interval).await,
+                status => return Ok(status),
+            }
+        }
+    }
+
+    pub fn pending(&self) -> Vec<PendingSwap> {
+        self.pending.iter().map(|p| p.clone()).collect()
+    }
+
+    async fn base_fee(&self) -> Result<U256, RouterError> {
+        let block = self
+            .client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch latest block: {}", e)))?
+            .ok_or_else(|| RouterError::ChainError("Latest block not found".to_string()))?;
+
+        Ok(block.base_fee_per_gas.unwrap_or_default())
+    }
+
+    async fn check(&self, swap: &PendingSwap, base_fee: U256) -> Result<Option<StuckSwap>, RouterError> {
+        let receipt = self
+            .client
+            .get_transaction_receipt(swap.hash)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch receipt: {}", e)))?;
+        if let Some(receipt) = receipt {
+            // Mined swaps are tracked until final, in case a reorg drops them
+            let block_number = receipt.block_number.map(|n| n.as_u64()).unwrap_or_default();
+            if self.finality().await?.is_final(&*self.client, block_number).await? {
+                self.pending.remove(&swap.hash);
+            }
+            return Ok(None);
+        }
+
+        let pending_for = now().saturating_sub(swap.submitted_at);
+        let max_fee = swap.tx.max_fee_per_gas.unwrap_or_default();
+
+        let reason = if max_fee < base_fee {
+            StuckReason::Underpriced {
+                max_fee_per_gas: max_fee,
+                base_fee_per_gas: base_fee,
+            }
+        } else if self
+            .client
+            .get_transaction(swap.hash)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to fetch transaction: {}", e)))?
+            .is_none()
+        {
+            StuckReason::Dropped
+        } else if pending_for >= self.stuck_after.as_secs() {
+            StuckReason::Stalled
+        } else {
+            return Ok(None);
+        };
+
+        Ok(Some(StuckSwap {
+            hash: swap.hash,
+            reason,
+            pending_for,
+        }))
+    }
+
+    // Tracked swaps that are underpriced, dropped or stalled; mined swaps stop being tracked
+    pub async fn detect_stuck(&self) -> Result<Vec<StuckSwap>, RouterError> {
+        let base_fee = self.base_fee().await?;
+        let mut stuck = Vec::new();
+
+        for swap in self.pending() {
+            if let Some(s) = self.check(&swap, base_fee).await? {
+                stuck.push(s);
+            }
+        }
+
+        Ok(stuck)
+    }
+
+    // Replacement fees: the old fees bumped by bump_percent, and a max fee that
+    // survives the base fee doubling
+    async fn bumped_fees(&self, tx: &Eip1559TransactionRequest, base_fee: U256) -> Result<(U256, U256), RouterError> {
+        let bump = |value: U256| value * (100 + self.bump_percent) / 100 + 1;
+
+        let (_, suggested_tip) = self
+            .client
+            .estimate_eip1559_fees(None)
+            .await
+            .map_err(|e| RouterError::ChainError(format!("Failed to estimate fees: {}", e)))?;
+
+        let tip = bump(tx.max_priority_fee_per_gas.unwrap_or_default()).max(suggested_tip);
+        let max_fee = bump(tx.max_fee_per_gas.unwrap_or_default()).max(base_fee * 2 + tip);
+
+        Ok((max_fee, tip))
+    }
+
+    // Remedies for a stuck swap, each a ready-to-sign transaction with the same nonce
+    pub async fn rescue(&self, hash: H256) -> Result<RescuePlan, RouterError> {
+        let swap = self
+            .pending
+            .get(&hash)
+            .map(|p| p.clone())
+            .ok_or_else(|| RouterError::ExecutionError(format!("Transaction {:?} is not tracked", hash)))?;
+
+        let base_fee = self.base_fee().await?;
+        let stuck = match self.check(&swap, base_fee).await? {
+            Some(stuck) => stuck,
+            None if self.pending.contains_key(&hash) => StuckSwap {
+                hash,
+                reason: StuckReason::Stalled,
+                pending_for: now().saturating_sub(swap.submitted_at),
+            },
+            None => {
+                return Err(RouterError::ExecutionError(format!("Transaction {:?} is already mined", hash)));
+            }
+        };
+
+        let (max_fee, tip) = self.bumped_fees(&swap.tx, base_fee).await?;
+        let bumped = swap
+            .tx
+            .clone()
+            .max_fee_per_gas(max_fee)
+            .max_priority_fee_per_gas(tip);
+
+        let mut remedies = vec![Remedy::FeeBump { tx: bumped.clone() }];
+
+        // The original quote may be stale by now, so pair the cancellation with a new one
+        match self.engine.find_routes(swap.request.clone()).await {
+            Ok(quote) => {
+                let mut cancel = Eip1559TransactionRequest::new()
+                    .value(U256::zero())
+                    .gas(21_000)
+                    .max_fee_per_gas(max_fee)
+                    .max_priority_fee_per_gas(tip);
+                cancel.from = swap.tx.from;
+                cancel.to = swap.tx.from.map(NameOrAddress::Address);
+                cancel.nonce = swap.tx.nonce;
+                cancel.chain_id = swap.tx.chain_id;
+                remedies.push(Remedy::CancelAndRequote { cancel, quote: Box::new(quote) });
+            }
+            Err(e) => warn!("Failed to requote stuck swap {:?}: {}", hash, e),
+        }
+
+        // Only worth it where the mempool is public
+        let relay_helps = !matches!(self.engine.mempool_kind(swap.request.chain_id), Some(kind) if !kind.is_public());
+        if let (Some(relay), MevPolicy::PublicMempool, true) = (&self.private_relay, swap.policy, relay_helps) {
+            remedies.push(Remedy::PrivateSubmission {
+                tx: bumped,
+                relay: relay.clone(),
+            });
+        }
+
+        info!("Prepared {} remedies for stuck swap {:?}", remedies.len(), hash);
+
+        Ok(RescuePlan { stuck, remedies })
+    }
+
+    // Track a submitted replacement in place of the swap it replaces
+    pub fn replaced(&self, old_hash: H256, new_hash: H256, tx: Eip1559TransactionRequest, policy: MevPolicy) {
+        if let Some((_, swap)) = self.pending.remove(&old_hash) {
+            self.track(new_hash, tx, swap.request, policy);
+        }
+    }
+}
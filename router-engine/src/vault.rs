@@ -0,0 +1,246 @@
+use std::path::PathBuf;
+
+use aes::cipher::{KeyIvInit, StreamCipher};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+use super::*;
+use crate::secrets::{self, Secret};
+
+type Aes256Ctr = ctr::Ctr128BE<aes::Aes256>;
+
+const NONCE_LEN: usize = 16;
+const TAG_LEN: usize = 32;
+
+// Encrypts secrets before they reach a VaultStore, e.g. backed by AWS KMS or Vault
+#[async_trait]
+pub trait Kms: Send + Sync {
+    async fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, RouterError>;
+    async fn decrypt(&self, ciphertext: &[u8]) -> Result<Secret<Vec<u8>>, RouterError>;
+}
+
+// AES-256-CTR with an HMAC-SHA256 tag under keys derived from a local master key.
+// Output is nonce || ciphertext || tag.
+pub struct LocalKms {
+    encryption_key: [u8; 32],
+    mac_key: [u8; 32],
+}
+
+impl LocalKms {
+    pub fn new(master_key: [u8; 32]) -> Self {
+        let mut master_key = master_key;
+        let derive = |label: &[u8]| -> [u8; 32] {
+            let mut hasher = Sha256::new();
+            hasher.update(label);
+            hasher.update(master_key);
+            hasher.finalize().into()
+        };
+        let kms = Self {
+            encryption_key: derive(b"auraagg-vault-enc"),
+            mac_key: derive(b"auraagg-vault-mac"),
+        };
+        secrets::wipe(&mut master_key);
+        kms
+    }
+
+    // Key that only lives in this process; stored secrets die with it
+    pub fn ephemeral() -> Self {
+        let mut master_key = Secret::new([0u8; 32]);
+        rand::thread_rng().fill_bytes(master_key.expose_mut());
+        Self::new(*master_key.expose())
+    }
+
+    // 64 hex characters
+    pub fn from_hex(master_key: &str) -> Result<Self, RouterError> {
+        let bytes = Secret::new(
+            hex::decode(master_key.trim_start_matches("0x"))
+                .map_err(|_| RouterError::ConfigError("Vault master key is not hex".to_string()))?,
+        );
+        let master_key: Secret<[u8; 32]> = bytes
+            .expose()
+            .as_slice()
+            .try_into()
+            .map(Secret::new)
+            .map_err(|_| RouterError::ConfigError("Vault master key must be 32 bytes".to_string()))?;
+        Ok(Self::new(*master_key.expose()))
+    }
+
+    fn tag(&self, data: &[u8]) -> Hmac<Sha256> {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.mac_key).expect("HMAC takes any key length");
+        mac.update(data);
+        mac
+    }
+}
+
+impl Drop for LocalKms {
+    fn drop(&mut self) {
+        secrets::wipe(&mut self.encryption_key);
+        secrets::wipe(&mut self.mac_key);
+    }
+}
+
+#[async_trait]
+impl Kms for LocalKms {
+    async fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, RouterError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        rand::thread_rng().fill_bytes(&mut nonce);
+
+        let mut out = nonce.to_vec();
+        let mut body = plaintext.to_vec();
+        Aes256Ctr::new(&self.encryption_key.into(), &nonce.into()).apply_keystream(&mut body);
+        out.extend(body);
+        let tag = self.tag(&out).finalize().into_bytes();
+        out.extend(tag);
+        Ok(out)
+    }
+
+    async fn decrypt(&self, ciphertext: &[u8]) -> Result<Secret<Vec<u8>>, RouterError> {
+        if ciphertext.len() < NONCE_LEN + TAG_LEN {
+            return Err(RouterError::ExecutionError("Vault ciphertext is truncated".to_string()));
+        }
+        let (sealed, tag) = ciphertext.split_at(ciphertext.len() - TAG_LEN);
+        self.tag(sealed)
+            .verify_slice(tag)
+            .map_err(|_| RouterError::ExecutionError("Vault ciphertext failed authentication".to_string()))?;
+
+        let (nonce, body) = sealed.split_at(NONCE_LEN);
+        let mut plaintext = Secret::new(body.to_vec());
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().expect("split at NONCE_LEN");
+        Aes256Ctr::new(&self.encryption_key.into(), &nonce.into()).apply_keystream(plaintext.expose_mut());
+        Ok(plaintext)
+    }
+}
+
+// Where encrypted secrets rest, keyed by secret hash
+#[async_trait]
+pub trait VaultStore: Send + Sync {
+    async fn put(&self, key: &str, ciphertext: Vec<u8>) -> Result<(), RouterError>;
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, RouterError>;
+    async fn remove(&self, key: &str) -> Result<(), RouterError>;
+}
+
+#[derive(Default)]
+pub struct MemoryVaultStore {
+    entries: DashMap<String, Vec<u8>>,
+}
+
+#[async_trait]
+impl VaultStore for MemoryVaultStore {
+    async fn put(&self, key: &str, ciphertext: Vec<u8>) -> Result<(), RouterError> {
+        self.entries.insert(key.to_string(), ciphertext);
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, RouterError> {
+        Ok(self.entries.get(key).map(|entry| entry.clone()))
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), RouterError> {
+        self.entries.remove(key);
+        Ok(())
+    }
+}
+
+// One file per secret under `dir`, so in-flight swaps survive restarts
+pub struct FileVaultStore {
+    dir: PathBuf,
+}
+
+impl FileVaultStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, RouterError> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| RouterError::ConfigError(format!("Failed to create vault dir {}: {}", dir.display(), e)))?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.secret", key.trim_start_matches("0x")))
+    }
+}
+
+#[async_trait]
+impl VaultStore for FileVaultStore {
+    async fn put(&self, key: &str, ciphertext: Vec<u8>) -> Result<(), RouterError> {
+        let path = self.path(key);
+        tokio::fs::write(&path, ciphertext)
+            .await
+            .map_err(|e| RouterError::ExecutionError(format!("Failed to write {}: {}", path.display(), e)))
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, RouterError> {
+        let path = self.path(key);
+        match tokio::fs::read(&path).await {
+            Ok(ciphertext) => Ok(Some(ciphertext)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(RouterError::ExecutionError(format!("Failed to read {}: {}", path.display(), e))),
+        }
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), RouterError> {
+        let path = self.path(key);
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(RouterError::ExecutionError(format!("Failed to remove {}: {}", path.display(), e)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// HTLC secrets encrypted at rest, looked up by their keccak256 hash
+pub struct SecretVault {
+    kms: Arc<dyn Kms>,
+    store: Arc<dyn VaultStore>,
+}
+
+impl SecretVault {
+    pub fn new(kms: Arc<dyn Kms>, store: Arc<dyn VaultStore>) -> Self {
+        Self { kms, store }
+    }
+
+    // In-memory store under a process-local key
+    pub fn ephemeral() -> Self {
+        Self::new(Arc::new(LocalKms::ephemeral()), Arc::new(MemoryVaultStore::default()))
+    }
+
+    // Fresh random secret; only its hash leaves the vault
+    pub async fn generate(&self) -> Result<H256, RouterError> {
+        let mut secret = Secret::new([0u8; 32]);
+        rand::thread_rng().fill_bytes(secret.expose_mut());
+        self.store(secret.expose()).await
+    }
+
+    pub async fn store(&self, secret: &[u8; 32]) -> Result<H256, RouterError> {
+        let hash = H256::from(ethers::utils::keccak256(secret));
+        let ciphertext = self.kms.encrypt(secret).await?;
+        self.store.put(&format!("{:?}", hash), ciphertext).await?;
+        Ok(hash)
+    }
+
+    // Decrypt the secret behind `hash`, checking it still hashes to it
+    pub async fn reveal(&self, hash: H256) -> Result<Secret<[u8; 32]>, RouterError> {
+        let ciphertext = self
+            .store
+            .get(&format!("{:?}", hash))
+            .await?
+            .ok_or_else(|| RouterError::ExecutionError(format!("No secret stored for {:?}", hash)))?;
+        let plaintext = self.kms.decrypt(&ciphertext).await?;
+        let secret: Secret<[u8; 32]> = plaintext
+            .expose()
+            .as_slice()
+            .try_into()
+            .map(Secret::new)
+            .map_err(|_| RouterError::ExecutionError(format!("Stored secret for {:?} is malformed", hash)))?;
+        if H256::from(ethers::utils::keccak256(secret.expose())) != hash {
+            return Err(RouterError::ExecutionError(format!("Stored secret does not match {:?}", hash)));
+        }
+        Ok(secret)
+    }
+
+    // Drop a secret once its swap is claimed or refunded
+    pub async fn remove(&self, hash: H256) -> Result<(), RouterError> {
+        self.store.remove(&format!("{:?}", hash)).await
+    }
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use super::*;
+
+// Routes within this many basis points of the best net output are ranked by how
+// many transactions the wallet needs instead
+pub const FEWER_TRANSACTIONS_TOLERANCE_BPS: u64 = 10;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountKind {
+    #[default]
+    Eoa,
+    SmartAccount,
+}
+
+// What the user's wallet can do beyond sending a plain transaction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletCapabilities {
+    #[serde(default)]
+    pub account: AccountKind,
+    // Sends several calls as one transaction (EIP-5792 wallet_sendCalls or a 4337 batch)
+    #[serde(default)]
+    pub batching: bool,
+    // Signs EIP-712 typed data, as permits and relayed intents need
+    #[serde(default)]
+    pub eip712: bool,
+}
+
+impl WalletCapabilities {
+    // Approve and swap can go out as one batch
+    pub fn can_batch(&self) -> bool {
+        self.account == AccountKind::SmartAccount && self.batching
+    }
+
+    // Why the wallet can't execute the route as planned, if it can't
+    pub fn incompatibility(&self, route: &SwapRoute) -> Option<String> {
+        match route.gas_payment.as_ref().map(|payment| &payment.mode) {
+            Some(gas_payment::GasPaymentMode::Paymaster { .. }) if self.account != AccountKind::SmartAccount => {
+                Some("paying gas through a paymaster needs a smart account".to_string())
+            }
+            // A relayer can only pull the input with the user's signed permit
+            Some(gas_payment::GasPaymentMode::Sponsored { .. })
+                if self.account != AccountKind::SmartAccount && !self.eip712 =>
+            {
+                Some("sponsored gas needs a smart account or EIP-712 signing".to_string())
+            }
+            _ => None,
+        }
+    }
+}
+
+// What the caller knows about the user's wallet
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WalletContext {
+    // Tokens already approved to the executor (or Permit2)
+    #[serde(default)]
+    pub approved_tokens: Vec<String>,
+    // Token address to balance in base units
+    #[serde(default)]
+    pub balances: HashMap<String, String>,
+    // Unknown capabilities rule nothing out and batch nothing
+    #[serde(default)]
+    pub capabilities: Option<WalletCapabilities>,
+}
+
+impl WalletContext {
+    pub fn is_approved(&self, token: &str) -> bool {
+        self.approved_tokens.iter().any(|t| t.eq_ignore_ascii_case(token))
+    }
+
+    pub fn can_batch(&self) -> bool {
+        matches!(&self.capabilities, Some(capabilities) if capabilities.can_batch())
+    }
+
+    pub fn incompatibility(&self, route: &SwapRoute) -> Option<String> {
+        self.capabilities.as_ref()?.incompatibility(route)
+    }
+
+    pub fn balance(&self, token: &str) -> BigUint {
+        self.balances
+            .iter()
+            .find(|(t, _)| t.eq_ignore_ascii_case(token))
+            .and_then(|(_, amount)| math::parse_amount(amount).ok())
+            .unwrap_or_default()
+    }
+}
+
+// Per-route consequences of the wallet context
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletHints {
+    pub approval_required: bool,
+    // Route tokens the wallet already holds, candidates for reuse
+    pub held_tokens: Vec<String>,
+    // The approval goes out in one batch with the swap
+    #[serde(default)]
+    pub batched: bool,
+    // Approval plus swap transactions the user will send
+    pub transactions: u8,
+}
+
+// Native ETH needs no approval
+fn needs_approval(token: &Token, wallet: &WalletContext) -> bool {
+    token.address != format!("{:?}", Address::zero()) && !wallet.is_approved(&token.address)
+}
+
+pub fn route_hints(route: &SwapRoute, wallet: &WalletContext) -> Option<WalletHints> {
+    let token_in = &route.steps.first()?.token_in;
+    let approval_required = needs_approval(token_in, wallet);
+
+    let mut held_tokens: Vec<String> = route
+        .steps
+        .iter()
+        .flat_map(|step| [&step.token_in.address, &step.token_out.address])
+        .filter(|token| wallet.balance(token) > BigUint::default())
+        .cloned()
+        .collect();
+    held_tokens.sort();
+    held_tokens.dedup();
+
+    let batched = approval_required && wallet.can_batch();
+    Some(WalletHints {
+        approval_required,
+        held_tokens,
+        batched,
+        transactions: 1 + (approval_required && !batched) as u8,
+    })
+}
+
+// Among routes whose net output is within tolerance of the best, move the ones
+// needing fewer transactions first. Expects routes sorted by net output.
+pub fn prefer_fewer_transactions(routes: &mut [SwapRoute]) {
+    let best = match routes.first() {
+        Some(route) => rebate::net_amount_out(route),
+        None => return,
+    };
+    let threshold = &best * BigUint::from(10_000 - FEWER_TRANSACTIONS_TOLERANCE_BPS) / BigUint::from(10_000u64);
+
+    let close = routes
+        .iter()
+        .take_while(|route| rebate::net_amount_out(route) >= threshold)
+        .count();
+    routes[..close].sort_by_key(|route| route.wallet_hints.as_ref().map(|h| h.transactions).unwrap_or(1));
+}